@@ -0,0 +1,67 @@
+//! Compares matching with the sparse `dfa::DFA` and the dense `dense::DFA`.
+//!
+//! Run with `cargo bench --bench dense`.
+use regexxx::expr::Expr;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Runs `f` until a second has passed and returns the mean time per run
+fn measure<F: FnMut() -> bool>(mut f: F) -> Duration {
+    let started = Instant::now();
+    let mut runs = 0u32;
+    while started.elapsed() < Duration::from_secs(1) {
+        black_box(f());
+        runs += 1;
+    }
+    started.elapsed() / runs
+}
+
+fn main() {
+    // RE (a+b+c+d)*e over a long haystack that only matches at the end
+    let machine = Expr::literal('a')
+        .union(Expr::literal('b'))
+        .union(Expr::literal('c'))
+        .union(Expr::literal('d'))
+        .star()
+        .concat(Expr::literal('e'))
+        .build()
+        .unwrap();
+    let sparse = machine.determinize().unwrap();
+    let dense = machine.to_dense().unwrap();
+    let mut haystack: String = "abcd".repeat(250_000);
+    haystack.push('e');
+    let bytes = haystack.len() as f64;
+
+    let sparse_time = measure(|| sparse.accepts(black_box(&haystack).chars()));
+    let dense_time = measure(|| dense.accepts(black_box(&haystack)));
+    let mut unanchored = String::from("x");
+    unanchored.push_str(&haystack);
+    let find_time = measure(|| dense.find(black_box(&unanchored)).is_some());
+
+    // RE a*b over a run of a's, where every position starts a partial match
+    // that only fails at the end, quadratic when each start is tried in turn
+    let a_star_b = Expr::literal('a')
+        .star()
+        .concat(Expr::literal('b'))
+        .build()
+        .unwrap()
+        .to_dense()
+        .unwrap();
+    let run = "a".repeat(haystack.len());
+    let run_time = measure(|| a_star_b.find(black_box(&run)).is_some());
+    for (name, time) in [
+        ("dfa::DFA::accepts", sparse_time),
+        ("dense::DFA::accepts", dense_time),
+        ("dense::DFA::find", find_time),
+        ("dense::DFA::find a*b", run_time),
+    ]
+    .iter()
+    {
+        println!(
+            "{:<20} {:>10.3?} {:>8.1} MB/s",
+            name,
+            time,
+            bytes / time.as_secs_f64() / 1e6
+        );
+    }
+}
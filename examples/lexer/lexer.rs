@@ -0,0 +1,102 @@
+// Generated by regexxx::codegen::emit_lexer, do not edit.
+
+/// Token kinds, in rule order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Token {
+    Let,
+    Ident,
+    Number,
+    Equals,
+    Plus,
+    Space,
+}
+
+fn accept(state: usize) -> Option<Token> {
+    match state {
+        1 => Some(Token::Ident),
+        2 => Some(Token::Ident),
+        3 => Some(Token::Number),
+        4 => Some(Token::Equals),
+        5 => Some(Token::Plus),
+        6 => Some(Token::Space),
+        7 => Some(Token::Ident),
+        9 => Some(Token::Let),
+        _ => None,
+    }
+}
+
+/// Returns the longest token at the start of `input` and its length in
+/// bytes. Where rules tie, the first rule wins.
+pub fn next_token(input: &str) -> Option<(Token, usize)> {
+    let mut state = 0;
+    let mut longest = None;
+    for (at, c) in input.char_indices() {
+        state = match (state, c) {
+            (0, '\n') => 6,
+            (0, ' ') => 6,
+            (0, '+') => 5,
+            (0, '0'..='9') => 3,
+            (0, '=') => 4,
+            (0, '_') => 2,
+            (0, 'a'..='k') => 2,
+            (0, 'l') => 1,
+            (0, 'm'..='z') => 2,
+            (1, '0'..='9') => 2,
+            (1, '_') => 2,
+            (1, 'a'..='d') => 2,
+            (1, 'e') => 7,
+            (1, 'f'..='z') => 2,
+            (2, '0'..='9') => 2,
+            (2, '_') => 2,
+            (2, 'a'..='z') => 2,
+            (3, '0'..='9') => 3,
+            (6, '\n') => 6,
+            (6, ' ') => 6,
+            (7, '0'..='9') => 2,
+            (7, '_') => 2,
+            (7, 'a'..='s') => 2,
+            (7, 't') => 9,
+            (7, 'u'..='z') => 2,
+            (9, '0'..='9') => 2,
+            (9, '_') => 2,
+            (9, 'a'..='z') => 2,
+            _ => break,
+        };
+        if let Some(token) = accept(state) {
+            longest = Some((token, at + c.len_utf8()));
+        }
+    }
+    longest
+}
+
+/// The tokens of an input, each with its text. An input that no rule
+/// matches yields the byte offset of the error, then ends.
+pub struct Lexer<'a> {
+    input: &'a str,
+    at: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Lexer<'a> {
+        Lexer { input, at: 0 }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<(Token, &'a str), usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.input.get(self.at..).filter(|rest| !rest.is_empty())?;
+        match next_token(rest) {
+            Some((token, len)) => {
+                self.at += len;
+                Some(Ok((token, &rest[..len])))
+            }
+            None => {
+                let at = self.at;
+                self.at = self.input.len() + 1;
+                Some(Err(at))
+            }
+        }
+    }
+}
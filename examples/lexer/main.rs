@@ -0,0 +1,27 @@
+//! A lexer generated by `codegen::emit_lexer`.
+//!
+//! `lexer.rs` is the generated source for six token rules: the keyword
+//! `let`, identifiers, numbers, `=`, `+`, and whitespace. It has no
+//! dependencies, so a build script could write it into `OUT_DIR` and the
+//! final binary would not depend on regexxx at all. The rules are in the
+//! tests of `src/codegen.rs`, which check that this copy is up to date.
+//!
+//! Run with `cargo run --example lexer -- 'let x1 = 40 + 2'`.
+mod lexer;
+
+use lexer::Lexer;
+
+fn main() {
+    let input = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("let x1 = 40 + 2"));
+    for token in Lexer::new(&input) {
+        match token {
+            Ok((token, text)) => println!("{:?} {:?}", token, text),
+            Err(at) => {
+                eprintln!("No token matches at byte {}", at);
+                std::process::exit(1);
+            }
+        }
+    }
+}
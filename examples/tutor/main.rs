@@ -0,0 +1,169 @@
+//! Learn-by-doing construction exercises.
+//!
+//! Each exercise describes a language. Build an automaton for it by typing the
+//! compiler operations in postfix order, the same order you would call them on
+//! a `Compiler`:
+//!
+//! ```text
+//! a      expr_a('a'), any single character that is not an operation
+//! 'x'    expr_a('x'), quoted to use an operation character as a literal
+//! 0      expr_0
+//! 1      expr_1
+//! .      concatenate
+//! |      union
+//! *      star
+//! ```
+//!
+//! For example `a b | *` builds (a+b)*. Your machine is checked against the
+//! answer with `ANFA::equivalent`, and a counterexample is shown when they differ.
+//!
+//! Run with `cargo run --example tutor --features tutor`.
+use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+use regexxx::expr::Expr;
+use regexxx::ANFA;
+use std::io::{self, BufRead, Write};
+
+fn lit(c: char) -> Expr {
+    Expr::literal(c)
+}
+
+fn exercises() -> Vec<(&'static str, Expr)> {
+    vec![
+        ("the single word \"ab\"", lit('a').concat(lit('b'))),
+        ("\"a\" or \"b\"", lit('a').union(lit('b'))),
+        ("any number of a's, including none", lit('a').star()),
+        (
+            "(ab)*a: \"a\", \"aba\", \"ababa\", ...",
+            lit('a').concat(lit('b')).star().concat(lit('a')),
+        ),
+        (
+            "words over a and b ending in b",
+            lit('a').union(lit('b')).star().concat(lit('b')),
+        ),
+        (
+            "one or more c's, i.e. c+ written without +",
+            lit('c').concat(lit('c').star()),
+        ),
+        (
+            "words over a and b with an even number of b's",
+            lit('a').star().concat(
+                lit('b')
+                    .concat(lit('a').star())
+                    .concat(lit('b'))
+                    .concat(lit('a').star())
+                    .star(),
+            ),
+        ),
+    ]
+}
+
+/// Runs the postfix program in `line` and returns the machine it builds
+fn assemble(line: &str) -> Result<ANFA, String> {
+    let mut machine: Option<ANFA> = None;
+    for token in line.split_whitespace() {
+        let mut chars = token.chars();
+        let (c, quoted) = match (chars.next(), chars.next(), chars.next(), chars.next()) {
+            (Some('\''), Some(c), Some('\''), None) => (c, true),
+            (Some(c), None, None, None) => (c, false),
+            _ => return Err(format!("I don't understand `{}`", token)),
+        };
+        let result = match (&mut machine, c, quoted) {
+            (None, '0', false) => ForwardCompiler::from_expr_0().map(|m| machine = Some(m)),
+            (None, '1', false) => ForwardCompiler::from_expr_1().map(|m| machine = Some(m)),
+            (None, '.', false) | (None, '|', false) | (None, '*', false) => {
+                Err("an operation needs operands, start with a literal")
+            }
+            (None, c, _) => ForwardCompiler::from_expr_a(c).map(|m| machine = Some(m)),
+            (Some(m), '0', false) => ForwardCompiler::expr_0(m),
+            (Some(m), '1', false) => ForwardCompiler::expr_1(m),
+            (Some(m), '.', false) => ForwardCompiler::concatenate(m),
+            (Some(m), '|', false) => ForwardCompiler::union(m),
+            (Some(m), '*', false) => ForwardCompiler::star(m),
+            (Some(m), c, _) => ForwardCompiler::expr_a(m, c),
+        };
+        if let Err(err) = result {
+            return Err(format!("`{}` failed: {}", token, err));
+        }
+    }
+    match machine {
+        None => Err(String::from("type some operations first")),
+        Some(machine) if machine.automata_refs.len() != 1 => Err(format!(
+            "{} machines are left on the stack, combine them into one",
+            machine.automata_refs.len()
+        )),
+        Some(machine) => Ok(machine),
+    }
+}
+
+fn show(word: &[char]) -> String {
+    if word.is_empty() {
+        String::from("the empty word")
+    } else {
+        format!("\"{}\"", word.iter().collect::<String>())
+    }
+}
+
+fn main() -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    println!("Build each language in postfix: a b | * . 0 1 'x'. Type `hint`, `skip`, or `quit`.");
+    let exercises = exercises();
+    let mut solved = 0;
+    for (i, (description, answer)) in exercises.iter().enumerate() {
+        let answer = answer.build().expect("exercise answers compile");
+        println!("\nExercise {}/{}: {}", i + 1, exercises.len(), description);
+        loop {
+            print!("> ");
+            io::stdout().flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+            match line.trim() {
+                "quit" => return Ok(()),
+                "skip" => break,
+                "hint" => {
+                    if let Ok(Some(word)) = answer.shortest_accepted() {
+                        println!("The shortest word in the language is {}.", show(&word));
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let machine = match assemble(&line) {
+                Ok(machine) => machine,
+                Err(err) => {
+                    println!("Hmm, {}.", err);
+                    continue;
+                }
+            };
+            match ANFA::counterexample(&answer, &machine) {
+                Ok(None) => {
+                    println!("Correct!");
+                    solved += 1;
+                    break;
+                }
+                Ok(Some(word)) => {
+                    let in_language = answer
+                        .determinize()
+                        .map(|dfa| dfa.accepts(word.iter().copied()))
+                        .unwrap_or(false);
+                    if in_language {
+                        println!(
+                            "Not quite: {} is in the language, but your machine rejects it.",
+                            show(&word)
+                        );
+                    } else {
+                        println!(
+                            "Not quite: your machine accepts {}, which is not in the language.",
+                            show(&word)
+                        );
+                    }
+                }
+                Err(err) => println!("Could not compare machines: {}", err),
+            }
+        }
+    }
+    println!("\nSolved {} of {} exercises.", solved, exercises.len());
+    Ok(())
+}
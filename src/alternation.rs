@@ -0,0 +1,203 @@
+use crate::expr::Expr;
+use crate::set::{Reached, Tagged, Visited};
+use crate::ANFA;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The leftmost match of an `Alternation` and the branch that produced it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BranchMatch {
+    /// The index of the branch, in the order the branches were given
+    pub branch: usize,
+    /// The byte span of the match
+    pub span: (usize, usize),
+}
+
+/// A top-level alternation that reports which branch matched, without
+/// capture groups.
+///
+/// The branches are imported into one tagged automaton, like the patterns
+/// of a `Set`. The search simulates
+/// every branch at once like `ANFA::find`, so the match and its branch are
+/// those the union of the branches would report: the leftmost match, and
+/// among those the one preferred by the earliest branch.
+///
+/// ```rust
+/// use regexxx::alternation::Alternation;
+/// use regexxx::expr::Expr;
+/// let levels = Expr::word("ERROR").union(Expr::word("WARN")).union(Expr::word("INFO"));
+/// let alternation = Alternation::from_expr(&levels).unwrap();
+/// let found = alternation.find("12:00 WARN disk full").unwrap();
+/// assert_eq!(found.branch, 1);
+/// assert_eq!(found.span, (6, 10));
+/// ```
+#[derive(Debug)]
+pub struct Alternation {
+    tagged: Tagged,
+}
+
+impl Alternation {
+    /// Builds an alternation of finalized machines, i.e. machines holding
+    /// exactly one automaton each. Branch indexes follow the order of
+    /// `branches`, which is also their priority.
+    pub fn new(branches: &[&ANFA]) -> Result<Alternation, &'static str> {
+        let mut tagged = Tagged::new();
+        for machine in branches.iter() {
+            tagged.push(machine)?;
+        }
+        Ok(Alternation { tagged })
+    }
+
+    /// Builds an alternation of the operands of the top-level unions of
+    /// `expr`, e.g. the three branches of `a+b+c`. An expression that is not
+    /// a union is a single branch.
+    pub fn from_expr(expr: &Expr) -> Result<Alternation, &'static str> {
+        let mut branches = Vec::new();
+        // operands are popped, so the right operand is pushed first
+        let mut stack = vec![expr];
+        while let Some(expr) = stack.pop() {
+            match expr {
+                Expr::Union(a, b) => {
+                    stack.push(b);
+                    stack.push(a);
+                }
+                branch => branches.push(branch.build()?),
+            }
+        }
+        Alternation::new(&branches.iter().collect::<Vec<_>>())
+    }
+
+    /// The number of branches
+    pub fn len(&self) -> usize {
+        self.tagged.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the leftmost match in `haystack` and the branch preferred
+    /// among the matches starting there, or `None`
+    pub fn find(&self, haystack: &str) -> Option<BranchMatch> {
+        let tagged = &self.tagged;
+        // threads in priority order, a thread that reached a final state is
+        // a match of its branch: (state reached, start of its match)
+        let mut current: Vec<(Reached, usize)> = Vec::new();
+        let mut next: Vec<(Reached, usize)> = Vec::new();
+        let mut visited = Visited::new(tagged.anfa.delta().len());
+        let mut matched = None;
+        let mut at = 0;
+        let mut before = None;
+        loop {
+            let after = haystack[at..].chars().next();
+            if matched.is_none() {
+                // lowest priority, threads that started earlier are preferred
+                for entry in tagged.entries.iter() {
+                    tagged.close(*entry, &mut visited, before, after, |reached| {
+                        current.push((reached, at))
+                    });
+                }
+            } else if current.is_empty() {
+                break;
+            }
+            visited.next_step();
+            let after_next = after.and_then(|c| haystack[at + c.len_utf8()..].chars().next());
+            for (reached, start) in current.iter() {
+                let q = match reached {
+                    Reached::Final(branch) => {
+                        // threads after this one have lower priority
+                        matched = Some(BranchMatch {
+                            branch: *branch,
+                            span: (*start, at),
+                        });
+                        break;
+                    }
+                    Reached::Labeled(q) => *q,
+                };
+                if let Some(q_next) = after.and_then(|c| tagged.step(q, c)) {
+                    tagged.close(q_next, &mut visited, after, after_next, |reached| {
+                        next.push((reached, *start))
+                    });
+                }
+            }
+            core::mem::swap(&mut current, &mut next);
+            next.clear();
+            match after {
+                None => break,
+                Some(c) => {
+                    at += c.len_utf8();
+                    before = Some(c);
+                }
+            }
+        }
+        matched
+    }
+
+    /// Returns the branch of the leftmost match in `haystack`, e.g. to
+    /// classify a line of a log
+    pub fn classify(&self, haystack: &str) -> Option<usize> {
+        self.find(haystack).map(|found| found.branch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alternation::{Alternation, BranchMatch};
+    use crate::assertion::Assertion;
+    use crate::expr::Expr;
+
+    #[test]
+    fn test_agrees_with_find() {
+        // RE a+ab+b*c+\bd
+        let expr = Expr::word("a")
+            .union(Expr::word("ab"))
+            .union(Expr::literal('b').star().concat(Expr::literal('c')))
+            .union(Expr::assert(Assertion::WordBoundary).concat(Expr::literal('d')));
+        let alternation = Alternation::from_expr(&expr).unwrap();
+        assert_eq!(alternation.len(), 4);
+        let union = expr.build().unwrap();
+        for (haystack, branch) in [
+            ("ab", Some(0)),
+            ("xbbc", Some(2)),
+            ("bbab", Some(0)),
+            ("dd", Some(3)),
+            ("xd", None),
+            ("", None),
+        ]
+        .iter()
+        {
+            let found = alternation.find(haystack);
+            assert_eq!(
+                found.map(|found| found.span),
+                union.find(haystack).unwrap(),
+                "{}",
+                haystack
+            );
+            assert_eq!(alternation.classify(haystack), *branch, "{}", haystack);
+        }
+    }
+
+    #[test]
+    fn test_priority() {
+        // both branches match at 0, the first wins even though it is shorter
+        let short = Expr::word("ab").build().unwrap();
+        let long = Expr::word("abc").build().unwrap();
+        let alternation = Alternation::new(&[&short, &long]).unwrap();
+        assert_eq!(
+            alternation.find("xabc"),
+            Some(BranchMatch {
+                branch: 0,
+                span: (1, 3)
+            })
+        );
+        let alternation = Alternation::new(&[&long, &short]).unwrap();
+        assert_eq!(alternation.classify("xabc"), Some(0));
+        assert_eq!(alternation.classify("xabx"), Some(1));
+        // an empty branch matches at 0 unless an earlier one matches there
+        let empty = Expr::one().build().unwrap();
+        let alternation = Alternation::new(&[&short, &empty]).unwrap();
+        assert_eq!(alternation.find("ab").map(|found| found.branch), Some(0));
+        assert_eq!(alternation.find("ba").map(|found| found.span), Some((0, 0)));
+        assert!(Alternation::new(&[]).unwrap().find("a").is_none());
+    }
+}
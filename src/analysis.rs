@@ -0,0 +1,888 @@
+use crate::dfa::DFA;
+use crate::{QId, ANFA};
+use alloc::collections::{BinaryHeap, VecDeque};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+/// A visited pair of product states, its parent index into the visited list,
+/// and the label read from the parent
+type Visit<S> = (QId, QId, Option<(usize, S)>);
+
+/// Breadth-first search of the product of `a` and `b` for the shortest word
+/// whose acceptance by `a` and `b` satisfies `differs(a_accepts, b_accepts)`.
+/// A missing transition leads to an implicit dead state.
+fn product_witness<S: Copy + Eq>(
+    a: &DFA<S>,
+    b: &DFA<S>,
+    differs: fn(bool, bool) -> bool,
+) -> Option<Vec<S>> {
+    let accepts = |dfa: &DFA<S>, q: Option<QId>| q.is_some_and(|q| dfa.finals[q]);
+    product_search(a, b, |q_a, q_b| differs(accepts(a, q_a), accepts(b, q_b)))
+        .map(|(word, _q_a, _q_b)| word)
+}
+
+/// Breadth-first search of the product of `a` and `b` for the shortest word
+/// leading to a pair of states satisfying `found`, where `None` is the
+/// implicit dead state a missing transition leads to. Returns the word and
+/// the pair of states it leads to.
+pub(crate) fn product_search<S: Copy + Eq, F: Fn(Option<QId>, Option<QId>) -> bool>(
+    a: &DFA<S>,
+    b: &DFA<S>,
+    found: F,
+) -> Option<(Vec<S>, Option<QId>, Option<QId>)> {
+    let a_dead = a.delta.len();
+    let b_dead = b.delta.len();
+    let live = |q: QId, dead: QId| if q == dead { None } else { Some(q) };
+    let pair_id = |q_a: QId, q_b: QId| q_a * (b_dead + 1) + q_b;
+    let mut seen = vec![false; (a_dead + 1) * (b_dead + 1)];
+    let mut visited: Vec<Visit<S>> = Vec::new();
+    let mut queue = VecDeque::new();
+    seen[pair_id(a.q0, b.q0)] = true;
+    visited.push((a.q0, b.q0, None));
+    queue.push_back(0);
+    while let Some(i) = queue.pop_front() {
+        let (q_a, q_b, _parent) = visited[i];
+        if found(live(q_a, a_dead), live(q_b, b_dead)) {
+            let mut word = Vec::new();
+            let mut i = i;
+            while let (_, _, Some((parent, c))) = visited[i] {
+                word.push(c);
+                i = parent;
+            }
+            word.reverse();
+            return Some((word, live(q_a, a_dead), live(q_b, b_dead)));
+        }
+        let a_transitions = if q_a == a_dead {
+            &[][..]
+        } else {
+            &a.delta[q_a][..]
+        };
+        let b_transitions = if q_b == b_dead {
+            &[][..]
+        } else {
+            &b.delta[q_b][..]
+        };
+        for (c, _q_next) in a_transitions.iter().chain(b_transitions.iter()) {
+            let q_a_next = if q_a == a_dead {
+                a_dead
+            } else {
+                a.next_state(q_a, *c).unwrap_or(a_dead)
+            };
+            let q_b_next = if q_b == b_dead {
+                b_dead
+            } else {
+                b.next_state(q_b, *c).unwrap_or(b_dead)
+            };
+            if !seen[pair_id(q_a_next, q_b_next)] {
+                seen[pair_id(q_a_next, q_b_next)] = true;
+                visited.push((q_a_next, q_b_next, Some((i, *c))));
+                queue.push_back(visited.len() - 1);
+            }
+        }
+    }
+    None
+}
+
+impl<S: Copy + Eq> ANFA<S> {
+    /// Returns true when `a` and `b` recognize the same language.
+    /// Both machines are determinized and their product is searched for a
+    /// word accepted by only one of them. Each ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// use regexxx::ANFA;
+    /// // RE a*a
+    /// let mut a = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::star(&mut a).unwrap();
+    /// ForwardCompiler::expr_a(&mut a, 'a').unwrap();
+    /// ForwardCompiler::concatenate(&mut a).unwrap();
+    /// // RE aa*
+    /// let mut b = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut b, 'a').unwrap();
+    /// ForwardCompiler::star(&mut b).unwrap();
+    /// ForwardCompiler::concatenate(&mut b).unwrap();
+    /// assert!(ANFA::equivalent(&a, &b).unwrap());
+    /// ```
+    pub fn equivalent(a: &ANFA<S>, b: &ANFA<S>) -> Result<bool, &'static str> {
+        Ok(ANFA::counterexample(a, b)?.is_none())
+    }
+
+    /// Returns true when every word accepted by `a` is accepted by `b`.
+    /// Each ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// use regexxx::ANFA;
+    /// let a = ForwardCompiler::from_expr_a('a').unwrap();
+    /// let mut b = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::star(&mut b).unwrap();
+    /// assert!(ANFA::is_subset(&a, &b).unwrap());
+    /// assert!(!ANFA::is_subset(&b, &a).unwrap());
+    /// ```
+    pub fn is_subset(a: &ANFA<S>, b: &ANFA<S>) -> Result<bool, &'static str> {
+        let witness = product_witness(&a.determinize()?, &b.determinize()?, |a, b| a && !b);
+        Ok(witness.is_none())
+    }
+
+    /// Returns a shortest word accepted by exactly one of `a` and `b`,
+    /// or `None` when they are equivalent.
+    pub fn counterexample(a: &ANFA<S>, b: &ANFA<S>) -> Result<Option<Vec<S>>, &'static str> {
+        Ok(product_witness(
+            &a.determinize()?,
+            &b.determinize()?,
+            |a, b| a != b,
+        ))
+    }
+
+    /// Returns true when there is no path from q0 to f, i.e. the automaton
+    /// accepts nothing. The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert!(!machine.is_empty().unwrap());
+    /// ForwardCompiler::expr_0(&mut machine).unwrap();
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// assert!(machine.is_empty().unwrap()); // 'a' ⋅ 0 accepts nothing
+    /// ```
+    pub fn is_empty(&self) -> Result<bool, &'static str> {
+        Ok(self.shortest_accepted()?.is_none())
+    }
+
+    /// Returns true when the automaton accepts every word over `alphabet`.
+    /// Symbols outside of `alphabet` are ignored.
+    /// The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// // RE (a+b)*
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::union(&mut machine).unwrap();
+    /// ForwardCompiler::star(&mut machine).unwrap();
+    /// assert!(machine.is_universal(&['a', 'b']).unwrap());
+    /// assert!(!machine.is_universal(&['a', 'b', 'c']).unwrap());
+    /// ```
+    pub fn is_universal(&self, alphabet: &[S]) -> Result<bool, &'static str> {
+        let dfa = self.determinize()?;
+        // every reachable state must accept and read every symbol of the alphabet
+        let mut seen = vec![false; dfa.delta.len()];
+        let mut stack = vec![dfa.q0];
+        seen[dfa.q0] = true;
+        while let Some(q) = stack.pop() {
+            if !dfa.finals[q] {
+                return Ok(false);
+            }
+            for c in alphabet.iter() {
+                match dfa.next_state(q, *c) {
+                    None => return Ok(false),
+                    Some(q_next) => {
+                        if !seen[q_next] {
+                            seen[q_next] = true;
+                            stack.push(q_next);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns a shortest word accepted by the automaton, or `None` when it is empty.
+    /// Epsilon transitions are free and every labeled transition costs one symbol,
+    /// so the search is a 0-1 breadth-first search over `delta`.
+    /// The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// // RE a*b
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::star(&mut machine).unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// let word: String = machine.shortest_accepted().unwrap().unwrap().into_iter().collect();
+    /// assert_eq!(word, "b");
+    /// ```
+    pub fn shortest_accepted(&self) -> Result<Option<Vec<S>>, &'static str> {
+        let [machine_q0, machine_f] = match self.automata_refs.as_slice() {
+            [machine] => *machine,
+            _ => {
+                return Err("Analysis requires exactly one automaton.");
+            }
+        };
+        if !self.assertions.is_empty() {
+            return Err("Analysis does not support assertions.");
+        }
+        let mut distance: Vec<Option<usize>> = vec![None; self.delta.len()];
+        // the state and label each state was first reached from
+        let mut parent: Vec<Option<(QId, Option<S>)>> = vec![None; self.delta.len()];
+        let mut done = vec![false; self.delta.len()];
+        let mut deque = VecDeque::new();
+        distance[machine_q0] = Some(0);
+        deque.push_back(machine_q0);
+        while let Some(q) = deque.pop_front() {
+            if done[q] {
+                continue;
+            }
+            done[q] = true;
+            if q == machine_f {
+                let mut word = Vec::new();
+                let mut q = q;
+                while let Some((q_prev, label)) = parent[q] {
+                    if let Some(c) = label {
+                        word.push(c);
+                    }
+                    q = q_prev;
+                }
+                word.reverse();
+                return Ok(Some(word));
+            }
+            let d = distance[q].unwrap_or(0);
+            let (label, q_nexts) = &self.delta[q];
+            let cost = if label.is_some() { 1 } else { 0 };
+            for q_next in q_nexts.iter().flatten() {
+                if distance[*q_next].is_none_or(|d_next| d + cost < d_next) {
+                    distance[*q_next] = Some(d + cost);
+                    parent[*q_next] = Some((q, *label));
+                    if cost == 0 {
+                        deque.push_front(*q_next);
+                    } else {
+                        deque.push_back(*q_next);
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns a cheapest accepted word and its cost, or `None` when the
+    /// automaton is empty. Reading `c` costs `weight(c)` and epsilon
+    /// transitions are free, so the search is Dijkstra's algorithm over
+    /// `delta`. For the most likely word, weigh each symbol by its negative
+    /// log probability, scaled to an integer. Costs saturate at `u64::MAX`.
+    /// The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE (ab+c)d, where c is expensive
+    /// let machine = Expr::literal('a')
+    ///     .concat(Expr::literal('b'))
+    ///     .union(Expr::literal('c'))
+    ///     .concat(Expr::literal('d'))
+    ///     .build()
+    ///     .unwrap();
+    /// let weight = |c: char| if c == 'c' { 5 } else { 1 };
+    /// let (word, cost) = machine.shortest_weighted_word(weight).unwrap().unwrap();
+    /// assert_eq!((word.into_iter().collect::<String>(), cost), ("abd".into(), 3));
+    /// ```
+    pub fn shortest_weighted_word<F: Fn(S) -> u64>(
+        &self,
+        weight: F,
+    ) -> Result<Option<(Vec<S>, u64)>, &'static str> {
+        let [machine_q0, machine_f] = match self.automata_refs.as_slice() {
+            [machine] => *machine,
+            _ => {
+                return Err("Analysis requires exactly one automaton.");
+            }
+        };
+        if !self.assertions.is_empty() {
+            return Err("Analysis does not support assertions.");
+        }
+        let mut distance: Vec<Option<u64>> = vec![None; self.delta.len()];
+        // the state and label each state was last improved from
+        let mut parent: Vec<Option<(QId, Option<S>)>> = vec![None; self.delta.len()];
+        let mut done = vec![false; self.delta.len()];
+        let mut heap = BinaryHeap::new();
+        distance[machine_q0] = Some(0);
+        heap.push(Reverse((0, machine_q0)));
+        while let Some(Reverse((d, q))) = heap.pop() {
+            if done[q] {
+                continue;
+            }
+            done[q] = true;
+            if q == machine_f {
+                let mut word = Vec::new();
+                let mut q = q;
+                while let Some((q_prev, label)) = parent[q] {
+                    if let Some(c) = label {
+                        word.push(c);
+                    }
+                    q = q_prev;
+                }
+                word.reverse();
+                return Ok(Some((word, d)));
+            }
+            let (label, q_nexts) = &self.delta[q];
+            let cost = label.map_or(0, &weight);
+            for q_next in q_nexts.iter().flatten() {
+                let d_next = d.saturating_add(cost);
+                if distance[*q_next].is_none_or(|d_old| d_next < d_old) {
+                    distance[*q_next] = Some(d_next);
+                    parent[*q_next] = Some((q, *label));
+                    heap.push(Reverse((d_next, *q_next)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the `k` shortest accepted words, or all of them when there are
+    /// fewer, see `k_cheapest_words`
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE a*b
+    /// let machine = Expr::literal('a').star().concat(Expr::literal('b')).build().unwrap();
+    /// let words: Vec<String> = machine
+    ///     .k_shortest_words(3)
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .map(|word| word.into_iter().collect())
+    ///     .collect();
+    /// assert_eq!(words, vec!["b", "ab", "aab"]);
+    /// ```
+    pub fn k_shortest_words(&self, k: usize) -> Result<Vec<Vec<S>>, &'static str> {
+        Ok(self
+            .k_cheapest_words(k, |_c| 1)?
+            .into_iter()
+            .map(|(word, _cost)| word)
+            .collect())
+    }
+
+    /// Returns the `k` cheapest distinct accepted words with their costs,
+    /// cheapest first, or all of them when there are fewer. Reading `c` costs
+    /// `weight(c)`, see `shortest_weighted_word`. Equally cheap words are
+    /// ordered by length.
+    ///
+    /// Paths of the minimal DFA are distinct words, so they are searched
+    /// lazily with a heap, ordered by their cost so far plus the cheapest cost
+    /// to finish from their last state (A*). Every popped path can be
+    /// finished at exactly that cost, so words come out in order even when
+    /// some cycle is free. The ANFA must hold exactly one automaton.
+    pub fn k_cheapest_words<F: Fn(S) -> u64>(
+        &self,
+        k: usize,
+        weight: F,
+    ) -> Result<Vec<(Vec<S>, u64)>, &'static str> {
+        let dfa = self.determinize()?.minimize();
+        let mut words = Vec::new();
+        if k == 0 || !dfa.finals.iter().any(|f| *f) {
+            return Ok(words);
+        }
+        // cheapest cost from each state to an accepting state, by Dijkstra's
+        // algorithm backwards from the accepting states
+        let mut into: Vec<Vec<(u64, QId)>> = vec![Vec::new(); dfa.delta.len()];
+        for (q, transitions) in dfa.delta.iter().enumerate() {
+            for (c, q_next) in transitions.iter() {
+                into[*q_next].push((weight(*c), q));
+            }
+        }
+        let mut to_final: Vec<Option<u64>> = vec![None; dfa.delta.len()];
+        let mut heap = BinaryHeap::new();
+        for q in (0..dfa.delta.len()).filter(|q| dfa.finals[*q]) {
+            to_final[q] = Some(0);
+            heap.push(Reverse((0, q)));
+        }
+        while let Some(Reverse((d, q))) = heap.pop() {
+            if to_final[q].is_some_and(|d_best| d > d_best) {
+                continue;
+            }
+            for (cost, q_prev) in into[q].iter() {
+                let d_prev = d.saturating_add(*cost);
+                if to_final[*q_prev].is_none_or(|d_old| d_prev < d_old) {
+                    to_final[*q_prev] = Some(d_prev);
+                    heap.push(Reverse((d_prev, *q_prev)));
+                }
+            }
+        }
+
+        // (word, cost, last state) of every path found
+        let mut paths: Vec<(Vec<S>, u64, QId)> = vec![(Vec::new(), 0, dfa.q0)];
+        // (estimate, length, 0 for a finished word and 1 for a path, path)
+        let mut heap = BinaryHeap::new();
+        heap.push(Reverse((to_final[dfa.q0].unwrap_or(0), 0, 1, 0)));
+        while let Some(Reverse((_estimate, _length, unfinished, i))) = heap.pop() {
+            let (word, cost, q) = paths[i].clone();
+            if unfinished == 0 {
+                words.push((word, cost));
+                if words.len() == k {
+                    break;
+                }
+                continue;
+            }
+            if dfa.finals[q] {
+                heap.push(Reverse((cost, word.len(), 0, i)));
+            }
+            for (c, q_next) in dfa.delta[q].iter() {
+                let cost_next = cost.saturating_add(weight(*c));
+                let estimate = cost_next.saturating_add(to_final[*q_next].unwrap_or(0));
+                let mut word_next = word.clone();
+                word_next.push(*c);
+                heap.push(Reverse((estimate, word_next.len(), 1, paths.len())));
+                paths.push((word_next, cost_next, *q_next));
+            }
+        }
+        Ok(words)
+    }
+
+    /// Returns, for each position `i < n`, the symbols that appear at position
+    /// `i` of some accepted word. Each symbol is paired with the number of
+    /// distinct prefixes of accepted words that end with it at position `i`,
+    /// saturating at `usize::MAX`. Symbols are listed in order of first
+    /// appearance. Positions past the longest accepted word are empty.
+    /// The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE (a+b)c
+    /// let machine = Expr::literal('a').union(Expr::literal('b')).concat(Expr::literal('c')).build().unwrap();
+    /// let positions = machine.symbols_by_position(3).unwrap();
+    /// assert_eq!(positions[0], vec![('a', 1), ('b', 1)]);
+    /// assert_eq!(positions[1], vec![('c', 2)]); // "ac" and "bc"
+    /// assert!(positions[2].is_empty());
+    /// ```
+    pub fn symbols_by_position(&self, n: usize) -> Result<Vec<Vec<(S, usize)>>, &'static str> {
+        // every state of the minimal DFA reaches an accepting state, so every
+        // path from q0 is a prefix of an accepted word, and distinct paths are
+        // distinct prefixes
+        let dfa = self.determinize()?.minimize();
+        let mut positions = Vec::with_capacity(n);
+        // prefixes of the current length that lead to each state
+        let mut prefixes = vec![0usize; dfa.delta.len()];
+        prefixes[dfa.q0] = 1;
+        for _i in 0..n {
+            let mut symbols: Vec<(S, usize)> = Vec::new();
+            let mut next = vec![0usize; dfa.delta.len()];
+            for (q, count) in prefixes.iter().enumerate() {
+                if *count == 0 {
+                    continue;
+                }
+                for (label, q_next) in dfa.delta[q].iter() {
+                    match symbols.iter_mut().find(|(c, _)| c == label) {
+                        Some((_c, total)) => *total = total.saturating_add(*count),
+                        None => symbols.push((*label, *count)),
+                    }
+                    next[*q_next] = next[*q_next].saturating_add(*count);
+                }
+            }
+            positions.push(symbols);
+            prefixes = next;
+        }
+        Ok(positions)
+    }
+
+    /// Returns the length of the longest prefix of `input` that is also a
+    /// prefix of some accepted word, e.g. how much of a partially typed value
+    /// can still be completed into a valid one. Returns `None` when the
+    /// automaton accepts nothing, so not even the empty prefix can be
+    /// completed. The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE ab*c
+    /// let machine = Expr::literal('a')
+    ///     .concat(Expr::literal('b').star())
+    ///     .concat(Expr::literal('c'))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(machine.longest_accepted_prefix("abbxc".chars()).unwrap(), Some(3));
+    /// assert_eq!(machine.longest_accepted_prefix("abc".chars()).unwrap(), Some(3));
+    /// assert_eq!(machine.longest_accepted_prefix("b".chars()).unwrap(), Some(0));
+    /// ```
+    pub fn longest_accepted_prefix<I: IntoIterator<Item = S>>(
+        &self,
+        input: I,
+    ) -> Result<Option<usize>, &'static str> {
+        // every state of the minimal DFA reaches an accepting state, unless
+        // the language is empty
+        let dfa = self.determinize()?.minimize();
+        if !dfa.finals.iter().any(|f| *f) {
+            return Ok(None);
+        }
+        let mut q = dfa.q0;
+        let mut read = 0;
+        for c in input {
+            match dfa.next_state(q, c) {
+                Some(q_next) => q = q_next,
+                None => break,
+            }
+            read += 1;
+        }
+        Ok(Some(read))
+    }
+}
+
+impl ANFA {
+    /// Returns up to `k` accepted words starting with `prefix`, shortest
+    /// first and in char order among words of equal length, e.g. the
+    /// suggestions of an autocomplete. Returns nothing when `prefix` can't be
+    /// completed. The ANFA must hold exactly one automaton.
+    ///
+    /// Words of each length are enumerated depth first through the states
+    /// that reach an accepting state in exactly the remaining number of
+    /// symbols, so no dead end is explored. When the completions are finite
+    /// they are all shorter than the number of states.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE id(0+1)*
+    /// let machine = Expr::literal('i')
+    ///     .concat(Expr::literal('d'))
+    ///     .concat(Expr::literal('0').union(Expr::literal('1')).star())
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(machine.complete("i", 4).unwrap(), vec!["id", "id0", "id1", "id00"]);
+    /// assert!(machine.complete("x", 4).unwrap().is_empty());
+    /// ```
+    pub fn complete(&self, prefix: &str, k: usize) -> Result<Vec<String>, &'static str> {
+        let dfa = self.determinize()?.minimize();
+        let mut completions = Vec::new();
+        if !dfa.finals.iter().any(|f| *f) {
+            return Ok(completions);
+        }
+        let mut q0 = dfa.q0;
+        for c in prefix.chars() {
+            match dfa.next_state(q0, c) {
+                Some(q_next) => q0 = q_next,
+                None => return Ok(completions),
+            }
+        }
+        let mut delta = dfa.delta.clone();
+        for transitions in delta.iter_mut() {
+            transitions.sort_unstable();
+        }
+        // the completions are infinite when a cycle is reachable from q0,
+        // found by removing states without predecessors (Kahn's algorithm)
+        let mut reachable = vec![false; delta.len()];
+        let mut stack = vec![q0];
+        reachable[q0] = true;
+        while let Some(q) = stack.pop() {
+            for (_c, q_next) in delta[q].iter() {
+                if !reachable[*q_next] {
+                    reachable[*q_next] = true;
+                    stack.push(*q_next);
+                }
+            }
+        }
+        let mut predecessors = vec![0usize; delta.len()];
+        for q in (0..delta.len()).filter(|q| reachable[*q]) {
+            for (_c, q_next) in delta[q].iter() {
+                predecessors[*q_next] += 1;
+            }
+        }
+        let mut sources: Vec<QId> = (0..delta.len())
+            .filter(|q| reachable[*q] && predecessors[*q] == 0)
+            .collect();
+        let mut removed = 0;
+        while let Some(q) = sources.pop() {
+            removed += 1;
+            for (_c, q_next) in delta[q].iter() {
+                predecessors[*q_next] -= 1;
+                if predecessors[*q_next] == 0 {
+                    sources.push(*q_next);
+                }
+            }
+        }
+        let infinite = removed < reachable.iter().filter(|r| **r).count();
+
+        // exact[r][q] is true when q reaches an accepting state in exactly r symbols
+        let mut exact = vec![dfa.finals.clone()];
+        let mut length = 0;
+        while completions.len() < k && (infinite || length < delta.len()) {
+            if exact[length][q0] {
+                // (state, symbols read, next transition to try)
+                let mut path: Vec<(QId, Vec<char>, usize)> = vec![(q0, Vec::new(), 0)];
+                while let Some((q, word, i)) = path.pop() {
+                    if word.len() == length {
+                        let mut completion = String::from(prefix);
+                        completion.extend(word.iter());
+                        completions.push(completion);
+                        if completions.len() == k {
+                            break;
+                        }
+                        continue;
+                    }
+                    let remaining = length - word.len() - 1;
+                    let next = delta[q][i..]
+                        .iter()
+                        .position(|(_c, q_next)| exact[remaining][*q_next]);
+                    if let Some(offset) = next {
+                        let (c, q_next) = delta[q][i + offset];
+                        let mut longer = word.clone();
+                        longer.push(c);
+                        path.push((q, word, i + offset + 1));
+                        path.push((q_next, longer, 0));
+                    }
+                }
+            }
+            let longer = (0..delta.len())
+                .map(|q| delta[q].iter().any(|(_c, q_next)| exact[length][*q_next]))
+                .collect();
+            exact.push(longer);
+            length += 1;
+        }
+        Ok(completions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::expr::Expr;
+    use crate::ANFA;
+    use alloc::vec;
+
+    #[test]
+    fn test_equivalent() {
+        let a = Expr::parse("(a+b)*b").unwrap().build().unwrap();
+        let b = Expr::parse("(a*b)*a*b").unwrap().build().unwrap();
+        assert!(ANFA::equivalent(&a, &b).unwrap(), "(a+b)*b = (a*b)*a*b");
+        assert!(
+            ANFA::equivalent(&b, &a).unwrap(),
+            "Equivalence is symmetric"
+        );
+        let c = Expr::parse("(a+b)*").unwrap().build().unwrap();
+        assert!(!ANFA::equivalent(&a, &c).unwrap(), "(a+b)*b != (a+b)*");
+        assert_eq!(
+            ANFA::counterexample(&a, &c).unwrap(),
+            Some(vec![]),
+            "The empty word is the shortest word accepted by only (a+b)*"
+        );
+    }
+
+    #[test]
+    fn test_is_subset() {
+        let a = Expr::parse("(a+b)*b").unwrap().build().unwrap();
+        let c = Expr::parse("(a+b)*").unwrap().build().unwrap();
+        assert!(ANFA::is_subset(&a, &c).unwrap(), "(a+b)*b ⊆ (a+b)*");
+        assert!(!ANFA::is_subset(&c, &a).unwrap(), "(a+b)* ⊄ (a+b)*b");
+        let empty = Expr::parse("0").unwrap().build().unwrap();
+        assert!(
+            ANFA::is_subset(&empty, &a).unwrap(),
+            "∅ is a subset of everything"
+        );
+    }
+
+    #[test]
+    fn test_is_empty() {
+        let nothing = Expr::parse("0").unwrap().build().unwrap();
+        assert!(nothing.is_empty().unwrap(), "0 is empty");
+        let nothing_star = Expr::parse("0*").unwrap().build().unwrap();
+        assert!(
+            !nothing_star.is_empty().unwrap(),
+            "0* accepts the empty word"
+        );
+        assert!(!Expr::parse("(a+b)*b")
+            .unwrap()
+            .build()
+            .unwrap()
+            .is_empty()
+            .unwrap());
+        let mut unfinished = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut unfinished, 'b').unwrap();
+        assert!(unfinished.is_empty().is_err());
+    }
+
+    #[test]
+    fn test_is_universal() {
+        let epsilon = Expr::parse("1").unwrap().build().unwrap();
+        assert!(epsilon.is_universal(&[]).unwrap(), "1 is universal over ∅");
+        assert!(!epsilon.is_universal(&['a']).unwrap(), "1 rejects 'a'");
+        assert!(!Expr::parse("(a+b)*b")
+            .unwrap()
+            .build()
+            .unwrap()
+            .is_universal(&['a', 'b'])
+            .unwrap());
+    }
+
+    #[test]
+    fn test_shortest_accepted() {
+        assert_eq!(
+            Expr::parse("(a+b)*b")
+                .unwrap()
+                .build()
+                .unwrap()
+                .shortest_accepted()
+                .unwrap(),
+            Some(vec!['b']),
+            "Shortest word of (a+b)*b"
+        );
+        assert_eq!(
+            Expr::parse("(a*b)*a*b")
+                .unwrap()
+                .build()
+                .unwrap()
+                .shortest_accepted()
+                .unwrap(),
+            Some(vec!['b']),
+            "Shortest word of (a*b)*a*b"
+        );
+        let abc = Expr::parse("(ab)*c+d").unwrap().build().unwrap();
+        assert_eq!(
+            abc.shortest_accepted().unwrap(),
+            Some(vec!['d']),
+            "The shorter alternative of (ab)*c+d is chosen"
+        );
+        let epsilon = Expr::parse("1").unwrap().build().unwrap();
+        assert_eq!(epsilon.shortest_accepted().unwrap(), Some(vec![]));
+        let nothing = Expr::parse("0").unwrap().build().unwrap();
+        assert_eq!(nothing.shortest_accepted().unwrap(), None);
+    }
+
+    #[test]
+    fn test_shortest_weighted_word() {
+        let machine = Expr::parse("(a+b)*b").unwrap().build().unwrap();
+        let (word, cost) = machine.shortest_weighted_word(|_c| 1).unwrap().unwrap();
+        assert_eq!((word, cost), (vec!['b'], 1));
+        // b is dear, a is cheap, the word still ends in b
+        let (word, cost) = machine
+            .shortest_weighted_word(|c| if c == 'b' { 10 } else { 0 })
+            .unwrap()
+            .unwrap();
+        assert_eq!((word, cost), (vec!['b'], 10));
+        let (_word, cost) = machine
+            .shortest_weighted_word(|_c| u64::MAX)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cost, u64::MAX);
+        let nothing = Expr::parse("0").unwrap().build().unwrap();
+        assert_eq!(nothing.shortest_weighted_word(|_c| 1).unwrap(), None);
+        let mut two = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut two, 'b').unwrap();
+        assert!(two.shortest_weighted_word(|_c| 1).is_err());
+    }
+
+    #[test]
+    fn test_k_cheapest_words() {
+        let machine = Expr::parse("(a+b)*b").unwrap().build().unwrap();
+        let words = machine.k_shortest_words(7).unwrap();
+        assert_eq!(words.len(), 7);
+        assert!(words.windows(2).all(|pair| pair[0].len() <= pair[1].len()));
+        assert_eq!(words[0], vec!['b']);
+        assert_eq!(words[3..].iter().filter(|word| word.len() == 3).count(), 4);
+        // a is free, so every a^n b costs the same as b, shortest first
+        let words = machine
+            .k_cheapest_words(3, |c| if c == 'a' { 0 } else { 1 })
+            .unwrap();
+        assert_eq!(
+            words,
+            vec![
+                (vec!['b'], 1),
+                (vec!['a', 'b'], 1),
+                (vec!['a', 'a', 'b'], 1)
+            ]
+        );
+        // RE (a+b)(c+1) is finite
+        let finite = Expr::parse("(a+b)(c+1)").unwrap().build().unwrap();
+        let words = finite
+            .k_cheapest_words(10, |c| if c == 'a' { 5 } else { 1 })
+            .unwrap();
+        assert_eq!(
+            words,
+            vec![
+                (vec!['b'], 1),
+                (vec!['b', 'c'], 2),
+                (vec!['a'], 5),
+                (vec!['a', 'c'], 6)
+            ]
+        );
+        assert!(finite.k_shortest_words(0).unwrap().is_empty());
+        let nothing = Expr::parse("0").unwrap().build().unwrap();
+        assert!(nothing.k_shortest_words(3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_symbols_by_position() {
+        let positions = Expr::parse("(a+b)*b")
+            .unwrap()
+            .build()
+            .unwrap()
+            .symbols_by_position(3)
+            .unwrap();
+        assert_eq!(positions[0], vec![('a', 1), ('b', 1)]);
+        assert_eq!(
+            positions[1],
+            vec![('a', 2), ('b', 2)],
+            "Every prefix of (a+b)*b extends with either symbol"
+        );
+        assert_eq!(positions[2], vec![('a', 4), ('b', 4)]);
+        let abc = Expr::parse("(ab+c)d").unwrap().build().unwrap();
+        assert_eq!(
+            abc.symbols_by_position(4).unwrap(),
+            vec![
+                vec![('a', 1), ('c', 1)],
+                vec![('b', 1), ('d', 1)],
+                vec![('d', 1)],
+                vec![]
+            ],
+            "(ab+c)d"
+        );
+        let nothing = Expr::parse("0").unwrap().build().unwrap();
+        assert_eq!(
+            nothing.symbols_by_position(2).unwrap(),
+            vec![vec![], vec![]]
+        );
+        let dead_end = Expr::parse("a0+b").unwrap().build().unwrap();
+        assert_eq!(
+            dead_end.symbols_by_position(1).unwrap(),
+            vec![vec![('b', 1)]],
+            "'a' only leads to a0, which accepts nothing"
+        );
+    }
+
+    #[test]
+    fn test_longest_accepted_prefix() {
+        let machine = Expr::parse("(a+b)*b").unwrap().build().unwrap();
+        assert_eq!(
+            machine.longest_accepted_prefix("abab".chars()).unwrap(),
+            Some(4)
+        );
+        assert_eq!(
+            machine.longest_accepted_prefix("abca".chars()).unwrap(),
+            Some(2)
+        );
+        assert_eq!(machine.longest_accepted_prefix(vec![]).unwrap(), Some(0));
+        // RE a0+b, 'a' can't be completed
+        let dead_end = Expr::parse("a0+b").unwrap().build().unwrap();
+        assert_eq!(
+            dead_end.longest_accepted_prefix("a".chars()).unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            dead_end.longest_accepted_prefix("bb".chars()).unwrap(),
+            Some(1)
+        );
+        let nothing = Expr::parse("0").unwrap().build().unwrap();
+        assert_eq!(nothing.longest_accepted_prefix("".chars()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_complete() {
+        let machine = Expr::parse("(a+b)*b").unwrap().build().unwrap();
+        assert_eq!(
+            machine.complete("", 5).unwrap(),
+            vec!["b", "ab", "bb", "aab", "abb"]
+        );
+        assert_eq!(machine.complete("ba", 2).unwrap(), vec!["bab", "baab"]);
+        assert_eq!(machine.complete("b", 1).unwrap(), vec!["b"]);
+        assert!(machine.complete("c", 3).unwrap().is_empty());
+        assert!(machine.complete("", 0).unwrap().is_empty());
+        // RE (a+b)(c+1), finite, so fewer than k
+        let finite = Expr::parse("(b+a)(c+1)").unwrap().build().unwrap();
+        assert_eq!(finite.complete("", 10).unwrap(), vec!["a", "b", "ac", "bc"]);
+        let nothing = Expr::parse("0").unwrap().build().unwrap();
+        assert!(nothing.complete("", 3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_requires_one_automaton() {
+        let a = Expr::parse("(a+b)*b").unwrap().build().unwrap();
+        let mut b = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut b, 'b').unwrap();
+        assert!(ANFA::equivalent(&a, &b).is_err());
+        assert!(ANFA::is_subset(&b, &a).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use crate::assertion::Assertion;
+use crate::expr::Expr;
+use crate::search::SearchOptions;
+use crate::ANFA;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+
+/// A finding of `Anchoring::infer` about where the matches of a pattern can
+/// start or end
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Diagnostic {
+    /// Every match starts with `^`, so only the start of the haystack needs
+    /// to be searched. The `^` was rewritten into `SearchOptions::anchored_start`.
+    AnchoredStart,
+    /// Every match ends with `$`. The `$` was rewritten into
+    /// `SearchOptions::anchored_end`.
+    AnchoredEnd,
+    /// The pattern is unanchored and starts with a star, e.g. `(a+b)*c`.
+    /// Each match then reaches back over every char the star accepts, and
+    /// the DFA engines, which try each start position in turn, read on from
+    /// every one of them. It is kept, since dropping it changes the spans
+    /// found, but `is_match` gives the same answer without it.
+    LeadingStar,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Diagnostic::AnchoredStart => "pattern is anchored at the start, searching from 0 only",
+            Diagnostic::AnchoredEnd => "pattern is anchored at the end, matching up to the end only",
+            Diagnostic::LeadingStar => {
+                "pattern starts with an unanchored star, which only widens matches and slows searches"
+            }
+        })
+    }
+}
+
+/// A pattern rewritten for searching, with the options that keep its matches
+/// and the diagnostics found on the way.
+///
+/// Only the chain of top-level concatenations is inspected: a `^` first in
+/// the chain or a `$` last in it is rewritten, and a star first in it is
+/// reported. Rewritten patterns report the same match with `find_with` and
+/// `is_match_with` as the original with `find` and `is_match`, i.e. outside
+/// multi-line mode, but not with `find_iter_with`, whose later searches
+/// anchor where they start instead of at 0.
+///
+/// ```rust
+/// use regexxx::anchoring::{Anchoring, Diagnostic};
+/// use regexxx::expr::Expr;
+/// let anchoring = Anchoring::infer(&Expr::parse("^ab$").unwrap());
+/// assert_eq!(anchoring.diagnostics, vec![Diagnostic::AnchoredStart, Diagnostic::AnchoredEnd]);
+/// assert_eq!(anchoring.expr, Expr::parse("ab").unwrap());
+/// let machine = anchoring.expr.build().unwrap();
+/// assert_eq!(machine.find_with("ab", &anchoring.options).unwrap(), Some((0, 2)));
+/// assert_eq!(machine.find_with("xab", &anchoring.options).unwrap(), None);
+///
+/// let anchoring = Anchoring::infer(&Expr::parse("(a+b)*c").unwrap());
+/// assert_eq!(anchoring.diagnostics, vec![Diagnostic::LeadingStar]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Anchoring {
+    pub expr: Expr<char>,
+    pub options: SearchOptions,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Anchoring {
+    /// Analyzes and rewrites `expr`, see `Anchoring`
+    pub fn infer(expr: &Expr<char>) -> Anchoring {
+        // the operands of the top-level concatenations, left to right
+        let mut operands: Vec<&Expr<char>> = Vec::new();
+        let mut stack = vec![expr];
+        while let Some(expr) = stack.pop() {
+            match expr {
+                Expr::Concat(a, b) => {
+                    stack.push(b);
+                    stack.push(a);
+                }
+                Expr::One => {}
+                operand => operands.push(operand),
+            }
+        }
+        let mut options = SearchOptions::default();
+        let mut diagnostics = Vec::new();
+        if let Some(Expr::Assert(Assertion::StartText)) = operands.first() {
+            operands.remove(0);
+            options.anchored_start = true;
+            diagnostics.push(Diagnostic::AnchoredStart);
+        }
+        if let Some(Expr::Assert(Assertion::EndText)) = operands.last() {
+            operands.pop();
+            options.anchored_end = true;
+            diagnostics.push(Diagnostic::AnchoredEnd);
+        }
+        if let (false, Some(Expr::Star(_))) = (options.anchored_start, operands.first()) {
+            diagnostics.push(Diagnostic::LeadingStar);
+        }
+        let expr = operands
+            .into_iter()
+            .cloned()
+            .reduce(Expr::concat)
+            .unwrap_or(Expr::One);
+        Anchoring {
+            expr,
+            options,
+            diagnostics,
+        }
+    }
+
+    /// Compiles the rewritten pattern, calling `warn` with each diagnostic,
+    /// e.g. to log it where patterns are loaded
+    ///
+    /// ```rust
+    /// use regexxx::anchoring::Anchoring;
+    /// use regexxx::expr::Expr;
+    /// let mut warnings = Vec::new();
+    /// let anchoring = Anchoring::infer(&Expr::parse("(a+b)*c").unwrap());
+    /// let machine = anchoring.build(|diagnostic| warnings.push(diagnostic.to_string())).unwrap();
+    /// assert!(machine.is_match("abc").unwrap());
+    /// assert_eq!(warnings.len(), 1);
+    /// ```
+    pub fn build<F: FnMut(Diagnostic)>(&self, mut warn: F) -> Result<ANFA, &'static str> {
+        for diagnostic in self.diagnostics.iter() {
+            warn(*diagnostic);
+        }
+        self.expr.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::anchoring::{Anchoring, Diagnostic};
+    use crate::expr::Expr;
+    use crate::search::SearchOptions;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_same_matches() {
+        let haystacks = ["", "ab", "xab", "abx", "aab", "ab\nab", "b"];
+        for pattern in [
+            "^ab$", "^a*b", "a*b$", "^$", "^", "(^a)b", "^(ab)*", "^a+b$", "a(b$)",
+        ]
+        .iter()
+        {
+            let original = Expr::parse(pattern).unwrap().build().unwrap();
+            let anchoring = Anchoring::infer(&Expr::parse(pattern).unwrap());
+            let machine = anchoring.build(|_diagnostic| {}).unwrap();
+            for haystack in haystacks.iter() {
+                assert_eq!(
+                    machine.find_with(haystack, &anchoring.options).unwrap(),
+                    original.find(haystack).unwrap(),
+                    "{} in {:?}",
+                    pattern,
+                    haystack
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let diagnostics =
+            |pattern: &str| Anchoring::infer(&Expr::parse(pattern).unwrap()).diagnostics;
+        assert_eq!(diagnostics("ab"), vec![]);
+        assert_eq!(diagnostics("^a*b"), vec![Diagnostic::AnchoredStart]);
+        assert_eq!(
+            diagnostics("a*b$"),
+            vec![Diagnostic::AnchoredEnd, Diagnostic::LeadingStar]
+        );
+        assert_eq!(diagnostics("(a*b)c"), vec![Diagnostic::LeadingStar]);
+        // only the top-level chain is inspected
+        assert_eq!(diagnostics("^a+^b"), vec![]);
+        assert_eq!(diagnostics("a(b$)"), vec![Diagnostic::AnchoredEnd]);
+        let anchoring = Anchoring::infer(&Expr::parse("^$").unwrap());
+        assert_eq!(anchoring.expr, Expr::one());
+        assert_eq!(
+            anchoring.options,
+            SearchOptions {
+                anchored_start: true,
+                anchored_end: true,
+                ..SearchOptions::default()
+            }
+        );
+        let mut warnings = Vec::new();
+        anchoring
+            .build(|diagnostic| warnings.push(diagnostic))
+            .unwrap();
+        assert_eq!(warnings, anchoring.diagnostics);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::ANFA;
+use core::fmt::{self, Display};
+
+/// A zero-width condition on the position between two symbols of the input.
+/// Assertions guard states: a state in `ANFA.assertions` may only be entered
+/// at positions where its assertion holds. See `Compiler::expr_assert`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Assertion {
+    /// `^`, the start of the input
+    StartText,
+    /// `$`, the end of the input
+    EndText,
+    /// `\b`, between a word character and a non-word character, see
+    /// `WordKind::Unicode`
+    WordBoundary,
+    /// `\B`, anywhere `\b` does not hold
+    NotWordBoundary,
+    /// `(?-u:\b)`, like `\b` with only ASCII word characters, see
+    /// `WordKind::Ascii`
+    AsciiWordBoundary,
+    /// `(?-u:\B)`, anywhere `(?-u:\b)` does not hold
+    AsciiNotWordBoundary,
+}
+
+/// Which characters are word characters for `\b` and `\B`. Both definitions
+/// depend only on the character, never on a locale.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum WordKind {
+    /// Characters with the Unicode `Alphabetic` or `Numeric` property, see
+    /// `char::is_alphanumeric`, and `_`
+    #[default]
+    Unicode,
+    /// `[0-9A-Za-z_]`
+    Ascii,
+}
+
+impl WordKind {
+    /// Returns true when `c` is a word character of this kind
+    ///
+    /// ```rust
+    /// use regexxx::assertion::WordKind;
+    /// assert!(WordKind::Unicode.is_word('é'));
+    /// assert!(!WordKind::Ascii.is_word('é'));
+    /// assert!(WordKind::Ascii.is_word('_'));
+    /// ```
+    pub fn is_word(self, c: char) -> bool {
+        match self {
+            WordKind::Unicode => c.is_alphanumeric() || c == '_',
+            WordKind::Ascii => c.is_ascii_alphanumeric() || c == '_',
+        }
+    }
+}
+
+/// Whether `c` is a word character, where `None` is the start or end of the input
+fn is_word(kind: WordKind, c: Option<char>) -> bool {
+    c.is_some_and(|c| kind.is_word(c))
+}
+
+impl Assertion {
+    /// Returns true when the assertion holds between `before` and `after`,
+    /// where `None` is the start or end of the input.
+    ///
+    /// ```rust
+    /// use regexxx::assertion::Assertion;
+    /// assert!(Assertion::StartText.holds(None, Some('a')));
+    /// assert!(Assertion::WordBoundary.holds(Some('a'), Some(' ')));
+    /// assert!(!Assertion::WordBoundary.holds(Some('a'), Some('b')));
+    /// ```
+    pub fn holds(self, before: Option<char>, after: Option<char>) -> bool {
+        match self {
+            Assertion::StartText => before.is_none(),
+            Assertion::EndText => after.is_none(),
+            Assertion::WordBoundary | Assertion::AsciiWordBoundary => {
+                let kind = self.word_kind().unwrap_or_default();
+                is_word(kind, before) != is_word(kind, after)
+            }
+            Assertion::NotWordBoundary | Assertion::AsciiNotWordBoundary => {
+                let kind = self.word_kind().unwrap_or_default();
+                is_word(kind, before) == is_word(kind, after)
+            }
+        }
+    }
+
+    /// The word characters a word boundary assertion tests, or `None` for
+    /// `^` and `$`
+    pub fn word_kind(self) -> Option<WordKind> {
+        match self {
+            Assertion::StartText | Assertion::EndText => None,
+            Assertion::WordBoundary | Assertion::NotWordBoundary => Some(WordKind::Unicode),
+            Assertion::AsciiWordBoundary | Assertion::AsciiNotWordBoundary => Some(WordKind::Ascii),
+        }
+    }
+
+    /// The same assertion testing word characters of `kind`. `^` and `$`
+    /// are returned unchanged.
+    ///
+    /// ```rust
+    /// use regexxx::assertion::{Assertion, WordKind};
+    /// assert_eq!(Assertion::WordBoundary.with_word_kind(WordKind::Ascii), Assertion::AsciiWordBoundary);
+    /// ```
+    pub fn with_word_kind(self, kind: WordKind) -> Assertion {
+        match (self, kind) {
+            (Assertion::WordBoundary, WordKind::Ascii) => Assertion::AsciiWordBoundary,
+            (Assertion::NotWordBoundary, WordKind::Ascii) => Assertion::AsciiNotWordBoundary,
+            (Assertion::AsciiWordBoundary, WordKind::Unicode) => Assertion::WordBoundary,
+            (Assertion::AsciiNotWordBoundary, WordKind::Unicode) => Assertion::NotWordBoundary,
+            (assertion, _) => assertion,
+        }
+    }
+
+    /// Like `holds`, except `^` also holds after a `\n` and `$` before one,
+    /// i.e. they match at the start and end of every line
+    ///
+    /// ```rust
+    /// use regexxx::assertion::Assertion;
+    /// assert!(Assertion::StartText.holds_multi_line(Some('\n'), Some('a')));
+    /// assert!(!Assertion::StartText.holds(Some('\n'), Some('a')));
+    /// ```
+    pub fn holds_multi_line(self, before: Option<char>, after: Option<char>) -> bool {
+        match self {
+            Assertion::StartText => before.is_none_or(|c| c == '\n'),
+            Assertion::EndText => after.is_none_or(|c| c == '\n'),
+            assertion => assertion.holds(before, after),
+        }
+    }
+
+    /// The assertion that holds at the same position of the reversed input:
+    /// the start and end of the input swap, word boundaries are symmetric.
+    pub fn reversed(self) -> Assertion {
+        match self {
+            Assertion::StartText => Assertion::EndText,
+            Assertion::EndText => Assertion::StartText,
+            assertion => assertion,
+        }
+    }
+}
+
+impl Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Assertion::StartText => "^",
+            Assertion::EndText => "$",
+            Assertion::WordBoundary => "\\b",
+            Assertion::NotWordBoundary => "\\B",
+            Assertion::AsciiWordBoundary => "(?-u:\\b)",
+            Assertion::AsciiNotWordBoundary => "(?-u:\\B)",
+        })
+    }
+}
+
+impl<S> ANFA<S> {
+    /// Switches every word boundary assertion of the ANFA to word characters
+    /// of `kind`, like compiling the whole pattern with or without `(?-u)`.
+    /// The choice is stored in the automaton, so every engine built from it
+    /// agrees, see `Assertion::with_word_kind`.
+    ///
+    /// ```rust
+    /// use regexxx::assertion::{Assertion, WordKind};
+    /// use regexxx::expr::Expr;
+    /// // RE \ba
+    /// let mut machine = Expr::assert(Assertion::WordBoundary).concat(Expr::literal('a')).build().unwrap();
+    /// assert!(!machine.is_match("éa").unwrap());
+    /// machine.set_word_kind(WordKind::Ascii);
+    /// assert!(machine.is_match("éa").unwrap());
+    /// ```
+    pub fn set_word_kind(&mut self, kind: WordKind) {
+        for assertion in self.assertions.values_mut() {
+            *assertion = assertion.with_word_kind(kind);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use crate::search::{MatchKind, SearchOptions, Threads};
+use crate::{QId, ANFA};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A searcher for many small haystacks, e.g. millions of short strings, with
+/// the same machine and options.
+///
+/// `ANFA::find_with` prepares the labels it reads and its thread lists anew
+/// for every haystack, which dominates the cost on short haystacks. A `Batch`
+/// prepares them once and reuses them for every search. It also keeps a
+/// prefilter: the chars a match can start with. A haystack without any of
+/// them is rejected without simulating the automaton, and otherwise the
+/// simulation starts at the first of them.
+///
+/// ```rust
+/// use regexxx::batch::Batch;
+/// use regexxx::expr::Expr;
+/// use regexxx::search::SearchOptions;
+/// // RE ab*
+/// let machine = Expr::literal('a').concat(Expr::literal('b').star()).build().unwrap();
+/// let mut batch = Batch::new(&machine, &SearchOptions::default()).unwrap();
+/// let mut found = Vec::new();
+/// batch.scan(["xyz", "xabb", "bba"], |i, span| found.push((i, span)));
+/// assert_eq!(found, vec![(1, (1, 4)), (2, (2, 3))]);
+/// assert_eq!(batch.skipped(), 1);
+/// ```
+pub struct Batch<'a> {
+    anfa: &'a ANFA,
+    machine: [QId; 2],
+    options: SearchOptions,
+    /// every symbol each labeled state reads
+    labels: Vec<Vec<char>>,
+    /// the chars a match may start with, sorted, or `None` when the empty
+    /// word may match
+    first: Option<Vec<char>>,
+    current: Threads,
+    next: Threads,
+    /// haystacks rejected by the prefilter
+    skipped: u64,
+}
+
+impl<'a> Batch<'a> {
+    /// Prepares searches of `anfa` with `options`. The ANFA must hold
+    /// exactly one automaton.
+    pub fn new(anfa: &'a ANFA, options: &SearchOptions) -> Result<Batch<'a>, &'static str> {
+        let machine = match anfa.automata_refs.as_slice() {
+            [machine] => *machine,
+            _ => {
+                return Err("Search requires exactly one automaton.");
+            }
+        };
+        let labels = anfa.search_labels(options);
+        let first = first_chars(anfa, machine, &labels);
+        Ok(Batch {
+            anfa,
+            machine,
+            options: *options,
+            labels,
+            first,
+            current: Threads::new(anfa.delta.len()),
+            next: Threads::new(anfa.delta.len()),
+            skipped: 0,
+        })
+    }
+
+    /// Where the simulation may start in `haystack`, or `None` when the
+    /// prefilter rules out a match
+    fn candidate(&mut self, haystack: &str) -> Option<usize> {
+        let first = match &self.first {
+            Some(first) => first,
+            None => return Some(0),
+        };
+        let candidate = if self.options.anchored_start {
+            haystack
+                .chars()
+                .next()
+                .filter(|c| first.binary_search(c).is_ok())
+                .map(|_c| 0)
+        } else {
+            haystack
+                .char_indices()
+                .find(|(_at, c)| first.binary_search(c).is_ok())
+                .map(|(at, _c)| at)
+        };
+        if candidate.is_none() {
+            self.skipped += 1;
+        }
+        candidate
+    }
+
+    fn search(&mut self, haystack: &str, options: &SearchOptions) -> Option<(usize, usize)> {
+        let start = self.candidate(haystack)?;
+        self.anfa.search(
+            self.machine,
+            haystack,
+            start,
+            options,
+            &self.labels,
+            &mut self.current,
+            &mut self.next,
+        )
+    }
+
+    /// Returns the match `ANFA::find_with` would report in `haystack`
+    pub fn find(&mut self, haystack: &str) -> Option<(usize, usize)> {
+        let options = self.options;
+        self.search(haystack, &options)
+    }
+
+    /// Returns true when `haystack` has a match, stopping at the first one
+    pub fn is_match(&mut self, haystack: &str) -> bool {
+        let earliest = SearchOptions {
+            match_kind: MatchKind::Earliest,
+            ..self.options
+        };
+        self.search(haystack, &earliest).is_some()
+    }
+
+    /// Searches every haystack in turn, calling `f` with the index of each
+    /// haystack that has a match and the match
+    pub fn scan<'h, I, F>(&mut self, haystacks: I, mut f: F)
+    where
+        I: IntoIterator<Item = &'h str>,
+        F: FnMut(usize, (usize, usize)),
+    {
+        for (i, haystack) in haystacks.into_iter().enumerate() {
+            if let Some(span) = self.find(haystack) {
+                f(i, span);
+            }
+        }
+    }
+
+    /// The number of haystacks rejected by the prefilter so far
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+/// The sorted chars read by the labeled states of the ε-closure of q0, or
+/// `None` when f is in the closure. Assertions are assumed to hold, so the
+/// set may be larger than needed but never misses a char.
+fn first_chars(anfa: &ANFA, machine: [QId; 2], labels: &[Vec<char>]) -> Option<Vec<char>> {
+    let [q0, f] = machine;
+    let mut first = Vec::new();
+    let mut visited = vec![false; anfa.delta.len()];
+    let mut stack = vec![q0];
+    while let Some(q) = stack.pop() {
+        if visited[q] {
+            continue;
+        }
+        visited[q] = true;
+        if q == f {
+            return None;
+        }
+        match anfa.delta[q] {
+            (Some(_), _) => first.extend(labels[q].iter().copied()),
+            (None, q_nexts) => stack.extend(q_nexts.iter().flatten().copied()),
+        }
+    }
+    first.sort_unstable();
+    first.dedup();
+    Some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::batch::Batch;
+    use crate::expr::Expr;
+    use crate::search::{MatchKind, SearchOptions};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_agrees_with_find() {
+        // RE \b(a+b)c*, and RE (a+b)* which matches the empty word
+        let expr = Expr::assert(Assertion::WordBoundary)
+            .concat(Expr::literal('a').union(Expr::literal('b')))
+            .concat(Expr::literal('c').star());
+        let machines = [
+            expr.build().unwrap(),
+            Expr::literal('a')
+                .union(Expr::literal('b'))
+                .star()
+                .build()
+                .unwrap(),
+        ];
+        let haystacks = ["", "xyz", "acc", "xAC b", "zb", "ccbc", "é a"];
+        for machine in machines.iter() {
+            for options in [
+                SearchOptions::default(),
+                SearchOptions {
+                    case_insensitive: true,
+                    match_kind: MatchKind::LeftmostLongest,
+                    ..SearchOptions::default()
+                },
+                SearchOptions {
+                    anchored_start: true,
+                    ..SearchOptions::default()
+                },
+                SearchOptions {
+                    anchored_end: true,
+                    match_kind: MatchKind::Earliest,
+                    ..SearchOptions::default()
+                },
+            ]
+            .iter()
+            {
+                let mut batch = Batch::new(machine, options).unwrap();
+                // twice, so searches see the state left by earlier ones
+                for haystack in haystacks.iter().chain(haystacks.iter()) {
+                    let expected = machine.find_with(haystack, options).unwrap();
+                    assert_eq!(batch.find(haystack), expected, "{}", haystack);
+                    assert_eq!(batch.is_match(haystack), expected.is_some());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_prefilter() {
+        // RE (a+b)c
+        let machine = Expr::literal('a')
+            .union(Expr::literal('b'))
+            .concat(Expr::literal('c'))
+            .build()
+            .unwrap();
+        let mut batch = Batch::new(&machine, &SearchOptions::default()).unwrap();
+        let mut found = Vec::new();
+        batch.scan(vec!["xyz", "zzbc", "c", "ab"], |i, span| {
+            found.push((i, span))
+        });
+        assert_eq!(found, vec![(1, (2, 4))]);
+        assert_eq!(batch.skipped(), 2);
+        let anchored = SearchOptions {
+            anchored_start: true,
+            ..SearchOptions::default()
+        };
+        let mut batch = Batch::new(&machine, &anchored).unwrap();
+        assert_eq!(batch.find("xbc"), None);
+        assert_eq!(batch.skipped(), 1);
+        let mut unfinished = Expr::literal('a').build().unwrap();
+        unfinished.automata_refs.clear();
+        assert!(Batch::new(&unfinished, &anchored).is_err());
+    }
+}
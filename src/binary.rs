@@ -0,0 +1,238 @@
+use crate::expr::Expr;
+use alloc::vec::Vec;
+
+/// The bytes whose `mask` bits equal those of `value`, e.g. any byte for
+/// mask 0, the byte `value` for mask `0xff`, or `0x40..=0x4f` for value
+/// `0x40` and mask `0xf0`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ByteMask {
+    pub value: u8,
+    pub mask: u8,
+}
+
+impl ByteMask {
+    /// Exactly `byte`
+    pub fn byte(byte: u8) -> ByteMask {
+        ByteMask {
+            value: byte,
+            mask: 0xff,
+        }
+    }
+
+    /// Any byte
+    pub fn any() -> ByteMask {
+        ByteMask { value: 0, mask: 0 }
+    }
+
+    pub fn matches(&self, byte: u8) -> bool {
+        byte & self.mask == self.value & self.mask
+    }
+
+    /// The bytes matched, in increasing order
+    pub fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=u8::MAX).filter(move |byte| self.matches(*byte))
+    }
+
+    /// Expands the mask into the union of a literal per byte matched. Labels
+    /// are single bytes, there are no labels for sets of bytes, so a mask
+    /// with `k` bits clear compiles to `2^k` literals and about `4 * 2^k`
+    /// states, e.g. about a thousand for any byte.
+    pub fn to_expr(&self) -> Expr<u8> {
+        self.bytes()
+            .map(Expr::literal)
+            .reduce(Expr::union)
+            .unwrap_or_else(Expr::zero)
+    }
+}
+
+/// The concatenation of `masks`, accepting every byte string of their length
+/// that they match position by position. Each mask is expanded as by
+/// `ByteMask::to_expr`, so the states grow with the bytes every mask
+/// matches, not only with the number of masks.
+pub fn to_expr(masks: &[ByteMask]) -> Expr<u8> {
+    masks
+        .iter()
+        .map(ByteMask::to_expr)
+        .reduce(Expr::concat)
+        .unwrap_or_else(Expr::one)
+}
+
+fn nibble(c: char) -> Result<Option<u8>, &'static str> {
+    match c {
+        '?' => Ok(None),
+        c => match c.to_digit(16) {
+            Some(digit) => Ok(Some(digit as u8)),
+            None => Err("Expected a hex digit or '?'."),
+        },
+    }
+}
+
+/// Two hex digits, either of which may be `?` for any nibble
+fn hex_byte(high: char, low: char) -> Result<ByteMask, &'static str> {
+    let (high, low) = (nibble(high)?, nibble(low)?);
+    Ok(ByteMask {
+        value: (high.unwrap_or(0) << 4) | low.unwrap_or(0),
+        mask: (if high.is_some() { 0xf0 } else { 0 }) | if low.is_some() { 0x0f } else { 0 },
+    })
+}
+
+/// Parses a signature written as hex bytes, e.g. `7F 45 4C 46 ?? 0?`. Bytes
+/// are pairs of hex digits in either case, optionally separated by
+/// whitespace, and `?` stands for any nibble, so `??` is any byte and `4?`
+/// any of `0x40..=0x4f`.
+///
+/// ```rust
+/// use regexxx::binary::{self, ByteMask};
+/// let masks = binary::parse_hex("7F 45 ?? 4?").unwrap();
+/// assert_eq!(masks[2], ByteMask::any());
+/// assert_eq!(masks[3], ByteMask { value: 0x40, mask: 0xf0 });
+/// let machine = binary::to_expr(&masks).build().unwrap();
+/// let nfa = machine.remove_epsilon().unwrap();
+/// assert!(nfa.accepts(vec![0x7f, 0x45, 0x00, 0x4c]));
+/// assert!(!nfa.accepts(vec![0x7f, 0x45, 0x00, 0x5c]));
+/// ```
+pub fn parse_hex(pattern: &str) -> Result<Vec<ByteMask>, &'static str> {
+    let mut masks = Vec::new();
+    let mut digits = pattern.chars().filter(|c| !c.is_whitespace());
+    while let Some(high) = digits.next() {
+        match digits.next() {
+            Some(low) => masks.push(hex_byte(high, low)?),
+            None => return Err("Hex bytes require two digits."),
+        }
+    }
+    Ok(masks)
+}
+
+/// Parses a signature written as text with escapes, e.g. `\x7fELF`. Every
+/// char is its UTF-8 bytes, except escapes:
+///
+/// - `\xHH` is a byte given as two hex digits, either of which may be `?`
+///   for any nibble, see `parse_hex`
+/// - `\\` and `\?` are `\` and `?`
+/// - `?` alone is any byte
+///
+/// ```rust
+/// use regexxx::binary;
+/// let nfa = binary::to_expr(&binary::parse_escaped(r"\x7fELF\x0?").unwrap())
+///     .build()
+///     .unwrap()
+///     .remove_epsilon()
+///     .unwrap();
+/// assert!(nfa.accepts(b"\x7fELF\x02".iter().copied()));
+/// assert!(!nfa.accepts(b"\x7fELF\x12".iter().copied()));
+/// ```
+pub fn parse_escaped(pattern: &str) -> Result<Vec<ByteMask>, &'static str> {
+    let mut masks = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('x') => match (chars.next(), chars.next()) {
+                    (Some(high), Some(low)) => masks.push(hex_byte(high, low)?),
+                    _ => return Err("Hex escapes require two digits."),
+                },
+                Some(c @ '\\') | Some(c @ '?') => masks.push(ByteMask::byte(c as u8)),
+                _ => return Err("Unknown escape."),
+            },
+            '?' => masks.push(ByteMask::any()),
+            c => {
+                let mut buffer = [0; 4];
+                masks.extend(c.encode_utf8(&mut buffer).bytes().map(ByteMask::byte));
+            }
+        }
+    }
+    Ok(masks)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binary::{self, parse_escaped, parse_hex, ByteMask};
+    use crate::signatures::Signatures;
+    use alloc::vec;
+
+    #[test]
+    fn test_byte_mask() {
+        assert_eq!(ByteMask::any().bytes().count(), 256);
+        assert_eq!(
+            ByteMask::byte(7).bytes().collect::<alloc::vec::Vec<_>>(),
+            vec![7]
+        );
+        let low = ByteMask {
+            value: 0xab,
+            mask: 0x0f,
+        };
+        assert!(low.matches(0x1b) && low.matches(0xfb) && !low.matches(0xba));
+        assert_eq!(low.bytes().count(), 16);
+        let any = ByteMask::any()
+            .to_expr()
+            .build()
+            .unwrap()
+            .remove_epsilon()
+            .unwrap();
+        assert!(any.accepts(vec![0xff]));
+        assert!(!any.accepts(vec![]));
+        assert!(binary::to_expr(&[])
+            .build()
+            .unwrap()
+            .remove_epsilon()
+            .unwrap()
+            .accepts(vec![]));
+    }
+
+    #[test]
+    fn test_expansion_size() {
+        let high = ByteMask {
+            value: 0x40,
+            mask: 0xf0,
+        };
+        for mask in [ByteMask::any(), ByteMask::byte(7), high].iter() {
+            let states = mask.to_expr().build().unwrap().delta.len();
+            assert!(states <= 4 * mask.bytes().count(), "{:?}", mask);
+        }
+        let any = [ByteMask::any(), ByteMask::any()];
+        assert!(binary::to_expr(&any).build().unwrap().delta.len() <= 2 * 4 * 256);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse_hex("aB\t?c\n").unwrap(),
+            vec![
+                ByteMask::byte(0xab),
+                ByteMask {
+                    value: 0x0c,
+                    mask: 0x0f
+                }
+            ]
+        );
+        assert_eq!(parse_hex("").unwrap(), vec![]);
+        assert!(parse_hex("A").is_err());
+        assert!(parse_hex("AG").is_err());
+        assert_eq!(
+            parse_escaped(r"é?\?\\\x??").unwrap(),
+            vec![
+                ByteMask::byte(0xc3),
+                ByteMask::byte(0xa9),
+                ByteMask::any(),
+                ByteMask::byte(b'?'),
+                ByteMask::byte(b'\\'),
+                ByteMask::any(),
+            ]
+        );
+        assert!(parse_escaped(r"\x4").is_err());
+        assert!(parse_escaped(r"\n").is_err());
+        assert!(parse_escaped("\\").is_err());
+    }
+
+    #[test]
+    fn test_signatures() {
+        // a PE header: MZ, anything, then PE\0\0 as hex
+        let mut signatures = Signatures::new();
+        let mz = binary::to_expr(&parse_escaped("MZ??").unwrap());
+        let pe = binary::to_expr(&parse_hex("50 45 00 00").unwrap());
+        let machine = mz.concat(pe).build().unwrap();
+        signatures.register("pe", &machine).unwrap();
+        assert_eq!(signatures.scan(b"MZ\x90\x00PE\x00\x00").len(), 1);
+        assert_eq!(signatures.scan(b"MZ\x90PE\x00\x00").len(), 0);
+    }
+}
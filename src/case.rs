@@ -0,0 +1,112 @@
+use crate::compilers::Compiler;
+use crate::ANFA;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The single character `c` maps to, or `None` when the mapping is empty or
+/// produces more than one character, e.g. `'ß'` uppercases to `"SS"`
+#[cfg(feature = "unicode-case")]
+fn single<I: Iterator<Item = char>>(mut mapping: I) -> Option<char> {
+    match (mapping.next(), mapping.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// Every character that matches `c` case-insensitively, starting with `c`.
+///
+/// Without the `unicode-case` feature only ASCII letters fold, so `'a'` and
+/// `'A'` match each other and every other character matches only itself.
+/// With `unicode-case`, the variants are the closure of `c` under the simple,
+/// one-to-one lowercase and uppercase mappings, e.g. `'Σ'`, `'σ'`, and `'ς'`.
+/// Mappings to several characters are not followed.
+///
+/// ```rust
+/// use regexxx::case::case_variants;
+/// assert_eq!(case_variants('a'), vec!['a', 'A']);
+/// assert_eq!(case_variants('1'), vec!['1']);
+/// ```
+pub fn case_variants(c: char) -> Vec<char> {
+    let mut variants = vec![c];
+    #[cfg(feature = "unicode-case")]
+    {
+        let mut next_unvisited = 0;
+        while next_unvisited < variants.len() {
+            let c = variants[next_unvisited];
+            next_unvisited += 1;
+            for mapped in [single(c.to_lowercase()), single(c.to_uppercase())]
+                .iter()
+                .flatten()
+            {
+                if !variants.contains(mapped) {
+                    variants.push(*mapped);
+                }
+            }
+        }
+    }
+    #[cfg(not(feature = "unicode-case"))]
+    {
+        for mapped in [c.to_ascii_lowercase(), c.to_ascii_uppercase()].iter() {
+            if !variants.contains(mapped) {
+                variants.push(*mapped);
+            }
+        }
+    }
+    variants
+}
+
+/// Pushes an automaton accepting any case variant of `c` with compiler `C`,
+/// i.e. the union of `expr_a` over `case_variants(c)`.
+///
+/// ```rust
+/// use regexxx::case::expr_a_ci;
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// // RE (?i)ab
+/// let mut machine = ForwardCompiler::from_expr_1().unwrap();
+/// expr_a_ci::<ForwardCompiler>(&mut machine, 'a').unwrap();
+/// ForwardCompiler::concatenate(&mut machine).unwrap();
+/// expr_a_ci::<ForwardCompiler>(&mut machine, 'b').unwrap();
+/// ForwardCompiler::concatenate(&mut machine).unwrap();
+/// assert!(machine.is_match("aB").unwrap());
+/// ```
+pub fn expr_a_ci<C: Compiler>(anfa: &mut ANFA, c: char) -> Result<(), &'static str> {
+    let variants = case_variants(c);
+    C::expr_a(anfa, variants[0])?;
+    for variant in variants[1..].iter() {
+        C::expr_a(anfa, *variant)?;
+        C::union(anfa)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::case::case_variants;
+    use alloc::vec;
+
+    #[test]
+    fn test_case_variants_ascii() {
+        assert_eq!(case_variants('a'), vec!['a', 'A']);
+        assert_eq!(case_variants('Z'), vec!['Z', 'z']);
+        assert_eq!(case_variants('_'), vec!['_']);
+    }
+
+    #[cfg(not(feature = "unicode-case"))]
+    #[test]
+    fn test_case_variants_ascii_only() {
+        assert_eq!(case_variants('é'), vec!['é'], "Only ASCII letters fold");
+    }
+
+    #[cfg(feature = "unicode-case")]
+    #[test]
+    fn test_case_variants_unicode() {
+        assert_eq!(case_variants('é'), vec!['é', 'É']);
+        assert_eq!(case_variants('ς'), vec!['ς', 'Σ', 'σ']);
+        assert_eq!(case_variants('ẞ'), vec!['ẞ', 'ß']);
+        assert_eq!(
+            case_variants('ß'),
+            vec!['ß'],
+            "'ß' uppercases to \"SS\", which is not a simple mapping"
+        );
+    }
+}
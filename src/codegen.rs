@@ -0,0 +1,583 @@
+use crate::dfa::DFA;
+use crate::nfa::NFA;
+use crate::{QId, ANFA};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// A symbol of the combined lexer automaton: a character of the input, or
+/// the acceptance of a rule, which leads to a shared final state
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Symbol {
+    Char(char),
+    Accept(usize),
+}
+
+/// Keywords that can't name an enum variant, including those reserved for
+/// future use
+const KEYWORDS: [&str; 52] = [
+    "_", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Whether `name` is an ASCII Rust identifier
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let first_ok = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    first_ok && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') && !KEYWORDS.contains(&name)
+}
+
+/// One NFA accepting the rules' languages, each followed by `Accept(rule)`
+fn combine(rules: &[(&str, &ANFA)]) -> Result<NFA<Symbol>, &'static str> {
+    // state 0 is the new q0, the last state is the shared final state
+    let mut nfa = NFA {
+        q0: 0,
+        finals: vec![false],
+        delta: vec![Vec::new()],
+    };
+    let mut accepts = Vec::new();
+    for (rule, (_name, anfa)) in rules.iter().enumerate() {
+        let rule_nfa = anfa.remove_epsilon()?;
+        if rule_nfa.finals[rule_nfa.q0] {
+            return Err("Token rules must not accept the empty string.");
+        }
+        let offset = nfa.delta.len();
+        for (q, transitions) in rule_nfa.delta.iter().enumerate() {
+            let mut transitions: Vec<(Symbol, QId)> = transitions
+                .iter()
+                .map(|(c, q_next)| (Symbol::Char(*c), offset + q_next))
+                .collect();
+            if rule_nfa.finals[q] {
+                // pointed at the final state once it exists
+                accepts.push(offset + q);
+                transitions.push((Symbol::Accept(rule), usize::MAX));
+            }
+            if q == rule_nfa.q0 {
+                nfa.delta[0].extend_from_slice(&transitions);
+            }
+            nfa.delta.push(transitions);
+            nfa.finals.push(false);
+        }
+    }
+    let f = nfa.delta.len();
+    nfa.delta.push(Vec::new());
+    nfa.finals.push(true);
+    for q in accepts.into_iter().chain(core::iter::once(0)) {
+        for (_symbol, q_next) in nfa.delta[q].iter_mut() {
+            if *q_next == usize::MAX {
+                *q_next = f;
+            }
+        }
+    }
+    Ok(nfa)
+}
+
+/// The minimal DFA of the rules, and the rule each of its states accepts.
+/// Where the rules overlap, the first rule wins.
+fn lexer_dfa(rules: &[(&str, &ANFA)]) -> Result<(DFA<Symbol>, Vec<Option<usize>>), &'static str> {
+    let mut dfa = combine(rules)?.determinize();
+    for transitions in dfa.delta.iter_mut() {
+        let first = transitions
+            .iter()
+            .filter_map(|(symbol, _q_next)| match symbol {
+                Symbol::Accept(rule) => Some(*rule),
+                Symbol::Char(_) => None,
+            })
+            .min();
+        transitions.retain(|(symbol, _q_next)| match symbol {
+            Symbol::Accept(rule) => Some(*rule) == first,
+            Symbol::Char(_) => true,
+        });
+        transitions.sort_unstable();
+    }
+    let dfa = dfa.minimize();
+    let accepts = dfa
+        .delta
+        .iter()
+        .map(|transitions| {
+            transitions
+                .iter()
+                .find_map(|(symbol, _q_next)| match symbol {
+                    Symbol::Accept(rule) => Some(*rule),
+                    Symbol::Char(_) => None,
+                })
+        })
+        .collect();
+    Ok((dfa, accepts))
+}
+
+/// Writes the transitions of `q` as match arms, merging consecutive
+/// characters with the same next state into ranges
+fn write_arms(out: &mut String, q: QId, transitions: &[(Symbol, QId)]) -> core::fmt::Result {
+    let mut chars = transitions
+        .iter()
+        .filter_map(|(symbol, q_next)| match symbol {
+            Symbol::Char(c) => Some((*c, *q_next)),
+            Symbol::Accept(_) => None,
+        })
+        .peekable();
+    while let Some((first, q_next)) = chars.next() {
+        let mut last = first;
+        while let Some((c, next)) = chars.peek() {
+            if *next != q_next || Some(*c) != char::from_u32(last as u32 + 1) {
+                break;
+            }
+            last = *c;
+            chars.next();
+        }
+        if first == last {
+            writeln!(out, "            ({}, {:?}) => {},", q, first, q_next)?;
+        } else {
+            writeln!(
+                out,
+                "            ({}, {:?}..={:?}) => {},",
+                q, first, last, q_next
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_lexer(
+    rules: &[(&str, &ANFA)],
+    dfa: &DFA<Symbol>,
+    accepts: &[Option<usize>],
+    out: &mut String,
+) -> core::fmt::Result {
+    writeln!(
+        out,
+        "// Generated by regexxx::codegen::emit_lexer, do not edit.\n"
+    )?;
+    writeln!(out, "/// Token kinds, in rule order")?;
+    writeln!(out, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]")?;
+    writeln!(out, "pub enum Token {{")?;
+    for (name, _anfa) in rules.iter() {
+        writeln!(out, "    {},", name)?;
+    }
+    writeln!(out, "}}\n")?;
+
+    writeln!(out, "fn accept(state: usize) -> Option<Token> {{")?;
+    writeln!(out, "    match state {{")?;
+    for (q, rule) in accepts.iter().enumerate() {
+        if let Some(rule) = rule {
+            writeln!(out, "        {} => Some(Token::{}),", q, rules[*rule].0)?;
+        }
+    }
+    writeln!(out, "        _ => None,\n    }}\n}}\n")?;
+
+    writeln!(
+        out,
+        "/// Returns the longest token at the start of `input` and its length in\n\
+         /// bytes. Where rules tie, the first rule wins.\n\
+         pub fn next_token(input: &str) -> Option<(Token, usize)> {{\n    \
+         let mut state = {};\n    \
+         let mut longest = None;\n    \
+         for (at, c) in input.char_indices() {{\n        \
+         state = match (state, c) {{",
+        dfa.q0
+    )?;
+    for (q, transitions) in dfa.delta.iter().enumerate() {
+        write_arms(out, q, transitions)?;
+    }
+    writeln!(
+        out,
+        "            _ => break,\n        \
+         }};\n        \
+         if let Some(token) = accept(state) {{\n            \
+         longest = Some((token, at + c.len_utf8()));\n        \
+         }}\n    \
+         }}\n    \
+         longest\n\
+         }}\n"
+    )?;
+
+    writeln!(
+        out,
+        "/// The tokens of an input, each with its text. An input that no rule\n\
+         /// matches yields the byte offset of the error, then ends.\n\
+         pub struct Lexer<'a> {{\n    \
+         input: &'a str,\n    \
+         at: usize,\n\
+         }}\n\n\
+         impl<'a> Lexer<'a> {{\n    \
+         pub fn new(input: &'a str) -> Lexer<'a> {{\n        \
+         Lexer {{ input, at: 0 }}\n    \
+         }}\n\
+         }}\n\n\
+         impl<'a> Iterator for Lexer<'a> {{\n    \
+         type Item = Result<(Token, &'a str), usize>;\n\n    \
+         fn next(&mut self) -> Option<Self::Item> {{\n        \
+         let rest = self.input.get(self.at..).filter(|rest| !rest.is_empty())?;\n        \
+         match next_token(rest) {{\n            \
+         Some((token, len)) => {{\n                \
+         self.at += len;\n                \
+         Some(Ok((token, &rest[..len])))\n            \
+         }}\n            \
+         None => {{\n                \
+         let at = self.at;\n                \
+         self.at = self.input.len() + 1;\n                \
+         Some(Err(at))\n            \
+         }}\n        \
+         }}\n    \
+         }}\n\
+         }}"
+    )?;
+    Ok(())
+}
+
+fn write_matcher(dfa: &DFA<u8>, fn_name: &str, out: &mut String) -> core::fmt::Result {
+    // state 0 is dead, DFA state q is state q + 1
+    let states = dfa.delta.len() + 1;
+    let state_type = if states <= 1 << 8 {
+        "u8"
+    } else if states <= 1 << 16 {
+        "u16"
+    } else {
+        "u32"
+    };
+    let prefix = fn_name.to_ascii_uppercase();
+    writeln!(
+        out,
+        "// Generated by regexxx::codegen::emit_matcher, do not edit.\n"
+    )?;
+    writeln!(
+        out,
+        "/// Accepting states of `{}`, state 0 is dead",
+        fn_name
+    )?;
+    write!(
+        out,
+        "static {}_ACCEPTING: [bool; {}] = [false",
+        prefix, states
+    )?;
+    for is_final in dfa.finals.iter() {
+        write!(out, ", {}", is_final)?;
+    }
+    writeln!(out, "];\n")?;
+    writeln!(
+        out,
+        "/// `{}_TABLE[q * 256 + byte]` is the state entered from `q` along `byte`",
+        prefix
+    )?;
+    writeln!(
+        out,
+        "static {}_TABLE: [{}; {}] = [",
+        prefix,
+        state_type,
+        states * 256
+    )?;
+    let mut row = [0; 256];
+    for q in 0..states {
+        row.iter_mut().for_each(|q_next| *q_next = 0);
+        if q > 0 {
+            for (byte, q_next) in dfa.delta[q - 1].iter() {
+                row[*byte as usize] = q_next + 1;
+            }
+        }
+        for line in row.chunks(16) {
+            out.push_str("   ");
+            for q_next in line.iter() {
+                write!(out, " {},", q_next)?;
+            }
+            out.push('\n');
+        }
+    }
+    writeln!(out, "];\n")?;
+    writeln!(
+        out,
+        "/// Returns true when `input` is accepted, reading every byte once\n\
+         pub fn {name}(input: &[u8]) -> bool {{\n    \
+         let mut q = {q0};\n    \
+         for byte in input {{\n        \
+         q = {prefix}_TABLE[q as usize * 256 + *byte as usize];\n        \
+         if q == 0 {{\n            \
+         return false;\n        \
+         }}\n    \
+         }}\n    \
+         {prefix}_ACCEPTING[q as usize]\n\
+         }}",
+        name = fn_name,
+        q0 = dfa.q0 + 1,
+        prefix = prefix
+    )?;
+    Ok(())
+}
+
+/// Returns the Rust source of a standalone lexer for `rules`, a list of
+/// token names and the machines of their patterns, e.g. for a build script
+/// to write into `OUT_DIR`. The source has no dependencies, not even on
+/// `alloc`, and defines:
+///
+/// - `enum Token`, one variant per rule, named after it
+/// - `fn next_token(&str) -> Option<(Token, usize)>`, the longest token at
+///   the start of the input and its length in bytes, as a `match` over the
+///   states of the minimal DFA of all rules
+/// - `struct Lexer`, an iterator over the tokens of an input
+///
+/// Where tokens of several rules are equally long, the first rule wins, so
+/// keywords go before identifiers. Names must be Rust identifiers and
+/// unique. Each machine must hold exactly one automaton without
+/// assertions, and must not accept the empty string.
+///
+/// ```rust
+/// use regexxx::codegen::emit_lexer;
+/// use regexxx::expr::Expr;
+/// let digit = || Expr::literal('0').union(Expr::literal('1'));
+/// let number = digit().concat(digit().star()).build().unwrap();
+/// let plus = Expr::literal('+').build().unwrap();
+/// let source = emit_lexer(&[("Number", &number), ("Plus", &plus)]).unwrap();
+/// assert!(source.contains("pub enum Token {\n    Number,\n    Plus,\n}"));
+/// assert!(source.contains("'0'..='1'"));
+/// ```
+///
+/// See `examples/lexer` for the source generated for a small language.
+pub fn emit_lexer(rules: &[(&str, &ANFA)]) -> Result<String, &'static str> {
+    if rules.is_empty() {
+        return Err("A lexer requires at least one rule.");
+    }
+    for (i, (name, _anfa)) in rules.iter().enumerate() {
+        if !is_identifier(name) {
+            return Err("Token names must be Rust identifiers.");
+        }
+        if rules[..i].iter().any(|(other, _anfa)| other == name) {
+            return Err("Token names must be unique.");
+        }
+    }
+    let (dfa, accepts) = lexer_dfa(rules)?;
+    let mut out = String::new();
+    write_lexer(rules, &dfa, &accepts, &mut out).map_err(|_| "Failed to format lexer source.")?;
+    Ok(out)
+}
+
+/// Returns the Rust source of a function `fn_name` taking `&[u8]`, which
+/// returns true when `dfa` accepts exactly its input, see `DFA::accepts`.
+/// The transition table is baked into statics named after the function, with
+/// the smallest integer type holding every state, so the source has no
+/// dependencies and does not allocate. `fn_name` must be a Rust identifier.
+///
+/// For text, build the automaton over the UTF-8 bytes of its literals; for a
+/// search rather than a whole-input match, surround the pattern with `Σ*`.
+///
+/// ```rust
+/// use regexxx::codegen::emit_matcher;
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// use regexxx::ANFA;
+/// // RE 0x7f 0x45*
+/// let mut machine: ANFA<u8> = ForwardCompiler::from_expr_a(0x7f).unwrap();
+/// ForwardCompiler::expr_a(&mut machine, 0x45).unwrap();
+/// ForwardCompiler::star(&mut machine).unwrap();
+/// ForwardCompiler::concatenate(&mut machine).unwrap();
+/// let dfa = machine.determinize().unwrap().minimize();
+/// let source = emit_matcher(&dfa, "is_header").unwrap();
+/// assert!(source.contains("pub fn is_header(input: &[u8]) -> bool {"));
+/// assert!(source.contains("static IS_HEADER_TABLE: [u8; 768] = ["));
+/// ```
+pub fn emit_matcher(dfa: &DFA<u8>, fn_name: &str) -> Result<String, &'static str> {
+    if !is_identifier(fn_name) {
+        return Err("Function names must be Rust identifiers.");
+    }
+    if dfa.finals.len() != dfa.delta.len() || dfa.q0 >= dfa.delta.len() {
+        return Err("DFA states are out of range.");
+    }
+    let states = dfa.delta.len();
+    if dfa
+        .delta
+        .iter()
+        .flatten()
+        .any(|(_byte, q_next)| *q_next >= states)
+    {
+        return Err("DFA states are out of range.");
+    }
+    if states >= u32::MAX as usize {
+        return Err("DFA has too many states for the generated table.");
+    }
+    let mut out = String::new();
+    write_matcher(dfa, fn_name, &mut out).map_err(|_| "Failed to format matcher source.")?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codegen::{emit_lexer, emit_matcher, lexer_dfa};
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::dfa::DFA;
+    use crate::expr::Expr;
+    use crate::ANFA;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn any_of(chars: impl Iterator<Item = char>) -> Expr {
+        chars
+            .map(Expr::literal)
+            .reduce(Expr::union)
+            .unwrap_or_else(Expr::zero)
+    }
+
+    /// The rules of `examples/lexer`
+    fn rules() -> Vec<(&'static str, ANFA)> {
+        let letter = || any_of(('a'..='z').chain(core::iter::once('_')));
+        let digit = || any_of('0'..='9');
+        let space = || any_of([' ', '\n'].iter().copied());
+        Vec::from([
+            ("Let", Expr::word("let").build().unwrap()),
+            (
+                "Ident",
+                letter()
+                    .concat(letter().union(digit()).star())
+                    .build()
+                    .unwrap(),
+            ),
+            ("Number", digit().concat(digit().star()).build().unwrap()),
+            ("Equals", Expr::word("=").build().unwrap()),
+            ("Plus", Expr::word("+").build().unwrap()),
+            ("Space", space().concat(space().star()).build().unwrap()),
+        ])
+    }
+
+    #[test]
+    fn test_emit_lexer_example() {
+        let rules = rules();
+        let rules: Vec<(&str, &ANFA)> = rules.iter().map(|(name, anfa)| (*name, anfa)).collect();
+        assert_eq!(
+            emit_lexer(&rules).unwrap(),
+            include_str!("../examples/lexer/lexer.rs"),
+            "examples/lexer/lexer.rs is up to date"
+        );
+    }
+
+    #[test]
+    fn test_lexer_dfa_priority() {
+        let rules = rules();
+        let rules: Vec<(&str, &ANFA)> = rules.iter().map(|(name, anfa)| (*name, anfa)).collect();
+        let (dfa, accepts) = lexer_dfa(&rules).unwrap();
+        let run = |input: &str| {
+            let mut q = dfa.q0;
+            for c in input.chars() {
+                q = dfa.next_state(q, super::Symbol::Char(c))?;
+            }
+            accepts[q]
+        };
+        assert_eq!(run("let"), Some(0), "Earlier rules win ties");
+        assert_eq!(run("lets"), Some(1));
+        assert_eq!(run("le"), Some(1));
+        assert_eq!(run("42"), Some(2));
+        assert_eq!(run("4a"), None);
+    }
+
+    #[test]
+    fn test_emit_lexer_errors() {
+        let a = Expr::literal('a').build().unwrap();
+        let empty = Expr::literal('a').star().build().unwrap();
+        assert!(emit_lexer(&[]).is_err());
+        assert!(emit_lexer(&[("A", &a), ("A", &a)]).is_err());
+        assert!(emit_lexer(&[("1A", &a)]).is_err());
+        assert!(emit_lexer(&[("A-B", &a)]).is_err());
+        assert!(emit_lexer(&[("match", &a)]).is_err());
+        assert!(emit_lexer(&[("abstract", &a)]).is_err());
+        assert!(emit_lexer(&[("A", &empty)]).is_err());
+        let mut none = Expr::literal('a').build().unwrap();
+        none.automata_refs.clear();
+        assert!(emit_lexer(&[("A", &none)]).is_err());
+    }
+
+    /// Runs the source of `emit_matcher` by reading its tables back
+    fn run_emitted(source: &str, input: &[u8]) -> bool {
+        let numbers = |line: &str| -> Vec<usize> {
+            line.split(|c: char| !c.is_ascii_digit())
+                .filter(|word| !word.is_empty())
+                .map(|word| word.parse().unwrap())
+                .collect()
+        };
+        let accepting: Vec<bool> = source
+            .lines()
+            .find(|line| line.contains("_ACCEPTING: "))
+            .unwrap()
+            .split("= [")
+            .nth(1)
+            .unwrap()
+            .split(", ")
+            .map(|word| word.starts_with("true"))
+            .collect();
+        let table: Vec<usize> = source
+            .lines()
+            .skip_while(|line| !line.contains("_TABLE: "))
+            .skip(1)
+            .take_while(|line| *line != "];")
+            .flat_map(numbers)
+            .collect();
+        let q0 = source
+            .lines()
+            .find(|line| line.contains("let mut q = "))
+            .map(numbers)
+            .unwrap()[0];
+        let mut q = q0;
+        for byte in input {
+            q = table[q * 256 + *byte as usize];
+            if q == 0 {
+                return false;
+            }
+        }
+        accepting[q]
+    }
+
+    #[test]
+    fn test_emit_matcher() {
+        // RE (0x00+0xff)*0x0a
+        let mut machine: ANFA<u8> = ForwardCompiler::from_expr_a(0x00).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 0xff).unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 0x0a).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let dfa = machine.determinize().unwrap().minimize();
+        let source = emit_matcher(&dfa, "ends_in_newline").unwrap();
+        assert_eq!(
+            source,
+            include_str!("../tests/emitted/ends_in_newline.rs"),
+            "tests/emitted/ends_in_newline.rs is up to date"
+        );
+        let inputs: [&[u8]; 6] = [b"", b"\n", b"\0\xff\n", b"\n\n", b"\x01\n", b"\xff"];
+        for input in inputs.iter() {
+            assert_eq!(
+                run_emitted(&source, input),
+                dfa.accepts(input.iter().copied()),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_emit_matcher_errors() {
+        let dfa = DFA {
+            q0: 0,
+            finals: vec![true],
+            delta: vec![vec![(b'a', 0)]],
+        };
+        assert!(emit_matcher(&dfa, "fn").is_err());
+        assert!(emit_matcher(&dfa, "try").is_err());
+        assert!(emit_matcher(&dfa, "yield").is_err());
+        assert!(emit_matcher(&dfa, "is-match").is_err());
+        assert!(emit_matcher(&dfa, "is_match").is_ok());
+        let out_of_range = DFA {
+            q0: 0,
+            finals: vec![true],
+            delta: vec![vec![(b'a', 1)]],
+        };
+        assert!(emit_matcher(&out_of_range, "is_match").is_err());
+        let mut wide = DFA {
+            q0: 0,
+            finals: vec![false; 300],
+            delta: vec![Vec::new(); 300],
+        };
+        wide.delta[0].push((b'a', 299));
+        let source = emit_matcher(&wide, "is_match").unwrap();
+        assert!(source.contains("static IS_MATCH_TABLE: [u16; 77056]"));
+    }
+}
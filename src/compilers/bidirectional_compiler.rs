@@ -1,44 +1,51 @@
 // size of QId
 // size of label
+use crate::assertion::Assertion;
 use crate::compilers::coverage_compiler::CoverageCompiler;
 use crate::compilers::forward_compiler::ForwardCompiler;
 pub use crate::compilers::Compiler;
 use crate::ANFA;
 
-pub trait Compilers {
-  fn from_expr_0() -> Result<[ANFA; 2], &'static str>;
-  fn from_expr_1() -> Result<[ANFA; 2], &'static str>;
-  fn from_expr_a(c: char) -> Result<[ANFA; 2], &'static str>;
+pub trait Compilers<S: Copy + Eq = char> {
+  fn from_expr_0() -> Result<[ANFA<S>; 2], &'static str>;
+  fn from_expr_1() -> Result<[ANFA<S>; 2], &'static str>;
+  fn from_expr_a(c: S) -> Result<[ANFA<S>; 2], &'static str>;
+  fn from_expr_assert(assertion: Assertion) -> Result<[ANFA<S>; 2], &'static str>;
   fn expr_0(
-    forward_machine: &mut ANFA,
-    coverage_machine: &mut ANFA,
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
   ) -> Result<((), ()), &'static str>;
   fn expr_1(
-    forward_machine: &mut ANFA,
-    coverage_machine: &mut ANFA,
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
   ) -> Result<((), ()), &'static str>;
   fn expr_a(
-    forward_machine: &mut ANFA,
-    coverage_machine: &mut ANFA,
-    c: char,
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
+    c: S,
+  ) -> Result<((), ()), &'static str>;
+  fn expr_assert(
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
+    assertion: Assertion,
   ) -> Result<((), ()), &'static str>;
   fn concatenate(
-    forward_machine: &mut ANFA,
-    coverage_machine: &mut ANFA,
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
   ) -> Result<((), ()), &'static str>;
   fn star(
-    forward_machine: &mut ANFA,
-    coverage_machine: &mut ANFA,
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
   ) -> Result<((), ()), &'static str>;
   fn union(
-    forward_machine: &mut ANFA,
-    coverage_machine: &mut ANFA,
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
   ) -> Result<((), ()), &'static str>;
 }
 
 pub struct BidirectionalCompiler {}
-impl Compilers for BidirectionalCompiler {
-  fn from_expr_0() -> Result<[ANFA; 2], &'static str> {
+impl<S: Copy + Eq> Compilers<S> for BidirectionalCompiler {
+  fn from_expr_0() -> Result<[ANFA<S>; 2], &'static str> {
     let mut forward_machine_a = ANFA::new();
     let mut coverage_machine_a = ANFA::new();
     match [
@@ -51,7 +58,7 @@ impl Compilers for BidirectionalCompiler {
     }
   }
 
-  fn from_expr_1() -> Result<[ANFA; 2], &'static str> {
+  fn from_expr_1() -> Result<[ANFA<S>; 2], &'static str> {
     let mut forward_machine_a = ANFA::new();
     let mut coverage_machine_a = ANFA::new();
     match [
@@ -64,7 +71,7 @@ impl Compilers for BidirectionalCompiler {
     }
   }
 
-  fn from_expr_a(c: char) -> Result<[ANFA; 2], &'static str> {
+  fn from_expr_a(c: S) -> Result<[ANFA<S>; 2], &'static str> {
     let mut forward_machine_a = ANFA::new();
     let mut coverage_machine_a = ANFA::new();
     match [
@@ -77,9 +84,22 @@ impl Compilers for BidirectionalCompiler {
     }
   }
 
+  fn from_expr_assert(assertion: Assertion) -> Result<[ANFA<S>; 2], &'static str> {
+    let mut forward_machine_a = ANFA::new();
+    let mut coverage_machine_a = ANFA::new();
+    match [
+      ForwardCompiler::expr_assert(&mut forward_machine_a, assertion),
+      CoverageCompiler::expr_assert(&mut coverage_machine_a, assertion),
+    ] {
+      [Ok(()), Ok(())] => Ok([forward_machine_a, coverage_machine_a]),
+      [Err(forward_machine_error), _] => Err(forward_machine_error),
+      [_, Err(coverage_machine_error)] => Err(coverage_machine_error),
+    }
+  }
+
   fn expr_0(
-    forward_machine: &mut ANFA,
-    coverage_machine: &mut ANFA,
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
   ) -> Result<((), ()), &'static str> {
     match [
       ForwardCompiler::expr_0(forward_machine),
@@ -92,8 +112,8 @@ impl Compilers for BidirectionalCompiler {
   }
 
   fn expr_1(
-    forward_machine: &mut ANFA,
-    coverage_machine: &mut ANFA,
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
   ) -> Result<((), ()), &'static str> {
     match [
       ForwardCompiler::expr_1(forward_machine),
@@ -106,9 +126,9 @@ impl Compilers for BidirectionalCompiler {
   }
 
   fn expr_a(
-    forward_machine: &mut ANFA,
-    coverage_machine: &mut ANFA,
-    c: char,
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
+    c: S,
   ) -> Result<((), ()), &'static str> {
     match [
       ForwardCompiler::expr_a(forward_machine, c),
@@ -120,9 +140,24 @@ impl Compilers for BidirectionalCompiler {
     }
   }
 
+  fn expr_assert(
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
+    assertion: Assertion,
+  ) -> Result<((), ()), &'static str> {
+    match [
+      ForwardCompiler::expr_assert(forward_machine, assertion),
+      CoverageCompiler::expr_assert(coverage_machine, assertion),
+    ] {
+      [Ok(()), Ok(())] => Ok(((), ())),
+      [Err(forward_machine_error), _] => Err(forward_machine_error),
+      [_, Err(coverage_machine_error)] => Err(coverage_machine_error),
+    }
+  }
+
   fn concatenate(
-    forward_machine: &mut ANFA,
-    coverage_machine: &mut ANFA,
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
   ) -> Result<((), ()), &'static str> {
     match [
       ForwardCompiler::concatenate(forward_machine),
@@ -135,8 +170,8 @@ impl Compilers for BidirectionalCompiler {
   }
 
   fn star(
-    forward_machine: &mut ANFA,
-    coverage_machine: &mut ANFA,
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
   ) -> Result<((), ()), &'static str> {
     match [
       ForwardCompiler::star(forward_machine),
@@ -149,8 +184,8 @@ impl Compilers for BidirectionalCompiler {
   }
 
   fn union(
-    forward_machine: &mut ANFA,
-    coverage_machine: &mut ANFA,
+    forward_machine: &mut ANFA<S>,
+    coverage_machine: &mut ANFA<S>,
   ) -> Result<((), ()), &'static str> {
     match [
       ForwardCompiler::union(forward_machine),
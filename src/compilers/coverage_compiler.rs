@@ -1,17 +1,19 @@
 // size of QId
 // size of label
+use crate::assertion::Assertion;
 pub use crate::compilers::Compiler;
 use crate::ANFA;
 
 pub struct CoverageCompiler {} // ForwardCompiler, but backwards!
-impl Compiler for CoverageCompiler {
+impl<S: Copy + Eq> Compiler<S> for CoverageCompiler {
     /// Returns a new ANFA that never transitions to a final state.
     ///
     /// ```rust
     /// use regexxx::compilers::coverage_compiler::{Compiler, CoverageCompiler};
-    /// let machine = CoverageCompiler::from_expr_0().unwrap(); // always safe!
+    /// use regexxx::ANFA;
+    /// let machine: ANFA = CoverageCompiler::from_expr_0().unwrap(); // always safe!
     /// ```
-    fn from_expr_0() -> Result<ANFA, &'static str> {
+    fn from_expr_0() -> Result<ANFA<S>, &'static str> {
         let mut machine_a = ANFA::new();
         match CoverageCompiler::expr_0(&mut machine_a) {
             Ok(()) => Ok(machine_a),
@@ -23,9 +25,10 @@ impl Compiler for CoverageCompiler {
     ///
     /// ```rust
     /// use regexxx::compilers::coverage_compiler::{Compiler, CoverageCompiler};
-    /// let machine = CoverageCompiler::from_expr_1().unwrap(); // always safe!
+    /// use regexxx::ANFA;
+    /// let machine: ANFA = CoverageCompiler::from_expr_1().unwrap(); // always safe!
     /// ```
-    fn from_expr_1() -> Result<ANFA, &'static str> {
+    fn from_expr_1() -> Result<ANFA<S>, &'static str> {
         let mut machine_a = ANFA::new();
         match CoverageCompiler::expr_1(&mut machine_a) {
             Ok(()) => Ok(machine_a),
@@ -39,7 +42,7 @@ impl Compiler for CoverageCompiler {
     /// use regexxx::compilers::coverage_compiler::{Compiler, CoverageCompiler};
     /// let mut machine = CoverageCompiler::from_expr_a('a').unwrap(); // always safe!
     /// ```
-    fn from_expr_a(c: char) -> Result<ANFA, &'static str> {
+    fn from_expr_a(c: S) -> Result<ANFA<S>, &'static str> {
         let mut machine_a = ANFA::new();
         match CoverageCompiler::expr_a(&mut machine_a, c) {
             Ok(()) => Ok(machine_a),
@@ -47,6 +50,22 @@ impl Compiler for CoverageCompiler {
         }
     }
 
+    /// Returns a new ANFA in its final state where `assertion` holds.
+    ///
+    /// ```rust
+    /// use regexxx::assertion::Assertion;
+    /// use regexxx::compilers::coverage_compiler::{Compiler, CoverageCompiler};
+    /// use regexxx::ANFA;
+    /// let machine: ANFA = CoverageCompiler::from_expr_assert(Assertion::StartText).unwrap(); // always safe!
+    /// ```
+    fn from_expr_assert(assertion: Assertion) -> Result<ANFA<S>, &'static str> {
+        let mut machine_a = ANFA::new();
+        match CoverageCompiler::expr_assert(&mut machine_a, assertion) {
+            Ok(()) => Ok(machine_a),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Pushes an acceptor that never transitions, i.e. accept nothing
     ///
     /// ```rust
@@ -72,16 +91,16 @@ impl Compiler for CoverageCompiler {
     /// Graph:
     /// --> ( 1 )  (( 0 ))
     /// ```
-    fn expr_0(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn expr_0(anfa: &mut ANFA<S>) -> Result<(), &'static str> {
         let f = anfa.delta.len();
         let q0 = f + 1;
         let machine_a = [q0, f];
-        anfa.delta.push((
+        anfa.push_state((
             // push non-transitioning state
             None,
             [None, None],
         ));
-        anfa.delta.push((
+        anfa.push_state((
             // push final state
             None,
             [None, None],
@@ -114,11 +133,11 @@ impl Compiler for CoverageCompiler {
     /// Graph:
     /// --> (( 0 ))
     /// ```
-    fn expr_1(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn expr_1(anfa: &mut ANFA<S>) -> Result<(), &'static str> {
         let q0 = anfa.delta.len();
         let f = q0;
         let machine_a = [q0, f];
-        anfa.delta.push((
+        anfa.push_state((
             // push final state
             None,
             [None, None],
@@ -127,6 +146,47 @@ impl Compiler for CoverageCompiler {
         Ok(())
     }
 
+    /// Pushes an acceptor in final state that may only be entered where `assertion` holds,
+    /// i.e. accept the empty word at positions matching `^`, `$`, `\b`, or `\B`.
+    /// The machine reads backwards, so `^` is checked at the end of its input and `$` at the start.
+    ///
+    /// ```rust
+    /// use regexxx::assertion::Assertion;
+    /// use regexxx::compilers::coverage_compiler::{Compiler, CoverageCompiler};
+    /// let mut machine = CoverageCompiler::from_expr_a('a').unwrap(); // always safe!
+    /// CoverageCompiler::expr_assert(&mut machine, Assertion::WordBoundary).unwrap();
+    /// CoverageCompiler::concatenate(&mut machine).unwrap();
+    /// // coverage machines read backwards, this is \b then 'a'
+    /// assert_eq!(machine.find("ba a").unwrap(), Some((3, 4)));
+    /// ```
+    ///
+    /// ```text
+    /// Definition of `^`
+    ///
+    /// State table:
+    /// | Q | T | Q |
+    /// |---|---|---|
+    /// | 0 |   |   | (q0 = f, ^)
+    ///
+    /// Graph:
+    /// --> (( 0 ^ ))
+    /// ```
+    fn expr_assert(anfa: &mut ANFA<S>, assertion: Assertion) -> Result<(), &'static str> {
+        // the machine reads its input backwards, so the start of the input is its end
+        let assertion = assertion.reversed();
+        let q0 = anfa.delta.len();
+        let f = q0;
+        let machine_a = [q0, f];
+        anfa.push_state((
+            // push final state
+            None,
+            [None, None],
+        ));
+        anfa.assertions.insert(q0, assertion);
+        anfa.automata_refs.push(machine_a);
+        Ok(())
+    }
+
     /// Pushes an automaton that transitions to a final state on 'a'
     ///
     /// ```rust
@@ -152,16 +212,16 @@ impl Compiler for CoverageCompiler {
     /// Graph:
     /// --> ( 1 ) -- 'a' --> (( 0 ))
     /// ```
-    fn expr_a(anfa: &mut ANFA, c: char) -> Result<(), &'static str> {
+    fn expr_a(anfa: &mut ANFA<S>, c: S) -> Result<(), &'static str> {
         let f = anfa.delta.len();
         let q0 = f + 1;
         let machine_a = [q0, f];
-        anfa.delta.push((
+        anfa.push_state((
             // push final state
             None,
             [None, None],
         ));
-        anfa.delta.push((
+        anfa.push_state((
             // push transition to Q `f` along Label `c`
             Some(c),
             [Some(f), None],
@@ -205,7 +265,7 @@ impl Compiler for CoverageCompiler {
     /// Expression 'a' ⋅ 'b'
     /// --> ( 3 ) -- 'b' --> ( 2 ) -- ε --> ( 1 ) -- 'a' --> (( 0 ))
     /// ```
-    fn concatenate(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn concatenate(anfa: &mut ANFA<S>) -> Result<(), &'static str> {
         match anfa.automata_refs.len() {
             0 | 1 => {
                 return Err("Concatenation requires two operands.");
@@ -271,12 +331,9 @@ impl Compiler for CoverageCompiler {
     /// --> ( 4 ) -- ε --> ( 3 ) <------------ ε ------------|
     ///                        \-- 1 --> (( 2 ))
     /// ```
-    fn star(anfa: &mut ANFA) -> Result<(), &'static str> {
-        match anfa.automata_refs.len() {
-            0 => {
-                return Err("Star requires one operand.");
-            }
-            _ => {}
+    fn star(anfa: &mut ANFA<S>) -> Result<(), &'static str> {
+        if anfa.automata_refs.is_empty() {
+            return Err("Star requires one operand.");
         };
         let [machine_a_q0, machine_a_f] = match anfa.automata_refs.pop() {
             None => {
@@ -289,17 +346,17 @@ impl Compiler for CoverageCompiler {
         let machine_b_q = machine_b_f + 1;
         let machine_b_q0 = machine_b_f + 2;
         let machine_b = [machine_b_q0, machine_b_f];
-        anfa.delta.push((
+        anfa.push_state((
             // push final state
             None,
             [None, None],
         ));
-        anfa.delta.push((
+        anfa.push_state((
             // push union of machine_a and final state
             None,
             [Some(machine_a_q0), Some(machine_b_f)],
         ));
-        anfa.delta.push((
+        anfa.push_state((
             // push epsilon transition to union
             None,
             [Some(machine_b_q), None],
@@ -351,7 +408,7 @@ impl Compiler for CoverageCompiler {
     /// --> ( 5 )                                    ε --> (( 4 ))
     ///         \ -- 1 --> ( 3 ) -- 'b' --> ( 2 ) --/
     /// ```
-    fn union(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn union(anfa: &mut ANFA<S>) -> Result<(), &'static str> {
         let machine_c_f = anfa.delta.len();
         match machine_c_f {
             0 | 1 => {
@@ -375,12 +432,12 @@ impl Compiler for CoverageCompiler {
             }
             Some(machine_a) => machine_a,
         };
-        anfa.delta.push((
+        anfa.push_state((
             // push final state
             None,
             [None, None],
         ));
-        anfa.delta.push((
+        anfa.push_state((
             // push union transition
             None,
             [Some(machine_a_q0), Some(machine_b_q0)],
@@ -402,11 +459,13 @@ impl Compiler for CoverageCompiler {
 
 #[cfg(test)]
 mod tests {
+    use crate::assertion::Assertion;
     use crate::compilers::coverage_compiler::{Compiler, CoverageCompiler};
+    use crate::ANFA;
 
     #[test]
     fn test_expr_0() {
-        let mut machine = CoverageCompiler::from_expr_0().unwrap();
+        let mut machine: ANFA = CoverageCompiler::from_expr_0().unwrap();
         assert_eq!(
             machine.automata_refs.len(),
             1,
@@ -449,7 +508,7 @@ mod tests {
 
     #[test]
     fn test_expr_1() {
-        let mut machine = CoverageCompiler::from_expr_1().unwrap();
+        let mut machine: ANFA = CoverageCompiler::from_expr_1().unwrap();
 
         assert_eq!(
             machine.automata_refs.len(),
@@ -528,6 +587,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_expr_assert() {
+        let machine: ANFA = CoverageCompiler::from_expr_assert(Assertion::StartText).unwrap();
+        let [machine_a_q0, machine_a_f] = machine.automata_refs[0];
+        assert_eq!(
+            machine_a_q0, machine_a_f,
+            "An assertion accepts the empty word"
+        );
+        assert_eq!(machine.delta[machine_a_q0], (None, [None, None]));
+        assert_eq!(
+            machine.assertions.get(&machine_a_q0),
+            Some(&Assertion::EndText),
+            "Coverage machines read backwards, so ^ is checked at the end"
+        );
+    }
+
     #[test]
     fn test_concatenate() {
         let mut machine = CoverageCompiler::from_expr_a('a').unwrap();
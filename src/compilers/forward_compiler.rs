@@ -1,17 +1,19 @@
 // size of QId
 // size of label
+use crate::assertion::Assertion;
 pub use crate::compilers::Compiler;
 use crate::ANFA;
 
 pub struct ForwardCompiler {}
-impl Compiler for ForwardCompiler {
+impl<S: Copy + Eq> Compiler<S> for ForwardCompiler {
     /// Returns a new ANFA that never transitions to a final state.
     ///
     /// ```rust
     /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
-    /// let machine = ForwardCompiler::from_expr_0().unwrap(); // always safe!
+    /// use regexxx::ANFA;
+    /// let machine: ANFA = ForwardCompiler::from_expr_0().unwrap(); // always safe!
     /// ```
-    fn from_expr_0() -> Result<ANFA, &'static str> {
+    fn from_expr_0() -> Result<ANFA<S>, &'static str> {
         let mut machine_a = ANFA::new();
         match ForwardCompiler::expr_0(&mut machine_a) {
             Ok(()) => Ok(machine_a),
@@ -23,9 +25,10 @@ impl Compiler for ForwardCompiler {
     ///
     /// ```rust
     /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
-    /// let machine = ForwardCompiler::from_expr_1().unwrap(); // always safe!
+    /// use regexxx::ANFA;
+    /// let machine: ANFA = ForwardCompiler::from_expr_1().unwrap(); // always safe!
     /// ```
-    fn from_expr_1() -> Result<ANFA, &'static str> {
+    fn from_expr_1() -> Result<ANFA<S>, &'static str> {
         let mut machine_a = ANFA::new();
         match ForwardCompiler::expr_1(&mut machine_a) {
             Ok(()) => Ok(machine_a),
@@ -39,7 +42,7 @@ impl Compiler for ForwardCompiler {
     /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
     /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap(); // always safe!
     /// ```
-    fn from_expr_a(c: char) -> Result<ANFA, &'static str> {
+    fn from_expr_a(c: S) -> Result<ANFA<S>, &'static str> {
         let mut machine_a = ANFA::new();
         match ForwardCompiler::expr_a(&mut machine_a, c) {
             Ok(()) => Ok(machine_a),
@@ -47,6 +50,22 @@ impl Compiler for ForwardCompiler {
         }
     }
 
+    /// Returns a new ANFA in its final state where `assertion` holds.
+    ///
+    /// ```rust
+    /// use regexxx::assertion::Assertion;
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// use regexxx::ANFA;
+    /// let machine: ANFA = ForwardCompiler::from_expr_assert(Assertion::StartText).unwrap(); // always safe!
+    /// ```
+    fn from_expr_assert(assertion: Assertion) -> Result<ANFA<S>, &'static str> {
+        let mut machine_a = ANFA::new();
+        match ForwardCompiler::expr_assert(&mut machine_a, assertion) {
+            Ok(()) => Ok(machine_a),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Pushes an acceptor that never transitions, i.e. accept nothing
     ///
     /// ```rust
@@ -72,16 +91,16 @@ impl Compiler for ForwardCompiler {
     /// Graph:
     /// --> ( 0 )  (( 1 ))
     /// ```
-    fn expr_0(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn expr_0(anfa: &mut ANFA<S>) -> Result<(), &'static str> {
         let q0 = anfa.delta.len();
         let f = q0 + 1;
         let machine_a = [q0, f];
-        anfa.delta.push((
+        anfa.push_state((
             // push non-transitioning state
             None,
             [None, None],
         ));
-        anfa.delta.push((
+        anfa.push_state((
             // push final state
             None,
             [None, None],
@@ -114,15 +133,52 @@ impl Compiler for ForwardCompiler {
     /// Graph:
     /// --> (( 0 ))
     /// ```
-    fn expr_1(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn expr_1(anfa: &mut ANFA<S>) -> Result<(), &'static str> {
+        let q0 = anfa.delta.len();
+        let f = q0;
+        let machine_a = [q0, f];
+        anfa.push_state((
+            // push final state
+            None,
+            [None, None],
+        ));
+        anfa.automata_refs.push(machine_a);
+        Ok(())
+    }
+
+    /// Pushes an acceptor in final state that may only be entered where `assertion` holds,
+    /// i.e. accept the empty word at positions matching `^`, `$`, `\b`, or `\B`
+    ///
+    /// ```rust
+    /// use regexxx::assertion::Assertion;
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap(); // always safe!
+    /// ForwardCompiler::expr_assert(&mut machine, Assertion::WordBoundary).unwrap();
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// assert_eq!(machine.find("ab a").unwrap(), Some((3, 4)));
+    /// ```
+    ///
+    /// ```text
+    /// Definition of `^`
+    ///
+    /// State table:
+    /// | Q | T | Q |
+    /// |---|---|---|
+    /// | 0 |   |   | (q0 = f, ^)
+    ///
+    /// Graph:
+    /// --> (( 0 ^ ))
+    /// ```
+    fn expr_assert(anfa: &mut ANFA<S>, assertion: Assertion) -> Result<(), &'static str> {
         let q0 = anfa.delta.len();
         let f = q0;
         let machine_a = [q0, f];
-        anfa.delta.push((
+        anfa.push_state((
             // push final state
             None,
             [None, None],
         ));
+        anfa.assertions.insert(q0, assertion);
         anfa.automata_refs.push(machine_a);
         Ok(())
     }
@@ -152,16 +208,16 @@ impl Compiler for ForwardCompiler {
     /// Graph:
     /// --> ( 0 ) -- 'a' --> (( 1 ))
     /// ```
-    fn expr_a(anfa: &mut ANFA, c: char) -> Result<(), &'static str> {
+    fn expr_a(anfa: &mut ANFA<S>, c: S) -> Result<(), &'static str> {
         let q0 = anfa.delta.len();
         let f = q0 + 1;
         let machine_a = [q0, f];
-        anfa.delta.push((
+        anfa.push_state((
             // push transition to Q `f` along Label `c`
             Some(c),
             [Some(f), None],
         ));
-        anfa.delta.push((
+        anfa.push_state((
             // push final state
             None,
             [None, None],
@@ -212,7 +268,7 @@ impl Compiler for ForwardCompiler {
     /// machine_n = (machine_a ⋅ machine_b) ⋅ machine_c
     /// machine_n = machine_a ⋅ (machine_b ⋅ machine_c)
     /// ```
-    fn concatenate(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn concatenate(anfa: &mut ANFA<S>) -> Result<(), &'static str> {
         match anfa.automata_refs.len() {
             0 | 1 => {
                 return Err("Concatenation requires two operands.");
@@ -277,12 +333,9 @@ impl Compiler for ForwardCompiler {
     /// --> ( 2 ) -- ε --> ( 3 ) <------------ ε ------------|
     ///                         \-- 1 --> (( 4 ))
     /// ```
-    fn star(anfa: &mut ANFA) -> Result<(), &'static str> {
-        match anfa.automata_refs.len() {
-            0 => {
-                return Err("Star requires one operand.");
-            }
-            _ => {}
+    fn star(anfa: &mut ANFA<S>) -> Result<(), &'static str> {
+        if anfa.automata_refs.is_empty() {
+            return Err("Star requires one operand.");
         };
         let [machine_a_q0, machine_a_f] = match anfa.automata_refs.pop() {
             None => {
@@ -295,17 +348,17 @@ impl Compiler for ForwardCompiler {
         let machine_b_q = machine_b_q0 + 1;
         let machine_b_f = machine_b_q0 + 2;
         let machine_b = [machine_b_q0, machine_b_f];
-        anfa.delta.push((
+        anfa.push_state((
             // push epsilon transition to union
             None,
             [Some(machine_b_q), None],
         ));
-        anfa.delta.push((
+        anfa.push_state((
             // push union of machine_a and final state
             None,
             [Some(machine_a_q0), Some(machine_b_f)],
         ));
-        anfa.delta.push((
+        anfa.push_state((
             // push final state
             None,
             [None, None],
@@ -357,7 +410,7 @@ impl Compiler for ForwardCompiler {
     /// ( 4 )                                    ε --> (( 5 ))
     ///     \ -- 1 --> ( 2 ) -- 'b' --> ( 3 ) --/
     /// ```
-    fn union(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn union(anfa: &mut ANFA<S>) -> Result<(), &'static str> {
         let machine_c_q0 = anfa.delta.len();
         match machine_c_q0 {
             0 | 1 => {
@@ -381,12 +434,12 @@ impl Compiler for ForwardCompiler {
             }
             Some(machine_a) => machine_a,
         };
-        anfa.delta.push((
+        anfa.push_state((
             // push union transition
             None,
             [Some(machine_a_q0), Some(machine_b_q0)],
         ));
-        anfa.delta.push((
+        anfa.push_state((
             // push final state
             None,
             [None, None],
@@ -408,11 +461,13 @@ impl Compiler for ForwardCompiler {
 
 #[cfg(test)]
 mod tests {
+    use crate::assertion::Assertion;
     use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::ANFA;
 
     #[test]
     fn test_expr_0() {
-        let mut machine = ForwardCompiler::from_expr_0().unwrap();
+        let mut machine: ANFA = ForwardCompiler::from_expr_0().unwrap();
         assert_eq!(
             machine.automata_refs.len(),
             1,
@@ -455,7 +510,7 @@ mod tests {
 
     #[test]
     fn test_expr_1() {
-        let mut machine = ForwardCompiler::from_expr_1().unwrap();
+        let mut machine: ANFA = ForwardCompiler::from_expr_1().unwrap();
 
         assert_eq!(
             machine.automata_refs.len(),
@@ -534,6 +589,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_expr_a_generic_symbol() {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        enum Event {
+            Open,
+            Close,
+        }
+        let mut machine = ForwardCompiler::from_expr_a(Event::Open).unwrap();
+        ForwardCompiler::expr_a(&mut machine, Event::Close).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let [machine_a_q0, machine_a_f] = machine.automata_refs[0];
+        assert_eq!(
+            machine.delta[machine_a_q0],
+            (Some(Event::Open), [Some(machine_a_q0 + 1), None]),
+            "Expression 'a' (literal) accepts any symbol type as its label"
+        );
+        assert_eq!(
+            machine.delta[machine_a_f - 1],
+            (Some(Event::Close), [Some(machine_a_f), None]),
+            "Expression 'a' (literal) accepts any symbol type as its label"
+        );
+    }
+
+    #[test]
+    fn test_expr_assert() {
+        let machine: ANFA = ForwardCompiler::from_expr_assert(Assertion::StartText).unwrap();
+        let [machine_a_q0, machine_a_f] = machine.automata_refs[0];
+        assert_eq!(
+            machine_a_q0, machine_a_f,
+            "An assertion accepts the empty word"
+        );
+        assert_eq!(machine.delta[machine_a_q0], (None, [None, None]));
+        assert_eq!(
+            machine.assertions.get(&machine_a_q0),
+            Some(&Assertion::StartText),
+            "Assertions are kept as given"
+        );
+    }
+
     #[test]
     fn test_concatenate() {
         let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
@@ -1,17 +1,20 @@
+use crate::assertion::Assertion;
 use crate::ANFA;
 
 pub mod bidirectional_compiler;
 pub mod coverage_compiler;
 pub mod forward_compiler;
 
-pub trait Compiler {
-  fn from_expr_0() -> Result<ANFA, &'static str>;
-  fn from_expr_1() -> Result<ANFA, &'static str>;
-  fn from_expr_a(c: char) -> Result<ANFA, &'static str>;
-  fn expr_0(anfa: &mut ANFA) -> Result<(), &'static str>;
-  fn expr_1(anfa: &mut ANFA) -> Result<(), &'static str>;
-  fn expr_a(anfa: &mut ANFA, c: char) -> Result<(), &'static str>;
-  fn concatenate(anfa: &mut ANFA) -> Result<(), &'static str>;
-  fn star(anfa: &mut ANFA) -> Result<(), &'static str>;
-  fn union(anfa: &mut ANFA) -> Result<(), &'static str>;
+pub trait Compiler<S: Copy + Eq = char> {
+  fn from_expr_0() -> Result<ANFA<S>, &'static str>;
+  fn from_expr_1() -> Result<ANFA<S>, &'static str>;
+  fn from_expr_a(c: S) -> Result<ANFA<S>, &'static str>;
+  fn from_expr_assert(assertion: Assertion) -> Result<ANFA<S>, &'static str>;
+  fn expr_0(anfa: &mut ANFA<S>) -> Result<(), &'static str>;
+  fn expr_1(anfa: &mut ANFA<S>) -> Result<(), &'static str>;
+  fn expr_a(anfa: &mut ANFA<S>, c: S) -> Result<(), &'static str>;
+  fn expr_assert(anfa: &mut ANFA<S>, assertion: Assertion) -> Result<(), &'static str>;
+  fn concatenate(anfa: &mut ANFA<S>) -> Result<(), &'static str>;
+  fn star(anfa: &mut ANFA<S>) -> Result<(), &'static str>;
+  fn union(anfa: &mut ANFA<S>) -> Result<(), &'static str>;
 }
@@ -0,0 +1,227 @@
+use crate::analysis::product_search;
+use crate::dfa::DFA;
+use crate::{QId, ANFA};
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// How two token patterns conflict, see `conflicts`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ConflictKind {
+    /// Some word is a token of both patterns, so which token it is depends
+    /// on rule order
+    Overlap,
+    /// Some token of the first pattern is a proper prefix of a token of the
+    /// second, e.g. `=` and `==`, so the lexer must look ahead to choose
+    Prefix,
+}
+
+/// Two token patterns that a lexer can't tell apart from the input alone
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict<S> {
+    /// The index of the first pattern
+    pub first: usize,
+    /// The index of the second pattern
+    pub second: usize,
+    pub kind: ConflictKind,
+    /// A shortest word accepted by the first pattern, and for an overlap by
+    /// the second too
+    pub witness: Vec<S>,
+    /// Empty for an overlap, otherwise a non-empty word such that the second
+    /// pattern accepts `witness` followed by `extension`
+    pub extension: Vec<S>,
+}
+
+/// Marks the states with a non-empty path to a final state
+fn extendable<S: Copy + Eq>(dfa: &DFA<S>) -> Vec<bool> {
+    let mut into: Vec<Vec<QId>> = vec![Vec::new(); dfa.delta.len()];
+    for (q, transitions) in dfa.delta.iter().enumerate() {
+        for (_c, q_next) in transitions.iter() {
+            into[*q_next].push(q);
+        }
+    }
+    // states one symbol away from a final state, then their ancestors
+    let mut extendable = vec![false; dfa.delta.len()];
+    let mut stack: Vec<QId> = (0..dfa.delta.len())
+        .filter(|q| dfa.finals[*q])
+        .flat_map(|q| into[q].iter().copied())
+        .collect();
+    while let Some(q) = stack.pop() {
+        if !extendable[q] {
+            extendable[q] = true;
+            stack.extend(into[q].iter().copied());
+        }
+    }
+    extendable
+}
+
+/// A shortest non-empty word leading from `q` to a final state
+fn extension<S: Copy + Eq>(dfa: &DFA<S>, q: QId) -> Option<Vec<S>> {
+    // (state, parent index, symbol read from the parent)
+    let mut visited: Vec<(QId, Option<(usize, S)>)> = vec![(q, None)];
+    let mut seen = vec![false; dfa.delta.len()];
+    let mut queue = VecDeque::from(vec![0]);
+    while let Some(i) = queue.pop_front() {
+        let (q, _parent) = visited[i];
+        for (c, q_next) in dfa.delta[q].iter() {
+            if seen[*q_next] {
+                continue;
+            }
+            seen[*q_next] = true;
+            visited.push((*q_next, Some((i, *c))));
+            if dfa.finals[*q_next] {
+                let mut word = Vec::new();
+                let mut i = visited.len() - 1;
+                while let (_, Some((parent, c))) = visited[i] {
+                    word.push(c);
+                    i = parent;
+                }
+                word.reverse();
+                return Some(word);
+            }
+            queue.push_back(visited.len() - 1);
+        }
+    }
+    None
+}
+
+/// Finds the pairs of token patterns that conflict, each with a witness, so
+/// ambiguities are found when the rules are written rather than at runtime.
+/// Every pair of patterns is checked for an overlap, and both orders of every
+/// pair for a prefix. Conflicts are ordered by pattern indexes, overlaps
+/// before prefixes. Each ANFA must hold exactly one automaton.
+///
+/// ```rust
+/// use regexxx::conflicts::{conflicts, Conflict, ConflictKind};
+/// use regexxx::expr::Expr;
+/// let assign = Expr::literal('=').build().unwrap();
+/// let equals = Expr::literal('=').concat(Expr::literal('=')).build().unwrap();
+/// let found = conflicts(&[&assign, &equals]).unwrap();
+/// assert_eq!(
+///     found,
+///     vec![Conflict {
+///         first: 0,
+///         second: 1,
+///         kind: ConflictKind::Prefix,
+///         witness: vec!['='],
+///         extension: vec!['='],
+///     }]
+/// );
+/// ```
+pub fn conflicts<S: Copy + Eq>(rules: &[&ANFA<S>]) -> Result<Vec<Conflict<S>>, &'static str> {
+    let dfas = rules
+        .iter()
+        .map(|rule| rule.determinize())
+        .collect::<Result<Vec<DFA<S>>, &'static str>>()?;
+    let extendable: Vec<Vec<bool>> = dfas.iter().map(extendable).collect();
+    let mut found = Vec::new();
+    for (first, a) in dfas.iter().enumerate() {
+        for (second, b) in dfas.iter().enumerate() {
+            if first == second {
+                continue;
+            }
+            let accepts = |dfa: &DFA<S>, q: Option<QId>| q.is_some_and(|q| dfa.finals[q]);
+            if first < second {
+                let overlap = product_search(a, b, |q_a, q_b| accepts(a, q_a) && accepts(b, q_b));
+                if let Some((witness, _q_a, _q_b)) = overlap {
+                    found.push(Conflict {
+                        first,
+                        second,
+                        kind: ConflictKind::Overlap,
+                        witness,
+                        extension: Vec::new(),
+                    });
+                }
+            }
+            let prefix = product_search(a, b, |q_a, q_b| {
+                accepts(a, q_a) && q_b.is_some_and(|q| extendable[second][q])
+            });
+            if let Some((witness, _q_a, Some(q_b))) = prefix {
+                found.push(Conflict {
+                    first,
+                    second,
+                    kind: ConflictKind::Prefix,
+                    witness,
+                    extension: extension(b, q_b).unwrap_or_default(),
+                });
+            }
+        }
+    }
+    found.sort_by_key(|conflict| {
+        (
+            conflict.first.min(conflict.second),
+            conflict.first.max(conflict.second),
+            conflict.kind == ConflictKind::Prefix,
+            conflict.first,
+        )
+    });
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conflicts::{conflicts, ConflictKind};
+    use crate::expr::Expr;
+    use crate::ANFA;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn letters() -> Expr {
+        Expr::literal('a')
+            .union(Expr::literal('f'))
+            .union(Expr::literal('i'))
+            .union(Expr::literal('n'))
+    }
+
+    #[test]
+    fn test_conflicts() {
+        // if, and identifiers of a, f, i, and n
+        let keyword = Expr::word("if").build().unwrap();
+        let identifier = letters().concat(letters().star()).build().unwrap();
+        let number = Expr::literal('0')
+            .union(Expr::literal('1'))
+            .build()
+            .unwrap();
+        let found = conflicts(&[&keyword, &identifier, &number]).unwrap();
+        let kinds: Vec<_> = found
+            .iter()
+            .map(|conflict| (conflict.first, conflict.second, conflict.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (0, 1, ConflictKind::Overlap),
+                (0, 1, ConflictKind::Prefix),
+                (1, 0, ConflictKind::Prefix),
+            ]
+        );
+        assert_eq!(found[0].witness, vec!['i', 'f']);
+        assert!(found[0].extension.is_empty());
+        // if, then any letter
+        assert_eq!(found[1].witness, vec!['i', 'f']);
+        assert_eq!(found[1].extension.len(), 1);
+        // i, then f
+        assert_eq!(found[2].witness, vec!['i']);
+        assert_eq!(found[2].extension, vec!['f']);
+        for conflict in found.iter().filter(|c| c.kind == ConflictKind::Prefix) {
+            let mut longer = conflict.witness.clone();
+            longer.extend(conflict.extension.iter().copied());
+            let second = [&keyword, &identifier][conflict.second]
+                .determinize()
+                .unwrap();
+            assert!(second.accepts(longer));
+        }
+    }
+
+    #[test]
+    fn test_no_conflicts() {
+        let a = Expr::word("ab").build().unwrap();
+        let b = Expr::word("ba").build().unwrap();
+        let empty: ANFA = Expr::zero().build().unwrap();
+        assert_eq!(conflicts(&[&a, &b, &empty]).unwrap(), vec![]);
+        assert_eq!(conflicts::<char>(&[]).unwrap(), vec![]);
+        let mut unfinished = Expr::word("a").build().unwrap();
+        unfinished.automata_refs.clear();
+        assert!(conflicts(&[&a, &unfinished]).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use crate::ANFA;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "rand")]
+use rand::Rng;
+
+impl<S: Copy + Eq> ANFA<S> {
+    /// Returns up to `k` distinct accepted words of at most `max_len` symbols,
+    /// shortest first. Words of the same length are ordered by the priority of
+    /// the transitions that read them. The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE (a+b)*c
+    /// let machine = Expr::literal('a').union(Expr::literal('b')).star().concat(Expr::literal('c')).build().unwrap();
+    /// let words: Vec<String> = machine
+    ///     .accepted_words(4, 2)
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .map(|word| word.into_iter().collect())
+    ///     .collect();
+    /// assert_eq!(words, vec!["c", "ac", "bc"]);
+    /// ```
+    pub fn accepted_words(&self, k: usize, max_len: usize) -> Result<Vec<Vec<S>>, &'static str> {
+        let dfa = self.determinize()?.minimize();
+        // finishes[r][q] is true when some word of exactly r symbols leads from q to a final state
+        let mut finishes = vec![dfa.finals.clone()];
+        for r in 1..=max_len {
+            let row = dfa
+                .delta
+                .iter()
+                .map(|transitions| {
+                    transitions
+                        .iter()
+                        .any(|(_c, q_next)| finishes[r - 1][*q_next])
+                })
+                .collect();
+            finishes.push(row);
+        }
+        let mut words = Vec::new();
+        for len in 0..=max_len {
+            if !finishes[len][dfa.q0] {
+                continue;
+            }
+            // depth-first search of the words of exactly `len` symbols,
+            // (state, symbols remaining, next transition to try)
+            let mut word = Vec::with_capacity(len);
+            let mut stack = vec![(dfa.q0, len, 0)];
+            while let Some((q, remaining, next)) = stack.pop() {
+                if words.len() == k {
+                    return Ok(words);
+                }
+                if remaining == 0 {
+                    words.push(word.clone());
+                    word.pop();
+                    continue;
+                }
+                match dfa.delta[q][next..]
+                    .iter()
+                    .position(|(_c, q_next)| finishes[remaining - 1][*q_next])
+                {
+                    Some(offset) => {
+                        let (c, q_next) = dfa.delta[q][next + offset];
+                        stack.push((q, remaining, next + offset + 1));
+                        stack.push((q_next, remaining - 1, 0));
+                        word.push(c);
+                    }
+                    None => {
+                        word.pop();
+                    }
+                }
+            }
+        }
+        Ok(words)
+    }
+
+    /// Returns up to `k` distinct accepted words of at most `max_len` symbols,
+    /// drawn with `sample`. Sampling stops after `k` words or after `attempts`
+    /// samples in a row produce nothing new, so fewer than `k` words are returned
+    /// when the language is small or its words are unlikely.
+    #[cfg(feature = "rand")]
+    pub fn sample_words<R: Rng>(
+        &self,
+        rng: &mut R,
+        k: usize,
+        max_len: usize,
+        attempts: usize,
+    ) -> Result<Vec<Vec<S>>, &'static str> {
+        let mut words: Vec<Vec<S>> = Vec::new();
+        let mut misses = 0;
+        while words.len() < k && misses < attempts {
+            match self.sample(rng, max_len)? {
+                None => break,
+                Some(word) => {
+                    if words.contains(&word) {
+                        misses += 1;
+                    } else {
+                        misses = 0;
+                        words.push(word);
+                    }
+                }
+            }
+        }
+        Ok(words)
+    }
+}
+
+/// Writes each distinct word on its own line, in order of first appearance.
+/// Lines end with `\n`. Words containing `\n` or `\r` cannot be told apart
+/// from the lines around them and are rejected; see `write_dir` for those.
+///
+/// ```rust
+/// use regexxx::corpus::to_lines;
+/// let words = vec![vec!['a', 'b'], vec!['c'], vec!['a', 'b']];
+/// assert_eq!(to_lines(&words).unwrap(), "ab\nc\n");
+/// ```
+pub fn to_lines(words: &[Vec<char>]) -> Result<String, &'static str> {
+    let mut lines = String::new();
+    let mut written: BTreeSet<&[char]> = BTreeSet::new();
+    for word in words.iter() {
+        if word.contains(&'\n') || word.contains(&'\r') {
+            return Err("A word contains a line break.");
+        }
+        if written.insert(word) {
+            lines.extend(word.iter());
+            lines.push('\n');
+        }
+    }
+    Ok(lines)
+}
+
+/// Writes `to_lines(words)` to the file at `path`, replacing it
+#[cfg(feature = "std")]
+pub fn write_lines<P: AsRef<std::path::Path>>(words: &[Vec<char>], path: P) -> std::io::Result<()> {
+    let lines =
+        to_lines(words).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    std::fs::write(path, lines)
+}
+
+/// Writes each distinct word to its own file in `dir`, which is created when
+/// missing. Files are named by the position of the word among the distinct
+/// words, `0`, `1`, ..., and hold the word's UTF-8 bytes without a trailing
+/// newline, the layout fuzzers such as libFuzzer and AFL read seed corpora from.
+/// Returns the number of files written.
+#[cfg(feature = "std")]
+pub fn write_dir<P: AsRef<std::path::Path>>(words: &[Vec<char>], dir: P) -> std::io::Result<usize> {
+    use std::string::ToString;
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    let mut written: BTreeSet<&[char]> = BTreeSet::new();
+    for word in words.iter() {
+        if written.insert(word) {
+            let contents: String = word.iter().collect();
+            std::fs::write(dir.join((written.len() - 1).to_string()), contents)?;
+        }
+    }
+    Ok(written.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::corpus::to_lines;
+    use crate::ANFA;
+    use alloc::string::String;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn strings(words: Vec<Vec<char>>) -> Vec<String> {
+        words
+            .into_iter()
+            .map(|word| word.into_iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_accepted_words() {
+        // RE (ab+a)*
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert_eq!(
+            strings(machine.accepted_words(100, 3).unwrap()),
+            vec!["", "a", "ab", "aa", "aba", "aab", "aaa"],
+            "Every word of (ab+a)* up to three symbols, shortest first"
+        );
+        assert_eq!(
+            strings(machine.accepted_words(3, 3).unwrap()),
+            vec!["", "a", "ab"]
+        );
+        assert!(machine.accepted_words(0, 3).unwrap().is_empty());
+        let nothing: ANFA = ForwardCompiler::from_expr_0().unwrap();
+        assert!(nothing.accepted_words(10, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_accepted_words_dead_ends() {
+        // RE a(b+0)c+d, the 0 branch and the words longer than max_len are skipped
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::expr_0(&mut machine).unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'd').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert_eq!(strings(machine.accepted_words(10, 2).unwrap()), vec!["d"]);
+        assert_eq!(
+            strings(machine.accepted_words(10, 3).unwrap()),
+            vec!["d", "abc"]
+        );
+    }
+
+    #[test]
+    fn test_to_lines() {
+        let words = vec![vec!['a'], vec![], vec!['a'], vec!['b', 'c']];
+        assert_eq!(
+            to_lines(&words).unwrap(),
+            "a\n\nbc\n",
+            "Duplicates are written once"
+        );
+        assert!(to_lines(&[vec!['a', '\n']]).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_dir() {
+        let dir = std::env::temp_dir().join("regexxx_test_write_dir");
+        let _ = std::fs::remove_dir_all(&dir);
+        let words = vec![vec!['a', '\n'], vec!['b'], vec!['a', '\n']];
+        assert_eq!(crate::corpus::write_dir(&words, &dir).unwrap(), 2);
+        assert_eq!(std::fs::read_to_string(dir.join("0")).unwrap(), "a\n");
+        assert_eq!(std::fs::read_to_string(dir.join("1")).unwrap(), "b");
+        assert!(!dir.join("2").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_sample_words() {
+        use rand::rngs::mock::StepRng;
+        // RE a+b
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        let mut rng = StepRng::new(0, 0x9e37_79b9_7f4a_7c15);
+        let mut words = machine.sample_words(&mut rng, 5, 1, 100).unwrap();
+        words.sort_unstable();
+        assert_eq!(
+            words,
+            vec![vec!['a'], vec!['b']],
+            "Sampling stops once no new words turn up"
+        );
+    }
+}
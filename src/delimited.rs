@@ -0,0 +1,281 @@
+use crate::ANFA;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// How delimiter-separated records are written. Records end in `\n`, and a
+/// `\r` before it is not part of the last field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dialect {
+    /// Separates the fields of a record
+    pub delimiter: char,
+    /// Encloses fields that contain delimiters or newlines. Inside a quoted
+    /// field, a doubled quote stands for one quote.
+    pub quote: Option<char>,
+}
+
+impl Dialect {
+    /// Comma-separated, fields quoted with `"`, as in RFC 4180
+    pub fn csv() -> Dialect {
+        Dialect {
+            delimiter: ',',
+            quote: Some('"'),
+        }
+    }
+
+    /// Tab-separated, without quoting
+    pub fn tsv() -> Dialect {
+        Dialect {
+            delimiter: '\t',
+            quote: None,
+        }
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Dialect {
+        Dialect::csv()
+    }
+}
+
+/// A match inside one field of delimited data, see `ANFA::find_fields`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FieldMatch {
+    /// The index of the record, from 0
+    pub record: usize,
+    /// The index of the field in its record, from 0
+    pub field: usize,
+    /// The byte span of the match in the whole input
+    pub span: (usize, usize),
+}
+
+/// The value of one field: its byte span in the input, without quotes, and
+/// whether it holds doubled quotes to unescape
+struct Field {
+    record: usize,
+    field: usize,
+    span: (usize, usize),
+    escaped: bool,
+}
+
+/// Splits `input` into fields, in order. A trailing newline does not start
+/// an empty record, and text after the closing quote of a field is ignored.
+fn fields(input: &str, dialect: &Dialect) -> Vec<Field> {
+    let mut fields = Vec::new();
+    if input.is_empty() {
+        return fields;
+    }
+    let is_end = |c: char| c == dialect.delimiter || c == '\n';
+    let (mut record, mut field, mut at) = (0, 0, 0);
+    loop {
+        let quote = dialect
+            .quote
+            .filter(|quote| input[at..].starts_with(*quote));
+        let (mut span, escaped, mut next) = match quote {
+            Some(quote) => {
+                let start = at + quote.len_utf8();
+                let mut escaped = false;
+                let mut i = start;
+                let end = loop {
+                    match input[i..].find(quote) {
+                        None => {
+                            i = input.len();
+                            break i;
+                        }
+                        Some(found) => {
+                            let after = i + found + quote.len_utf8();
+                            if input[after..].starts_with(quote) {
+                                escaped = true;
+                                i = after + quote.len_utf8();
+                            } else {
+                                i = after;
+                                break i - quote.len_utf8();
+                            }
+                        }
+                    }
+                };
+                let next = input[i..].find(is_end).map_or(input.len(), |k| i + k);
+                ((start, end), escaped, next)
+            }
+            None => {
+                let next = input[at..].find(is_end).map_or(input.len(), |k| at + k);
+                ((at, next), false, next)
+            }
+        };
+        if quote.is_none() && !input[next..].starts_with(dialect.delimiter) {
+            // the last field of the record
+            if input[span.0..span.1].ends_with('\r') {
+                span.1 -= 1;
+            }
+        }
+        fields.push(Field {
+            record,
+            field,
+            span,
+            escaped,
+        });
+        match input[next..].chars().next() {
+            Some(c) if c == dialect.delimiter => {
+                field += 1;
+                next += c.len_utf8();
+            }
+            Some(_) => {
+                // a newline
+                record += 1;
+                field = 0;
+                next += 1;
+                if next == input.len() {
+                    break;
+                }
+            }
+            None => break,
+        }
+        at = next;
+    }
+    fields
+}
+
+impl ANFA {
+    /// Returns the successive non-overlapping matches inside every field of
+    /// the delimiter-separated records of `input`, see `find_iter`, with
+    /// their record, field and span in `input`, so nothing needs to be split
+    /// or re-mapped by the caller. Each field is searched on its own, so a
+    /// match never crosses a delimiter and `^`/`$` match at the ends of
+    /// fields. Quoted fields are searched without their quotes and with
+    /// doubled quotes unescaped. The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::delimited::{Dialect, FieldMatch};
+    /// use regexxx::expr::Expr;
+    /// // RE a,b
+    /// let machine = Expr::literal('a').concat(Expr::literal(',')).concat(Expr::literal('b')).build().unwrap();
+    /// let matches = machine.find_fields("a,b,\"a,b\"\nx,\"a,b\"\n", &Dialect::csv()).unwrap();
+    /// assert_eq!(
+    ///     matches,
+    ///     vec![
+    ///         FieldMatch { record: 0, field: 2, span: (5, 8) },
+    ///         FieldMatch { record: 1, field: 1, span: (13, 16) },
+    ///     ]
+    /// );
+    /// ```
+    pub fn find_fields(
+        &self,
+        input: &str,
+        dialect: &Dialect,
+    ) -> Result<Vec<FieldMatch>, &'static str> {
+        if self.automata_refs.len() != 1 {
+            return Err("Search requires exactly one automaton.");
+        }
+        let mut matches = Vec::new();
+        for field in fields(input, dialect) {
+            let text = &input[field.span.0..field.span.1];
+            let push = |matches: &mut Vec<FieldMatch>, span| {
+                matches.push(FieldMatch {
+                    record: field.record,
+                    field: field.field,
+                    span,
+                })
+            };
+            match dialect.quote.filter(|_| field.escaped) {
+                None => {
+                    for (start, end) in self.find_iter(text)? {
+                        push(&mut matches, (field.span.0 + start, field.span.0 + end));
+                    }
+                }
+                Some(quote) => {
+                    // the unescaped value, and the input offset of each of its bytes
+                    let mut value = String::with_capacity(text.len());
+                    let mut offsets = Vec::with_capacity(text.len() + 1);
+                    let mut chars = text.char_indices();
+                    while let Some((i, c)) = chars.next() {
+                        if c == quote {
+                            chars.next();
+                        }
+                        offsets.extend((0..c.len_utf8()).map(|k| field.span.0 + i + k));
+                        value.push(c);
+                    }
+                    offsets.push(field.span.1);
+                    for (start, end) in self.find_iter(&value)? {
+                        push(&mut matches, (offsets[start], offsets[end]));
+                    }
+                }
+            }
+        }
+        Ok(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::delimited::{fields, Dialect, FieldMatch};
+    use crate::expr::Expr;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn values<'a>(input: &'a str, dialect: &Dialect) -> Vec<(usize, usize, &'a str)> {
+        fields(input, dialect)
+            .iter()
+            .map(|field| {
+                (
+                    field.record,
+                    field.field,
+                    &input[field.span.0..field.span.1],
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fields() {
+        let csv = Dialect::csv();
+        assert_eq!(values("", &csv), vec![]);
+        assert_eq!(values("\n", &csv), vec![(0, 0, "")]);
+        assert_eq!(
+            values("a,,b\r\n\"c\nd\",\"e\"\"f\"", &csv),
+            vec![
+                (0, 0, "a"),
+                (0, 1, ""),
+                (0, 2, "b"),
+                (1, 0, "c\nd"),
+                (1, 1, "e\"\"f"),
+            ]
+        );
+        assert_eq!(values("\"ab\"x,y", &csv), vec![(0, 0, "ab"), (0, 1, "y")]);
+        assert_eq!(values("\"open", &csv), vec![(0, 0, "open")]);
+        assert_eq!(
+            values("\"a\"\tb\n", &Dialect::tsv()),
+            vec![(0, 0, "\"a\""), (0, 1, "b")]
+        );
+    }
+
+    #[test]
+    fn test_find_fields() {
+        // RE ^q"+$, a whole field of q and quotes
+        let machine = Expr::assert(Assertion::StartText)
+            .concat(Expr::literal('q'))
+            .concat(Expr::literal('"').concat(Expr::literal('"').star()))
+            .concat(Expr::assert(Assertion::EndText))
+            .build()
+            .unwrap();
+        let input = "q\",\"q\"\"\"\"\"\r\nxq\"";
+        let matches = machine.find_fields(input, &Dialect::csv()).unwrap();
+        assert_eq!(
+            matches,
+            vec![
+                FieldMatch {
+                    record: 0,
+                    field: 0,
+                    span: (0, 2)
+                },
+                FieldMatch {
+                    record: 0,
+                    field: 1,
+                    span: (4, 9)
+                },
+            ]
+        );
+        let mut unfinished = Expr::literal('q').build().unwrap();
+        unfinished.automata_refs.clear();
+        assert!(unfinished.find_fields("", &Dialect::csv()).is_err());
+    }
+}
@@ -0,0 +1,1321 @@
+use crate::dfa;
+use crate::stats::{Metric, MetricKind, Metrics};
+use crate::ANFA;
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// Premultiplied id of the dead state, which every missing transition enters
+const DEAD: usize = 0;
+
+/// A DFA laid out for matching: one flat transition table indexed by
+/// premultiplied state ids and symbol classes.
+///
+/// Symbols that every state treats the same are merged into one class, so a
+/// row holds one entry per class instead of one per symbol. Class 0 holds
+/// every symbol the DFA has no transition for. State ids are premultiplied
+/// by the row length, so the next state is `table[q + class]` without a
+/// multiplication, and the dead state is id 0.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// // RE (a+b)*c
+/// let machine = Expr::literal('a').union(Expr::literal('b')).star().concat(Expr::literal('c')).build().unwrap();
+/// let dense = machine.to_dense().unwrap();
+/// assert_eq!(dense.class_count(), 3, "a and b are one class");
+/// assert!(dense.accepts("abbac"));
+/// assert_eq!(dense.find("xxbac!"), Some((2, 5)));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DFA {
+    /// premultiplied id of q0
+    start: usize,
+    /// number of symbol classes, the length of a row of `table`
+    stride: usize,
+    /// class of each ASCII symbol
+    ascii_classes: [u8; 128],
+    /// class of every other symbol with a class, sorted by symbol
+    classes: Vec<(char, usize)>,
+    /// `table[q + class]` is the premultiplied id of the next state
+    table: Vec<usize>,
+    /// `accepting[q / stride]` is true when state `q` is accepting
+    accepting: Vec<bool>,
+}
+
+/// Symbol classes shared by one or more DFAs
+struct Classes {
+    /// one representative symbol per class, class 0 has none
+    representatives: Vec<Option<char>>,
+    /// class of each ASCII symbol
+    ascii_classes: [u8; 128],
+    /// class of every other symbol with a class, sorted by symbol
+    classes: Vec<(char, usize)>,
+}
+
+impl Classes {
+    /// Merges the symbols that every state of every DFA in `dfas` treats the same
+    fn new(dfas: &[&dfa::DFA<char>]) -> Classes {
+        // a symbol's signature is the state it enters from each state of
+        // each DFA, symbols with equal signatures are indistinguishable
+        let state_count: usize = dfas.iter().map(|dfa| dfa.delta.len()).sum();
+        let mut signatures: BTreeMap<char, Vec<Option<usize>>> = BTreeMap::new();
+        let mut offset = 0;
+        for dfa in dfas.iter() {
+            for (q, transitions) in dfa.delta.iter().enumerate() {
+                for (label, q_next) in transitions.iter() {
+                    signatures
+                        .entry(*label)
+                        .or_insert_with(|| vec![None; state_count])[offset + q] = Some(*q_next);
+                }
+            }
+            offset += dfa.delta.len();
+        }
+        let mut class_ids: BTreeMap<&Vec<Option<usize>>, usize> = BTreeMap::new();
+        let mut representatives: Vec<Option<char>> = vec![None];
+        let mut ascii_classes = [0u8; 128];
+        let mut classes = Vec::new();
+        for (c, signature) in signatures.iter() {
+            let class = match class_ids.get(signature) {
+                Some(class) => *class,
+                None => {
+                    let class = representatives.len();
+                    class_ids.insert(signature, class);
+                    representatives.push(Some(*c));
+                    class
+                }
+            };
+            match (c.is_ascii(), u8::try_from(class)) {
+                (true, Ok(class)) => ascii_classes[*c as usize] = class,
+                _ => classes.push((*c, class)),
+            }
+        }
+        Classes {
+            representatives,
+            ascii_classes,
+            classes,
+        }
+    }
+}
+
+impl DFA {
+    /// Lays out `dfa` as a dense table. Symbols are only merged when they
+    /// agree on every transition of `dfa`, so minimize it first, see
+    /// `dfa::DFA::minimize`.
+    pub fn new(dfa: &dfa::DFA<char>) -> DFA {
+        DFA::with_classes(dfa, &Classes::new(&[dfa]))
+    }
+
+    /// Lays out `dfa` with symbol classes computed for it and possibly other
+    /// DFAs over the same symbols
+    fn with_classes(dfa: &dfa::DFA<char>, classes: &Classes) -> DFA {
+        let stride = classes.representatives.len();
+        // state 0 is dead, DFA state q is dense state q + 1
+        let premultiply = |q: usize| (q + 1) * stride;
+        let mut table = vec![DEAD; (dfa.delta.len() + 1) * stride];
+        let mut accepting = vec![false; dfa.delta.len() + 1];
+        for (q, transitions) in dfa.delta.iter().enumerate() {
+            accepting[q + 1] = dfa.finals[q];
+            for (class, representative) in classes.representatives.iter().enumerate() {
+                if let Some(c) = representative {
+                    if let Some((_label, q_next)) = transitions.iter().find(|(label, _)| label == c)
+                    {
+                        table[premultiply(q) + class] = premultiply(*q_next);
+                    }
+                }
+            }
+        }
+        DFA {
+            start: premultiply(dfa.q0),
+            stride,
+            ascii_classes: classes.ascii_classes,
+            classes: classes.classes.clone(),
+            table,
+            accepting,
+        }
+    }
+
+    /// The number of symbol classes, including the class of unknown symbols
+    pub fn class_count(&self) -> usize {
+        self.stride
+    }
+
+    /// The number of states, including the dead state
+    pub fn state_count(&self) -> usize {
+        self.accepting.len()
+    }
+
+    #[inline]
+    fn class(&self, c: char) -> usize {
+        class_of(&self.ascii_classes, &self.classes, c)
+    }
+
+    /// Returns true when the DFA accepts exactly `haystack`
+    pub fn accepts(&self, haystack: &str) -> bool {
+        Table::accepts(self, haystack)
+    }
+
+    /// Returns the byte span of the leftmost-longest match in `haystack`.
+    ///
+    /// A DFA no longer knows which union branch a path took, so among matches
+    /// at the leftmost position the longest wins, unlike `ANFA::find`.
+    /// The haystack is read once, see `Table::find`, in time linear in its
+    /// length times the number of states.
+    pub fn find(&self, haystack: &str) -> Option<(usize, usize)> {
+        Table::find(self, haystack)
+    }
+
+    /// Run-length encodes every row of the table, see `CompressedDFA`
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE grüße, every state reads one symbol and sends the others to the dead state
+    /// let machine = Expr::word("grüße").build().unwrap();
+    /// let compressed = machine.to_dense().unwrap().compress();
+    /// assert!(compressed.accepts("grüße"));
+    /// assert_eq!(compressed.find("Grüße, grüße"), Some((9, 16)));
+    /// assert!(compressed.stats().ratio() < 1.0);
+    /// ```
+    pub fn compress(&self) -> CompressedDFA {
+        let mut rows = Vec::with_capacity(self.state_count() + 1);
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for row in self.table.chunks(self.stride) {
+            rows.push(runs.len());
+            let mut last = None;
+            for (class, q_next) in row.iter().enumerate() {
+                let q_next = q_next / self.stride;
+                if last != Some(q_next) {
+                    runs.push((class, q_next));
+                    last = Some(q_next);
+                }
+            }
+        }
+        rows.push(runs.len());
+        CompressedDFA {
+            start: self.start / self.stride,
+            stride: self.stride,
+            ascii_classes: self.ascii_classes,
+            classes: self.classes.clone(),
+            rows,
+            runs,
+            accepting: self.accepting.clone(),
+        }
+    }
+}
+
+/// The transitions of a DFA laid out for matching, and the searches they
+/// support. Every layout has its dead state at id 0.
+trait Table {
+    fn start(&self) -> usize;
+
+    /// The number of states, including the dead state
+    fn state_count(&self) -> usize;
+
+    /// The position of state `q` among all states, from 0
+    fn state_index(&self, q: usize) -> usize;
+
+    fn next_state(&self, q: usize, c: char) -> usize;
+
+    fn is_accepting(&self, q: usize) -> bool;
+
+    fn accepts(&self, haystack: &str) -> bool {
+        let mut q = self.start();
+        for c in haystack.chars() {
+            q = self.next_state(q, c);
+            if q == DEAD {
+                return false;
+            }
+        }
+        self.is_accepting(q)
+    }
+
+    /// Scans `haystack` once, starting a thread in q0 at every position
+    /// until a match is found. Threads in the same state accept the same
+    /// continuations, so only the one that started first is kept, and at
+    /// most one thread per state is alive. Once a match is found, threads
+    /// that started after it are dropped, and the others run until they
+    /// enter the dead state in search of a longer or further left match.
+    fn find(&self, haystack: &str) -> Option<(usize, usize)> {
+        // (state, start of its match), earliest start first
+        let mut current: Vec<(usize, usize)> = Vec::new();
+        let mut next: Vec<(usize, usize)> = Vec::new();
+        // the offset each state was last entered at
+        let mut entered = vec![usize::MAX; self.state_count()];
+        let mut best: Option<(usize, usize)> = None;
+        let mut at = 0;
+        loop {
+            if best.is_none() {
+                // lowest priority, threads that started earlier are preferred
+                let q = self.start();
+                if entered[self.state_index(q)] != at {
+                    entered[self.state_index(q)] = at;
+                    current.push((q, at));
+                    if self.is_accepting(q) {
+                        best = Some((at, at));
+                    }
+                }
+            } else if current.is_empty() {
+                break;
+            }
+            let c = match haystack[at..].chars().next() {
+                None => break,
+                Some(c) => c,
+            };
+            let end = at + c.len_utf8();
+            for (q, start) in current.iter() {
+                #[cfg(test)]
+                crate::engine::steps::count();
+                if best.is_some_and(|(best_start, _)| *start > best_start) {
+                    break;
+                }
+                let q_next = self.next_state(*q, c);
+                if q_next == DEAD || entered[self.state_index(q_next)] == end {
+                    continue;
+                }
+                entered[self.state_index(q_next)] = end;
+                next.push((q_next, *start));
+                if self.is_accepting(q_next)
+                    && best.is_none_or(|(best_start, _)| *start <= best_start)
+                {
+                    best = Some((*start, end));
+                }
+            }
+            core::mem::swap(&mut current, &mut next);
+            next.clear();
+            at = end;
+        }
+        best
+    }
+
+    /// Runs the DFA from q0 over `chars`, each paired with the offset reached
+    /// after reading it, and returns the offset after the longest accepted
+    /// prefix, `from` when only the empty prefix is accepted. Stops as soon
+    /// as it enters the dead state.
+    fn longest<I: Iterator<Item = (usize, char)>>(&self, from: usize, chars: I) -> Option<usize> {
+        let mut q = self.start();
+        let mut end = if self.is_accepting(q) {
+            Some(from)
+        } else {
+            None
+        };
+        for (at, c) in chars {
+            q = self.next_state(q, c);
+            if q == DEAD {
+                break;
+            }
+            if self.is_accepting(q) {
+                end = Some(at);
+            }
+        }
+        end
+    }
+}
+
+/// The class of `c` in the lookups of a dense DFA
+#[inline]
+fn class_of(ascii_classes: &[u8; 128], classes: &[(char, usize)], c: char) -> usize {
+    if c.is_ascii() {
+        let class = ascii_classes[c as usize] as usize;
+        if class != 0 {
+            return class;
+        }
+    }
+    match classes.binary_search_by_key(&c, |(symbol, _class)| *symbol) {
+        Ok(i) => classes[i].1,
+        Err(_) => 0,
+    }
+}
+
+impl Table for DFA {
+    fn start(&self) -> usize {
+        self.start
+    }
+
+    fn state_count(&self) -> usize {
+        self.accepting.len()
+    }
+
+    fn state_index(&self, q: usize) -> usize {
+        q / self.stride
+    }
+
+    #[inline]
+    fn next_state(&self, q: usize, c: char) -> usize {
+        self.table[q + self.class(c)]
+    }
+
+    #[inline]
+    fn is_accepting(&self, q: usize) -> bool {
+        self.accepting[q / self.stride]
+    }
+}
+
+/// A dense DFA whose rows are run-length encoded, see `DFA::compress`.
+///
+/// Most states send most classes to one target, usually the dead state, so
+/// a row of `class_count` entries shrinks to a few runs of consecutive
+/// classes with the same target. Finding the next state binary searches the
+/// runs of a row instead of indexing it, and the savings grow with the
+/// number of classes, e.g. in machines over many Unicode symbols.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressedDFA {
+    /// id of q0, ids are not premultiplied
+    start: usize,
+    /// number of symbol classes, the length of a row before compression
+    stride: usize,
+    /// class of each ASCII symbol
+    ascii_classes: [u8; 128],
+    /// class of every other symbol with a class, sorted by symbol
+    classes: Vec<(char, usize)>,
+    /// the runs of state `q` are `runs[rows[q]..rows[q + 1]]`
+    rows: Vec<usize>,
+    /// the first class of each run and the id of the state it enters
+    runs: Vec<(usize, usize)>,
+    /// `accepting[q]` is true when state `q` is accepting
+    accepting: Vec<bool>,
+}
+
+/// How much `DFA::compress` saved on one machine, see `CompressedDFA::stats`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CompressionStats {
+    /// States, including the dead state
+    pub states: usize,
+    /// Symbol classes, the entries of an uncompressed row
+    pub classes: usize,
+    /// Runs of all rows
+    pub runs: usize,
+    /// Bytes of the uncompressed transition table
+    pub dense_bytes: usize,
+    /// Bytes of the runs and their row offsets
+    pub compressed_bytes: usize,
+}
+
+impl CompressionStats {
+    /// The compressed size relative to the dense size, below 1 when
+    /// compression saved memory
+    pub fn ratio(&self) -> f64 {
+        self.compressed_bytes as f64 / self.dense_bytes as f64
+    }
+}
+
+impl Metrics for CompressionStats {
+    fn metrics(&self) -> Vec<Metric> {
+        let gauge = |name, help, value: usize| Metric {
+            name,
+            help,
+            kind: MetricKind::Gauge,
+            value: value as u64,
+        };
+        vec![
+            gauge("dense_dfa_states", "Number of DFA states", self.states),
+            gauge(
+                "dense_dfa_classes",
+                "Number of symbol classes",
+                self.classes,
+            ),
+            gauge(
+                "dense_dfa_runs",
+                "Number of runs of compressed rows",
+                self.runs,
+            ),
+            gauge(
+                "dense_dfa_table_bytes",
+                "Bytes of the uncompressed transition table",
+                self.dense_bytes,
+            ),
+            gauge(
+                "dense_dfa_compressed_bytes",
+                "Bytes of the compressed transition table",
+                self.compressed_bytes,
+            ),
+        ]
+    }
+}
+
+impl CompressedDFA {
+    /// The number of symbol classes, including the class of unknown symbols
+    pub fn class_count(&self) -> usize {
+        self.stride
+    }
+
+    /// The number of states, including the dead state
+    pub fn state_count(&self) -> usize {
+        self.accepting.len()
+    }
+
+    /// The sizes of the table before and after compression
+    pub fn stats(&self) -> CompressionStats {
+        let word = core::mem::size_of::<usize>();
+        CompressionStats {
+            states: self.state_count(),
+            classes: self.stride,
+            runs: self.runs.len(),
+            dense_bytes: self.state_count() * self.stride * word,
+            compressed_bytes: self.runs.len() * 2 * word + self.rows.len() * word,
+        }
+    }
+
+    /// Returns true when the DFA accepts exactly `haystack`
+    pub fn accepts(&self, haystack: &str) -> bool {
+        Table::accepts(self, haystack)
+    }
+
+    /// Returns the byte span of the leftmost-longest match, see `DFA::find`
+    pub fn find(&self, haystack: &str) -> Option<(usize, usize)> {
+        Table::find(self, haystack)
+    }
+}
+
+impl Table for CompressedDFA {
+    fn start(&self) -> usize {
+        self.start
+    }
+
+    fn state_count(&self) -> usize {
+        self.accepting.len()
+    }
+
+    fn state_index(&self, q: usize) -> usize {
+        q
+    }
+
+    #[inline]
+    fn next_state(&self, q: usize, c: char) -> usize {
+        let class = class_of(&self.ascii_classes, &self.classes, c);
+        let runs = &self.runs[self.rows[q]..self.rows[q + 1]];
+        // every row has a run starting at class 0
+        let run = runs.partition_point(|(first, _q_next)| *first <= class) - 1;
+        runs[run].1
+    }
+
+    #[inline]
+    fn is_accepting(&self, q: usize) -> bool {
+        self.accepting[q]
+    }
+}
+
+/// Marks the slots of a `DoubleArrayDFA` that no state owns
+const EMPTY: usize = usize::MAX;
+
+/// A dense DFA in the double-array layout of Darts and other trie
+/// libraries, see `DFA::to_double_array`.
+///
+/// The rows of every state are overlaid in one array of slots: the
+/// transition of state `q` on class `c` is in slot `base[q] + c` when
+/// `check` of that slot is `q`, and enters the dead state otherwise. Rows
+/// are placed so their live transitions never collide, so the arrays grow
+/// with the number of live transitions rather than states times classes,
+/// and every transition is still two array reads. Unlike a trie, a DFA state
+/// may be entered from several states, so the state a slot enters is kept in
+/// its own array instead of being the slot index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DoubleArrayDFA {
+    start: usize,
+    /// number of symbol classes
+    stride: usize,
+    /// class of each ASCII symbol
+    ascii_classes: [u8; 128],
+    /// class of every other symbol with a class, sorted by symbol
+    classes: Vec<(char, usize)>,
+    /// the first slot of the row of each state
+    base: Vec<usize>,
+    /// the state owning each slot, or `EMPTY`
+    check: Vec<usize>,
+    /// the state each owned slot enters
+    next: Vec<usize>,
+    /// `accepting[q]` is true when state `q` is accepting
+    accepting: Vec<bool>,
+}
+
+impl DFA {
+    /// Overlays the rows of the table in a double array, see
+    /// `DoubleArrayDFA`
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE 東京+京都
+    /// let machine = Expr::word("東京").union(Expr::word("京都")).build().unwrap();
+    /// let dense = machine.to_dense().unwrap();
+    /// let double = dense.to_double_array();
+    /// assert!(double.accepts("京都"));
+    /// assert_eq!(double.find("ここは東京です"), Some((9, 15)));
+    /// assert!(double.slot_count() < dense.state_count() * dense.class_count());
+    /// ```
+    pub fn to_double_array(&self) -> DoubleArrayDFA {
+        let state_count = self.state_count();
+        let mut base = vec![0; state_count];
+        let mut check: Vec<usize> = Vec::new();
+        let mut next: Vec<usize> = Vec::new();
+        // the first slot that may be empty
+        let mut first_empty: usize = 0;
+        for (q, row) in self.table.chunks(self.stride).enumerate() {
+            let live: Vec<(usize, usize)> = row
+                .iter()
+                .enumerate()
+                .filter(|(_class, q_next)| **q_next != DEAD)
+                .map(|(class, q_next)| (class, q_next / self.stride))
+                .collect();
+            let first_class = match live.first() {
+                Some((class, _q_next)) => *class,
+                None => continue,
+            };
+            // first fit, starting where the first live class lands on the first empty slot
+            let mut q_base = first_empty.saturating_sub(first_class);
+            while live.iter().any(|(class, _q_next)| {
+                check
+                    .get(q_base + class)
+                    .is_some_and(|owner| *owner != EMPTY)
+            }) {
+                q_base += 1;
+            }
+            base[q] = q_base;
+            for (class, q_next) in live.iter() {
+                let slot = q_base + class;
+                if slot >= check.len() {
+                    check.resize(slot + 1, EMPTY);
+                    next.resize(slot + 1, DEAD);
+                }
+                check[slot] = q;
+                next[slot] = *q_next;
+            }
+            while check.get(first_empty).is_some_and(|owner| *owner != EMPTY) {
+                first_empty += 1;
+            }
+        }
+        DoubleArrayDFA {
+            start: self.start / self.stride,
+            stride: self.stride,
+            ascii_classes: self.ascii_classes,
+            classes: self.classes.clone(),
+            base,
+            check,
+            next,
+            accepting: self.accepting.clone(),
+        }
+    }
+}
+
+impl DoubleArrayDFA {
+    /// The number of symbol classes, including the class of unknown symbols
+    pub fn class_count(&self) -> usize {
+        self.stride
+    }
+
+    /// The number of states, including the dead state
+    pub fn state_count(&self) -> usize {
+        self.accepting.len()
+    }
+
+    /// The number of slots of the double array, owned or empty
+    pub fn slot_count(&self) -> usize {
+        self.check.len()
+    }
+
+    /// Returns true when the DFA accepts exactly `haystack`
+    pub fn accepts(&self, haystack: &str) -> bool {
+        Table::accepts(self, haystack)
+    }
+
+    /// Returns the byte span of the leftmost-longest match, see `DFA::find`
+    pub fn find(&self, haystack: &str) -> Option<(usize, usize)> {
+        Table::find(self, haystack)
+    }
+}
+
+impl Table for DoubleArrayDFA {
+    fn start(&self) -> usize {
+        self.start
+    }
+
+    fn state_count(&self) -> usize {
+        self.accepting.len()
+    }
+
+    fn state_index(&self, q: usize) -> usize {
+        q
+    }
+
+    #[inline]
+    fn next_state(&self, q: usize, c: char) -> usize {
+        let slot = self.base[q] + class_of(&self.ascii_classes, &self.classes, c);
+        match self.check.get(slot) {
+            Some(owner) if *owner == q => self.next[slot],
+            _ => DEAD,
+        }
+    }
+
+    #[inline]
+    fn is_accepting(&self, q: usize) -> bool {
+        self.accepting[q]
+    }
+}
+
+/// The byte order of the numbers in a serialized `BiDfa`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    /// The byte order of the target, whose artifacts load without swapping
+    pub const NATIVE: Endian = if cfg!(target_endian = "big") {
+        Endian::Big
+    } else {
+        Endian::Little
+    };
+
+    fn write(self, value: u32) -> [u8; 4] {
+        match self {
+            Endian::Little => value.to_le_bytes(),
+            Endian::Big => value.to_be_bytes(),
+        }
+    }
+
+    fn read(self, word: [u8; 4]) -> u32 {
+        match self {
+            Endian::Little => u32::from_le_bytes(word),
+            Endian::Big => u32::from_be_bytes(word),
+        }
+    }
+}
+
+/// Written in the artifact's byte order after the magic bytes, so loaders
+/// can tell which order the artifact was written in
+const BYTE_ORDER_MARK: u32 = 0x0102_0304;
+
+/// Appends `value` as 4 bytes in `endian` order
+fn write_u32(bytes: &mut Vec<u8>, endian: Endian, value: usize) -> Result<(), &'static str> {
+    let value = u32::try_from(value).map_err(|_err| "DFA is too large to serialize.")?;
+    bytes.extend_from_slice(&endian.write(value));
+    Ok(())
+}
+
+/// The zero bytes after `len` bytes that align the next field to 4 bytes
+fn padding(len: usize) -> usize {
+    (4 - len % 4) % 4
+}
+
+/// Reads the fields written by `write_u32` back, front to back
+struct Reader<'a> {
+    bytes: &'a [u8],
+    endian: Endian,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        if self.bytes.len() < len {
+            return Err("Serialized DFA is truncated.");
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn read_word(&mut self) -> Result<[u8; 4], &'static str> {
+        let mut word = [0u8; 4];
+        word.copy_from_slice(self.take(4)?);
+        Ok(word)
+    }
+
+    fn read_u32(&mut self) -> Result<usize, &'static str> {
+        let word = self.read_word()?;
+        usize::try_from(self.endian.read(word)).map_err(|_err| "Serialized DFA is too large.")
+    }
+}
+
+/// Magic bytes at the start of a serialized `BiDfa`
+const BIDFA_MAGIC: &[u8; 4] = b"RXBD";
+
+/// Version of the serialized `BiDfa` layout written by `BiDfa::to_bytes`
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Engine features a serialized `BiDfa` can require of the library loading
+/// it, each one bit of the feature flags in its header. A loader rejects
+/// artifacts requiring a feature it doesn't support before reading the
+/// tables, instead of misinterpreting them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+    /// Classes of symbols outside ASCII
+    Unicode = 1,
+    /// Transitions on ranges of symbols, not yet supported
+    Ranges = 1 << 1,
+    /// States guarded by assertions, not yet supported
+    Assertions = 1 << 2,
+    /// Bounded repetition counters, not yet supported
+    Counters = 1 << 3,
+}
+
+impl Feature {
+    /// The feature flags this version of the library can load
+    pub const SUPPORTED: u32 = Feature::Unicode as u32;
+
+    /// Returns an error naming the first feature in `flags` this version
+    /// doesn't support
+    fn check(flags: u32) -> Result<(), &'static str> {
+        let unsupported = flags & !Feature::SUPPORTED;
+        if unsupported == 0 {
+            Ok(())
+        } else if unsupported & Feature::Ranges as u32 != 0 {
+            Err("Serialized BiDfa requires feature ranges.")
+        } else if unsupported & Feature::Assertions as u32 != 0 {
+            Err("Serialized BiDfa requires feature assertions.")
+        } else if unsupported & Feature::Counters as u32 != 0 {
+            Err("Serialized BiDfa requires feature counters.")
+        } else {
+            Err("Serialized BiDfa requires an unknown feature.")
+        }
+    }
+}
+
+/// A dense DFA of a machine and a dense DFA of its reverse, built together.
+///
+/// Both DFAs share one set of symbol classes, computed over the transitions
+/// of both, and they serialize into one artifact. The forward DFA finds where
+/// matches end, and the reverse DFA, run backwards from an end, finds where
+/// the match starts, see `start_of`.
+///
+/// ```rust
+/// use regexxx::dense::BiDfa;
+/// use regexxx::expr::Expr;
+/// // RE a*b
+/// let machine = Expr::literal('a').star().concat(Expr::literal('b')).build().unwrap();
+/// let bidfa = BiDfa::new(&machine).unwrap();
+/// assert_eq!(bidfa.find("xaabaab"), Some((1, 4)));
+/// assert_eq!(bidfa.rfind("xaabaab"), Some((4, 7)));
+/// assert_eq!(bidfa.start_of("xaab", 4), Some(1));
+/// let bytes = bidfa.to_bytes().unwrap();
+/// assert_eq!(BiDfa::from_bytes(&bytes).unwrap(), bidfa);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BiDfa {
+    forward: DFA,
+    reverse: DFA,
+}
+
+impl BiDfa {
+    /// Determinizes and minimizes the automaton and its reverse, see
+    /// `ANFA::reverse`, and lays both out with shared symbol classes.
+    /// The ANFA must hold exactly one automaton.
+    pub fn new(anfa: &ANFA) -> Result<BiDfa, &'static str> {
+        let forward = anfa.determinize()?.minimize();
+        let reverse = anfa.reverse()?.determinize()?.minimize();
+        let classes = Classes::new(&[&forward, &reverse]);
+        Ok(BiDfa {
+            forward: DFA::with_classes(&forward, &classes),
+            reverse: DFA::with_classes(&reverse, &classes),
+        })
+    }
+
+    /// The DFA of the machine
+    pub fn forward(&self) -> &DFA {
+        &self.forward
+    }
+
+    /// The DFA of the reversed machine, reading its input back to front
+    pub fn reverse(&self) -> &DFA {
+        &self.reverse
+    }
+
+    /// Returns the byte span of the leftmost-longest match, see `DFA::find`
+    pub fn find(&self, haystack: &str) -> Option<(usize, usize)> {
+        self.forward.find(haystack)
+    }
+
+    /// Returns true when loading the serialized `BiDfa` requires `feature`,
+    /// see `to_bytes_with`
+    pub fn requires(&self, feature: Feature) -> bool {
+        self.feature_flags() & feature as u32 != 0
+    }
+
+    fn feature_flags(&self) -> u32 {
+        if self.forward.classes.is_empty() {
+            0
+        } else {
+            Feature::Unicode as u32
+        }
+    }
+
+    /// Returns the byte span of the rightmost-longest match: the match that
+    /// ends last, and among those the one that starts first.
+    pub fn rfind(&self, haystack: &str) -> Option<(usize, usize)> {
+        let ends = haystack
+            .char_indices()
+            .map(|(at, c)| at + c.len_utf8())
+            .rev()
+            .chain(core::iter::once(0));
+        for end in ends {
+            if let Some(start) = self.start_of(haystack, end) {
+                return Some((start, end));
+            }
+        }
+        None
+    }
+
+    /// Returns the first byte offset `start` such that `haystack[start..end]`
+    /// is accepted, or `None`. `end` must be a char boundary of `haystack`.
+    pub fn start_of(&self, haystack: &str, end: usize) -> Option<usize> {
+        self.reverse
+            .longest(end, haystack[..end].char_indices().rev())
+    }
+
+    /// Serializes both DFAs and their shared classes as little-endian, see
+    /// `to_bytes_with`
+    pub fn to_bytes(&self) -> Result<Vec<u8>, &'static str> {
+        self.to_bytes_with(Endian::Little)
+    }
+
+    /// Serializes both DFAs and their shared classes. Every number is a `u32`
+    /// in `endian` byte order, and every number starts at a multiple of 4
+    /// bytes from the start of the artifact:
+    ///
+    /// ```text
+    /// "RXBD", byte order mark 0x01020304, format version, feature flags,
+    /// class count,
+    /// 128 ASCII class bytes,
+    /// non-ASCII class count, (symbol, class) for each,
+    /// for the forward then the reverse DFA:
+    ///     start, state count, one accepting byte per state,
+    ///     zero bytes up to a multiple of 4, the table
+    /// ```
+    ///
+    /// `from_bytes` reads either byte order on any target, so artifacts can
+    /// be built on one platform and loaded on another. Writing in
+    /// `Endian::NATIVE` order of the loading target saves swapping bytes.
+    /// The format version is `FORMAT_VERSION`, and the feature flags are the
+    /// `Feature`s the artifact requires, see `requires`.
+    ///
+    /// ```rust
+    /// use regexxx::dense::{BiDfa, Endian};
+    /// use regexxx::expr::Expr;
+    /// let machine = Expr::literal('a').star().build().unwrap();
+    /// let bidfa = BiDfa::new(&machine).unwrap();
+    /// let big = bidfa.to_bytes_with(Endian::Big).unwrap();
+    /// assert_eq!(&big[4..8], &[1, 2, 3, 4]);
+    /// assert_eq!(BiDfa::from_bytes(&big).unwrap(), bidfa);
+    /// ```
+    pub fn to_bytes_with(&self, endian: Endian) -> Result<Vec<u8>, &'static str> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(BIDFA_MAGIC);
+        write_u32(&mut bytes, endian, BYTE_ORDER_MARK as usize)?;
+        write_u32(&mut bytes, endian, FORMAT_VERSION as usize)?;
+        write_u32(&mut bytes, endian, self.feature_flags() as usize)?;
+        write_u32(&mut bytes, endian, self.forward.stride)?;
+        bytes.extend_from_slice(&self.forward.ascii_classes);
+        write_u32(&mut bytes, endian, self.forward.classes.len())?;
+        for (c, class) in self.forward.classes.iter() {
+            write_u32(&mut bytes, endian, *c as usize)?;
+            write_u32(&mut bytes, endian, *class)?;
+        }
+        for dfa in [&self.forward, &self.reverse].iter() {
+            write_u32(&mut bytes, endian, dfa.start)?;
+            write_u32(&mut bytes, endian, dfa.accepting.len())?;
+            bytes.extend(dfa.accepting.iter().map(|accepting| *accepting as u8));
+            bytes.resize(bytes.len() + padding(dfa.accepting.len()), 0);
+            for q_next in dfa.table.iter() {
+                write_u32(&mut bytes, endian, *q_next)?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Reads a `BiDfa` written by `to_bytes` or `to_bytes_with` in either byte
+    /// order, checking the byte order mark, the format version, the feature
+    /// flags, the padding, and that every class and state id is in bounds
+    pub fn from_bytes(bytes: &[u8]) -> Result<BiDfa, &'static str> {
+        let mut reader = Reader {
+            bytes,
+            endian: Endian::Little,
+        };
+        if reader.take(4)? != BIDFA_MAGIC {
+            return Err("Bytes are not a serialized BiDfa.");
+        }
+        let mark = reader.read_word()?;
+        reader.endian = if Endian::Little.read(mark) == BYTE_ORDER_MARK {
+            Endian::Little
+        } else if Endian::Big.read(mark) == BYTE_ORDER_MARK {
+            Endian::Big
+        } else {
+            return Err("Serialized BiDfa has an unknown byte order.");
+        };
+        if reader.read_u32()? != FORMAT_VERSION as usize {
+            return Err("Serialized BiDfa has an unsupported format version.");
+        }
+        let flags = reader.endian.read(reader.read_word()?);
+        Feature::check(flags)?;
+        let stride = reader.read_u32()?;
+        if stride == 0 {
+            return Err("Serialized DFA has no symbol classes.");
+        }
+        let mut ascii_classes = [0u8; 128];
+        ascii_classes.copy_from_slice(reader.take(128)?);
+        if ascii_classes.iter().any(|class| *class as usize >= stride) {
+            return Err("Serialized DFA has a symbol class out of bounds.");
+        }
+        let class_count = reader.read_u32()?;
+        if class_count != 0 && flags & Feature::Unicode as u32 == 0 {
+            return Err("Serialized BiDfa uses a feature it does not declare.");
+        }
+        let mut classes: Vec<(char, usize)> = Vec::new();
+        for _ in 0..class_count {
+            let c = u32::try_from(reader.read_u32()?)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or("Serialized DFA has an invalid symbol.")?;
+            let class = reader.read_u32()?;
+            if class >= stride {
+                return Err("Serialized DFA has a symbol class out of bounds.");
+            }
+            if classes.last().is_some_and(|(last, _class)| *last >= c) {
+                return Err("Serialized DFA symbols are not sorted.");
+            }
+            classes.push((c, class));
+        }
+        let mut read_dfa = || -> Result<DFA, &'static str> {
+            let start = reader.read_u32()?;
+            let state_count = reader.read_u32()?;
+            let accepting: Vec<bool> = reader.take(state_count)?.iter().map(|b| *b != 0).collect();
+            if reader.take(padding(state_count))?.iter().any(|b| *b != 0) {
+                return Err("Serialized DFA has non-zero padding.");
+            }
+            let len = state_count
+                .checked_mul(stride)
+                .ok_or("Serialized DFA is too large.")?;
+            let mut table = Vec::with_capacity(len.min(reader.bytes.len() / 4));
+            for _ in 0..len {
+                table.push(reader.read_u32()?);
+            }
+            let in_bounds = |q: &usize| q.is_multiple_of(stride) && *q < len;
+            if !in_bounds(&start) || !table.iter().all(in_bounds) {
+                return Err("Serialized DFA has a state id out of bounds.");
+            }
+            Ok(DFA {
+                start,
+                stride,
+                ascii_classes,
+                classes: classes.clone(),
+                table,
+                accepting,
+            })
+        };
+        let forward = read_dfa()?;
+        let reverse = read_dfa()?;
+        if !reader.bytes.is_empty() {
+            return Err("Serialized BiDfa has trailing bytes.");
+        }
+        Ok(BiDfa { forward, reverse })
+    }
+}
+
+impl ANFA {
+    /// Determinizes and minimizes the automaton into a dense DFA for matching.
+    /// The ANFA must hold exactly one automaton.
+    pub fn to_dense(&self) -> Result<DFA, &'static str> {
+        Ok(DFA::new(&self.determinize()?.minimize()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dense::{BiDfa, Endian, Feature, FORMAT_VERSION};
+    use crate::expr::Expr;
+    use crate::stats::Metrics;
+
+    #[test]
+    fn test_classes() {
+        // RE (a+b+c)*d, a, b, and c are indistinguishable
+        let machine = Expr::literal('a')
+            .union(Expr::literal('b'))
+            .union(Expr::literal('c'))
+            .star()
+            .concat(Expr::literal('d'))
+            .build()
+            .unwrap();
+        let dfa = machine.determinize().unwrap().minimize();
+        let dense = machine.to_dense().unwrap();
+        assert_eq!(dense.class_count(), 3);
+        assert_eq!(dense.class('a'), dense.class('c'));
+        assert_ne!(dense.class('a'), dense.class('d'));
+        assert_eq!(dense.class('z'), 0, "Unknown symbols are class 0");
+        assert_eq!(
+            dense.state_count(),
+            dfa.delta.len() + 1,
+            "States and the dead state"
+        );
+    }
+
+    #[test]
+    fn test_accepts_and_find() {
+        // RE (é+b)*c
+        let machine = Expr::literal('é')
+            .union(Expr::literal('b'))
+            .star()
+            .concat(Expr::literal('c'))
+            .build()
+            .unwrap();
+        let dense = machine.to_dense().unwrap();
+        let dfa = machine.determinize().unwrap();
+        for word in ["c", "éc", "bébc", "", "é", "cc", "xc"].iter() {
+            assert_eq!(dense.accepts(word), dfa.accepts(word.chars()), "{}", word);
+        }
+        assert_eq!(dense.find("xxébcé"), Some((2, 6)), "Spans are in bytes");
+        assert_eq!(dense.find("bbb"), None);
+
+        // RE a*, empty and longest matches
+        let machine = Expr::literal('a').star().build().unwrap();
+        let dense = machine.to_dense().unwrap();
+        assert_eq!(dense.find("baa"), Some((0, 0)));
+        assert_eq!(dense.find("aab"), Some((0, 2)));
+        assert_eq!(dense.find(""), Some((0, 0)));
+
+        // RE a+ab, leftmost-longest
+        let machine = Expr::literal('a')
+            .union(Expr::literal('a').concat(Expr::literal('b')))
+            .build()
+            .unwrap();
+        assert_eq!(machine.to_dense().unwrap().find("xab"), Some((1, 3)));
+
+        // RE a(a+b+c)*c+b, a match that ends later but starts further left wins
+        let any = Expr::literal('a')
+            .union(Expr::literal('b'))
+            .union(Expr::literal('c'))
+            .star();
+        let machine = Expr::literal('a')
+            .concat(any)
+            .concat(Expr::literal('c'))
+            .union(Expr::literal('b'))
+            .build()
+            .unwrap();
+        let dense = machine.to_dense().unwrap();
+        assert_eq!(dense.find("xabcbc"), Some((1, 6)));
+        assert_eq!(dense.find("xabb"), Some((2, 3)));
+    }
+
+    #[test]
+    fn test_compress() {
+        // RE (a+é+z)*(b+c)d, reading a, é and z loops, the others move on
+        let machine = Expr::literal('a')
+            .union(Expr::literal('é'))
+            .union(Expr::literal('z'))
+            .star()
+            .concat(Expr::literal('b').union(Expr::literal('c')))
+            .concat(Expr::literal('d'))
+            .build()
+            .unwrap();
+        let dense = machine.to_dense().unwrap();
+        let compressed = dense.compress();
+        assert_eq!(compressed.class_count(), dense.class_count());
+        assert_eq!(compressed.state_count(), dense.state_count());
+        for word in ["bd", "aézcd", "ad", "", "zzbdd", "é"].iter() {
+            assert_eq!(compressed.accepts(word), dense.accepts(word), "{}", word);
+        }
+        for haystack in ["xxébcd", "cdbd", "xx", "éébd"].iter() {
+            assert_eq!(
+                compressed.find(haystack),
+                dense.find(haystack),
+                "{}",
+                haystack
+            );
+        }
+        let stats = compressed.stats();
+        assert_eq!(stats.states, dense.state_count());
+        assert_eq!(stats.classes, 4, "Unknown, a é z, b c, and d");
+        assert!(stats.runs >= stats.states, "Every row has a run");
+        assert!(stats.runs <= stats.states * stats.classes);
+        assert!(stats.to_json().contains("\"dense_dfa_runs\":"));
+    }
+
+    #[test]
+    fn test_double_array() {
+        // RE (a+é+z)*(b+c)d+dd+x
+        let machine = Expr::literal('a')
+            .union(Expr::literal('é'))
+            .union(Expr::literal('z'))
+            .star()
+            .concat(Expr::literal('b').union(Expr::literal('c')))
+            .concat(Expr::literal('d'))
+            .union(Expr::literal('d').concat(Expr::literal('d')))
+            .union(Expr::literal('x'))
+            .build()
+            .unwrap();
+        let dense = machine.to_dense().unwrap();
+        let double = dense.to_double_array();
+        assert_eq!(double.class_count(), dense.class_count());
+        assert_eq!(double.state_count(), dense.state_count());
+        let live = dense.table.iter().filter(|q_next| **q_next != 0).count();
+        assert!(double.slot_count() >= live);
+        let owned = double.check.iter().filter(|owner| **owner != super::EMPTY);
+        assert_eq!(owned.count(), live, "One slot per live transition");
+        for word in ["bd", "aézcd", "dd", "x", "", "d", "zzbdd", "é", "xx"].iter() {
+            assert_eq!(double.accepts(word), dense.accepts(word), "{}", word);
+        }
+        for haystack in ["xxébcd", "cdbd", "", "éébd", "qdd"].iter() {
+            assert_eq!(double.find(haystack), dense.find(haystack), "{}", haystack);
+        }
+        let empty = Expr::zero().build().unwrap().to_dense().unwrap();
+        assert!(!empty.to_double_array().accepts(""));
+    }
+
+    #[test]
+    fn test_bidfa() {
+        // RE (ab+c)*d+é
+        let machine = Expr::literal('a')
+            .concat(Expr::literal('b'))
+            .union(Expr::literal('c'))
+            .star()
+            .concat(Expr::literal('d'))
+            .union(Expr::literal('é'))
+            .build()
+            .unwrap();
+        let bidfa = BiDfa::new(&machine).unwrap();
+        assert_eq!(
+            bidfa.forward().class_count(),
+            bidfa.reverse().class_count(),
+            "Classes are shared"
+        );
+        assert!(bidfa.reverse().accepts("dbac"), "cabd reversed");
+        let haystack = "xabcdyé";
+        assert_eq!(bidfa.find(haystack), Some((1, 5)));
+        assert_eq!(bidfa.rfind(haystack), Some((6, 8)), "Spans are in bytes");
+        assert_eq!(
+            bidfa.start_of(haystack, 5),
+            Some(1),
+            "Longest match ending at 5"
+        );
+        assert_eq!(bidfa.start_of(haystack, 2), None);
+        assert_eq!(bidfa.rfind("xyz"), None);
+    }
+
+    #[test]
+    fn test_bidfa_bytes() {
+        let machine = Expr::literal('a')
+            .union(Expr::literal('λ'))
+            .star()
+            .concat(Expr::literal('b'))
+            .build()
+            .unwrap();
+        let bidfa = BiDfa::new(&machine).unwrap();
+        let bytes = bidfa.to_bytes().unwrap();
+        assert_eq!(&bytes[..4], b"RXBD");
+        let read = BiDfa::from_bytes(&bytes).unwrap();
+        assert_eq!(read, bidfa);
+        assert_eq!(read.find("xλab"), Some((1, 5)));
+        for len in 0..bytes.len() {
+            assert!(
+                BiDfa::from_bytes(&bytes[..len]).is_err(),
+                "Truncated at {}",
+                len
+            );
+        }
+        let mut corrupt = bytes.clone();
+        let last = corrupt.len() - 4;
+        corrupt[last..].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(
+            BiDfa::from_bytes(&corrupt).is_err(),
+            "State ids are checked"
+        );
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(BiDfa::from_bytes(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_bidfa_bytes_endian() {
+        // RE (a+λ+b)*c
+        let machine = Expr::literal('a')
+            .union(Expr::literal('λ'))
+            .union(Expr::literal('b'))
+            .star()
+            .concat(Expr::literal('c'))
+            .build()
+            .unwrap();
+        let bidfa = BiDfa::new(&machine).unwrap();
+        let little = bidfa.to_bytes_with(Endian::Little).unwrap();
+        let big = bidfa.to_bytes_with(Endian::Big).unwrap();
+        assert_eq!(little, bidfa.to_bytes().unwrap());
+        assert_eq!(little.len(), big.len());
+        assert_eq!(little.len() % 4, 0, "Fields are aligned");
+        assert_ne!(little, big);
+        assert_eq!(&big[4..8], &[1, 2, 3, 4]);
+        assert_eq!(&little[4..8], &[4, 3, 2, 1]);
+        assert_eq!(BiDfa::from_bytes(&little).unwrap(), bidfa);
+        assert_eq!(BiDfa::from_bytes(&big).unwrap(), bidfa);
+        assert!(BiDfa::from_bytes(&bidfa.to_bytes_with(Endian::NATIVE).unwrap()).is_ok());
+
+        let mut unknown = big.clone();
+        unknown[4..8].copy_from_slice(&[2, 1, 4, 3]);
+        assert!(
+            BiDfa::from_bytes(&unknown).is_err(),
+            "Byte order mark is checked"
+        );
+        // the forward DFA's accepting bytes follow the classes, start, and count
+        let accepting = 16 + 4 + 128 + 4 + 8 * bidfa.forward().classes.len() + 8;
+        let state_count = bidfa.forward().accepting.len();
+        assert_eq!(state_count, 3, "Two states and the dead state");
+        let mut padded = big;
+        padded[accepting + state_count] = 1;
+        assert!(BiDfa::from_bytes(&padded).is_err(), "Padding must be zero");
+    }
+
+    #[test]
+    fn test_bidfa_bytes_features() {
+        let ascii = BiDfa::new(&Expr::literal('a').build().unwrap()).unwrap();
+        let unicode = BiDfa::new(&Expr::literal('λ').build().unwrap()).unwrap();
+        assert!(!ascii.requires(Feature::Unicode));
+        assert!(unicode.requires(Feature::Unicode));
+        let bytes = unicode.to_bytes().unwrap();
+        assert_eq!(&bytes[8..12], &FORMAT_VERSION.to_le_bytes());
+        assert_eq!(&bytes[12..16], &(Feature::Unicode as u32).to_le_bytes());
+
+        let with_flags = |flags: u32| {
+            let mut bytes = bytes.clone();
+            bytes[12..16].copy_from_slice(&flags.to_le_bytes());
+            BiDfa::from_bytes(&bytes)
+        };
+        assert_eq!(
+            with_flags(Feature::Unicode as u32 | Feature::Assertions as u32),
+            Err("Serialized BiDfa requires feature assertions.")
+        );
+        assert_eq!(
+            with_flags(Feature::Counters as u32),
+            Err("Serialized BiDfa requires feature counters.")
+        );
+        assert_eq!(
+            with_flags(1 << 31),
+            Err("Serialized BiDfa requires an unknown feature.")
+        );
+        assert!(with_flags(0).is_err(), "Non-ASCII classes must be declared");
+
+        let mut newer = bytes.clone();
+        newer[8..12].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            BiDfa::from_bytes(&newer),
+            Err("Serialized BiDfa has an unsupported format version.")
+        );
+    }
+
+    #[test]
+    fn test_bidfa_from_bytes_corrupt() {
+        // loaded tables are indexed without further checks, so every artifact
+        // from_bytes accepts must be safe to search
+        let machine = Expr::literal('a')
+            .union(Expr::literal('λ'))
+            .star()
+            .concat(Expr::literal('b'))
+            .build()
+            .unwrap();
+        let bytes = BiDfa::new(&machine).unwrap().to_bytes().unwrap();
+        for at in 0..bytes.len() {
+            for flip in [1u8, 0x80, 0xff].iter() {
+                let mut corrupt = bytes.clone();
+                corrupt[at] ^= *flip;
+                if let Ok(bidfa) = BiDfa::from_bytes(&corrupt) {
+                    for haystack in ["", "aλb", "xbλ\u{10ffff}a"].iter() {
+                        let _ = bidfa.find(haystack);
+                        let _ = bidfa.rfind(haystack);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use crate::nfa::NFA;
+use crate::{QId, ANFA};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A deterministic finite automaton over the alphabet `S`.
+/// Each state has at most one transition per symbol; a missing transition
+/// rejects the input. State 0 is not necessarily q0.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DFA<S = char> {
+    pub q0: QId,
+    /// `finals[q]` is true when state `q` is accepting
+    pub finals: Vec<bool>,
+    /// `delta[q]` lists every `(label, q_next)` leaving state `q`, labels are unique
+    pub delta: Vec<Vec<(S, QId)>>,
+}
+
+impl<S: Copy + Eq> DFA<S> {
+    /// Returns the state entered from `q` along `c`, if any
+    pub fn next_state(&self, q: QId, c: S) -> Option<QId> {
+        self.delta[q]
+            .iter()
+            .find(|(label, _q_next)| *label == c)
+            .map(|(_label, q_next)| *q_next)
+    }
+
+    /// Returns true when the DFA accepts exactly `input`
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::star(&mut machine).unwrap();
+    /// let dfa = machine.determinize().unwrap();
+    /// assert!(dfa.accepts("aaa".chars()));
+    /// assert!(!dfa.accepts("ab".chars()));
+    /// ```
+    pub fn accepts<I: IntoIterator<Item = S>>(&self, input: I) -> bool {
+        let mut q = self.q0;
+        for c in input {
+            match self.next_state(q, c) {
+                Some(q_next) => q = q_next,
+                None => return false,
+            }
+        }
+        self.finals[q]
+    }
+}
+
+impl<S: Copy + Eq> DFA<S> {
+    /// Returns the minimal DFA accepting the same language.
+    ///
+    /// States that cannot reach an accepting state are removed first, so their
+    /// transitions become missing transitions. The rest are partitioned into
+    /// accepting and rejecting states and the partition is refined until no
+    /// two states of a block disagree on the block any symbol leads to
+    /// (Moore's algorithm). Each block becomes one state.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE aa*+a, i.e. a+, needs two states
+    /// let machine = Expr::literal('a').concat(Expr::literal('a').star()).union(Expr::literal('a')).build().unwrap();
+    /// assert_eq!(machine.determinize().unwrap().minimize().delta.len(), 2);
+    /// ```
+    pub fn minimize(&self) -> DFA<S> {
+        // states that reach an accepting state, found backwards from the accepting states
+        let mut live = self.finals.clone();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (q, transitions) in self.delta.iter().enumerate() {
+                if !live[q] && transitions.iter().any(|(_label, q_next)| live[*q_next]) {
+                    live[q] = true;
+                    changed = true;
+                }
+            }
+        }
+        let mut alphabet: Vec<S> = Vec::new();
+        for transitions in self.delta.iter() {
+            for (label, _q_next) in transitions.iter() {
+                if !alphabet.contains(label) {
+                    alphabet.push(*label);
+                }
+            }
+        }
+        let next_live = |q: QId, c: S| self.next_state(q, c).filter(|q_next| live[*q_next]);
+
+        // block of each state, states that are not live share the block usize::MAX
+        let mut blocks: Vec<usize> = (0..self.delta.len())
+            .map(|q| match (live[q], self.finals[q]) {
+                (false, _) => usize::MAX,
+                (true, false) => 0,
+                (true, true) => 1,
+            })
+            .collect();
+        let mut block_count = 0;
+        loop {
+            let mut block_ids: BTreeMap<Vec<Option<usize>>, usize> = BTreeMap::new();
+            let mut refined = vec![usize::MAX; self.delta.len()];
+            for q in 0..self.delta.len() {
+                if !live[q] {
+                    continue;
+                }
+                let mut signature = vec![Some(blocks[q])];
+                for c in alphabet.iter() {
+                    signature.push(next_live(q, *c).map(|q_next| blocks[q_next]));
+                }
+                let next_id = block_ids.len();
+                refined[q] = *block_ids.entry(signature).or_insert(next_id);
+            }
+            blocks = refined;
+            if block_ids.len() == block_count {
+                break;
+            }
+            block_count = block_ids.len();
+        }
+
+        if !live[self.q0] {
+            return DFA {
+                q0: 0,
+                finals: vec![false],
+                delta: vec![Vec::new()],
+            };
+        }
+        let mut dfa = DFA {
+            q0: blocks[self.q0],
+            finals: vec![false; block_count],
+            delta: vec![Vec::new(); block_count],
+        };
+        let mut built = vec![false; block_count];
+        for q in 0..self.delta.len() {
+            if !live[q] || built[blocks[q]] {
+                continue;
+            }
+            built[blocks[q]] = true;
+            dfa.finals[blocks[q]] = self.finals[q];
+            dfa.delta[blocks[q]] = self.delta[q]
+                .iter()
+                .filter(|(_label, q_next)| live[*q_next])
+                .map(|(label, q_next)| (*label, blocks[*q_next]))
+                .collect();
+        }
+        dfa
+    }
+}
+
+impl<S: Copy + Eq> NFA<S> {
+    /// Subset construction. Only subsets reachable from q0 become DFA states.
+    pub fn determinize(&self) -> DFA<S> {
+        let mut dfa = DFA {
+            q0: 0,
+            finals: Vec::new(),
+            delta: Vec::new(),
+        };
+        let mut subsets: Vec<Vec<QId>> = vec![vec![self.q0]];
+        let mut subset_ids: BTreeMap<Vec<QId>, QId> = BTreeMap::new();
+        subset_ids.insert(vec![self.q0], 0);
+        let mut next_unvisited = 0;
+        while next_unvisited < subsets.len() {
+            let subset = subsets[next_unvisited].clone();
+            next_unvisited += 1;
+            // group every transition of the subset by its label, in order of appearance
+            let mut targets: Vec<(S, Vec<QId>)> = Vec::new();
+            for q in subset.iter() {
+                for (label, q_next) in self.delta[*q].iter() {
+                    match targets.iter_mut().find(|(c, _)| c == label) {
+                        Some((_c, q_nexts)) => {
+                            if !q_nexts.contains(q_next) {
+                                q_nexts.push(*q_next);
+                            }
+                        }
+                        None => targets.push((*label, vec![*q_next])),
+                    }
+                }
+            }
+            let mut transitions = Vec::with_capacity(targets.len());
+            for (label, mut q_nexts) in targets {
+                q_nexts.sort_unstable();
+                let dfa_id = match subset_ids.get(&q_nexts) {
+                    Some(dfa_id) => *dfa_id,
+                    None => {
+                        let dfa_id = subsets.len();
+                        subset_ids.insert(q_nexts.clone(), dfa_id);
+                        subsets.push(q_nexts);
+                        dfa_id
+                    }
+                };
+                transitions.push((label, dfa_id));
+            }
+            dfa.finals.push(subset.iter().any(|q| self.finals[*q]));
+            dfa.delta.push(transitions);
+        }
+        dfa
+    }
+}
+
+impl<S: Copy + Eq> ANFA<S> {
+    /// Removes epsilon transitions and determinizes the automaton.
+    /// The ANFA must hold exactly one automaton.
+    pub fn determinize(&self) -> Result<DFA<S>, &'static str> {
+        Ok(self.remove_epsilon()?.determinize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_determinize() {
+        // RE (a+b)*b
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let dfa = machine.determinize().unwrap();
+        for (q, transitions) in dfa.delta.iter().enumerate() {
+            for (i, (label, _q_next)) in transitions.iter().enumerate() {
+                assert!(
+                    transitions[i + 1..].iter().all(|(c, _)| c != label),
+                    "DFA state {} has one transition per label",
+                    q
+                );
+            }
+        }
+        for input in ["b", "ab", "bb", "aabab"] {
+            assert!(dfa.accepts(input.chars()), "(a+b)*b accepts {}", input);
+        }
+        for input in ["", "a", "ba", "abc"] {
+            assert!(!dfa.accepts(input.chars()), "(a+b)*b rejects {}", input);
+        }
+    }
+
+    #[test]
+    fn test_minimize() {
+        // RE (a+b)*b and (a*b)*a*b are the same language
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let dfa = machine.determinize().unwrap();
+        let minimal = dfa.minimize();
+        assert_eq!(minimal.delta.len(), 2, "(a+b)*b needs two states");
+        for input in ["b", "ab", "bb", "aabab", "", "a", "ba", "abc"] {
+            assert_eq!(minimal.accepts(input.chars()), dfa.accepts(input.chars()));
+        }
+
+        // RE a0, nothing is live
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_0(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let minimal = machine.determinize().unwrap().minimize();
+        assert_eq!(minimal.delta, vec![Vec::new()]);
+        assert!(!minimal.accepts("a".chars()));
+    }
+}
@@ -0,0 +1,389 @@
+use crate::{AutomataRef, QId, ANFA};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Write};
+use core::ops::Range;
+
+/// Graphviz `rankdir`, the direction edges flow in the rendered graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RankDir {
+    TopBottom,
+    LeftRight,
+    BottomTop,
+    RightLeft,
+}
+
+impl RankDir {
+    fn as_str(self) -> &'static str {
+        match self {
+            RankDir::TopBottom => "TB",
+            RankDir::LeftRight => "LR",
+            RankDir::BottomTop => "BT",
+            RankDir::RightLeft => "RL",
+        }
+    }
+}
+
+/// Styling for `ANFA::to_dot`. `DotOptions::default()` renders every state
+/// left-to-right without clusters, colors, or collapsed chains.
+#[derive(Clone, Debug)]
+pub struct DotOptions {
+    pub rankdir: RankDir,
+    /// Draw the states of each imported machine inside a cluster labeled
+    /// with their ids, see `ANFA::provenance`. Machines imported by an
+    /// imported machine are drawn as clusters nested in its cluster.
+    pub cluster_provenance: bool,
+    /// Draw ε and union (0, 1) transitions in gray and labeled transitions in black.
+    pub color_transitions: bool,
+    /// Hide intermediate states that only pass through along ε,
+    /// i.e. states with one incoming transition and one outgoing ε transition.
+    pub collapse_chains: bool,
+    /// Only draw states reachable from this automaton's q0.
+    /// When `None`, every automaton on `automata_refs` is drawn.
+    /// The root must still be on `automata_refs`, see `ANFA::check_ref`.
+    pub root: Option<AutomataRef>,
+}
+
+impl Default for DotOptions {
+    fn default() -> DotOptions {
+        DotOptions {
+            rankdir: RankDir::LeftRight,
+            cluster_provenance: false,
+            color_transitions: false,
+            collapse_chains: false,
+            root: None,
+        }
+    }
+}
+
+/// Marks every state reachable from `q0`, including `q0`
+fn reachable<S>(anfa: &ANFA<S>, q0: QId, seen: &mut [bool]) {
+    let mut stack = vec![q0];
+    while let Some(q) = stack.pop() {
+        if seen[q] {
+            continue;
+        }
+        seen[q] = true;
+        for q_next in anfa.delta[q].1.iter().flatten() {
+            if !seen[*q_next] {
+                stack.push(*q_next);
+            }
+        }
+    }
+}
+
+/// Escapes a DOT string literal
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '"' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl<S: Copy + Eq + Debug> ANFA<S> {
+    /// Renders the automaton as a Graphviz DOT digraph.
+    /// Symbols are labeled with their `Debug` representation.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// use regexxx::dot::{DotOptions, RankDir};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::star(&mut machine).unwrap();
+    /// let dot = machine
+    ///     .to_dot(&DotOptions {
+    ///         rankdir: RankDir::TopBottom,
+    ///         color_transitions: true,
+    ///         ..DotOptions::default()
+    ///     })
+    ///     .unwrap();
+    /// assert!(dot.starts_with("digraph anfa {"));
+    /// ```
+    pub fn to_dot(&self, options: &DotOptions) -> Result<String, &'static str> {
+        let roots = match options.root {
+            Some(root) => vec![root],
+            None => self.automata_refs.clone(),
+        };
+        for root in roots.iter() {
+            self.check_ref(*root)?;
+        }
+        let state_count = self.delta.len();
+
+        let mut drawn = vec![false; state_count];
+        for [q0, _f] in roots.iter() {
+            reachable(self, *q0, &mut drawn);
+        }
+        let mut endpoint = vec![false; state_count];
+        for [q0, f] in roots.iter() {
+            endpoint[*q0] = true;
+            endpoint[*f] = true;
+        }
+        let mut in_degree = vec![0usize; state_count];
+        for (q, (_label, q_nexts)) in self.delta.iter().enumerate() {
+            if drawn[q] {
+                for q_next in q_nexts.iter().flatten() {
+                    in_degree[*q_next] += 1;
+                }
+            }
+        }
+        let collapsed = |q: QId| {
+            options.collapse_chains
+                && !endpoint[q]
+                && !self.assertions.contains_key(&q)
+                && in_degree[q] == 1
+                && matches!(self.delta[q], (None, [Some(_), None]))
+        };
+
+        let mut out = String::new();
+        self.write_dot(&mut out, options, &roots, &drawn, &collapsed)
+            .map_err(|_| "Failed to format DOT output.")?;
+        Ok(out)
+    }
+
+    fn write_dot(
+        &self,
+        out: &mut String,
+        options: &DotOptions,
+        roots: &[AutomataRef],
+        drawn: &[bool],
+        collapsed: &dyn Fn(QId) -> bool,
+    ) -> fmt::Result {
+        writeln!(out, "digraph anfa {{")?;
+        writeln!(out, "  rankdir={};", options.rankdir.as_str())?;
+        writeln!(out, "  node [shape=circle];")?;
+
+        if options.cluster_provenance {
+            // imports are disjoint or nested, so sorting by start and then
+            // by length puts every range after the ranges containing it
+            let mut sources: Vec<&Range<QId>> = self.provenance().iter().collect();
+            sources.sort_by_key(|states| (states.start, core::cmp::Reverse(states.end)));
+            let mut sources = sources.into_iter().enumerate().peekable();
+            let mut open: Vec<&Range<QId>> = Vec::new();
+            for (q, is_drawn) in drawn.iter().enumerate() {
+                while open.last().is_some_and(|states| states.end <= q) {
+                    open.pop();
+                    writeln!(out, "{:1$}}}", "", 2 * open.len() + 2)?;
+                }
+                while let Some((i, states)) = sources.next_if(|(_i, states)| states.start == q) {
+                    let indent = 2 * open.len() + 2;
+                    writeln!(out, "{:1$}subgraph cluster_{2} {{", "", indent, i)?;
+                    writeln!(
+                        out,
+                        "{:1$}label=\"{2}..{3}\";",
+                        "",
+                        indent + 2,
+                        states.start,
+                        states.end
+                    )?;
+                    open.push(states);
+                }
+                if !open.is_empty() && *is_drawn && !collapsed(q) {
+                    writeln!(out, "{:1$}{2};", "", 2 * open.len() + 2, q)?;
+                }
+            }
+            while open.pop().is_some() {
+                writeln!(out, "{:1$}}}", "", 2 * open.len() + 2)?;
+            }
+        }
+
+        for (i, [q0, f]) in roots.iter().enumerate() {
+            writeln!(out, "  start_{} [shape=point];", i)?;
+            writeln!(out, "  start_{} -> {};", i, q0)?;
+            writeln!(out, "  {} [shape=doublecircle];", f)?;
+        }
+        for (q, assertion) in self.assertions.iter() {
+            if drawn[*q] {
+                let mut text = String::new();
+                write!(text, "{}", assertion)?;
+                writeln!(out, "  {} [xlabel=\"{}\"];", q, escape(&text))?;
+            }
+        }
+
+        for (q, (label, q_nexts)) in self.delta.iter().enumerate() {
+            if !drawn[q] || collapsed(q) {
+                continue;
+            }
+            let branches = q_nexts.iter().flatten().count();
+            for (branch, q_next) in q_nexts.iter().flatten().enumerate() {
+                // follow pass-through states to the end of the chain
+                let mut q_next = *q_next;
+                let mut steps = 0;
+                while collapsed(q_next) && steps < self.delta.len() {
+                    q_next = self.delta[q_next].1[0].unwrap_or(q_next);
+                    steps += 1;
+                }
+                let text = match label {
+                    Some(c) => {
+                        let mut text = String::new();
+                        write!(text, "{:?}", c)?;
+                        text
+                    }
+                    None if branches == 2 => {
+                        let mut text = String::new();
+                        write!(text, "{}", branch)?;
+                        text
+                    }
+                    None => String::from("ε"),
+                };
+                write!(out, "  {} -> {} [label=\"{}\"", q, q_next, escape(&text))?;
+                if options.color_transitions {
+                    match label {
+                        Some(_) => write!(out, ", color=black")?,
+                        None => write!(out, ", color=gray")?,
+                    }
+                }
+                writeln!(out, "];")?;
+            }
+        }
+        writeln!(out, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::dot::{DotOptions, RankDir};
+
+    #[test]
+    fn test_to_dot() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let dot = machine.to_dot(&DotOptions::default()).unwrap();
+        assert!(
+            dot.contains("rankdir=LR;"),
+            "Default rankdir is left to right"
+        );
+        assert!(
+            dot.contains("start_0 -> 0;"),
+            "q0 is marked by a start arrow"
+        );
+        assert!(
+            dot.contains("3 [shape=doublecircle];"),
+            "f is drawn as a double circle"
+        );
+        assert!(
+            dot.contains("0 -> 1 [label=\"'a'\"];"),
+            "Symbols are labeled with Debug"
+        );
+        assert!(
+            dot.contains("1 -> 2 [label=\"ε\"];"),
+            "Epsilon transitions are labeled ε"
+        );
+    }
+
+    #[test]
+    fn test_to_dot_options() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, '"').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        let dot = machine
+            .to_dot(&DotOptions {
+                rankdir: RankDir::TopBottom,
+                color_transitions: true,
+                collapse_chains: true,
+                root: Some(machine.automata_refs[0]),
+                ..DotOptions::default()
+            })
+            .unwrap();
+        assert!(dot.contains("rankdir=TB;"), "rankdir is configurable");
+        assert!(
+            dot.contains("4 -> 0 [label=\"0\", color=gray];"),
+            "Union branches are labeled 0 and 1 and colored as ε"
+        );
+        assert!(
+            dot.contains("0 -> 5 [label=\"'a'\", color=black];"),
+            "Pass-through ε states are collapsed into the transition before them"
+        );
+        assert!(
+            dot.contains("2 -> 5 [label=\"'\\\"'\", color=black];"),
+            "Quotes in labels are escaped"
+        );
+        assert!(
+            !dot.contains("'c'"),
+            "States not reachable from root are not drawn"
+        );
+        assert!(
+            machine
+                .to_dot(&DotOptions {
+                    root: Some([99, 100]),
+                    ..DotOptions::default()
+                })
+                .is_err(),
+            "Roots outside of delta are rejected"
+        );
+        let stale = machine.automata_refs[1];
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(
+            machine
+                .to_dot(&DotOptions {
+                    root: Some(stale),
+                    ..DotOptions::default()
+                })
+                .is_err(),
+            "Stale roots are rejected"
+        );
+    }
+
+    #[test]
+    fn test_to_dot_provenance() {
+        // RE a(bc*), c* is imported into bc*, which is imported after a
+        let mut c = ForwardCompiler::from_expr_a('c').unwrap();
+        ForwardCompiler::star(&mut c).unwrap();
+        let mut bc = ForwardCompiler::from_expr_a('b').unwrap();
+        bc.import(&c).unwrap();
+        ForwardCompiler::concatenate(&mut bc).unwrap();
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        machine.import(&bc).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert_eq!(machine.provenance(), &[2..9, 4..9]);
+        let dot = machine
+            .to_dot(&DotOptions {
+                cluster_provenance: true,
+                ..DotOptions::default()
+            })
+            .unwrap();
+        assert!(
+            dot.contains(concat!(
+                "  subgraph cluster_0 {\n",
+                "    label=\"2..9\";\n",
+                "    2;\n",
+                "    3;\n",
+                "    subgraph cluster_1 {\n",
+                "      label=\"4..9\";\n",
+                "      4;\n"
+            )),
+            "Imports of imports are nested"
+        );
+        assert!(dot.contains("      8;\n    }\n  }\n"));
+        assert!(
+            !dot.contains("    0;"),
+            "States compiled in place are not clustered"
+        );
+        let plain = machine.to_dot(&DotOptions::default()).unwrap();
+        assert!(!plain.contains("subgraph"));
+    }
+
+    #[test]
+    fn test_to_dot_assertions() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_assert(&mut machine, Assertion::WordBoundary).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let dot = machine
+            .to_dot(&DotOptions {
+                collapse_chains: true,
+                ..DotOptions::default()
+            })
+            .unwrap();
+        assert!(
+            dot.contains("2 [xlabel=\"\\\\b\"];"),
+            "Guarded states are labeled with their assertion and never collapsed"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+/// The matching engines of this crate
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EngineKind {
+    /// `ANFA::find` and the searches built on it, simulating every thread of
+    /// the ANFA at once
+    PikeVm,
+    /// `ANFA::is_match_tiny`, a bitset simulation of machines of at most 64 states
+    Tiny,
+    /// `set::Set::matches`, simulating every pattern of a set at once
+    Set,
+    /// `dense::DFA::find` and `dense::BiDfa`
+    Dense,
+    /// `hybrid::Regex`, a lazy DFA
+    Hybrid,
+}
+
+/// How the time of one search grows with a machine of `m` states and a
+/// haystack of `n` characters, independent of the pattern and haystack
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ComplexityClass {
+    /// `O(m · n)`, every character is read once by at most `m` threads
+    Linear,
+    /// `O(m · n²)`, each start position may read to the end of the haystack
+    Quadratic,
+}
+
+impl EngineKind {
+    /// Every engine, in the order declared
+    pub const ALL: [EngineKind; 5] = [
+        EngineKind::PikeVm,
+        EngineKind::Tiny,
+        EngineKind::Set,
+        EngineKind::Dense,
+        EngineKind::Hybrid,
+    ];
+
+    /// The worst case time of one search with this engine, for any pattern
+    /// and any haystack. Iterating over matches runs one search per match,
+    /// so only a single search is bounded.
+    ///
+    /// The lazy DFA tries each start position in turn, so a haystack where
+    /// every position starts a long partial match, e.g. `a*b` against
+    /// `aaaa…`, takes quadratic time. The simulations, and the dense DFA
+    /// with one thread per DFA state, start a thread at every position
+    /// within the same pass and stay linear.
+    ///
+    /// ```rust
+    /// use regexxx::engine::{ComplexityClass, EngineKind};
+    /// assert_eq!(EngineKind::PikeVm.complexity(), ComplexityClass::Linear);
+    /// assert_eq!(EngineKind::Hybrid.complexity(), ComplexityClass::Quadratic);
+    /// ```
+    pub const fn complexity(self) -> ComplexityClass {
+        match self {
+            EngineKind::PikeVm | EngineKind::Tiny | EngineKind::Set | EngineKind::Dense => {
+                ComplexityClass::Linear
+            }
+            EngineKind::Hybrid => ComplexityClass::Quadratic,
+        }
+    }
+
+    /// Whether one search allocates scratch memory, at most linear in the
+    /// number of states. Only `Tiny` searches without allocating.
+    pub const fn allocates(self) -> bool {
+        !matches!(self, EngineKind::Tiny)
+    }
+}
+
+/// Counts the threads the simulations step on this thread, so tests can
+/// bound the work of a search without timing it
+#[cfg(test)]
+pub(crate) mod steps {
+    extern crate std;
+
+    use core::cell::Cell;
+
+    std::thread_local! {
+        static STEPS: Cell<u64> = const { Cell::new(0) };
+    }
+
+    /// Counts one thread stepped over one character
+    pub(crate) fn count() {
+        STEPS.with(|steps| steps.set(steps.get() + 1));
+    }
+
+    /// Returns the steps counted since the last call
+    pub(crate) fn take() -> u64 {
+        STEPS.with(|steps| steps.replace(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::engine::{steps, ComplexityClass, EngineKind};
+    use crate::expr::Expr;
+    use crate::set::Set;
+    use crate::ANFA;
+    use alloc::string::String;
+
+    /// RE (a+a)*b, every position starts two threads that never match
+    fn adversarial() -> ANFA {
+        Expr::literal('a')
+            .union(Expr::literal('a'))
+            .star()
+            .concat(Expr::literal('b'))
+            .build()
+            .unwrap()
+    }
+
+    /// The steps `search` takes on `n` characters, see `steps`
+    fn steps<F: Fn(&str)>(search: &F, n: usize) -> u64 {
+        let haystack: String = "a".repeat(n);
+        steps::take();
+        search(&haystack);
+        steps::take()
+    }
+
+    /// Fails when `search` does more than linear work on the adversarial
+    /// haystack: 8 times the input may take at most 9 times the steps, where
+    /// quadratic work would take 64 times as many
+    fn assert_linear<F: Fn(&str)>(engine: EngineKind, search: F) {
+        assert_eq!(engine.complexity(), ComplexityClass::Linear);
+        let small = steps(&search, 1_000);
+        let large = steps(&search, 8_000);
+        assert!(small > 0, "{:?} counts its steps", engine);
+        assert!(
+            large <= small * 9,
+            "{:?} took {} steps on 1000 characters and {} on 8000",
+            engine,
+            small,
+            large
+        );
+    }
+
+    #[test]
+    fn test_linear_engines() {
+        let machine = adversarial();
+        assert_linear(EngineKind::PikeVm, |haystack| {
+            assert_eq!(machine.find(haystack).unwrap(), None);
+        });
+        assert!(machine.delta.len() <= crate::tiny::MAX_STATES);
+        assert_linear(EngineKind::Tiny, |haystack| {
+            assert!(!machine.is_match_tiny(haystack).unwrap());
+        });
+        let set = Set::new(&[&machine, &adversarial()]).unwrap();
+        assert_linear(EngineKind::Set, |haystack| {
+            assert!(!set.matches(haystack).unwrap().matched_any());
+        });
+        let dense = machine.to_dense().unwrap();
+        assert_linear(EngineKind::Dense, |haystack| {
+            assert_eq!(dense.find(haystack), None);
+        });
+    }
+
+    #[test]
+    fn test_quadratic_engines() {
+        let quadratic: alloc::vec::Vec<_> = EngineKind::ALL
+            .iter()
+            .filter(|engine| engine.complexity() == ComplexityClass::Quadratic)
+            .collect();
+        assert_eq!(quadratic, [&EngineKind::Hybrid]);
+        // slow, but still correct on the adversarial haystack
+        let machine = adversarial();
+        let haystack = "a".repeat(1_000);
+        let hybrid = crate::hybrid::Regex::new(&machine).unwrap();
+        assert_eq!(hybrid.find(&haystack), None);
+    }
+}
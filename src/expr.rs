@@ -0,0 +1,514 @@
+use crate::assertion::Assertion;
+use crate::case;
+use crate::compilers::forward_compiler::ForwardCompiler;
+use crate::compilers::Compiler;
+use crate::ANFA;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Write};
+
+/// A regular expression as a value. Expressions are composed by value and
+/// compiled in one pass, so no `AutomataRef` is ever exposed, reused, or stale.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// // RE a*b
+/// let machine = Expr::literal('a').star().concat(Expr::literal('b')).build().unwrap();
+/// assert_eq!(machine.shortest_accepted().unwrap(), Some(vec!['b']));
+/// ```
+///
+/// Expressions may be nested arbitrarily deep: compiling, cloning, comparing,
+/// displaying, and dropping them use explicit stacks instead of recursion.
+#[derive(Debug)]
+pub enum Expr<S = char> {
+    /// `0`, accepts nothing
+    Zero,
+    /// `1`, accepts the empty word
+    One,
+    /// `'a'`, accepts one symbol
+    Literal(S),
+    /// `^`, `$`, `\b`, or `\B`, accepts the empty word where the assertion holds
+    Assert(Assertion),
+    Concat(Box<Expr<S>>, Box<Expr<S>>),
+    Union(Box<Expr<S>>, Box<Expr<S>>),
+    Star(Box<Expr<S>>),
+}
+
+impl<S: Copy + Eq> Expr<S> {
+    pub fn zero() -> Expr<S> {
+        Expr::Zero
+    }
+
+    pub fn one() -> Expr<S> {
+        Expr::One
+    }
+
+    pub fn literal(c: S) -> Expr<S> {
+        Expr::Literal(c)
+    }
+
+    pub fn assert(assertion: Assertion) -> Expr<S> {
+        Expr::Assert(assertion)
+    }
+
+    /// `self ⋅ other`
+    pub fn concat(self, other: Expr<S>) -> Expr<S> {
+        Expr::Concat(Box::new(self), Box::new(other))
+    }
+
+    /// `self ∪ other`
+    pub fn union(self, other: Expr<S>) -> Expr<S> {
+        Expr::Union(Box::new(self), Box::new(other))
+    }
+
+    /// `self *`
+    pub fn star(self) -> Expr<S> {
+        Expr::Star(Box::new(self))
+    }
+
+    /// The number of nodes of the expression, e.g. 4 for `(a+b)*`
+    pub fn size(&self) -> usize {
+        let mut size = 0;
+        let mut stack = vec![self];
+        while let Some(expr) = stack.pop() {
+            size += 1;
+            stack.extend(expr.operands().iter().flatten().copied());
+        }
+        size
+    }
+
+    /// Compiles the expression into a new ANFA with the `ForwardCompiler`
+    pub fn build(&self) -> Result<ANFA<S>, &'static str> {
+        self.build_with::<ForwardCompiler>()
+    }
+
+    /// Compiles the expression into a new ANFA with compiler `C`
+    ///
+    /// ```rust
+    /// use regexxx::compilers::coverage_compiler::CoverageCompiler;
+    /// use regexxx::expr::Expr;
+    /// let machine = Expr::literal('a').union(Expr::literal('b')).build_with::<CoverageCompiler>();
+    /// assert!(machine.is_ok());
+    /// ```
+    pub fn build_with<C: Compiler<S>>(&self) -> Result<ANFA<S>, &'static str> {
+        let mut anfa = ANFA::new();
+        self.compile::<C>(&mut anfa)?;
+        Ok(anfa)
+    }
+
+    /// Pushes the expression onto `anfa` as one new automaton with compiler `C`.
+    /// Sub-expressions are compiled in post-order with an explicit stack,
+    /// so deeply nested expressions do not grow the call stack.
+    pub fn compile<C: Compiler<S>>(&self, anfa: &mut ANFA<S>) -> Result<(), &'static str> {
+        self.compile_literals_with::<C, _>(anfa, C::expr_a)
+    }
+
+    /// `compile`, with each literal pushed by `expr_a` instead of `C::expr_a`
+    fn compile_literals_with<C: Compiler<S>, F: Fn(&mut ANFA<S>, S) -> Result<(), &'static str>>(
+        &self,
+        anfa: &mut ANFA<S>,
+        expr_a: F,
+    ) -> Result<(), &'static str> {
+        // (expression, operands already compiled)
+        let mut stack = vec![(self, false)];
+        while let Some((expr, operands_compiled)) = stack.pop() {
+            match (expr, operands_compiled) {
+                (Expr::Zero, _) => C::expr_0(anfa)?,
+                (Expr::One, _) => C::expr_1(anfa)?,
+                (Expr::Literal(c), _) => expr_a(anfa, *c)?,
+                (Expr::Assert(assertion), _) => C::expr_assert(anfa, *assertion)?,
+                (Expr::Concat(a, b), false) | (Expr::Union(a, b), false) => {
+                    stack.push((expr, true));
+                    stack.push((b, false));
+                    stack.push((a, false));
+                }
+                (Expr::Star(a), false) => {
+                    stack.push((expr, true));
+                    stack.push((a, false));
+                }
+                (Expr::Concat(_, _), true) => C::concatenate(anfa)?,
+                (Expr::Union(_, _), true) => C::union(anfa)?,
+                (Expr::Star(_), true) => C::star(anfa)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S> Expr<S> {
+    /// The operands of the node, left to right
+    fn operands(&self) -> [Option<&Expr<S>>; 2] {
+        match self {
+            Expr::Zero | Expr::One | Expr::Literal(_) | Expr::Assert(_) => [None, None],
+            Expr::Concat(a, b) | Expr::Union(a, b) => [Some(a), Some(b)],
+            Expr::Star(a) => [Some(a), None],
+        }
+    }
+}
+
+impl<S: Clone> Expr<S> {
+    /// Copies the expression in post-order, replacing every sub-expression
+    /// for which `replace` returns a replacement instead of copying it
+    pub(crate) fn rebuild<F: Fn(&Expr<S>) -> Option<Expr<S>>>(&self, replace: F) -> Expr<S> {
+        // (expression, operands already copied onto `built`)
+        let mut stack = vec![(self, false)];
+        let mut built: Vec<Expr<S>> = Vec::new();
+        while let Some((expr, operands_built)) = stack.pop() {
+            if !operands_built {
+                if let Some(replacement) = replace(expr) {
+                    built.push(replacement);
+                    continue;
+                }
+            }
+            match (expr, operands_built) {
+                (Expr::Zero, _) => built.push(Expr::Zero),
+                (Expr::One, _) => built.push(Expr::One),
+                (Expr::Literal(c), _) => built.push(Expr::Literal(c.clone())),
+                (Expr::Assert(assertion), _) => built.push(Expr::Assert(*assertion)),
+                (Expr::Concat(a, b), false) | (Expr::Union(a, b), false) => {
+                    stack.push((expr, true));
+                    stack.push((b, false));
+                    stack.push((a, false));
+                }
+                (Expr::Star(a), false) => {
+                    stack.push((expr, true));
+                    stack.push((a, false));
+                }
+                (_, true) => {
+                    let operand_count = expr.operands().iter().flatten().count();
+                    let mut operands = built.split_off(built.len() - operand_count).into_iter();
+                    let mut next = || Box::new(operands.next().unwrap_or(Expr::Zero));
+                    built.push(match expr {
+                        Expr::Concat(_, _) => Expr::Concat(next(), next()),
+                        Expr::Union(_, _) => Expr::Union(next(), next()),
+                        _ => Expr::Star(next()),
+                    });
+                }
+            }
+        }
+        built.pop().unwrap_or(Expr::Zero)
+    }
+}
+
+impl<S: Clone> Clone for Expr<S> {
+    fn clone(&self) -> Expr<S> {
+        self.rebuild(|_expr| None)
+    }
+}
+
+impl<S: PartialEq> PartialEq for Expr<S> {
+    fn eq(&self, other: &Expr<S>) -> bool {
+        let mut stack = vec![(self, other)];
+        while let Some((a, b)) = stack.pop() {
+            match (a, b) {
+                (Expr::Zero, Expr::Zero) | (Expr::One, Expr::One) => {}
+                (Expr::Literal(a), Expr::Literal(b)) if a == b => {}
+                (Expr::Assert(a), Expr::Assert(b)) if a == b => {}
+                (Expr::Concat(a0, a1), Expr::Concat(b0, b1))
+                | (Expr::Union(a0, a1), Expr::Union(b0, b1)) => {
+                    stack.push((a1, b1));
+                    stack.push((a0, b0));
+                }
+                (Expr::Star(a), Expr::Star(b)) => stack.push((a, b)),
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+impl<S: Eq> Eq for Expr<S> {}
+
+impl<S> Drop for Expr<S> {
+    /// Moves the operands onto a stack before they are dropped, so dropping
+    /// never recurses deeper than one level
+    fn drop(&mut self) {
+        let mut stack: Vec<Expr<S>> = Vec::new();
+        let take_operands = |expr: &mut Expr<S>, stack: &mut Vec<Expr<S>>| match expr {
+            Expr::Zero | Expr::One | Expr::Literal(_) | Expr::Assert(_) => {}
+            Expr::Concat(a, b) | Expr::Union(a, b) => {
+                stack.push(core::mem::replace(&mut **a, Expr::Zero));
+                stack.push(core::mem::replace(&mut **b, Expr::Zero));
+            }
+            Expr::Star(a) => stack.push(core::mem::replace(&mut **a, Expr::Zero)),
+        };
+        take_operands(self, &mut stack);
+        while let Some(mut expr) = stack.pop() {
+            take_operands(&mut expr, &mut stack);
+        }
+    }
+}
+
+impl Expr<char> {
+    /// Accepts any case variant of `c`, see `case::case_variants`
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// let machine = Expr::literal_ci('a').build().unwrap();
+    /// assert!(machine.is_match("A").unwrap());
+    /// ```
+    pub fn literal_ci(c: char) -> Expr<char> {
+        let variants = case::case_variants(c);
+        let mut expr = Expr::literal(variants[0]);
+        for variant in variants[1..].iter() {
+            expr = expr.union(Expr::literal(*variant));
+        }
+        expr
+    }
+
+    /// Accepts exactly `word`, the concatenation of its chars, or only the
+    /// empty string when `word` is empty
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// let machine = Expr::word("ab").build().unwrap();
+    /// assert_eq!(machine.find("cab").unwrap(), Some((1, 3)));
+    /// assert_eq!(Expr::word(""), Expr::one());
+    /// ```
+    pub fn word(word: &str) -> Expr<char> {
+        word.chars()
+            .map(Expr::literal)
+            .reduce(Expr::concat)
+            .unwrap_or(Expr::One)
+    }
+
+    /// Accepts one ASCII word character, `(?-u:\w)`, i.e. `[0-9A-Za-z_]`.
+    /// See `assertion::WordKind`. Labels are single characters, so the
+    /// Unicode `\w` would take one state per word character; word boundaries
+    /// of either kind are assertions instead, see `ANFA::set_word_kind`.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// let machine = Expr::ascii_word().concat(Expr::ascii_word()).build().unwrap();
+    /// assert!(machine.is_match("-a_-").unwrap());
+    /// assert!(!machine.is_match("é_").unwrap());
+    /// ```
+    pub fn ascii_word() -> Expr<char> {
+        let mut expr = Expr::literal('_');
+        for c in ('0'..='9').chain('A'..='Z').chain('a'..='z') {
+            expr = expr.union(Expr::literal(c));
+        }
+        expr
+    }
+
+    /// Compiles the expression case-insensitively, like `(?i)`, into a new
+    /// ANFA with the `ForwardCompiler`. Every literal accepts any of its case
+    /// variants, see `case::expr_a_ci`.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE (?i)ab*
+    /// let machine = Expr::literal('a').concat(Expr::literal('b').star()).build_case_insensitive().unwrap();
+    /// assert!(machine.is_match("AbB").unwrap());
+    /// ```
+    pub fn build_case_insensitive(&self) -> Result<ANFA<char>, &'static str> {
+        self.build_case_insensitive_with::<ForwardCompiler>()
+    }
+
+    /// Compiles the expression case-insensitively into a new ANFA with compiler `C`
+    pub fn build_case_insensitive_with<C: Compiler>(&self) -> Result<ANFA<char>, &'static str> {
+        let mut anfa = ANFA::new();
+        self.compile_literals_with::<C, _>(&mut anfa, case::expr_a_ci::<C>)?;
+        Ok(anfa)
+    }
+}
+
+/// Writes `c`, escaping characters that are part of the pattern syntax
+fn write_literal<S: Display>(f: &mut fmt::Formatter<'_>, c: &S) -> fmt::Result {
+    let mut symbol = String::new();
+    write!(symbol, "{}", c)?;
+    for c in symbol.chars() {
+        if "01+*()^$\\".contains(c) {
+            f.write_char('\\')?;
+        }
+        f.write_char(c)?;
+    }
+    Ok(())
+}
+
+impl<S> Expr<S> {
+    /// Binding strength, operands that bind weaker are parenthesized
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Union(_, _) => 0,
+            Expr::Concat(_, _) => 1,
+            Expr::Zero | Expr::One | Expr::Literal(_) | Expr::Assert(_) | Expr::Star(_) => 2,
+        }
+    }
+}
+
+/// Work left while displaying an expression, see `Display for Expr`
+enum Token<'a, S> {
+    Expr(&'a Expr<S>),
+    Str(&'static str),
+}
+
+/// Pushes `operand` to be written next, parenthesized when it binds weaker
+/// than `precedence`
+fn push_operand<'a, S>(stack: &mut Vec<Token<'a, S>>, operand: &'a Expr<S>, precedence: u8) {
+    if operand.precedence() < precedence {
+        stack.push(Token::Str(")"));
+        stack.push(Token::Expr(operand));
+        stack.push(Token::Str("("));
+    } else {
+        stack.push(Token::Expr(operand));
+    }
+}
+
+/// Writes the expression in the notation used throughout this crate, e.g.
+/// `(a+b)*c`: `0` accepts nothing, `1` accepts the empty word, `+` is union,
+/// `*` is star, and concatenation is juxtaposition. Assertions are written
+/// `^`, `$`, `\b`, and `\B`. Literal `0`, `1`, `+`, `*`, `(`, `)`, `^`, `$`,
+/// and `\` are escaped with `\`.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// let expr = Expr::literal('a').union(Expr::literal('b')).star().concat(Expr::literal('c'));
+/// assert_eq!(expr.to_string(), "(a+b)*c");
+/// ```
+impl<S: Display> Display for Expr<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // tokens are popped, so operands are pushed right to left
+        let mut stack = vec![Token::Expr(self)];
+        while let Some(token) = stack.pop() {
+            let expr = match token {
+                Token::Str(text) => {
+                    f.write_str(text)?;
+                    continue;
+                }
+                Token::Expr(expr) => expr,
+            };
+            match expr {
+                Expr::Zero => f.write_char('0')?,
+                Expr::One => f.write_char('1')?,
+                Expr::Literal(c) => write_literal(f, c)?,
+                Expr::Assert(assertion) => write!(f, "{}", assertion)?,
+                Expr::Concat(a, b) => {
+                    push_operand(&mut stack, b, 1);
+                    push_operand(&mut stack, a, 1);
+                }
+                Expr::Union(a, b) => {
+                    push_operand(&mut stack, b, 0);
+                    stack.push(Token::Str("+"));
+                    push_operand(&mut stack, a, 0);
+                }
+                Expr::Star(a) => {
+                    stack.push(Token::Str("*"));
+                    push_operand(&mut stack, a, 2);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::expr::Expr;
+    use crate::ANFA;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_build() {
+        // RE a(b|c)*d
+        let expr = Expr::literal('a').concat(
+            Expr::literal('b')
+                .union(Expr::literal('c'))
+                .star()
+                .concat(Expr::literal('d')),
+        );
+        let built = expr.build().unwrap();
+        let mut manual = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut manual, 'b').unwrap();
+        ForwardCompiler::expr_a(&mut manual, 'c').unwrap();
+        ForwardCompiler::union(&mut manual).unwrap();
+        ForwardCompiler::star(&mut manual).unwrap();
+        ForwardCompiler::expr_a(&mut manual, 'd').unwrap();
+        ForwardCompiler::concatenate(&mut manual).unwrap();
+        ForwardCompiler::concatenate(&mut manual).unwrap();
+        assert_eq!(
+            built.delta, manual.delta,
+            "Expr compiles to the same calls as the manual construction"
+        );
+        assert_eq!(built.automata_refs, manual.automata_refs);
+    }
+
+    #[test]
+    fn test_build_zero_and_one() {
+        let zero: ANFA = Expr::zero().build().unwrap();
+        assert!(zero.is_empty().unwrap());
+        let one: ANFA = Expr::one().build().unwrap();
+        assert_eq!(one.shortest_accepted().unwrap(), Some(alloc::vec![]));
+        let a_or_zero = Expr::literal('a').union(Expr::zero()).build().unwrap();
+        let a = Expr::literal('a').build().unwrap();
+        assert!(ANFA::equivalent(&a, &a_or_zero).unwrap(), "a ∪ 0 = a");
+    }
+
+    #[test]
+    fn test_build_deeply_nested() {
+        let mut expr = Expr::literal('a');
+        for _ in 0..10_000 {
+            expr = Expr::literal('a').concat(expr);
+        }
+        let machine = expr.build().unwrap();
+        assert_eq!(machine.automata_refs.len(), 1);
+        assert_eq!(machine.delta.len(), 20_002);
+        assert_eq!(expr.size(), 20_001);
+        let copy = expr.clone();
+        assert_eq!(copy, expr);
+        assert_ne!(copy, Expr::literal('a').concat(Expr::literal('a')));
+        assert_eq!(expr.to_string().len(), 10_001);
+        let mut expr = expr;
+        for _ in 0..10_000 {
+            expr = expr.star();
+        }
+        assert_eq!(expr.to_string().len(), 10_001 + 2 + 10_000);
+    }
+
+    #[test]
+    fn test_build_case_insensitive() {
+        // RE (?i)(ab)*c
+        let expr = Expr::literal('a')
+            .concat(Expr::literal('b'))
+            .star()
+            .concat(Expr::literal('c'));
+        let machine = expr.build_case_insensitive().unwrap();
+        let literals_ci = Expr::literal_ci('a')
+            .concat(Expr::literal_ci('b'))
+            .star()
+            .concat(Expr::literal_ci('c'))
+            .build()
+            .unwrap();
+        assert!(ANFA::equivalent(&machine, &literals_ci).unwrap());
+        let dfa = machine.determinize().unwrap();
+        for input in ["c", "C", "abC", "AbaBc"] {
+            assert!(dfa.accepts(input.chars()), "(?i)(ab)*c accepts {}", input);
+        }
+        assert!(!dfa.accepts("abd".chars()));
+        let sensitive = expr.build().unwrap().determinize().unwrap();
+        assert!(
+            !sensitive.accepts("AbC".chars()),
+            "build stays case-sensitive"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let a = || Expr::literal('a');
+        let b = || Expr::literal('b');
+        assert_eq!(a().concat(b()).star().to_string(), "(ab)*");
+        assert_eq!(a().concat(b().star()).to_string(), "ab*");
+        assert_eq!(a().union(b()).concat(a()).to_string(), "(a+b)a");
+        assert_eq!(a().concat(b()).union(a()).to_string(), "ab+a");
+        assert_eq!(a().star().star().to_string(), "a**");
+        assert_eq!(Expr::<char>::one().union(Expr::zero()).to_string(), "1+0");
+        assert_eq!(
+            Expr::literal('+').concat(Expr::literal('1')).to_string(),
+            "\\+\\1",
+            "Pattern syntax is escaped"
+        );
+    }
+}
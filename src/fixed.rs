@@ -0,0 +1,275 @@
+use crate::assertion::Assertion;
+use crate::tiny::{self, TinyMachine};
+use crate::{AutomataRef, QId, Transition, ANFA};
+
+/// An ANFA of at most `N` states in fixed-capacity arrays, built entirely by
+/// `const fn`s, so a machine can be a `static` with no heap and no lazy
+/// initialization. Construction mirrors `ForwardCompiler` on a stack of
+/// automata, producing the same states in the same order.
+///
+/// Mistakes are caught while the constant is evaluated: exceeding the
+/// capacity, an operator without enough operands, or `N` above
+/// `tiny::MAX_STATES` panic, which fails compilation of a `const` or
+/// `static`. Matching uses the allocation-free simulation of
+/// `ANFA::is_match_tiny`.
+///
+/// ```rust
+/// use regexxx::fixed::FixedANFA;
+/// // RE (a+b)*c
+/// static MACHINE: FixedANFA<11> = FixedANFA::new()
+///     .expr_a('a')
+///     .expr_a('b')
+///     .union()
+///     .star()
+///     .expr_a('c')
+///     .concatenate();
+/// assert!(MACHINE.is_match("xxabc").unwrap());
+/// assert!(!MACHINE.is_match("ab").unwrap());
+/// ```
+///
+/// ```compile_fail
+/// use regexxx::fixed::FixedANFA;
+/// // 4 states don't fit in 3
+/// static MACHINE: FixedANFA<3> = FixedANFA::new().expr_a('a').expr_a('b').concatenate();
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct FixedANFA<const N: usize> {
+    delta: [Transition; N],
+    states: usize,
+    assertions: [Option<Assertion>; N],
+    automata_refs: [AutomataRef; N],
+    automata: usize,
+}
+
+impl<const N: usize> FixedANFA<N> {
+    /// An empty machine, which is not a valid automaton until an expression
+    /// is pushed
+    pub const fn new() -> FixedANFA<N> {
+        assert!(
+            N <= tiny::MAX_STATES,
+            "Fixed machines have at most 64 states."
+        );
+        FixedANFA {
+            delta: [(None, [None, None]); N],
+            states: 0,
+            assertions: [None; N],
+            automata_refs: [[0, 0]; N],
+            automata: 0,
+        }
+    }
+
+    /// The transitions of the states pushed so far
+    pub const fn delta(&self) -> &[Transition] {
+        self.delta.split_at(self.states).0
+    }
+
+    /// The automata on the stack, the last one on top
+    pub const fn automata_refs(&self) -> &[AutomataRef] {
+        self.automata_refs.split_at(self.automata).0
+    }
+
+    /// The assertion guarding state `q`, see `ANFA::assertions`
+    pub const fn assertion(&self, q: QId) -> Option<Assertion> {
+        if q < self.states {
+            self.assertions[q]
+        } else {
+            None
+        }
+    }
+
+    const fn push_state(mut self, transition: Transition) -> FixedANFA<N> {
+        assert!(self.states < N, "Fixed machine capacity exceeded.");
+        self.delta[self.states] = transition;
+        self.states += 1;
+        self
+    }
+
+    const fn push_ref(mut self, automaton: AutomataRef) -> FixedANFA<N> {
+        self.automata_refs[self.automata] = automaton;
+        self.automata += 1;
+        self
+    }
+
+    const fn pop_ref(mut self, message: &'static str) -> (FixedANFA<N>, AutomataRef) {
+        if self.automata == 0 {
+            panic!("{}", message);
+        }
+        self.automata -= 1;
+        let automaton = self.automata_refs[self.automata];
+        (self, automaton)
+    }
+
+    /// Pushes an acceptor that never transitions, see `Compiler::expr_0`
+    pub const fn expr_0(self) -> FixedANFA<N> {
+        let q0 = self.states;
+        self.push_state((None, [None, None]))
+            .push_state((None, [None, None]))
+            .push_ref([q0, q0 + 1])
+    }
+
+    /// Pushes an acceptor in its final state, see `Compiler::expr_1`
+    pub const fn expr_1(self) -> FixedANFA<N> {
+        let q0 = self.states;
+        self.push_state((None, [None, None])).push_ref([q0, q0])
+    }
+
+    /// Pushes an acceptor in its final state where `assertion` holds, see
+    /// `Compiler::expr_assert`
+    pub const fn expr_assert(self, assertion: Assertion) -> FixedANFA<N> {
+        let q0 = self.states;
+        let mut anfa = self.push_state((None, [None, None])).push_ref([q0, q0]);
+        anfa.assertions[q0] = Some(assertion);
+        anfa
+    }
+
+    /// Pushes an automaton that transitions to a final state on `c`, see
+    /// `Compiler::expr_a`
+    pub const fn expr_a(self, c: char) -> FixedANFA<N> {
+        let q0 = self.states;
+        let f = q0 + 1;
+        self.push_state((Some(c), [Some(f), None]))
+            .push_state((None, [None, None]))
+            .push_ref([q0, f])
+    }
+
+    /// Concatenates the top two automata, see `Compiler::concatenate`
+    pub const fn concatenate(self) -> FixedANFA<N> {
+        let message = "Concatenation requires two operands.";
+        let (anfa, [machine_b_q0, machine_b_f]) = self.pop_ref(message);
+        let (mut anfa, [machine_a_q0, machine_a_f]) = anfa.pop_ref(message);
+        anfa.delta[machine_a_f] = (None, [Some(machine_b_q0), None]);
+        anfa.push_ref([machine_a_q0, machine_b_f])
+    }
+
+    /// Unions the top two automata, see `Compiler::union`
+    pub const fn union(self) -> FixedANFA<N> {
+        let message = "Union requires two operands.";
+        let (anfa, [machine_b_q0, machine_b_f]) = self.pop_ref(message);
+        let (mut anfa, [machine_a_q0, machine_a_f]) = anfa.pop_ref(message);
+        let machine_c_q0 = anfa.states;
+        let machine_c_f = machine_c_q0 + 1;
+        anfa.delta[machine_a_f] = (None, [Some(machine_c_f), None]);
+        anfa.delta[machine_b_f] = (None, [Some(machine_c_f), None]);
+        anfa.push_state((None, [Some(machine_a_q0), Some(machine_b_q0)]))
+            .push_state((None, [None, None]))
+            .push_ref([machine_c_q0, machine_c_f])
+    }
+
+    /// Repeats the top automaton 0 or more times, see `Compiler::star`
+    pub const fn star(self) -> FixedANFA<N> {
+        let (mut anfa, [machine_a_q0, machine_a_f]) = self.pop_ref("Star requires one operand.");
+        let machine_b_q0 = anfa.states;
+        let machine_b_q = machine_b_q0 + 1;
+        let machine_b_f = machine_b_q0 + 2;
+        anfa.delta[machine_a_f] = (None, [Some(machine_b_q), None]);
+        anfa.push_state((None, [Some(machine_b_q), None]))
+            .push_state((None, [Some(machine_a_q0), Some(machine_b_f)]))
+            .push_state((None, [None, None]))
+            .push_ref([machine_b_q0, machine_b_f])
+    }
+
+    /// Returns true when some substring of `haystack` is accepted, without
+    /// allocating, see `ANFA::is_match_tiny`. The machine must hold exactly
+    /// one automaton.
+    pub fn is_match(&self, haystack: &str) -> Result<bool, &'static str> {
+        let [machine_q0, machine_f] = match self.automata_refs() {
+            [machine] => *machine,
+            _ => return Err("Search requires exactly one automaton."),
+        };
+        Ok(tiny::is_match(self, machine_q0, machine_f, haystack))
+    }
+
+    /// Copies the machine into a heap-allocated ANFA, for the searches and
+    /// analyses that need one
+    pub fn to_anfa(&self) -> ANFA {
+        let mut anfa = ANFA::new();
+        for transition in self.delta().iter() {
+            anfa.push_state(*transition);
+        }
+        anfa.automata_refs.extend_from_slice(self.automata_refs());
+        for q in 0..self.states {
+            if let Some(assertion) = self.assertions[q] {
+                anfa.assertions.insert(q, assertion);
+            }
+        }
+        anfa
+    }
+}
+
+impl<const N: usize> Default for FixedANFA<N> {
+    fn default() -> FixedANFA<N> {
+        FixedANFA::new()
+    }
+}
+
+impl<const N: usize> TinyMachine for FixedANFA<N> {
+    fn transition(&self, q: QId) -> Transition {
+        self.delta[q]
+    }
+
+    fn assertion(&self, q: QId) -> Option<Assertion> {
+        self.assertions[q]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::expr::Expr;
+    use crate::fixed::FixedANFA;
+
+    /// RE ^(ab+b)*\b
+    const MACHINE: FixedANFA<16> = FixedANFA::new()
+        .expr_assert(Assertion::StartText)
+        .expr_a('a')
+        .expr_a('b')
+        .concatenate()
+        .expr_a('b')
+        .union()
+        .star()
+        .concatenate()
+        .expr_assert(Assertion::WordBoundary)
+        .concatenate();
+
+    #[test]
+    fn test_matches_forward_compiler() {
+        let expr = Expr::assert(Assertion::StartText)
+            .concat(
+                Expr::literal('a')
+                    .concat(Expr::literal('b'))
+                    .union(Expr::literal('b'))
+                    .star(),
+            )
+            .concat(Expr::assert(Assertion::WordBoundary));
+        let anfa = expr.build().unwrap();
+        let fixed = MACHINE.to_anfa();
+        assert_eq!(fixed.delta, anfa.delta, "Same states in the same order");
+        assert_eq!(fixed.automata_refs, anfa.automata_refs);
+        assert_eq!(fixed.assertions, anfa.assertions);
+        for haystack in ["", "ab", "abb ", "ba", " ab", "abbab", "x"].iter() {
+            assert_eq!(
+                MACHINE.is_match(haystack).unwrap(),
+                anfa.is_match(haystack).unwrap(),
+                "{:?}",
+                haystack
+            );
+        }
+    }
+
+    #[test]
+    fn test_stack() {
+        let machine: FixedANFA<4> = FixedANFA::new().expr_1().expr_0();
+        assert_eq!(machine.automata_refs(), &[[0, 0], [1, 2]]);
+        assert_eq!(machine.delta().len(), 3);
+        assert!(machine.is_match("a").is_err());
+        assert!(machine.to_anfa().check_ref([1, 2]).is_ok());
+        assert!(FixedANFA::<4>::new().expr_1().is_match("").unwrap());
+        assert!(!FixedANFA::<4>::new().expr_0().is_match("").unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "Union requires two operands.")]
+    fn test_missing_operand() {
+        FixedANFA::<4>::new().expr_a('a').union();
+    }
+}
@@ -0,0 +1,207 @@
+use crate::assertion::WordKind;
+use crate::expr::Expr;
+use crate::pattern;
+use crate::search::{MatchKind, SearchOptions};
+use crate::ANFA;
+
+/// Which chars a `\` may escape in a pattern, besides `\b` and `\B`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Escapes {
+    /// Only the syntax chars `01+*()^$\`, as written by the `Display`
+    /// implementation of `Expr`
+    #[default]
+    Syntax,
+    /// Any ASCII punctuation, and the control escapes `\a`, `\f`, `\n`, `\r`,
+    /// `\t` and `\v`, like RE2
+    Punctuation,
+    /// Any char, unknown escapes standing for the char itself, and the
+    /// control escapes, like Perl
+    Any,
+}
+
+impl Escapes {
+    /// Returns the literal `\c` stands for
+    ///
+    /// ```rust
+    /// use regexxx::flavor::Escapes;
+    /// assert_eq!(Escapes::Syntax.unescape('*'), Ok('*'));
+    /// assert!(Escapes::Syntax.unescape('.').is_err());
+    /// assert_eq!(Escapes::Punctuation.unescape('.'), Ok('.'));
+    /// assert_eq!(Escapes::Punctuation.unescape('n'), Ok('\n'));
+    /// assert!(Escapes::Punctuation.unescape('q').is_err());
+    /// assert_eq!(Escapes::Any.unescape('q'), Ok('q'));
+    /// ```
+    pub fn unescape(self, c: char) -> Result<char, &'static str> {
+        if "01+*()^$\\".contains(c) {
+            return Ok(c);
+        }
+        let control = match c {
+            'a' => Some('\u{7}'),
+            'f' => Some('\u{c}'),
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            'v' => Some('\u{b}'),
+            _ => None,
+        };
+        match (self, control) {
+            (Escapes::Syntax, _) => Err("Unknown escape."),
+            (_, Some(control)) => Ok(control),
+            (Escapes::Punctuation, None) if c.is_ascii_punctuation() => Ok(c),
+            (Escapes::Punctuation, None) => Err("Unknown escape."),
+            (Escapes::Any, None) => Ok(c),
+        }
+    }
+}
+
+/// The defaults of a regex engine, bundled so code migrating from it gets
+/// its semantics with one switch. `Flavor::default()` is this crate's own.
+///
+/// A flavor decides how patterns are parsed, see `Flavor::parse`, and which
+/// match a search reports, see `Flavor::search_options`. The notation is
+/// always that of `Expr::parse`, so `+` is still union, not repetition.
+///
+/// ```rust
+/// use regexxx::flavor::Flavor;
+/// // \b only knows ASCII word chars in RE2, so é is not one
+/// let re2 = Flavor::re2().build(r"\bb").unwrap();
+/// assert_eq!(re2.find("éb").unwrap(), Some((2, 3)));
+/// let own = Flavor::default().build(r"\bb").unwrap();
+/// assert_eq!(own.find("éb").unwrap(), None);
+/// // POSIX reports the longest of the leftmost matches
+/// let posix = Flavor::posix();
+/// let machine = posix.build("a+ab").unwrap();
+/// assert_eq!(machine.find_with("ab", &posix.search_options()).unwrap(), Some((0, 2)));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Flavor {
+    /// Which match a search reports, see `MatchKind`
+    pub match_kind: MatchKind,
+    /// Which chars are word chars for `\b` and `\B`. `(?-u:\b)` and
+    /// `(?-u:\B)` are ASCII in every flavor.
+    pub word_kind: WordKind,
+    pub escapes: Escapes,
+    /// Empty patterns, groups and alternatives, like `()` or `a+`, match the
+    /// empty word instead of being errors
+    pub empty_operands: bool,
+}
+
+impl Flavor {
+    /// Leftmost-first matches, ASCII word boundaries, punctuation escapes,
+    /// and empty operands, like RE2
+    pub fn re2() -> Flavor {
+        Flavor {
+            match_kind: MatchKind::LeftmostFirst,
+            word_kind: WordKind::Ascii,
+            escapes: Escapes::Punctuation,
+            empty_operands: true,
+        }
+    }
+
+    /// Leftmost-first matches, Unicode word boundaries, escapes of any char,
+    /// and empty operands, like Perl and PCRE matching Unicode strings
+    pub fn perl() -> Flavor {
+        Flavor {
+            match_kind: MatchKind::LeftmostFirst,
+            word_kind: WordKind::Unicode,
+            escapes: Escapes::Any,
+            empty_operands: true,
+        }
+    }
+
+    /// Leftmost-longest matches, ASCII word boundaries, and punctuation
+    /// escapes, like POSIX extended regular expressions, which leave empty
+    /// operands undefined
+    pub fn posix() -> Flavor {
+        Flavor {
+            match_kind: MatchKind::LeftmostLongest,
+            word_kind: WordKind::Ascii,
+            escapes: Escapes::Punctuation,
+            empty_operands: false,
+        }
+    }
+
+    /// Parses `pattern` in the notation of `Expr::parse`, with the escapes,
+    /// empty operands and word boundaries of this flavor
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::flavor::Flavor;
+    /// let expr = Flavor::re2().parse(r"a\.+").unwrap();
+    /// assert_eq!(expr, Expr::literal('a').concat(Expr::literal('.')).union(Expr::one()));
+    /// assert!(Flavor::default().parse(r"a\.+").is_err());
+    /// ```
+    pub fn parse(&self, pattern: &str) -> Result<Expr<char>, &'static str> {
+        pattern::parse_with(pattern, self)
+    }
+
+    /// Parses `pattern` and compiles it into a new ANFA
+    pub fn build(&self, pattern: &str) -> Result<ANFA, &'static str> {
+        self.parse(pattern)?.build()
+    }
+
+    /// The default search options of this flavor
+    pub fn search_options(&self) -> SearchOptions {
+        SearchOptions {
+            match_kind: self.match_kind,
+            ..SearchOptions::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::expr::Expr;
+    use crate::flavor::{Escapes, Flavor};
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_parse() {
+        let flavors = [
+            Flavor::default(),
+            Flavor::re2(),
+            Flavor::perl(),
+            Flavor::posix(),
+        ];
+        // the notation written by Display parses the same in every flavor
+        let expr = Expr::parse(r"(a+\+)*\0(?-u:\b)").unwrap();
+        for flavor in flavors.iter() {
+            assert_eq!(flavor.parse(&expr.to_string()).unwrap(), expr);
+        }
+        assert_eq!(
+            Flavor::re2().parse(r"\b").unwrap(),
+            Expr::assert(Assertion::AsciiWordBoundary)
+        );
+        assert_eq!(
+            Flavor::perl().parse(r"\B").unwrap(),
+            Expr::assert(Assertion::NotWordBoundary)
+        );
+        assert_eq!(Flavor::perl().parse(r"\q").unwrap(), Expr::literal('q'));
+        assert!(Flavor::re2().parse(r"\q").is_err());
+        assert_eq!(Flavor::re2().parse(r"\t").unwrap(), Expr::literal('\t'));
+        assert_eq!(Flavor::re2().parse("").unwrap(), Expr::one());
+        assert_eq!(
+            Flavor::re2().parse("+a").unwrap(),
+            Expr::one().union(Expr::literal('a'))
+        );
+        assert_eq!(Flavor::perl().parse("()").unwrap(), Expr::one());
+        assert!(Flavor::posix().parse("a+").is_err());
+        assert!(Flavor::re2().parse("*").is_err());
+        assert_eq!(Escapes::default(), Escapes::Syntax);
+    }
+
+    #[test]
+    fn test_search_options() {
+        let haystack = "ab";
+        for (flavor, span) in [(Flavor::re2(), (0, 1)), (Flavor::posix(), (0, 2))].iter() {
+            let machine = flavor.build("a+ab").unwrap();
+            assert_eq!(
+                machine
+                    .find_with(haystack, &flavor.search_options())
+                    .unwrap(),
+                Some(*span)
+            );
+        }
+    }
+}
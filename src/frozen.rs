@@ -0,0 +1,343 @@
+use crate::assertion::Assertion;
+use crate::{is_well_formed, QId, ANFA};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// Magic bytes at the start of a serialized `FrozenANFA`
+const FROZEN_MAGIC: &[u8; 4] = b"RXFA";
+
+/// Version of the serialized `FrozenANFA` layout written by
+/// `FrozenANFA::to_bytes`
+pub const FROZEN_VERSION: u32 = 1;
+
+/// Assertions by their number in the serialized tables
+const ASSERTIONS: [Assertion; 6] = [
+    Assertion::StartText,
+    Assertion::EndText,
+    Assertion::WordBoundary,
+    Assertion::NotWordBoundary,
+    Assertion::AsciiWordBoundary,
+    Assertion::AsciiNotWordBoundary,
+];
+
+/// Stands for `None` among the numbers of the serialized tables
+const NONE: u64 = u64::MAX;
+
+/// 64-bit FNV-1a of `bytes`
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in bytes.iter() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// The tables of `anfa` as little-endian `u64`s: the state count, then the
+/// label and both next states of each state, the automaton count and each
+/// automaton, and the assertion count and each guarded state and assertion
+fn encode(anfa: &ANFA) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut write = |value: u64| bytes.extend_from_slice(&value.to_le_bytes());
+    let id = |q: Option<QId>| q.map_or(NONE, |q| q as u64);
+    write(anfa.delta.len() as u64);
+    for (label, [q_left, q_right]) in anfa.delta.iter() {
+        write(label.map_or(NONE, u64::from));
+        write(id(*q_left));
+        write(id(*q_right));
+    }
+    write(anfa.automata_refs.len() as u64);
+    for [q0, f] in anfa.automata_refs.iter() {
+        write(*q0 as u64);
+        write(*f as u64);
+    }
+    write(anfa.assertions.len() as u64);
+    for (q, assertion) in anfa.assertions.iter() {
+        write(*q as u64);
+        write(*assertion as u64);
+    }
+    bytes
+}
+
+/// Reads the tables written by `encode` back, checking every id and the shape
+/// of every transition, as `ANFA::from_storage` does
+fn decode(bytes: &[u8]) -> Result<ANFA, &'static str> {
+    let mut words = bytes.chunks(8);
+    let mut read = || -> Result<u64, &'static str> {
+        match words.next() {
+            Some(word) if word.len() == 8 => {
+                let mut buffer = [0u8; 8];
+                buffer.copy_from_slice(word);
+                Ok(u64::from_le_bytes(buffer))
+            }
+            _ => Err("Frozen ANFA is truncated."),
+        }
+    };
+    let count = |value: u64| usize::try_from(value).map_err(|_err| "Frozen ANFA is too large.");
+    let mut anfa = ANFA::new();
+    let state_count = count(read()?)?;
+    let state = |value: u64| match usize::try_from(value) {
+        Ok(q) if q < state_count => Ok(q),
+        _ => Err("Frozen ANFA has a state out of bounds."),
+    };
+    for _ in 0..state_count {
+        let label = match read()? {
+            NONE => None,
+            c => Some(
+                u32::try_from(c)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or("Frozen ANFA has an invalid symbol.")?,
+            ),
+        };
+        let mut q_nexts = [None, None];
+        for q_next in q_nexts.iter_mut() {
+            *q_next = match read()? {
+                NONE => None,
+                q => Some(state(q)?),
+            };
+        }
+        if !is_well_formed(&(label, q_nexts), state_count) {
+            return Err("Frozen ANFA has a malformed transition.");
+        }
+        anfa.push_state((label, q_nexts));
+    }
+    for _ in 0..count(read()?)? {
+        anfa.automata_refs.push([state(read()?)?, state(read()?)?]);
+    }
+    for _ in 0..count(read()?)? {
+        let q = state(read()?)?;
+        let assertion = usize::try_from(read()?)
+            .ok()
+            .and_then(|i| ASSERTIONS.get(i))
+            .ok_or("Frozen ANFA has an unknown assertion.")?;
+        anfa.assertions.insert(q, *assertion);
+    }
+    if words.next().is_some() {
+        return Err("Frozen ANFA has trailing bytes.");
+    }
+    Ok(anfa)
+}
+
+/// An ANFA that can no longer change, carrying a checksum of its tables, see
+/// `ANFA::freeze`.
+///
+/// The checksum travels with the serialized machine and is verified when it
+/// is read back, so a corrupted precompiled artifact fails to load instead
+/// of silently matching something else. In paranoid mode it is verified
+/// again before every search, catching corruption in memory at the cost of
+/// reading the whole machine per search. The checksum is 64-bit FNV-1a,
+/// which detects accidents, not tampering.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::frozen::FrozenANFA;
+/// let frozen = Expr::literal('a').star().concat(Expr::literal('b')).build().unwrap().freeze();
+/// let mut bytes = frozen.to_bytes();
+/// let loaded = FrozenANFA::from_bytes(&bytes).unwrap();
+/// assert_eq!(loaded.checksum(), frozen.checksum());
+/// assert_eq!(loaded.find("xaab").unwrap(), Some((1, 4)));
+/// let last = bytes.len() - 1;
+/// bytes[last] ^= 1;
+/// assert!(FrozenANFA::from_bytes(&bytes).is_err());
+/// ```
+#[derive(Debug)]
+pub struct FrozenANFA {
+    anfa: ANFA,
+    checksum: u64,
+    paranoid: bool,
+}
+
+impl ANFA {
+    /// Freezes the machine, computing the checksum of its tables
+    pub fn freeze(self) -> FrozenANFA {
+        let checksum = fnv1a(&encode(&self));
+        FrozenANFA {
+            anfa: self,
+            checksum,
+            paranoid: false,
+        }
+    }
+}
+
+impl FrozenANFA {
+    /// The machine, read only
+    pub fn anfa(&self) -> &ANFA {
+        &self.anfa
+    }
+
+    /// Unfreezes the machine, for further changes
+    pub fn thaw(self) -> ANFA {
+        self.anfa
+    }
+
+    /// The checksum of the tables, computed when the machine was frozen
+    pub fn checksum(&self) -> u64 {
+        self.checksum
+    }
+
+    /// Turns verifying the checksum before every search on or off
+    pub fn paranoid(self, paranoid: bool) -> FrozenANFA {
+        FrozenANFA { paranoid, ..self }
+    }
+
+    pub fn is_paranoid(&self) -> bool {
+        self.paranoid
+    }
+
+    /// Recomputes the checksum of the tables and compares it with the one
+    /// computed when the machine was frozen
+    pub fn verify(&self) -> Result<(), &'static str> {
+        if fnv1a(&encode(&self.anfa)) == self.checksum {
+            Ok(())
+        } else {
+            Err("Frozen ANFA checksum mismatch.")
+        }
+    }
+
+    fn check(&self) -> Result<&ANFA, &'static str> {
+        if self.paranoid {
+            self.verify()?;
+        }
+        Ok(&self.anfa)
+    }
+
+    /// See `ANFA::find`, verifying the checksum first in paranoid mode
+    pub fn find(&self, haystack: &str) -> Result<Option<(usize, usize)>, &'static str> {
+        self.check()?.find(haystack)
+    }
+
+    /// See `ANFA::is_match`, verifying the checksum first in paranoid mode
+    pub fn is_match(&self, haystack: &str) -> Result<bool, &'static str> {
+        self.check()?.is_match(haystack)
+    }
+
+    /// Serializes the machine as `"RXFA"`, `FROZEN_VERSION` as a
+    /// little-endian `u32`, the checksum as a little-endian `u64`, then the
+    /// tables as little-endian `u64`s
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(FROZEN_MAGIC);
+        bytes.extend_from_slice(&FROZEN_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.checksum.to_le_bytes());
+        bytes.extend(encode(&self.anfa));
+        bytes
+    }
+
+    /// Reads a machine written by `to_bytes`, verifying its checksum before
+    /// reading the tables, then that every id is in bounds. The machine is
+    /// not paranoid.
+    pub fn from_bytes(bytes: &[u8]) -> Result<FrozenANFA, &'static str> {
+        if bytes.len() < 16 {
+            return Err("Frozen ANFA is truncated.");
+        }
+        let (header, tables) = bytes.split_at(16);
+        if &header[..4] != FROZEN_MAGIC {
+            return Err("Bytes are not a frozen ANFA.");
+        }
+        let mut version = [0u8; 4];
+        version.copy_from_slice(&header[4..8]);
+        if u32::from_le_bytes(version) != FROZEN_VERSION {
+            return Err("Frozen ANFA has an unsupported format version.");
+        }
+        let mut checksum = [0u8; 8];
+        checksum.copy_from_slice(&header[8..]);
+        let checksum = u64::from_le_bytes(checksum);
+        if fnv1a(tables) != checksum {
+            return Err("Frozen ANFA checksum mismatch.");
+        }
+        Ok(FrozenANFA {
+            anfa: decode(tables)?,
+            checksum,
+            paranoid: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::expr::Expr;
+    use crate::frozen::{FrozenANFA, FROZEN_VERSION};
+
+    fn machine() -> FrozenANFA {
+        // RE ^(é+b)*\b
+        Expr::assert(Assertion::StartText)
+            .concat(Expr::literal('é').union(Expr::literal('b')).star())
+            .concat(Expr::assert(Assertion::AsciiWordBoundary))
+            .build()
+            .unwrap()
+            .freeze()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let frozen = machine();
+        assert!(frozen.verify().is_ok());
+        let loaded = FrozenANFA::from_bytes(&frozen.to_bytes()).unwrap();
+        assert_eq!(loaded.checksum(), frozen.checksum());
+        assert_eq!(loaded.anfa().delta, frozen.anfa().delta);
+        assert_eq!(loaded.anfa().automata_refs, frozen.anfa().automata_refs);
+        assert_eq!(loaded.anfa().assertions, frozen.anfa().assertions);
+        assert!(!loaded.is_paranoid());
+        assert_ne!(
+            Expr::literal('a').build().unwrap().freeze().checksum(),
+            Expr::literal('b').build().unwrap().freeze().checksum()
+        );
+        let thawed = loaded.thaw();
+        assert_eq!(thawed.find("bé").unwrap(), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_corruption() {
+        let bytes = machine().to_bytes();
+        for i in 0..bytes.len() {
+            let mut corrupted = bytes.clone();
+            corrupted[i] ^= 0x10;
+            assert!(FrozenANFA::from_bytes(&corrupted).is_err(), "byte {}", i);
+        }
+        assert!(FrozenANFA::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(FrozenANFA::from_bytes(&bytes[..8]).is_err());
+        let mut newer = bytes.clone();
+        newer[4..8].copy_from_slice(&(FROZEN_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            FrozenANFA::from_bytes(&newer).unwrap_err(),
+            "Frozen ANFA has an unsupported format version."
+        );
+
+        // well-formed bytes with a valid checksum, of malformed tables
+        let labeled = |q_nexts| {
+            let mut anfa = Expr::literal('a').build().unwrap();
+            anfa.delta[0] = (Some('a'), q_nexts);
+            anfa.freeze().to_bytes()
+        };
+        for q_nexts in [[Some(1), Some(1)], [None, None], [None, Some(1)]].iter() {
+            assert_eq!(
+                FrozenANFA::from_bytes(&labeled(*q_nexts)).unwrap_err(),
+                "Frozen ANFA has a malformed transition."
+            );
+        }
+    }
+
+    #[test]
+    fn test_paranoid() {
+        let frozen = machine().paranoid(true);
+        assert!(frozen.is_paranoid());
+        assert_eq!(frozen.find("bb").unwrap(), Some((0, 2)));
+        assert!(frozen.is_match("x").unwrap());
+        // corrupt the tables behind the checksum, as memory corruption would
+        let mut anfa = frozen.thaw();
+        anfa.delta[1].0 = Some('x');
+        let checksum = anfa.freeze().checksum();
+        let corrupted = FrozenANFA {
+            anfa: machine().thaw(),
+            checksum,
+            paranoid: true,
+        };
+        assert_eq!(
+            corrupted.find("bb").unwrap_err(),
+            "Frozen ANFA checksum mismatch."
+        );
+        assert!(corrupted.paranoid(false).find("bb").is_ok());
+    }
+}
@@ -0,0 +1,116 @@
+use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+use crate::ANFA;
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+/// Most compiler calls made for one arbitrary ANFA
+const MAX_OPERATIONS: usize = 64;
+
+/// Builds a finalized ANFA, i.e. one holding exactly one automaton, from a
+/// random sequence of `ForwardCompiler` calls. Every operation that needs
+/// operands is only chosen when enough automata are on the stack, and the
+/// stack is concatenated down to one automaton at the end, so every generated
+/// machine is structurally valid. Assertions are never generated, because
+/// most analyses reject them.
+///
+/// ```rust
+/// use arbitrary::{Arbitrary, Unstructured};
+/// use regexxx::ANFA;
+/// let bytes = [7u8; 64];
+/// let machine = ANFA::<char>::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+/// assert_eq!(machine.automata_refs.len(), 1);
+/// ```
+impl<'a, S: Arbitrary<'a> + Copy + Eq> Arbitrary<'a> for ANFA<S> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<ANFA<S>> {
+        let compiled = |result: core::result::Result<(), &'static str>| {
+            result.map_err(|_err| arbitrary::Error::IncorrectFormat)
+        };
+        let mut anfa = ANFA::new();
+        let operations = u.int_in_range(1..=MAX_OPERATIONS)?;
+        for _ in 0..operations {
+            if u.is_empty() {
+                break;
+            }
+            let operands = anfa.automata_refs.len();
+            match u.int_in_range(0..=6u8)? {
+                0 => compiled(ForwardCompiler::expr_0(&mut anfa))?,
+                1 => compiled(ForwardCompiler::expr_1(&mut anfa))?,
+                2 if operands >= 1 => compiled(ForwardCompiler::star(&mut anfa))?,
+                3 if operands >= 2 => compiled(ForwardCompiler::union(&mut anfa))?,
+                4 if operands >= 2 => compiled(ForwardCompiler::concatenate(&mut anfa))?,
+                _ => {
+                    let c = S::arbitrary(u)?;
+                    compiled(ForwardCompiler::expr_a(&mut anfa, c))?
+                }
+            }
+        }
+        if anfa.automata_refs.is_empty() {
+            compiled(ForwardCompiler::expr_1(&mut anfa))?;
+        }
+        while anfa.automata_refs.len() > 1 {
+            compiled(ForwardCompiler::concatenate(&mut anfa))?;
+        }
+        Ok(anfa)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ANFA;
+    use alloc::vec::Vec;
+    use arbitrary::{Arbitrary, Unstructured};
+
+    /// Deterministic bytes for one arbitrary machine, xorshift64 from `seed`
+    fn bytes(seed: u64) -> Vec<u8> {
+        let mut state = seed | 1;
+        (0..256)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    /// Machines over a small alphabet, so random operands share symbols
+    fn machines() -> impl Iterator<Item = ANFA<bool>> {
+        (0..200).map(|seed| {
+            let bytes = bytes(seed);
+            ANFA::<bool>::arbitrary(&mut Unstructured::new(&bytes)).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_arbitrary_is_finalized() {
+        for machine in machines() {
+            assert_eq!(machine.automata_refs.len(), 1);
+            assert!(machine.check_ref(machine.automata_refs[0]).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_minimize_preserves_language() {
+        for machine in machines() {
+            let dfa = machine.determinize().unwrap();
+            let minimal = dfa.minimize();
+            assert!(minimal.delta.len() <= dfa.delta.len().max(1));
+            let nfa = machine.remove_epsilon().unwrap();
+            for word in machine.accepted_words(20, 4).unwrap() {
+                assert!(minimal.accepts(word.iter().copied()));
+                assert!(nfa.accepts(word.iter().copied()));
+            }
+            // every word of up to 4 symbols is judged alike
+            for n in 0..(1 << 5) {
+                let word: Vec<bool> = (0..n % 5).map(|i| n & (1 << i) != 0).collect();
+                assert_eq!(
+                    dfa.accepts(word.iter().copied()),
+                    minimal.accepts(word.iter().copied())
+                );
+                assert_eq!(
+                    dfa.accepts(word.iter().copied()),
+                    nfa.accepts(word.iter().copied())
+                );
+            }
+        }
+    }
+}
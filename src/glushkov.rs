@@ -0,0 +1,255 @@
+use crate::expr::Expr;
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A literal of an expression, numbered from 0 left to right
+pub type Position = usize;
+
+/// The position analyses of an expression that Glushkov's construction is
+/// built from, see `Expr::positions`.
+///
+/// A word is accepted exactly when it is spelled by a sequence of positions
+/// starting in `first`, moving from each position to one in its `follow`
+/// set, and ending in `last`, or is empty and the expression is `nullable`.
+/// Assertions are zero-width, so they are treated as `1`.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// // RE (a+b)*ab, positions a0 b1 a2 b3
+/// let expr = Expr::literal('a').union(Expr::literal('b')).star()
+///     .concat(Expr::literal('a'))
+///     .concat(Expr::literal('b'));
+/// let positions = expr.positions();
+/// assert_eq!(positions.symbols, vec!['a', 'b', 'a', 'b']);
+/// assert_eq!(positions.first.iter().copied().collect::<Vec<_>>(), vec![0, 1, 2]);
+/// assert_eq!(positions.last.iter().copied().collect::<Vec<_>>(), vec![3]);
+/// assert_eq!(positions.follow[2].iter().copied().collect::<Vec<_>>(), vec![3]);
+/// assert!(!positions.nullable);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Positions<S> {
+    /// The symbol of each position
+    pub symbols: Vec<S>,
+    /// Whether the expression accepts the empty word
+    pub nullable: bool,
+    /// The positions that can spell the first symbol of a word
+    pub first: BTreeSet<Position>,
+    /// The positions that can spell the last symbol of a word
+    pub last: BTreeSet<Position>,
+    /// The positions that can come right after each position
+    pub follow: Vec<BTreeSet<Position>>,
+}
+
+impl<S: Copy + Eq> Positions<S> {
+    /// The distinct symbols of `positions`, in position order, e.g. the
+    /// symbols a predictive parser may see first
+    pub fn symbols_of<'a, I: IntoIterator<Item = &'a Position>>(&self, positions: I) -> Vec<S> {
+        let mut symbols = Vec::new();
+        for p in positions {
+            if !symbols.contains(&self.symbols[*p]) {
+                symbols.push(self.symbols[*p]);
+            }
+        }
+        symbols
+    }
+}
+
+/// `nullable`, `first` and `last` of a sub-expression
+type Summary = (bool, BTreeSet<Position>, BTreeSet<Position>);
+
+impl<S: Copy + Eq> Expr<S> {
+    /// Numbers the literals and computes their first, last and follow sets
+    /// in one post-order pass with an explicit stack, see `Positions`
+    pub fn positions(&self) -> Positions<S> {
+        let mut symbols = Vec::new();
+        let mut follow: Vec<BTreeSet<Position>> = Vec::new();
+        let mut summaries: Vec<Summary> = Vec::new();
+        // (expression, operands already summarized)
+        let mut stack = vec![(self, false)];
+        while let Some((expr, operands_summarized)) = stack.pop() {
+            match (expr, operands_summarized) {
+                (Expr::Zero, _) => summaries.push((false, BTreeSet::new(), BTreeSet::new())),
+                (Expr::One, _) | (Expr::Assert(_), _) => {
+                    summaries.push((true, BTreeSet::new(), BTreeSet::new()))
+                }
+                (Expr::Literal(c), _) => {
+                    let p = symbols.len();
+                    symbols.push(*c);
+                    follow.push(BTreeSet::new());
+                    let only: BTreeSet<Position> = [p].iter().copied().collect();
+                    summaries.push((false, only.clone(), only));
+                }
+                (Expr::Concat(a, b), false) | (Expr::Union(a, b), false) => {
+                    stack.push((expr, true));
+                    stack.push((b, false));
+                    stack.push((a, false));
+                }
+                (Expr::Star(a), false) => {
+                    stack.push((expr, true));
+                    stack.push((a, false));
+                }
+                (Expr::Concat(_, _), true) => {
+                    let (b_nullable, b_first, b_last) = summaries.pop().unwrap_or_default();
+                    let (a_nullable, a_first, a_last) = summaries.pop().unwrap_or_default();
+                    for p in a_last.iter() {
+                        follow[*p].extend(b_first.iter().copied());
+                    }
+                    let first = if a_nullable {
+                        a_first.union(&b_first).copied().collect()
+                    } else {
+                        a_first
+                    };
+                    let last = if b_nullable {
+                        a_last.union(&b_last).copied().collect()
+                    } else {
+                        b_last
+                    };
+                    summaries.push((a_nullable && b_nullable, first, last));
+                }
+                (Expr::Union(_, _), true) => {
+                    let (b_nullable, b_first, b_last) = summaries.pop().unwrap_or_default();
+                    let (a_nullable, mut first, mut last) = summaries.pop().unwrap_or_default();
+                    first.extend(b_first);
+                    last.extend(b_last);
+                    summaries.push((a_nullable || b_nullable, first, last));
+                }
+                (Expr::Star(_), true) => {
+                    let (_nullable, first, last) = summaries.pop().unwrap_or_default();
+                    for p in last.iter() {
+                        follow[*p].extend(first.iter().copied());
+                    }
+                    summaries.push((true, first, last));
+                }
+            }
+        }
+        let (nullable, first, last) = summaries.pop().unwrap_or_default();
+        Positions {
+            symbols,
+            nullable,
+            first,
+            last,
+            follow,
+        }
+    }
+
+    /// The positions that can spell the first symbol of a word, see
+    /// `positions`
+    pub fn first_set(&self) -> BTreeSet<Position> {
+        self.positions().first
+    }
+
+    /// The positions that can spell the last symbol of a word, see
+    /// `positions`
+    pub fn last_set(&self) -> BTreeSet<Position> {
+        self.positions().last
+    }
+
+    /// The positions that can come right after position `p`, or `None` when
+    /// the expression has no position `p`, see `positions`
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE (ab)*, positions a0 b1
+    /// let expr = Expr::literal('a').concat(Expr::literal('b')).star();
+    /// assert_eq!(expr.follow(1).unwrap().into_iter().collect::<Vec<_>>(), vec![0]);
+    /// assert_eq!(expr.follow(2), None);
+    /// ```
+    pub fn follow(&self, p: Position) -> Option<BTreeSet<Position>> {
+        let mut follow = self.positions().follow;
+        if p < follow.len() {
+            Some(follow.swap_remove(p))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::expr::Expr;
+    use crate::glushkov::Positions;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    /// Runs the Glushkov automaton of `positions` on `word`
+    fn accepts(positions: &Positions<char>, word: &str) -> bool {
+        let mut current: Option<Vec<usize>> = None;
+        for c in word.chars() {
+            let candidates: Vec<usize> = match current {
+                None => positions.first.iter().copied().collect(),
+                Some(ps) => ps
+                    .iter()
+                    .flat_map(|p| positions.follow[*p].iter().copied())
+                    .collect(),
+            };
+            current = Some(
+                candidates
+                    .into_iter()
+                    .filter(|p| positions.symbols[*p] == c)
+                    .collect(),
+            );
+        }
+        match current {
+            None => positions.nullable,
+            Some(ps) => ps.iter().any(|p| positions.last.contains(p)),
+        }
+    }
+
+    #[test]
+    fn test_positions_accept_like_the_machine() {
+        // RE (a+1)(ba)*b*, and an assertion and 0 that change nothing here
+        let expr = Expr::literal('a')
+            .union(Expr::one())
+            .concat(Expr::literal('b').concat(Expr::literal('a')).star())
+            .concat(Expr::assert(Assertion::WordBoundary).union(Expr::zero()))
+            .concat(Expr::literal('b').star());
+        let positions = expr.positions();
+        assert_eq!(positions.symbols.len(), 4);
+        assert!(positions.nullable);
+        assert_eq!(positions.symbols_of(&positions.first), vec!['a', 'b']);
+        let machine = Expr::literal('a')
+            .union(Expr::one())
+            .concat(Expr::literal('b').concat(Expr::literal('a')).star())
+            .concat(Expr::literal('b').star())
+            .build()
+            .unwrap()
+            .remove_epsilon()
+            .unwrap();
+        for word in [
+            "", "a", "ab", "aba", "abab", "abb", "bab", "ba", "aa", "bba", "abbab",
+        ]
+        .iter()
+        {
+            assert_eq!(
+                accepts(&positions, word),
+                machine.accepts(word.chars()),
+                "{}",
+                word
+            );
+        }
+    }
+
+    #[test]
+    fn test_sets() {
+        let zero: Expr = Expr::zero();
+        assert!(zero.positions().first.is_empty());
+        assert!(!zero.positions().nullable);
+        // RE a(b+c)
+        let expr = Expr::literal('a').concat(Expr::literal('b').union(Expr::literal('c')));
+        assert_eq!(expr.first_set().into_iter().collect::<Vec<_>>(), [0]);
+        assert_eq!(expr.last_set().into_iter().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(
+            expr.follow(0).unwrap().into_iter().collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert!(expr.follow(1).unwrap().is_empty());
+
+        // deep nesting doesn't grow the call stack
+        let deep = (0..100_000).fold(Expr::literal('a'), |expr, _| {
+            expr.concat(Expr::literal('a'))
+        });
+        assert_eq!(deep.positions().symbols.len(), 100_001);
+    }
+}
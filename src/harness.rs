@@ -0,0 +1,475 @@
+use crate::expr::Expr;
+use crate::search::{MatchKind, SearchOptions};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A behavioral test: a pattern in the notation of `Expr::parse`, a
+/// haystack, and every byte span `ANFA::find_iter_with` must report in it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Case {
+    pub name: String,
+    pub pattern: String,
+    pub input: String,
+    pub options: SearchOptions,
+    pub matches: Vec<(usize, usize)>,
+}
+
+impl Case {
+    /// Compiles the pattern and returns every span found in the input
+    pub fn find_all(&self) -> Result<Vec<(usize, usize)>, &'static str> {
+        let machine = Expr::parse(&self.pattern)?.build()?;
+        let matches = machine.find_iter_with(&self.input, &self.options)?;
+        Ok(matches.collect())
+    }
+}
+
+/// A case that found other spans than expected, or failed to run
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Failure {
+    /// The position of the case among those run
+    pub case: usize,
+    pub name: String,
+    pub expected: Vec<(usize, usize)>,
+    pub found: Result<Vec<(usize, usize)>, &'static str>,
+}
+
+/// The outcome of `run`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub passed: usize,
+    pub failures: Vec<Failure>,
+}
+
+impl Report {
+    /// Returns true when every case passed
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Writes a summary line, then a line per failure
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} passed, {} failed", self.passed, self.failures.len())?;
+        for failure in self.failures.iter() {
+            write!(
+                f,
+                "\ncase {} {:?}: expected {:?}, ",
+                failure.case, failure.name, failure.expected
+            )?;
+            match &failure.found {
+                Ok(found) => write!(f, "found {:?}", found)?,
+                Err(err) => write!(f, "failed: {}", err)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs every case, collecting the failures
+pub fn run(cases: &[Case]) -> Report {
+    let mut report = Report::default();
+    for (i, case) in cases.iter().enumerate() {
+        let found = case.find_all();
+        if found.as_ref() == Ok(&case.matches) {
+            report.passed += 1;
+        } else {
+            report.failures.push(Failure {
+                case: i,
+                name: case.name.clone(),
+                expected: case.matches.clone(),
+                found,
+            });
+        }
+    }
+    report
+}
+
+/// A TOML value of the subset `parse_cases` reads
+enum Value {
+    String(String),
+    Integer(usize),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+/// Arrays nest at most this deep, bounding the recursion of `Reader::value`
+const MAX_DEPTH: usize = 8;
+
+/// Reads TOML from the front of `rest`
+struct Reader<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Reader<'a> {
+    fn peek(&self) -> Option<char> {
+        self.rest.chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.rest = &self.rest[c.len_utf8()..];
+        Some(c)
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        match self.rest.strip_prefix(text) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Skips spaces, tabs, and a comment up to the end of the line
+    fn skip_spaces(&mut self) {
+        self.rest = self.rest.trim_start_matches([' ', '\t']);
+        if self.rest.starts_with('#') {
+            let end = self.rest.find('\n').unwrap_or(self.rest.len());
+            self.rest = &self.rest[end..];
+        }
+    }
+
+    /// Skips spaces, comments, and line breaks
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_spaces();
+            if !self.eat("\n") && !self.eat("\r\n") {
+                return;
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), &'static str> {
+        self.skip_spaces();
+        if self.rest.is_empty() || self.eat("\n") || self.eat("\r\n") {
+            Ok(())
+        } else {
+            Err("Expected the end of the line.")
+        }
+    }
+
+    fn key(&mut self) -> Result<&'a str, &'static str> {
+        let end = self
+            .rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return Err("Expected a key.");
+        }
+        let (key, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Ok(key)
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, &'static str> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => {
+                self.bump();
+                let end = self.rest.find(['\'', '\n']).ok_or("Unterminated string.")?;
+                let (text, rest) = self.rest.split_at(end);
+                self.rest = rest;
+                if !self.eat("'") {
+                    return Err("Unterminated string.");
+                }
+                Ok(Value::String(String::from(text)))
+            }
+            Some('[') => {
+                if depth == MAX_DEPTH {
+                    return Err("Arrays are nested too deeply.");
+                }
+                self.bump();
+                let mut items = Vec::new();
+                loop {
+                    self.skip_blank();
+                    if self.eat("]") {
+                        return Ok(Value::Array(items));
+                    }
+                    items.push(self.value(depth + 1)?);
+                    self.skip_blank();
+                    if !self.eat(",") {
+                        self.skip_blank();
+                        return if self.eat("]") {
+                            Ok(Value::Array(items))
+                        } else {
+                            Err("Expected ',' or ']'.")
+                        };
+                    }
+                }
+            }
+            Some('0'..='9') => {
+                let end = self
+                    .rest
+                    .find(|c: char| !(c.is_ascii_digit() || c == '_'))
+                    .unwrap_or(self.rest.len());
+                let (digits, rest) = self.rest.split_at(end);
+                self.rest = rest;
+                let mut value: usize = 0;
+                for digit in digits.chars().filter_map(|c| c.to_digit(10)) {
+                    value = value
+                        .checked_mul(10)
+                        .and_then(|value| value.checked_add(digit as usize))
+                        .ok_or("Integer is too large.")?;
+                }
+                Ok(Value::Integer(value))
+            }
+            _ if self.eat("true") => Ok(Value::Boolean(true)),
+            _ if self.eat("false") => Ok(Value::Boolean(false)),
+            _ => Err("Expected a string, integer, boolean, or array."),
+        }
+    }
+
+    /// A `"` string with the escapes of TOML basic strings
+    fn basic_string(&mut self) -> Result<String, &'static str> {
+        self.bump();
+        let mut text = String::new();
+        loop {
+            match self.bump().ok_or("Unterminated string.")? {
+                '"' => return Ok(text),
+                '\n' => return Err("Unterminated string."),
+                '\\' => text.push(match self.bump().ok_or("Unterminated string.")? {
+                    'b' => '\u{8}',
+                    't' => '\t',
+                    'n' => '\n',
+                    'f' => '\u{c}',
+                    'r' => '\r',
+                    '"' => '"',
+                    '\\' => '\\',
+                    escape @ ('u' | 'U') => {
+                        let digits = if escape == 'u' { 4 } else { 8 };
+                        let hex = self.rest.get(..digits).ok_or("Invalid unicode escape.")?;
+                        self.rest = &self.rest[digits..];
+                        u32::from_str_radix(hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or("Invalid unicode escape.")?
+                    }
+                    _ => return Err("Unknown escape."),
+                }),
+                c => text.push(c),
+            }
+        }
+    }
+}
+
+/// The keys of a case read so far
+#[derive(Default)]
+struct Fields {
+    name: Option<String>,
+    pattern: Option<String>,
+    input: Option<String>,
+    options: SearchOptions,
+    matches: Option<Vec<(usize, usize)>>,
+    seen: Vec<String>,
+}
+
+impl Fields {
+    fn set(&mut self, key: &str, value: Value) -> Result<(), &'static str> {
+        if self.seen.iter().any(|seen| seen == key) {
+            return Err("Duplicate key in a case.");
+        }
+        self.seen.push(String::from(key));
+        match (key, value) {
+            ("name", Value::String(text)) => self.name = Some(text),
+            ("pattern", Value::String(text)) => self.pattern = Some(text),
+            ("input", Value::String(text)) => self.input = Some(text),
+            ("matches", Value::Array(spans)) => {
+                let mut matches = Vec::new();
+                for span in spans {
+                    match span {
+                        Value::Array(ends) => match ends.as_slice() {
+                            [Value::Integer(start), Value::Integer(end)] => {
+                                matches.push((*start, *end))
+                            }
+                            _ => return Err("Spans are arrays of a start and an end."),
+                        },
+                        _ => return Err("Spans are arrays of a start and an end."),
+                    }
+                }
+                self.matches = Some(matches);
+            }
+            ("case_insensitive", Value::Boolean(flag)) => self.options.case_insensitive = flag,
+            ("multi_line", Value::Boolean(flag)) => self.options.multi_line = flag,
+            ("anchored_start", Value::Boolean(flag)) => self.options.anchored_start = flag,
+            ("anchored_end", Value::Boolean(flag)) => self.options.anchored_end = flag,
+            ("match_kind", Value::String(kind)) => {
+                self.options.match_kind = match kind.as_str() {
+                    "leftmost-first" => MatchKind::LeftmostFirst,
+                    "leftmost-longest" => MatchKind::LeftmostLongest,
+                    "earliest" => MatchKind::Earliest,
+                    _ => return Err("Unknown match kind."),
+                }
+            }
+            ("name", _) | ("pattern", _) | ("input", _) | ("match_kind", _) => {
+                return Err("Expected a string.")
+            }
+            ("matches", _) => return Err("Expected an array of spans."),
+            ("case_insensitive", _)
+            | ("multi_line", _)
+            | ("anchored_start", _)
+            | ("anchored_end", _) => return Err("Expected a boolean."),
+            _ => return Err("Unknown key in a case."),
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Case, &'static str> {
+        Ok(Case {
+            name: self.name.unwrap_or_default(),
+            pattern: self.pattern.ok_or("Case is missing its pattern.")?,
+            input: self.input.ok_or("Case is missing its input.")?,
+            options: self.options,
+            matches: self.matches.ok_or("Case is missing its matches.")?,
+        })
+    }
+}
+
+/// Reads cases from a TOML document, one `[[case]]` table each:
+///
+/// ```toml
+/// [[case]]
+/// name = "star prefers longer matches"
+/// pattern = "ba*"
+/// input = "xbaab b"
+/// matches = [[1, 4], [5, 6]]
+/// ```
+///
+/// `pattern`, `input`, and `matches` are required; `name` is optional. The
+/// search options are optional booleans `case_insensitive`, `multi_line`,
+/// `anchored_start`, and `anchored_end`, and `match_kind`, one of
+/// `"leftmost-first"`, `"leftmost-longest"`, or `"earliest"`, see
+/// `SearchOptions`. Only the TOML this schema needs is read: comments,
+/// `[[case]]` headers, and bare keys set to strings, integers, booleans, or
+/// arrays of those.
+///
+/// ```rust
+/// use regexxx::harness;
+/// let cases = harness::parse_cases(r#"
+/// [[case]]
+/// pattern = '(a+b)*c'   # literal strings need no escapes
+/// input = "abc, c"
+/// matches = [[0, 3], [5, 6]]
+///
+/// [[case]]
+/// pattern = "a+ab"
+/// input = "ab"
+/// match_kind = "leftmost-longest"
+/// matches = [[0, 2]]
+/// "#).unwrap();
+/// let report = harness::run(&cases);
+/// assert!(report.is_ok(), "{}", report);
+/// assert_eq!(report.passed, 2);
+/// ```
+pub fn parse_cases(toml: &str) -> Result<Vec<Case>, &'static str> {
+    let mut reader = Reader { rest: toml };
+    let mut cases: Vec<Fields> = Vec::new();
+    loop {
+        reader.skip_blank();
+        if reader.rest.is_empty() {
+            break;
+        }
+        if reader.eat("[[case]]") {
+            reader.end_of_line()?;
+            cases.push(Fields::default());
+            continue;
+        }
+        if reader.rest.starts_with('[') {
+            return Err("Only [[case]] tables are supported.");
+        }
+        let key = reader.key()?;
+        reader.skip_spaces();
+        if !reader.eat("=") {
+            return Err("Expected '=' after a key.");
+        }
+        reader.skip_spaces();
+        let value = reader.value(0)?;
+        reader.end_of_line()?;
+        cases
+            .last_mut()
+            .ok_or("Keys must follow a [[case]] header.")?
+            .set(key, value)?;
+    }
+    cases.into_iter().map(Fields::finish).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::harness::{parse_cases, run};
+    use crate::search::MatchKind;
+    use alloc::string::String;
+    use alloc::vec;
+
+    #[test]
+    fn test_parse_cases() {
+        let cases = parse_cases(
+            "# a comment\r\n\
+             [[case]] # another\r\n\
+             name = \"escapes \\\"\\u00e9\\\" and \\\\\"\r\n\
+             pattern = 'é'\n\
+             input = \"\\u00e9\\te\"\n\
+             case_insensitive = true\n\
+             match_kind = \"earliest\"\n\
+             matches = [\n  [0, 2], # é is two bytes\n  [3, 4],\n]\n\
+             [[case]]\n\
+             pattern = \"1\"\n\
+             input = \"\"\n\
+             matches = [[0,0]]",
+        )
+        .unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].name, "escapes \"é\" and \\");
+        assert_eq!(cases[0].input, "é\te");
+        assert!(cases[0].options.case_insensitive);
+        assert_eq!(cases[0].options.match_kind, MatchKind::Earliest);
+        assert_eq!(cases[0].matches, vec![(0, 2), (3, 4)]);
+        assert_eq!(cases[1].name, "");
+        assert_eq!(cases[1].matches, vec![(0, 0)]);
+        assert!(parse_cases("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let case = |body: &str| parse_cases(&(String::from("[[case]]\n") + body));
+        assert!(
+            case("pattern = 'a'\ninput = 'a'").is_err(),
+            "missing matches"
+        );
+        assert!(case("pattern = 'a'\npattern = 'b'").is_err(), "duplicate");
+        assert!(case("flavor = 'a'").is_err(), "unknown key");
+        assert!(case("pattern = 1").is_err(), "wrong type");
+        assert!(case("matches = [[0]]").is_err(), "short span");
+        assert!(case("matches = [[0, 1]").is_err(), "unclosed array");
+        assert!(case("input = \"a").is_err(), "unterminated string");
+        assert!(case("input = \"\\q\"").is_err(), "unknown escape");
+        assert!(case("input = 'a' 'b'").is_err(), "two values");
+        assert!(case("matches = ").is_err(), "no value");
+        assert!(case(&(String::from("matches = ") + &"[".repeat(100))).is_err());
+        assert!(parse_cases("pattern = 'a'").is_err(), "no header");
+        assert!(parse_cases("[cases]").is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        let mut cases = parse_cases(
+            "[[case]]\npattern = 'a*'\ninput = 'baa'\nmatches = [[0, 0], [1, 3]]\n\
+             [[case]]\nname = 'wrong'\npattern = 'a'\ninput = 'aa'\nmatches = [[0, 1]]\n\
+             [[case]]\nname = 'invalid'\npattern = '(a'\ninput = ''\nmatches = []\n",
+        )
+        .unwrap();
+        let report = run(&cases);
+        assert_eq!(report.passed, 1);
+        assert_eq!(report.failures.len(), 2);
+        assert_eq!(report.failures[0].case, 1);
+        assert_eq!(report.failures[0].found, Ok(vec![(0, 1), (1, 2)]));
+        assert!(report.failures[1].found.is_err());
+        assert_eq!(
+            alloc::format!("{}", report).lines().next(),
+            Some("1 passed, 2 failed")
+        );
+        cases.truncate(1);
+        assert!(run(&cases).is_ok());
+    }
+}
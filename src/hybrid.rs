@@ -0,0 +1,671 @@
+use crate::frozen::fnv1a;
+use crate::nfa::NFA;
+use crate::stats::{Metric, MetricKind, Metrics};
+use crate::{QId, ANFA};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::mem::size_of;
+
+/// What `Regex` does when its cache is full
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Eviction {
+    /// The rest of the search simulates the NFA, and the cache is cleared
+    /// before the next search
+    #[default]
+    BetweenSearches,
+    /// The cache is cleared at once and the search keeps caching states.
+    /// Suits long haystacks, at the cost of rebuilding the states still in use.
+    Immediately,
+    /// The cache is never cleared. States that don't fit are simulated, so
+    /// the states built by the first searches are kept for the life of the
+    /// `Regex`.
+    Never,
+}
+
+/// Tuning for `Regex`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Approximate number of bytes the DFA cache may use, see `eviction`
+    pub cache_capacity: usize,
+    pub eviction: Eviction,
+    /// When the cache has been cleared this many times, the `Regex` gives up
+    /// on caching: the cache is dropped and every later search simulates the
+    /// NFA, which is slower per character but never rebuilds states. `None`
+    /// never gives up.
+    pub max_evictions: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            cache_capacity: 1 << 20,
+            eviction: Eviction::default(),
+            max_evictions: None,
+        }
+    }
+}
+
+/// Lazily built DFA states, each a set of NFA states
+#[derive(Debug, Default)]
+struct Cache {
+    /// the sorted NFA states of each DFA state
+    states: Vec<Vec<QId>>,
+    ids: BTreeMap<Vec<QId>, usize>,
+    /// `transitions[q]` lists the `(symbol, q_next)` computed so far
+    transitions: Vec<Vec<(char, usize)>>,
+    accepting: Vec<bool>,
+    /// approximate bytes used
+    memory: usize,
+    /// set when a search ran out of capacity
+    exhausted: bool,
+    /// transitions found in the cache, kept when the cache is cleared
+    hits: u64,
+    /// transitions computed from the NFA, kept when the cache is cleared
+    misses: u64,
+    /// times the cache was cleared
+    evictions: u64,
+}
+
+impl Cache {
+    /// Clears the cache, keeping the counters
+    fn evict(&mut self) {
+        *self = Cache {
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions + 1,
+            ..Cache::default()
+        };
+    }
+}
+
+/// Usage of the cache of a `Regex`, see `Regex::cache_stats`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CacheStats {
+    /// DFA states currently cached
+    pub states: usize,
+    /// Approximate bytes currently used
+    pub memory: usize,
+    /// Transitions found in the cache, over the life of the `Regex`
+    pub hits: u64,
+    /// Transitions computed from the NFA, over the life of the `Regex`
+    pub misses: u64,
+    /// Times the cache was cleared, over the life of the `Regex`
+    pub evictions: u64,
+    /// Whether the `Regex` gave up on caching, see `Config::max_evictions`
+    pub gave_up: bool,
+}
+
+impl CacheStats {
+    /// The share of transitions found in the cache, from 0 to 1, or `None`
+    /// before the first transition
+    pub fn hit_rate(&self) -> Option<f64> {
+        match self.hits + self.misses {
+            0 => None,
+            total => Some(self.hits as f64 / total as f64),
+        }
+    }
+}
+
+impl Metrics for CacheStats {
+    fn metrics(&self) -> Vec<Metric> {
+        vec![
+            Metric {
+                name: "lazy_dfa_cached_states",
+                help: "Number of DFA states cached",
+                kind: MetricKind::Gauge,
+                value: self.states as u64,
+            },
+            Metric {
+                name: "lazy_dfa_cache_bytes",
+                help: "Approximate bytes used by the DFA cache",
+                kind: MetricKind::Gauge,
+                value: self.memory as u64,
+            },
+            Metric {
+                name: "lazy_dfa_cache_hits_total",
+                help: "Transitions found in the DFA cache",
+                kind: MetricKind::Counter,
+                value: self.hits,
+            },
+            Metric {
+                name: "lazy_dfa_cache_misses_total",
+                help: "Transitions computed from the NFA",
+                kind: MetricKind::Counter,
+                value: self.misses,
+            },
+            Metric {
+                name: "lazy_dfa_cache_evictions_total",
+                help: "Times the DFA cache was cleared",
+                kind: MetricKind::Counter,
+                value: self.evictions,
+            },
+            Metric {
+                name: "lazy_dfa_gave_up",
+                help: "1 when the lazy DFA gave up on caching",
+                kind: MetricKind::Gauge,
+                value: self.gave_up as u64,
+            },
+        ]
+    }
+}
+
+/// The result of `Regex::audit`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditReport {
+    /// Whether the tables of the NFA still have the checksum computed when
+    /// the `Regex` was built
+    pub checksum_ok: bool,
+    /// Each kind of broken invariant of the NFA or the cache, in the order
+    /// found
+    pub violations: Vec<&'static str>,
+    /// Cached DFA states checked
+    pub states: usize,
+    /// Cached transitions checked
+    pub transitions: usize,
+}
+
+impl AuditReport {
+    /// True when the checksum matches and no invariant is broken
+    pub fn is_ok(&self) -> bool {
+        self.checksum_ok && self.violations.is_empty()
+    }
+
+    fn violation(&mut self, violation: &'static str) {
+        if !self.violations.contains(&violation) {
+            self.violations.push(violation);
+        }
+    }
+}
+
+/// 64-bit FNV-1a of the tables of `nfa` as little-endian `u64`s: q0, the
+/// state count, then whether each state is final, its transition count and
+/// each transition
+fn checksum(nfa: &NFA) -> u64 {
+    let mut bytes = Vec::new();
+    let mut write = |value: u64| bytes.extend_from_slice(&value.to_le_bytes());
+    write(nfa.q0 as u64);
+    write(nfa.delta.len() as u64);
+    for (is_final, transitions) in nfa.finals.iter().zip(nfa.delta.iter()) {
+        write(*is_final as u64);
+        write(transitions.len() as u64);
+        for (label, q_next) in transitions.iter() {
+            write(u64::from(*label));
+            write(*q_next as u64);
+        }
+    }
+    fnv1a(&bytes)
+}
+
+/// Where a search is: a cached DFA state, or a set of NFA states once the
+/// cache is exhausted
+enum Cursor {
+    Cached(usize),
+    Uncached(Vec<QId>),
+}
+
+/// A lazy DFA. DFA states are built by subset construction only when a search
+/// first needs them and are kept in a cache of bounded size, so patterns whose
+/// full DFA would be exponentially large still match in linear time per start
+/// position. When the cache is full, the search continues by simulating the
+/// NFA directly.
+///
+/// Matches are leftmost-longest, like `dense::DFA::find`.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::hybrid::Regex;
+/// // RE (a+b)*a(a+b)(a+b)(a+b), the full DFA has 16 states
+/// let a_or_b = || Expr::literal('a').union(Expr::literal('b'));
+/// let expr = a_or_b().star().concat(Expr::literal('a')).concat(a_or_b()).concat(a_or_b()).concat(a_or_b());
+/// let regex = Regex::new(&expr.build().unwrap()).unwrap();
+/// assert_eq!(regex.find("xxbabbbx"), Some((2, 7)));
+/// assert!(!regex.is_match("bbbb"));
+/// ```
+#[derive(Debug)]
+pub struct Regex {
+    nfa: NFA,
+    /// the checksum of the NFA tables, see `audit`
+    checksum: u64,
+    config: Config,
+    cache: RefCell<Cache>,
+}
+
+impl Regex {
+    /// Builds a lazy DFA for the automaton with the default `Config`.
+    /// The ANFA must hold exactly one automaton.
+    pub fn new(anfa: &ANFA) -> Result<Regex, &'static str> {
+        Regex::with_config(anfa, Config::default())
+    }
+
+    pub fn with_config(anfa: &ANFA, config: Config) -> Result<Regex, &'static str> {
+        let nfa = anfa.remove_epsilon()?;
+        Ok(Regex {
+            checksum: checksum(&nfa),
+            nfa,
+            config,
+            cache: RefCell::new(Cache::default()),
+        })
+    }
+
+    /// The number of DFA states currently cached
+    pub fn cached_states(&self) -> usize {
+        self.cache.borrow().states.len()
+    }
+
+    /// Usage of the cache so far. Hits and misses count transitions and
+    /// are never reset, so a monitoring system can derive hit rates over
+    /// any window.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::hybrid::Regex;
+    /// let regex = Regex::new(&Expr::literal('a').star().build().unwrap()).unwrap();
+    /// regex.find("aaaa");
+    /// let stats = regex.cache_stats();
+    /// assert_eq!((stats.hits, stats.misses), (2, 2));
+    /// assert_eq!(stats.hit_rate(), Some(0.5));
+    /// ```
+    pub fn cache_stats(&self) -> CacheStats {
+        let cache = self.cache.borrow();
+        CacheStats {
+            states: cache.states.len(),
+            memory: cache.memory,
+            hits: cache.hits,
+            misses: cache.misses,
+            evictions: cache.evictions,
+            gave_up: self.gave_up(&cache),
+        }
+    }
+
+    /// Re-validates the tables on demand: the checksum of the NFA computed
+    /// when the `Regex` was built, that every NFA id is in bounds, and that
+    /// every cached DFA state and transition still agrees with the NFA and
+    /// the cache's memory accounting. Meant to run now and then in services
+    /// holding a `Regex` for a long time, so corrupted memory is noticed and
+    /// the `Regex` rebuilt from its ANFA, instead of silently matching
+    /// something else. The audit reads the NFA and the whole cache.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::hybrid::Regex;
+    /// let regex = Regex::new(&Expr::literal('a').star().build().unwrap()).unwrap();
+    /// regex.find("aaab");
+    /// let report = regex.audit();
+    /// assert!(report.is_ok());
+    /// assert_eq!(report.states, regex.cached_states());
+    /// ```
+    pub fn audit(&self) -> AuditReport {
+        let mut report = AuditReport {
+            checksum_ok: checksum(&self.nfa) == self.checksum,
+            ..AuditReport::default()
+        };
+        let n = self.nfa.delta.len();
+        if self.nfa.q0 >= n || self.nfa.finals.len() != n {
+            report.violation("NFA tables are inconsistent.");
+        }
+        for transitions in self.nfa.delta.iter() {
+            if transitions.iter().any(|(_label, q_next)| *q_next >= n) {
+                report.violation("NFA transition leads out of bounds.");
+            }
+        }
+        if !report.violations.is_empty() {
+            // the cache can't be checked against a broken NFA
+            return report;
+        }
+
+        let cache = self.cache.borrow();
+        let count = cache.states.len();
+        if cache.ids.len() != count
+            || cache.transitions.len() != count
+            || cache.accepting.len() != count
+        {
+            report.violation("Cache tables have different lengths.");
+            return report;
+        }
+        let mut memory = 0;
+        for (q, states) in cache.states.iter().enumerate() {
+            report.states += 1;
+            memory += 2 * states.len() * size_of::<QId>() + 4 * size_of::<usize>();
+            if states.iter().any(|q_nfa| *q_nfa >= n) {
+                report.violation("Cached state holds an NFA state out of bounds.");
+                continue;
+            }
+            if states.windows(2).any(|pair| pair[0] >= pair[1]) {
+                report.violation("Cached state is not sorted.");
+            }
+            if cache.ids.get(states) != Some(&q) {
+                report.violation("Cache index disagrees with its states.");
+            }
+            if cache.accepting[q] != self.is_accepting(states) {
+                report.violation("Cached acceptance disagrees with the NFA.");
+            }
+            for (c, q_next) in cache.transitions[q].iter() {
+                report.transitions += 1;
+                memory += size_of::<(char, usize)>();
+                if *q_next >= count {
+                    report.violation("Cached transition leads out of the cache.");
+                } else if cache.states[*q_next] != self.step(states, *c) {
+                    report.violation("Cached transition disagrees with the NFA.");
+                }
+            }
+        }
+        if memory != cache.memory {
+            report.violation("Cache memory accounting is off.");
+        }
+        if cache.memory > self.config.cache_capacity {
+            report.violation("Cache exceeds its capacity.");
+        }
+        report
+    }
+
+    fn gave_up(&self, cache: &Cache) -> bool {
+        self.config
+            .max_evictions
+            .is_some_and(|max_evictions| cache.evictions >= max_evictions)
+    }
+
+    fn is_accepting(&self, states: &[QId]) -> bool {
+        states.iter().any(|q| self.nfa.finals[*q])
+    }
+
+    /// The NFA states entered from `states` along `c`, sorted
+    fn step(&self, states: &[QId], c: char) -> Vec<QId> {
+        let mut next: Vec<QId> = Vec::new();
+        for q in states.iter() {
+            for (label, q_next) in self.nfa.delta[*q].iter() {
+                if *label == c {
+                    next.push(*q_next);
+                }
+            }
+        }
+        next.sort_unstable();
+        next.dedup();
+        next
+    }
+
+    /// Returns the id of the DFA state for `states`, adding it to the cache,
+    /// or `None` when the cache is full. Ids from before the call are stale
+    /// when it evicts the cache.
+    fn intern(&self, cache: &mut Cache, states: Vec<QId>) -> Option<usize> {
+        if self.gave_up(cache) {
+            return None;
+        }
+        if let Some(q) = cache.ids.get(&states) {
+            return Some(*q);
+        }
+        // the set is stored twice, once as a key
+        let memory = 2 * states.len() * size_of::<QId>() + 4 * size_of::<usize>();
+        if cache.memory + memory > self.config.cache_capacity {
+            cache.exhausted = true;
+            if self.config.eviction != Eviction::Immediately || memory > self.config.cache_capacity
+            {
+                return None;
+            }
+            cache.evict();
+            if self.gave_up(cache) {
+                return None;
+            }
+        }
+        cache.memory += memory;
+        let q = cache.states.len();
+        cache.accepting.push(self.is_accepting(&states));
+        cache.ids.insert(states.clone(), q);
+        cache.states.push(states);
+        cache.transitions.push(Vec::new());
+        Some(q)
+    }
+
+    /// Moves `cursor` along `c`, returns false when no NFA state is left
+    fn advance(&self, cache: &mut Cache, cursor: &mut Cursor, c: char) -> bool {
+        let next = match cursor {
+            Cursor::Cached(q) => {
+                if let Some((_c, q_next)) = cache.transitions[*q]
+                    .iter()
+                    .find(|(symbol, _)| *symbol == c)
+                {
+                    cache.hits += 1;
+                    *cursor = Cursor::Cached(*q_next);
+                    return !cache.states[*q_next].is_empty();
+                }
+                cache.misses += 1;
+                let next = self.step(&cache.states[*q], c);
+                let q = *q;
+                let evictions = cache.evictions;
+                match self.intern(cache, next.clone()) {
+                    Some(q_next) => {
+                        let memory = size_of::<(char, usize)>();
+                        if cache.evictions == evictions
+                            && cache.memory + memory <= self.config.cache_capacity
+                        {
+                            cache.memory += memory;
+                            cache.transitions[q].push((c, q_next));
+                        }
+                        *cursor = Cursor::Cached(q_next);
+                        return !next.is_empty();
+                    }
+                    None => next,
+                }
+            }
+            Cursor::Uncached(states) => {
+                cache.misses += 1;
+                self.step(states, c)
+            }
+        };
+        let alive = !next.is_empty();
+        *cursor = Cursor::Uncached(next);
+        alive
+    }
+
+    fn cursor_is_accepting(&self, cache: &Cache, cursor: &Cursor) -> bool {
+        match cursor {
+            Cursor::Cached(q) => cache.accepting[*q],
+            Cursor::Uncached(states) => self.is_accepting(states),
+        }
+    }
+
+    /// Returns the byte span of the leftmost-longest match in `haystack`
+    pub fn find(&self, haystack: &str) -> Option<(usize, usize)> {
+        let mut cache = self.cache.borrow_mut();
+        if cache.exhausted && self.config.eviction == Eviction::BetweenSearches {
+            cache.evict();
+        }
+        let starts = haystack
+            .char_indices()
+            .map(|(start, _c)| start)
+            .chain(core::iter::once(haystack.len()));
+        for start in starts {
+            let mut cursor = match self.intern(&mut cache, vec![self.nfa.q0]) {
+                Some(q0) => Cursor::Cached(q0),
+                None => Cursor::Uncached(vec![self.nfa.q0]),
+            };
+            let mut end = None;
+            if self.cursor_is_accepting(&cache, &cursor) {
+                end = Some(start);
+            }
+            for (at, c) in haystack[start..].char_indices() {
+                if !self.advance(&mut cache, &mut cursor, c) {
+                    break;
+                }
+                if self.cursor_is_accepting(&cache, &cursor) {
+                    end = Some(start + at + c.len_utf8());
+                }
+            }
+            if let Some(end) = end {
+                return Some((start, end));
+            }
+        }
+        None
+    }
+
+    /// Returns true when some substring of `haystack` is accepted
+    pub fn is_match(&self, haystack: &str) -> bool {
+        self.find(haystack).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::Expr;
+    use crate::hybrid::{Config, Eviction, Regex};
+    use crate::ANFA;
+    use alloc::vec;
+
+    /// RE (a+b)*a(a+b)^n, whose DFA has 2^(n+1) states
+    fn nth_from_last(n: usize) -> ANFA {
+        let a_or_b = || Expr::literal('a').union(Expr::literal('b'));
+        let mut expr = a_or_b().star().concat(Expr::literal('a'));
+        for _ in 0..n {
+            expr = expr.concat(a_or_b());
+        }
+        expr.build().unwrap()
+    }
+
+    #[test]
+    fn test_find() {
+        let machine = nth_from_last(2);
+        let regex = Regex::new(&machine).unwrap();
+        let dense = machine.to_dense().unwrap();
+        for haystack in ["", "a", "aab", "bbabb", "xabaxbbaab", "ébaaé"].iter() {
+            assert_eq!(regex.find(haystack), dense.find(haystack), "{}", haystack);
+        }
+        assert!(
+            regex.cached_states() > 0,
+            "States are cached between searches"
+        );
+    }
+
+    #[test]
+    fn test_cache_capacity() {
+        let machine = nth_from_last(12);
+        let haystack = "ab".repeat(200) + "abbbbbbbbbbbbb";
+        let unbounded = Regex::new(&machine).unwrap();
+        let tiny = Regex::with_config(
+            &machine,
+            Config {
+                cache_capacity: 256,
+                ..Config::default()
+            },
+        )
+        .unwrap();
+        let empty = Regex::with_config(
+            &machine,
+            Config {
+                cache_capacity: 0,
+                ..Config::default()
+            },
+        )
+        .unwrap();
+        let expected = unbounded.find(&haystack);
+        assert!(expected.is_some());
+        assert_eq!(
+            tiny.find(&haystack),
+            expected,
+            "Exhausted caches fall back to the NFA"
+        );
+        assert_eq!(empty.find(&haystack), expected);
+        assert_eq!(empty.cached_states(), 0);
+        let first = tiny.cache_stats();
+        assert_eq!(
+            tiny.find(&haystack),
+            expected,
+            "Exhausted caches are cleared"
+        );
+        let stats = tiny.cache_stats();
+        assert!(stats.memory <= 256);
+        assert!(
+            stats.misses > first.misses && stats.hits >= first.hits,
+            "Counters are kept when the cache is cleared"
+        );
+        assert!(unbounded.cache_stats().hit_rate() > tiny.cache_stats().hit_rate());
+        assert_eq!(Regex::new(&machine).unwrap().cache_stats().hit_rate(), None);
+        assert_eq!(tiny.cache_stats().evictions, 1);
+    }
+
+    #[test]
+    fn test_eviction() {
+        let machine = nth_from_last(12);
+        let haystack = "ab".repeat(200) + "abbbbbbbbbbbbb";
+        let expected = Regex::new(&machine).unwrap().find(&haystack);
+        let with = |eviction, max_evictions| {
+            Regex::with_config(
+                &machine,
+                Config {
+                    cache_capacity: 1024,
+                    eviction,
+                    max_evictions,
+                },
+            )
+            .unwrap()
+        };
+        let immediately = with(Eviction::Immediately, None);
+        assert_eq!(immediately.find(&haystack), expected);
+        let stats = immediately.cache_stats();
+        assert!(stats.evictions > 1, "Cleared during the search");
+        assert!(stats.memory <= 1024);
+
+        let never = with(Eviction::Never, None);
+        assert_eq!(never.find(&haystack), expected);
+        let states = never.cached_states();
+        assert_eq!(never.find(&haystack), expected);
+        assert_eq!(never.cached_states(), states, "States are kept");
+        assert_eq!(never.cache_stats().evictions, 0);
+
+        let giving_up = with(Eviction::Immediately, Some(2));
+        assert_eq!(giving_up.find(&haystack), expected);
+        let stats = giving_up.cache_stats();
+        assert!(stats.gave_up);
+        assert_eq!(stats.evictions, 2);
+        assert_eq!((stats.states, stats.memory), (0, 0), "The cache is dropped");
+        assert_eq!(giving_up.find(&haystack), expected);
+        assert_eq!(giving_up.cache_stats().hits, stats.hits);
+        assert!(!immediately.cache_stats().gave_up);
+    }
+
+    #[test]
+    fn test_audit() {
+        let machine = nth_from_last(3);
+        let regex = Regex::new(&machine).unwrap();
+        let fresh = regex.audit();
+        assert!(fresh.is_ok());
+        assert_eq!((fresh.states, fresh.transitions), (0, 0));
+        regex.find("abbabaab");
+        let report = regex.audit();
+        assert!(report.is_ok(), "{:?}", report.violations);
+        assert!(report.states > 0 && report.transitions > 0);
+
+        // corrupt the cache, a transition to the wrong state
+        {
+            let mut cache = regex.cache.borrow_mut();
+            let q = (0..cache.transitions.len())
+                .find(|q| !cache.transitions[*q].is_empty())
+                .unwrap();
+            let (c, q_next) = cache.transitions[q][0];
+            let wrong = (0..cache.states.len())
+                .find(|q_wrong| cache.states[*q_wrong] != cache.states[q_next])
+                .unwrap();
+            cache.transitions[q][0] = (c, wrong);
+            cache.accepting[0] = !cache.accepting[0];
+        }
+        let report = regex.audit();
+        assert!(report.checksum_ok);
+        assert_eq!(
+            report.violations,
+            vec![
+                "Cached acceptance disagrees with the NFA.",
+                "Cached transition disagrees with the NFA."
+            ]
+        );
+
+        // corrupt the NFA
+        let mut regex = Regex::new(&machine).unwrap();
+        regex.nfa.finals[0] = !regex.nfa.finals[0];
+        assert!(!regex.audit().checksum_ok);
+        regex.nfa.delta[0].push(('a', usize::MAX));
+        assert_eq!(
+            regex.audit().violations,
+            vec!["NFA transition leads out of bounds."]
+        );
+    }
+}
@@ -0,0 +1,389 @@
+use crate::{QId, ANFA};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+/// Escapes text for XML element content
+fn escape_xml(out: &mut String, c: char) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        c => out.push(c),
+    }
+}
+
+/// Replaces the predefined XML entities and character references in `text`
+fn unescape_xml(text: &str) -> Result<String, &'static str> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let end = match rest[start..].find(';') {
+            Some(end) => start + end,
+            None => return Err("Unterminated XML entity."),
+        };
+        let c = match &rest[start + 1..end] {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            entity => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16)
+                } else if let Some(decimal) = entity.strip_prefix('#') {
+                    decimal.parse::<u32>()
+                } else {
+                    return Err("Unknown XML entity.");
+                };
+                match code.ok().and_then(char::from_u32) {
+                    Some(c) => c,
+                    None => return Err("Invalid XML character reference."),
+                }
+            }
+        };
+        out.push(c);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Returns the content of every `<tag ...>...</tag>` element in `xml` and the
+/// attributes of its start tag. Self-closing elements have empty content.
+/// Nested elements of the same name are not supported, and JFLAP does not use them.
+fn elements<'a>(xml: &'a str, tag: &str) -> Result<Vec<(&'a str, &'a str)>, &'static str> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    let mut open = String::from("<");
+    open.push_str(tag);
+    let mut close = String::from("</");
+    close.push_str(tag);
+    close.push('>');
+    while let Some(start) = rest.find(open.as_str()) {
+        let after_name = &rest[start + open.len()..];
+        // skip longer tag names that share this prefix, e.g. <finalize> for <final>
+        if !after_name.starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace()) {
+            rest = after_name;
+            continue;
+        }
+        let tag_end = match after_name.find('>') {
+            Some(tag_end) => tag_end,
+            None => return Err("Unterminated XML tag."),
+        };
+        let attributes = &after_name[..tag_end];
+        if let Some(attributes) = attributes.strip_suffix('/') {
+            found.push((attributes, ""));
+            rest = &after_name[tag_end + 1..];
+            continue;
+        }
+        let content = &after_name[tag_end + 1..];
+        let content_end = match content.find(close.as_str()) {
+            Some(content_end) => content_end,
+            None => return Err("Missing XML end tag."),
+        };
+        found.push((attributes, &content[..content_end]));
+        rest = &content[content_end + close.len()..];
+    }
+    Ok(found)
+}
+
+/// Returns the value of `name="value"` in a start tag's attributes
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attributes;
+    while let Some(start) = rest.find(name) {
+        let preceded_by_space = start == 0 || rest[..start].ends_with(|c: char| c.is_whitespace());
+        let after = rest[start + name.len()..].trim_start();
+        if preceded_by_space {
+            if let Some(after) = after.strip_prefix('=') {
+                let after = after.trim_start();
+                let quote = after.chars().next()?;
+                if quote == '"' || quote == '\'' {
+                    let value = &after[1..];
+                    return value.find(quote).map(|end| &value[..end]);
+                }
+            }
+        }
+        rest = &rest[start + name.len()..];
+    }
+    None
+}
+
+/// Returns the text of the single `<tag>` child element in `xml`
+fn child_text(xml: &str, tag: &str) -> Result<String, &'static str> {
+    match elements(xml, tag)?.as_slice() {
+        [(_attributes, content)] => unescape_xml(content.trim()),
+        _ => Err("Expected exactly one child element."),
+    }
+}
+
+fn parse_id(text: &str) -> Result<usize, &'static str> {
+    text.trim()
+        .parse::<usize>()
+        .map_err(|_| "JFLAP state ids must be non-negative integers.")
+}
+
+/// Exports the automaton as a JFLAP finite automaton (`.jff`).
+/// Every ANFA state becomes a JFLAP state and ε transitions become λ transitions.
+/// The ANFA must hold exactly one automaton.
+///
+/// ```rust
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// use regexxx::jflap;
+/// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+/// let jff = jflap::to_jff(&machine).unwrap();
+/// assert!(jff.contains("<transition><from>0</from><to>1</to><read>a</read></transition>"));
+/// ```
+pub fn to_jff(anfa: &ANFA) -> Result<String, &'static str> {
+    let [q0, f] = match anfa.automata_refs.as_slice() {
+        [machine] => *machine,
+        _ => return Err("JFLAP export requires exactly one automaton."),
+    };
+    if !anfa.assertions.is_empty() {
+        return Err("JFLAP export does not support assertions.");
+    }
+    let mut out = String::new();
+    write_jff(anfa, q0, f, &mut out).map_err(|_| "Failed to format JFLAP output.")?;
+    Ok(out)
+}
+
+fn write_jff(anfa: &ANFA, q0: QId, f: QId, out: &mut String) -> fmt::Result {
+    writeln!(
+        out,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>"
+    )?;
+    writeln!(out, "<structure>\n<type>fa</type>\n<automaton>")?;
+    for q in 0..anfa.delta.len() {
+        // JFLAP requires coordinates, lay states out on a grid
+        write!(
+            out,
+            "<state id=\"{}\" name=\"q{}\"><x>{}.0</x><y>{}.0</y>",
+            q,
+            q,
+            100 + (q % 10) * 100,
+            100 + (q / 10) * 100
+        )?;
+        if q == q0 {
+            write!(out, "<initial/>")?;
+        }
+        if q == f {
+            write!(out, "<final/>")?;
+        }
+        writeln!(out, "</state>")?;
+    }
+    for (q, (label, q_nexts)) in anfa.delta.iter().enumerate() {
+        for q_next in q_nexts.iter().flatten() {
+            write!(out, "<transition><from>{}</from><to>{}</to>", q, q_next)?;
+            match label {
+                Some(c) => {
+                    out.push_str("<read>");
+                    escape_xml(out, *c);
+                    out.push_str("</read>");
+                }
+                None => out.push_str("<read/>"),
+            }
+            writeln!(out, "</transition>")?;
+        }
+    }
+    writeln!(out, "</automaton>\n</structure>")
+}
+
+/// Imports a JFLAP finite automaton (`.jff`) as a composed ANFA.
+///
+/// JFLAP states may have any number of transitions and final states, so each
+/// JFLAP state is represented by an ANFA state that fans out along ε unions to
+/// one branch per transition, plus an ε branch to a single final state when
+/// the JFLAP state is final. A transition reading several characters becomes a
+/// chain of labeled states; a λ transition reads nothing.
+///
+/// ```rust
+/// use regexxx::jflap;
+/// let jff = "<structure><type>fa</type><automaton>\
+///     <state id=\"0\"><initial/></state><state id=\"1\"><final/></state>\
+///     <transition><from>0</from><to>1</to><read>ab</read></transition>\
+///     </automaton></structure>";
+/// let machine = jflap::from_jff(jff).unwrap();
+/// assert_eq!(machine.shortest_accepted().unwrap(), Some(vec!['a', 'b']));
+/// ```
+pub fn from_jff(jff: &str) -> Result<ANFA, &'static str> {
+    let automaton = match elements(jff, "automaton")?.as_slice() {
+        [(_attributes, automaton)] => *automaton,
+        _ => return Err("JFLAP file must contain exactly one automaton."),
+    };
+    if let [(_attributes, fa_type)] = elements(jff, "type")?.as_slice() {
+        if fa_type.trim() != "fa" {
+            return Err("Only JFLAP finite automata (type fa) can be imported.");
+        }
+    }
+
+    // JFLAP ids are arbitrary integers, map them to hub states 0..n
+    let mut jflap_ids: Vec<usize> = Vec::new();
+    let mut initial: Option<QId> = None;
+    let mut finals: Vec<bool> = Vec::new();
+    for (attributes, content) in elements(automaton, "state")? {
+        let id = match attribute(attributes, "id") {
+            Some(id) => parse_id(id)?,
+            None => return Err("JFLAP state is missing its id."),
+        };
+        if jflap_ids.contains(&id) {
+            return Err("JFLAP state ids must be unique.");
+        }
+        if !elements(content, "initial")?.is_empty() {
+            if initial.is_some() {
+                return Err("JFLAP automaton has more than one initial state.");
+            }
+            initial = Some(jflap_ids.len());
+        }
+        finals.push(!elements(content, "final")?.is_empty());
+        jflap_ids.push(id);
+    }
+    let initial = match initial {
+        Some(initial) => initial,
+        None => return Err("JFLAP automaton has no initial state."),
+    };
+    let hub = |id: usize| -> Result<QId, &'static str> {
+        jflap_ids
+            .iter()
+            .position(|jflap_id| *jflap_id == id)
+            .ok_or("JFLAP transition refers to an unknown state.")
+    };
+
+    let mut anfa = ANFA::new();
+    for _ in 0..jflap_ids.len() {
+        anfa.push_state((None, [None, None]));
+    }
+    let f = anfa.push_state((None, [None, None]));
+
+    // entry states of the branches leaving each hub
+    let mut branches: Vec<Vec<QId>> = vec![Vec::new(); jflap_ids.len()];
+    for (_attributes, content) in elements(automaton, "transition")? {
+        let from = hub(parse_id(&child_text(content, "from")?)?)?;
+        let to = hub(parse_id(&child_text(content, "to")?)?)?;
+        let read = match elements(content, "read")?.as_slice() {
+            [] => String::new(),
+            [(_attributes, read)] => unescape_xml(read)?,
+            _ => return Err("JFLAP transition has more than one read element."),
+        };
+        // push the chain of labeled states back to front
+        let mut entry = to;
+        for c in read.chars().rev() {
+            entry = anfa.push_state((Some(c), [Some(entry), None]));
+        }
+        branches[from].push(entry);
+    }
+    for (q, is_final) in finals.iter().enumerate() {
+        if *is_final {
+            branches[q].push(f);
+        }
+    }
+
+    for (q, entries) in branches.iter().enumerate() {
+        // fan out along ε unions: q -> (entry_0, (entry_1, (entry_2, ...)))
+        let mut q_union = q;
+        for (i, entry) in entries.iter().enumerate() {
+            if i + 2 < entries.len() {
+                let q_rest = anfa.push_state((None, [None, None]));
+                anfa.delta[q_union] = (None, [Some(*entry), Some(q_rest)]);
+                q_union = q_rest;
+            } else if i + 2 == entries.len() {
+                anfa.delta[q_union] = (None, [Some(*entry), Some(entries[i + 1])]);
+                break;
+            } else {
+                anfa.delta[q_union] = (None, [Some(*entry), None]);
+            }
+        }
+    }
+    anfa.automata_refs.push([initial, f]);
+    Ok(anfa)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::jflap::{from_jff, to_jff};
+    use crate::ANFA;
+
+    #[test]
+    fn test_round_trip() {
+        // RE (a+<)*b
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, '<').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let jff = to_jff(&machine).unwrap();
+        assert!(jff.contains("<read>&lt;</read>"), "Labels are escaped");
+        assert!(jff.contains("<read/>"), "ε transitions are λ transitions");
+        let imported = from_jff(&jff).unwrap();
+        assert!(
+            ANFA::equivalent(&machine, &imported).unwrap(),
+            "Importing an exported machine preserves its language"
+        );
+    }
+
+    #[test]
+    fn test_from_jff() {
+        // JFLAP's own layout: arbitrary ids, many transitions per state, several finals
+        let jff = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?><!--Created with JFLAP 7.1.--><structure>
+	<type>fa</type>
+	<automaton>
+		<state id="7" name="q7"><x>50.0</x><y>50.0</y><initial/></state>
+		<state id="3" name="q3"><x>150.0</x><y>50.0</y><final/></state>
+		<state id="5" name="q5"><x>250.0</x><y>50.0</y><final/></state>
+		<transition><from>7</from><to>3</to><read>a</read></transition>
+		<transition><from>7</from><to>5</to><read>b</read></transition>
+		<transition><from>7</from><to>7</to><read>c</read></transition>
+		<transition><from>3</from><to>5</to><read/></transition>
+		<transition><from>5</from><to>5</to><read>&amp;d</read></transition>
+	</automaton>
+</structure>"#;
+        let machine = from_jff(jff).unwrap();
+        let dfa = machine.determinize().unwrap();
+        for input in ["a", "b", "cca", "a&d&d", "cb&d"] {
+            assert!(
+                dfa.accepts(input.chars()),
+                "Imported machine accepts {}",
+                input
+            );
+        }
+        for input in ["", "c", "ab", "a&", "d"] {
+            assert!(
+                !dfa.accepts(input.chars()),
+                "Imported machine rejects {}",
+                input
+            );
+        }
+        for (_label, q_nexts) in machine.delta.iter() {
+            if q_nexts[0].is_none() {
+                assert!(q_nexts[1].is_none(), "Transitions stay ordered");
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_jff_errors() {
+        assert!(from_jff("<structure></structure>").is_err());
+        assert!(
+            from_jff(
+                "<structure><type>fa</type><automaton><state id=\"0\"/></automaton></structure>"
+            )
+            .is_err(),
+            "An initial state is required"
+        );
+        assert!(
+            from_jff(
+                "<structure><type>fa</type><automaton><state id=\"0\"><initial/></state>\
+             <transition><from>0</from><to>1</to><read/></transition></automaton></structure>"
+            )
+            .is_err(),
+            "Transitions must refer to declared states"
+        );
+        assert!(from_jff(
+            "<structure><type>pda</type><automaton><state id=\"0\"><initial/></state></automaton></structure>"
+        )
+        .is_err(), "Only finite automata are imported");
+    }
+}
@@ -0,0 +1,560 @@
+use crate::assertion::Assertion;
+use crate::{QId, ANFA};
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+/// Value of the `"format"` field of every machine written by `ANFA::to_json`
+const FORMAT: &str = "regexxx-anfa";
+
+/// Version of the JSON interchange format written by `ANFA::to_json`
+pub const VERSION: usize = 1;
+
+const ASSERTIONS: [Assertion; 6] = [
+    Assertion::StartText,
+    Assertion::EndText,
+    Assertion::WordBoundary,
+    Assertion::NotWordBoundary,
+    Assertion::AsciiWordBoundary,
+    Assertion::AsciiNotWordBoundary,
+];
+
+/// Writes `text` as a JSON string
+fn write_json_str(out: &mut String, text: &str) -> fmt::Result {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    Ok(())
+}
+
+fn write_json(anfa: &ANFA, q0: QId, f: QId, out: &mut String) -> fmt::Result {
+    write!(out, "{{\"format\":")?;
+    write_json_str(out, FORMAT)?;
+    write!(
+        out,
+        ",\"version\":{},\"q0\":{},\"f\":{},\"states\":[",
+        VERSION, q0, f
+    )?;
+    for (q, (label, q_nexts)) in anfa.delta.iter().enumerate() {
+        if q > 0 {
+            out.push(',');
+        }
+        out.push_str("{\"label\":");
+        match label {
+            Some(c) => write_json_str(out, c.encode_utf8(&mut [0; 4]))?,
+            None => out.push_str("null"),
+        }
+        out.push_str(",\"next\":[");
+        for (i, q_next) in q_nexts.iter().flatten().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write!(out, "{}", q_next)?;
+        }
+        out.push(']');
+        if let Some(assertion) = anfa.assertions.get(&q) {
+            out.push_str(",\"assertion\":");
+            write_json_str(out, &assertion.to_string())?;
+        }
+        out.push('}');
+    }
+    out.push_str("]}");
+    Ok(())
+}
+
+/// Reads JSON front to back. Only the values of the interchange format are
+/// parsed, everything else is skipped by `skip_value`.
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start_matches([' ', '\t', '\n', '\r']);
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest.chars().next()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), &'static str> {
+        if self.peek() != Some(c) {
+            return Err("Malformed JSON.");
+        }
+        self.rest = &self.rest[c.len_utf8()..];
+        Ok(())
+    }
+
+    /// Consumes `literal`, e.g. `null`, when the input continues with it
+    fn eat(&mut self, literal: &str) -> bool {
+        self.skip_whitespace();
+        match self.rest.strip_prefix(literal) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Calls `field` with the key of every member of an object, which must
+    /// consume the member's value
+    fn object<F>(&mut self, mut field: F) -> Result<(), &'static str>
+    where
+        F: FnMut(&mut Parser<'a>, String) -> Result<(), &'static str>,
+    {
+        self.expect('{')?;
+        if self.peek() == Some('}') {
+            return self.expect('}');
+        }
+        loop {
+            let key = self.string()?;
+            self.expect(':')?;
+            field(self, key)?;
+            match self.peek() {
+                Some(',') => self.expect(',')?,
+                _ => return self.expect('}'),
+            }
+        }
+    }
+
+    /// Calls `element` once per element of an array, which must consume it
+    fn array<F>(&mut self, mut element: F) -> Result<(), &'static str>
+    where
+        F: FnMut(&mut Parser<'a>) -> Result<(), &'static str>,
+    {
+        self.expect('[')?;
+        if self.peek() == Some(']') {
+            return self.expect(']');
+        }
+        loop {
+            element(self)?;
+            match self.peek() {
+                Some(',') => self.expect(',')?,
+                _ => return self.expect(']'),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, &'static str> {
+        let digits = self.rest.get(..4).ok_or("Malformed JSON string escape.")?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| "Malformed JSON string escape.")?;
+        self.rest = &self.rest[4..];
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, &'static str> {
+        self.expect('"')?;
+        let mut out = String::new();
+        let mut chars = self.rest.char_indices();
+        loop {
+            let (at, c) = chars.next().ok_or("Unterminated JSON string.")?;
+            match c {
+                '"' => {
+                    self.rest = &self.rest[at + 1..];
+                    return Ok(out);
+                }
+                '\\' => {
+                    let (_at, escape) = chars.next().ok_or("Unterminated JSON string.")?;
+                    match escape {
+                        '"' | '\\' | '/' => out.push(escape),
+                        'b' => out.push('\u{8}'),
+                        'f' => out.push('\u{c}'),
+                        'n' => out.push('\n'),
+                        'r' => out.push('\r'),
+                        't' => out.push('\t'),
+                        'u' => {
+                            self.rest = chars.as_str();
+                            let high = self.hex4()?;
+                            let code = if (0xd800..0xdc00).contains(&high) {
+                                // a surrogate pair encodes a char outside the BMP
+                                if !self.rest.starts_with("\\u") {
+                                    return Err("Invalid JSON surrogate pair.");
+                                }
+                                self.rest = &self.rest[2..];
+                                let low = self.hex4()?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return Err("Invalid JSON surrogate pair.");
+                                }
+                                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                            } else {
+                                high
+                            };
+                            out.push(char::from_u32(code).ok_or("Invalid JSON surrogate pair.")?);
+                            // offsets of `chars` are relative to the new `rest`
+                            chars = self.rest.char_indices();
+                        }
+                        _ => return Err("Malformed JSON string escape."),
+                    }
+                }
+                c if (c as u32) < 0x20 => {
+                    return Err("Unescaped control character in JSON string.")
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn integer(&mut self) -> Result<usize, &'static str> {
+        self.skip_whitespace();
+        let len = self
+            .rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest.len());
+        let value = self.rest[..len]
+            .parse::<usize>()
+            .map_err(|_| "Expected a non-negative JSON integer.")?;
+        self.rest = &self.rest[len..];
+        Ok(value)
+    }
+
+    /// Skips a value of any shape, counting brackets instead of recursing
+    fn skip_value(&mut self) -> Result<(), &'static str> {
+        let mut depth = 0usize;
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.string()?;
+                }
+                Some('{') | Some('[') => {
+                    depth += 1;
+                    self.rest = &self.rest[1..];
+                }
+                Some('}') | Some(']') => {
+                    depth = depth.checked_sub(1).ok_or("Malformed JSON.")?;
+                    self.rest = &self.rest[1..];
+                }
+                Some(',') | Some(':') if depth > 0 => self.rest = &self.rest[1..],
+                Some(_) => {
+                    let len = self
+                        .rest
+                        .find([',', ':', '{', '}', '[', ']', '"'])
+                        .unwrap_or(self.rest.len());
+                    let token = self.rest[..len].trim_end();
+                    if token.is_empty() {
+                        return Err("Malformed JSON.");
+                    }
+                    self.rest = &self.rest[len..];
+                }
+                None => return Err("Malformed JSON."),
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// One element of `"states"` as read, before its successors are checked
+struct State {
+    label: Option<char>,
+    next: Vec<QId>,
+    assertion: Option<Assertion>,
+}
+
+fn parse_state(parser: &mut Parser) -> Result<State, &'static str> {
+    let mut label = None;
+    let mut next = None;
+    let mut assertion = None;
+    parser.object(|parser, key| {
+        match key.as_str() {
+            "label" => {
+                if !parser.eat("null") {
+                    let text = parser.string()?;
+                    let mut chars = text.chars();
+                    label = match (chars.next(), chars.next()) {
+                        (Some(c), None) => Some(c),
+                        _ => return Err("JSON state labels must be one character or null."),
+                    };
+                }
+            }
+            "next" => {
+                let mut q_nexts = Vec::new();
+                parser.array(|parser| {
+                    q_nexts.push(parser.integer()?);
+                    Ok(())
+                })?;
+                next = Some(q_nexts);
+            }
+            "assertion" => {
+                let text = parser.string()?;
+                assertion = Some(
+                    *ASSERTIONS
+                        .iter()
+                        .find(|assertion| assertion.to_string() == text)
+                        .ok_or("Unknown JSON state assertion.")?,
+                );
+            }
+            _ => parser.skip_value()?,
+        }
+        Ok(())
+    })?;
+    Ok(State {
+        label,
+        next: next.ok_or("JSON state is missing next.")?,
+        assertion,
+    })
+}
+
+impl ANFA {
+    /// Exports the automaton in the JSON interchange format, a stable format
+    /// for handing machines to other languages, e.g. a JavaScript runtime:
+    ///
+    /// ```text
+    /// {
+    ///   "format": "regexxx-anfa",
+    ///   "version": 1,
+    ///   "q0": <state index>,
+    ///   "f": <state index>,
+    ///   "states": [
+    ///     { "label": <one character or null>, "next": [<state index>, ...],
+    ///       "assertion": <optional, one of "^", "$", "\\b", "\\B", "(?-u:\\b)", "(?-u:\\B)"> },
+    ///     ...
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// A state with a label reads it and moves to its single next state. A
+    /// state without one moves to any of its up to two next states without
+    /// reading, the first preferred over the second. A state with an
+    /// assertion may only be entered where it holds, see `Assertion`.
+    /// State indices are positions in `"states"`.
+    /// The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(
+    ///     machine.to_json().unwrap(),
+    ///     r#"{"format":"regexxx-anfa","version":1,"q0":0,"f":1,"states":[{"label":"a","next":[1]},{"label":null,"next":[]}]}"#
+    /// );
+    /// ```
+    pub fn to_json(&self) -> Result<String, &'static str> {
+        let [q0, f] = match self.automata_refs.as_slice() {
+            [machine] => *machine,
+            _ => return Err("JSON export requires exactly one automaton."),
+        };
+        let mut out = String::new();
+        write_json(self, q0, f, &mut out).map_err(|_| "Failed to format JSON output.")?;
+        Ok(out)
+    }
+
+    /// Imports a machine written in the JSON interchange format, see
+    /// `to_json`. Members in any order and unknown members are accepted;
+    /// missing members, state indices out of range, labeled states with more
+    /// than one next state, and ε states with more than two are rejected.
+    ///
+    /// ```rust
+    /// use regexxx::ANFA;
+    /// let json = r#"{"format": "regexxx-anfa", "version": 1, "q0": 0, "f": 1,
+    ///     "states": [{"label": "é", "next": [1]}, {"label": null, "next": []}]}"#;
+    /// let machine = ANFA::from_json(json).unwrap();
+    /// assert_eq!(machine.shortest_accepted().unwrap(), Some(vec!['é']));
+    /// assert!(ANFA::from_json(r#"{"format": "regexxx-anfa", "version": 1, "f": 0, "states": [{"label": null, "next": []}]}"#).is_err());
+    /// ```
+    pub fn from_json(json: &str) -> Result<ANFA, &'static str> {
+        let mut parser = Parser { rest: json };
+        let mut format = None;
+        let mut version = None;
+        let mut q0 = None;
+        let mut f = None;
+        let mut states = None;
+        parser.object(|parser, key| {
+            match key.as_str() {
+                "format" => format = Some(parser.string()?),
+                "version" => version = Some(parser.integer()?),
+                "q0" => q0 = Some(parser.integer()?),
+                "f" => f = Some(parser.integer()?),
+                "states" => {
+                    let mut parsed = Vec::new();
+                    parser.array(|parser| {
+                        parsed.push(parse_state(parser)?);
+                        Ok(())
+                    })?;
+                    states = Some(parsed);
+                }
+                _ => parser.skip_value()?,
+            }
+            Ok(())
+        })?;
+        if parser.peek().is_some() {
+            return Err("Trailing characters after JSON machine.");
+        }
+        if format.as_deref() != Some(FORMAT) {
+            return Err("JSON is not a regexxx machine.");
+        }
+        if version != Some(VERSION) {
+            return Err("Unsupported JSON machine version.");
+        }
+        let q0 = q0.ok_or("JSON machine is missing q0.")?;
+        let f = f.ok_or("JSON machine is missing f.")?;
+        let states = states.ok_or("JSON machine is missing states.")?;
+        if q0 >= states.len() || f >= states.len() {
+            return Err("JSON machine q0 or f is out of range.");
+        }
+        let mut anfa = ANFA::new();
+        let mut assertions = BTreeMap::new();
+        for (q, state) in states.iter().enumerate() {
+            if state.next.iter().any(|q_next| *q_next >= states.len()) {
+                return Err("JSON state refers to a state out of range.");
+            }
+            let max_next = if state.label.is_some() { 1 } else { 2 };
+            if state.next.len() > max_next {
+                return Err("JSON state has too many next states.");
+            }
+            anfa.push_state((
+                state.label,
+                [state.next.first().copied(), state.next.get(1).copied()],
+            ));
+            if let Some(assertion) = state.assertion {
+                assertions.insert(q, assertion);
+            }
+        }
+        anfa.assertions = assertions;
+        anfa.automata_refs.push([q0, f]);
+        Ok(anfa)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::ANFA;
+    use alloc::string::String;
+    use alloc::vec;
+
+    #[test]
+    fn test_round_trip() {
+        // RE ^("+\+é+😀)*\n
+        let mut machine = ForwardCompiler::from_expr_assert(Assertion::StartText).unwrap();
+        for c in ['"', '\\', 'é', '😀'].iter() {
+            ForwardCompiler::expr_a(&mut machine, *c).unwrap();
+        }
+        for _ in 0..3 {
+            ForwardCompiler::union(&mut machine).unwrap();
+        }
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, '\n').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let json = machine.to_json().unwrap();
+        assert!(json.contains(r#""label":"\"""#), "Labels are escaped");
+        assert!(json.contains(r#""label":"\n""#));
+        assert!(json.contains(r#""assertion":"^""#));
+        let imported = ANFA::from_json(&json).unwrap();
+        assert_eq!(
+            imported.delta, machine.delta,
+            "Importing an export is lossless"
+        );
+        assert_eq!(imported.assertions, machine.assertions);
+        assert_eq!(imported.automata_refs, machine.automata_refs);
+        assert_eq!(imported.to_json().unwrap(), json);
+
+        // RE (?-u:\b)a(?-u:\B)
+        let mut machine = ForwardCompiler::from_expr_assert(Assertion::AsciiWordBoundary).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_assert(&mut machine, Assertion::AsciiNotWordBoundary).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let json = machine.to_json().unwrap();
+        assert!(json.contains(r#""assertion":"(?-u:\\b)""#));
+        let imported = ANFA::from_json(&json).unwrap();
+        assert_eq!(imported.assertions, machine.assertions);
+    }
+
+    #[test]
+    fn test_from_json() {
+        // escapes, whitespace, member order, and unknown members
+        let json = "{ \"states\" : [ {\"next\": [1], \"label\": \"\\ud83d\\ude00\", \"x\": {\"y\": [1, \"]\"]}},\n\
+            {\"label\": null, \"next\": []} ], \"f\": 1, \"q0\": 0,\n\
+            \"comment\": \"made by hand\", \"version\": 1, \"format\": \"regexxx-anfa\" }";
+        let machine = ANFA::from_json(json).unwrap();
+        assert_eq!(machine.shortest_accepted().unwrap(), Some(vec!['😀']));
+        assert_eq!(machine.automata_refs, vec![[0, 1]]);
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        let machine = |body: &str| {
+            let mut json = String::from(r#"{"format":"regexxx-anfa","version":1"#);
+            json.push_str(body);
+            json.push('}');
+            ANFA::from_json(&json)
+        };
+        let states = r#","states":[{"label":"a","next":[1]},{"label":null,"next":[]}]"#;
+        let mut valid = String::from(r#","q0":0,"f":1"#);
+        valid.push_str(states);
+        assert!(machine(&valid).is_ok());
+
+        let mut missing_q0 = String::from(r#","f":1"#);
+        missing_q0.push_str(states);
+        assert_eq!(
+            machine(&missing_q0).err(),
+            Some("JSON machine is missing q0.")
+        );
+        let mut missing_f = String::from(r#","q0":0"#);
+        missing_f.push_str(states);
+        assert_eq!(
+            machine(&missing_f).err(),
+            Some("JSON machine is missing f.")
+        );
+        let mut f_out_of_range = String::from(r#","q0":0,"f":2"#);
+        f_out_of_range.push_str(states);
+        assert!(machine(&f_out_of_range).is_err());
+        assert_eq!(
+            machine(
+                r#","q0":0,"f":1,"states":[{"label":"a","next":[2]},{"label":null,"next":[]}]"#
+            )
+            .err(),
+            Some("JSON state refers to a state out of range.")
+        );
+        assert_eq!(
+            machine(
+                r#","q0":0,"f":1,"states":[{"label":"a","next":[1,1]},{"label":null,"next":[]}]"#
+            )
+            .err(),
+            Some("JSON state has too many next states.")
+        );
+        assert_eq!(
+            machine(
+                r#","q0":0,"f":1,"states":[{"label":"ab","next":[1]},{"label":null,"next":[]}]"#
+            )
+            .err(),
+            Some("JSON state labels must be one character or null.")
+        );
+        assert_eq!(
+            machine(
+                r#","q0":0,"f":1,"states":[{"label":"a","next":[-1]},{"label":null,"next":[]}]"#
+            )
+            .err(),
+            Some("Expected a non-negative JSON integer.")
+        );
+        let mut other_format = String::from(r#"{"format":"other","version":1"#);
+        other_format.push_str(&valid);
+        other_format.push('}');
+        assert_eq!(
+            ANFA::from_json(&other_format).err(),
+            Some("JSON is not a regexxx machine.")
+        );
+        assert!(ANFA::from_json("").is_err());
+        assert!(ANFA::from_json("{").is_err());
+        assert!(ANFA::from_json("{}").is_err());
+        let mut trailing = String::from(r#"{"format":"regexxx-anfa","version":1"#);
+        trailing.push_str(&valid);
+        trailing.push_str("}}");
+        assert!(ANFA::from_json(&trailing).is_err());
+    }
+}
@@ -1,6 +1,9 @@
 #![no_std]
+#![forbid(unsafe_code)]
 
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 // for ease of copy+paste:
 // ε
@@ -8,43 +11,300 @@ extern crate alloc;
 
 // size of QId
 // size of label
+use alloc::collections::BTreeMap;
 use alloc::vec;
+use assertion::Assertion;
+use core::ops::Range;
 
 /// Unique state id
+///
+/// State ids are assigned in creation order: every state is created by
+/// `ANFA::push_state`, which appends it, so its id is the number of states
+/// created before it. Compilers
+/// may rewrite the transition of an existing state, e.g. to point a final
+/// state at the next operand, but they never remove or reorder states, so an
+/// id keeps naming the same state for the life of the ANFA. Only
+/// `ANFA::compact` removes states; it keeps their relative order and returns
+/// the new ids. See `ANFA::push_state`, `ANFA::states`, and `ANFA::creation_index`.
 /// TODO: Compile a regex large enough to overflow
 pub type QId = usize;
 
 /// A transition along an optional label to zero, one, or two States.
+/// Labels are symbols of the alphabet `S`, which defaults to `char`.
 /// When a label is `None`, transition is an epsilon transition
 /// and it always advances to its final states. When QId is `None`,
 /// there is no transition. Transition is ordered. If `Transition.1[1]`
 /// is `Some(QId)`, then `Transition.1[0]` must also be `Some(QId)`.
 /// i.e. a union operation is when both `Option<QId>` are `Some(QId)`.
-pub type Transition = (Option<char>, [Option<QId>; 2]);
+pub type Transition<S = char> = (Option<S>, [Option<QId>; 2]);
+
+/// Returns true when `transition` has a shape `Transition` allows, i.e. a
+/// label reads into exactly one state and a second ε next state comes with
+/// a first, and every next state is below `state_count`. Used where tables
+/// are read from outside, e.g. storage or frozen bytes.
+pub(crate) fn is_well_formed<S>(transition: &Transition<S>, state_count: usize) -> bool {
+    match transition {
+        (Some(_), [Some(_), None]) | (None, [None, None]) | (None, [Some(_), _]) => transition
+            .1
+            .iter()
+            .flatten()
+            .all(|q_next| *q_next < state_count),
+        _ => false,
+    }
+}
 
 /// DeltaFunction is a vector of ordered transitions that satisfy
 /// the function `δ ⊆ State × T × State`. An index of `DeltaFunction`
 /// is the first `State` paramter in the function. See `Transition`.
-pub type DeltaFunction = vec::Vec<Transition>;
+pub type DeltaFunction<S = char> = vec::Vec<Transition<S>>;
 
 /// The initial and final states of an expression: [q0, f]
 pub type AutomataRef = [QId; 2];
 
-#[derive(Debug)]
-pub struct ANFA {
+/// An augmented non-deterministic finite automaton over the alphabet `S`.
+/// Any `Copy + Eq` symbol may label a transition, so the same construction
+/// works for characters, bytes, tokens, or application-specific events.
+///
+/// ```rust
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// use regexxx::ANFA;
+/// // RE 0x7f 0x45, over bytes instead of chars
+/// let mut machine: ANFA<u8> = ForwardCompiler::from_expr_a(0x7f).unwrap();
+/// ForwardCompiler::expr_a(&mut machine, 0x45).unwrap();
+/// ForwardCompiler::concatenate(&mut machine).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct ANFA<S = char> {
     pub automata_refs: vec::Vec<AutomataRef>,
-    pub delta: DeltaFunction,
+    /// Private, so states are only ever created by `push_state`, see `delta()`
+    delta: DeltaFunction<S>,
+    /// States that may only be entered where an assertion holds, i.e. every
+    /// transition into the state is also labeled with the assertion.
+    /// See `Compiler::expr_assert`.
+    pub assertions: BTreeMap<QId, Assertion>,
+    /// The states copied in by each `import`, see `provenance()`
+    provenance: vec::Vec<Range<QId>>,
 }
 
-impl ANFA {
+impl<S: Copy + Eq> ANFA<S> {
     /// The ANFA constructor does not return a valid automaton.
     /// ANFA must be constructed by a static compiler factory
-    fn new() -> ANFA {
+    fn new() -> ANFA<S> {
         ANFA {
-            automata_refs: vec::Vec::with_capacity(u32::MAX as usize),
-            delta: vec::Vec::with_capacity(u32::MAX as usize),
+            automata_refs: vec::Vec::new(),
+            delta: vec::Vec::new(),
+            assertions: BTreeMap::new(),
+            provenance: vec::Vec::new(),
         }
     }
+
+    /// Appends a new state and returns its id, which is always
+    /// `delta().len()` before the push. This is the only way to create a
+    /// state: `delta` is private, and the compilers and every other
+    /// constructor in the crate create their states here too.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(machine.push_state((None, [None, None])), 2);
+    /// ```
+    pub fn push_state(&mut self, transition: Transition<S>) -> QId {
+        self.delta.push(transition);
+        self.delta.len() - 1
+    }
+
+    /// The transitions of every state, indexed by `QId`, in creation order
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(machine.delta(), &[(Some('a'), [Some(1), None]), (None, [None, None])]);
+    /// ```
+    pub fn delta(&self) -> &[Transition<S>] {
+        &self.delta
+    }
+
+    /// The ids of the states each imported machine was copied into, in
+    /// import order, see `ANFA::import`. A machine brings the ranges of its
+    /// own imports along, nested in its range. Only `import` records
+    /// provenance, so machines rebuilt from tables, e.g. by `determinize`
+    /// or `from_storage`, have none.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// let fragment = ForwardCompiler::from_expr_a('b').unwrap();
+    /// machine.import(&fragment).unwrap();
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// assert_eq!(machine.provenance(), &[2..4]);
+    /// ```
+    pub fn provenance(&self) -> &[Range<QId>] {
+        &self.provenance
+    }
+
+    /// Iterates over the states and their transitions in creation order
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// let states: Vec<_> = machine.states().collect();
+    /// assert_eq!(states, vec![(0, &(Some('a'), [Some(1), None])), (1, &(None, [None, None]))]);
+    /// ```
+    pub fn states(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (QId, &Transition<S>)> + ExactSizeIterator + '_ {
+        self.delta.iter().enumerate()
+    }
+
+    /// Returns the position of state `q` in creation order, or `None` when `q`
+    /// is not a state of this ANFA. State ids are assigned in creation order,
+    /// so the creation index of a state is its id, and tables keyed by creation
+    /// index may be indexed by `QId` directly.
+    pub fn creation_index(&self, q: QId) -> Option<usize> {
+        if q < self.delta.len() {
+            Some(q)
+        } else {
+            None
+        }
+    }
+
+    /// Checks that `automaton` may still be used with this ANFA.
+    /// Compilers pop their operands from `automata_refs` and push the composed
+    /// automaton, so the refs on the stack are exactly the live automata.
+    /// Any other ref was consumed by a composition and its final state may
+    /// since have been rewritten, e.g. concatenation points it at the next operand.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// let machine_a = machine.automata_refs[0];
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// assert!(machine.check_ref(machine_a).is_err());
+    /// assert!(machine.check_ref(machine.automata_refs[0]).is_ok());
+    /// ```
+    pub fn check_ref(&self, automaton: AutomataRef) -> Result<(), &'static str> {
+        let [q0, f] = automaton;
+        if q0 >= self.delta.len() || f >= self.delta.len() {
+            return Err("AutomataRef is not an automaton of this ANFA.");
+        }
+        if !self.automata_refs.contains(&automaton) {
+            return Err("AutomataRef is stale, it was consumed by a composition.");
+        }
+        Ok(())
+    }
 }
 
+pub mod alternation;
+pub mod analysis;
+pub mod anchoring;
+pub mod assertion;
+pub mod batch;
+pub mod binary;
+pub mod case;
+pub mod codegen;
 pub mod compilers;
+pub mod conflicts;
+pub mod corpus;
+pub mod delimited;
+pub mod dense;
+pub mod dfa;
+pub mod dot;
+pub mod engine;
+pub mod expr;
+pub mod fixed;
+pub mod flavor;
+pub mod frozen;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
+pub mod glushkov;
+pub mod harness;
+pub mod hybrid;
+pub mod jflap;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod magic;
+pub mod nfa;
+pub mod ops;
+#[cfg(feature = "std")]
+pub mod par;
+pub mod pattern;
+pub mod priority;
+pub mod redact;
+pub mod render;
+pub mod replace;
+pub mod route;
+#[cfg(feature = "rand")]
+pub mod sample;
+pub mod scxml;
+pub mod search;
+pub mod set;
+pub mod shrink;
+pub mod signatures;
+pub mod stats;
+pub mod storage;
+pub mod template;
+pub mod tiny;
+pub mod transform;
+pub mod transitions;
+pub mod visit;
+pub mod viz;
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+
+    #[test]
+    fn test_check_ref() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        let [machine_a, machine_b] = [machine.automata_refs[0], machine.automata_refs[1]];
+        assert!(machine.check_ref(machine_a).is_ok());
+        assert!(machine.check_ref(machine_b).is_ok());
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert_eq!(
+            machine.check_ref(machine_a),
+            Err("AutomataRef is stale, it was consumed by a composition.")
+        );
+        assert!(machine.check_ref(machine_b).is_err());
+        ForwardCompiler::star(&mut machine).unwrap();
+        let machine_c = machine.automata_refs[0];
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(
+            machine.check_ref(machine_c).is_err(),
+            "Already composed refs are stale"
+        );
+        assert!(machine.check_ref(machine.automata_refs[0]).is_ok());
+        assert_eq!(
+            machine.check_ref([99, 100]),
+            Err("AutomataRef is not an automaton of this ANFA.")
+        );
+    }
+
+    #[test]
+    fn test_creation_order() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        let mut created = machine.delta.len();
+        for step in 0..5 {
+            let before = machine.delta.len();
+            match step {
+                0 => ForwardCompiler::expr_a(&mut machine, 'b'),
+                1 => ForwardCompiler::union(&mut machine),
+                2 => ForwardCompiler::star(&mut machine),
+                3 => ForwardCompiler::expr_1(&mut machine),
+                _ => ForwardCompiler::concatenate(&mut machine),
+            }
+            .unwrap();
+            assert!(machine.delta.len() >= before, "States are never removed");
+            for (q, _transition) in machine.states().skip(before) {
+                assert_eq!(q, created, "New states take the next creation index");
+                created += 1;
+            }
+        }
+        assert_eq!(machine.states().len(), created);
+        let q = machine.push_state((None, [None, None]));
+        assert_eq!(machine.creation_index(q), Some(created));
+        assert_eq!(machine.creation_index(created + 1), None);
+    }
+}
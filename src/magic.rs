@@ -0,0 +1,189 @@
+use crate::binary;
+use crate::set::{Reached, Tagged, Visited};
+use crate::ANFA;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Identifies a file type of a `Magic` table, in the order added
+pub type TypeId = usize;
+
+/// Collects the signatures of a `Magic` table. Every signature is imported
+/// into one tagged automaton, like the patterns of a `Set`.
+///
+/// ```rust
+/// use regexxx::magic::MagicBuilder;
+/// let mut builder = MagicBuilder::new();
+/// let elf = builder.add_escaped("elf", r"\x7fELF").unwrap();
+/// let png = builder.add_hex("png", "89 50 4E 47 0D 0A 1A 0A").unwrap();
+/// let gif = builder.add_escaped("gif", "GIF8?a").unwrap();
+/// let magic = builder.build();
+/// assert_eq!(magic.sniff(b"\x7fELF\x02\x01"), Some(elf));
+/// assert_eq!(magic.sniff(b"GIF89a..."), Some(gif));
+/// assert_eq!(magic.sniff(b"..GIF89a"), None, "Signatures are anchored");
+/// assert_eq!(magic.name(png), Some("png"));
+/// ```
+#[derive(Debug)]
+pub struct MagicBuilder {
+    tagged: Tagged<u8>,
+    names: Vec<String>,
+}
+
+impl Default for MagicBuilder {
+    fn default() -> MagicBuilder {
+        MagicBuilder::new()
+    }
+}
+
+impl MagicBuilder {
+    pub fn new() -> MagicBuilder {
+        MagicBuilder {
+            tagged: Tagged::new(),
+            names: Vec::new(),
+        }
+    }
+
+    /// Adds a type recognized by the prefixes `machine` accepts, and returns
+    /// its id. The machine must hold exactly one automaton without
+    /// assertions, and must not accept the empty string, which would
+    /// recognize every file.
+    pub fn add(&mut self, name: &str, machine: &ANFA<u8>) -> Result<TypeId, &'static str> {
+        let type_id = self.tagged.push_non_empty(machine)?;
+        self.names.push(name.to_string());
+        Ok(type_id)
+    }
+
+    /// Adds a type recognized by a hex signature, see `binary::parse_hex`
+    pub fn add_hex(&mut self, name: &str, pattern: &str) -> Result<TypeId, &'static str> {
+        let masks = binary::parse_hex(pattern)?;
+        self.add(name, &binary::to_expr(&masks).build()?)
+    }
+
+    /// Adds a type recognized by an escaped signature, see
+    /// `binary::parse_escaped`
+    pub fn add_escaped(&mut self, name: &str, pattern: &str) -> Result<TypeId, &'static str> {
+        let masks = binary::parse_escaped(pattern)?;
+        self.add(name, &binary::to_expr(&masks).build()?)
+    }
+
+    pub fn build(self) -> Magic {
+        Magic {
+            tagged: self.tagged,
+            names: self.names,
+        }
+    }
+}
+
+/// A table of file signatures matched together at the start of a file, see
+/// `MagicBuilder`
+#[derive(Debug)]
+pub struct Magic {
+    tagged: Tagged<u8>,
+    names: Vec<String>,
+}
+
+impl Magic {
+    /// The number of types in the table
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The name `type_id` was added with
+    pub fn name(&self, type_id: TypeId) -> Option<&str> {
+        self.names.get(type_id).map(String::as_str)
+    }
+
+    /// Returns the type whose signature matches the longest prefix of
+    /// `bytes`, the first added among equally long matches, or `None`. All
+    /// signatures are matched at once, and the bytes are read only until no
+    /// signature can match further.
+    ///
+    /// ```rust
+    /// use regexxx::magic::MagicBuilder;
+    /// let mut builder = MagicBuilder::new();
+    /// let zip = builder.add_hex("zip", "50 4B").unwrap();
+    /// let docx = builder.add_escaped("office", "PK\x03\x04??\x06").unwrap();
+    /// let magic = builder.build();
+    /// assert_eq!(magic.sniff(b"PK\x03\x04\x14\x00\x06\x00"), Some(docx), "Longest wins");
+    /// assert_eq!(magic.sniff(b"PK\x05\x06"), Some(zip));
+    /// ```
+    pub fn sniff(&self, bytes: &[u8]) -> Option<TypeId> {
+        let tagged = &self.tagged;
+        let mut current = Vec::new();
+        let mut next = Vec::new();
+        let mut visited = Visited::new(tagged.anfa.delta().len());
+        let mut best = None;
+        for entry in tagged.entries.iter() {
+            // empty signatures are rejected, so no type is found here
+            tagged.close(*entry, &mut visited, None, None, |reached| {
+                if let Reached::Labeled(q) = reached {
+                    current.push(q);
+                }
+            });
+        }
+        for byte in bytes.iter() {
+            visited.next_step();
+            let mut found: Option<TypeId> = None;
+            for q in current.iter() {
+                if let Some(q_next) = tagged.step(*q, *byte) {
+                    tagged.close(q_next, &mut visited, None, None, |reached| match reached {
+                        Reached::Labeled(q) => next.push(q),
+                        Reached::Final(type_id) => {
+                            found = Some(found.map_or(type_id, |found| found.min(type_id)));
+                        }
+                    });
+                }
+            }
+            if found.is_some() {
+                best = found;
+            }
+            core::mem::swap(&mut current, &mut next);
+            next.clear();
+            if current.is_empty() {
+                break;
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binary;
+    use crate::magic::MagicBuilder;
+
+    #[test]
+    fn test_sniff() {
+        let mut builder = MagicBuilder::new();
+        let pdf = builder.add_escaped("pdf", "%PDF-").unwrap();
+        let pdf_too = builder.add_hex("pdf again", "25 50 44 46 2D").unwrap();
+        // ID3 tags, or an MPEG frame sync with any of its low 4 bits
+        let id3 = binary::to_expr(&binary::parse_escaped("ID3").unwrap());
+        let sync = binary::to_expr(&binary::parse_hex("FF F?").unwrap());
+        let mp3 = builder
+            .add("mp3", &id3.union(sync).build().unwrap())
+            .unwrap();
+        let magic = builder.build();
+        assert_eq!(magic.len(), 3);
+        assert_eq!(magic.sniff(b"%PDF-1.7"), Some(pdf), "First added wins ties");
+        assert_ne!(pdf, pdf_too);
+        assert_eq!(magic.sniff(b"ID3\x04"), Some(mp3));
+        assert_eq!(magic.sniff(b"\xff\xfb\x90"), Some(mp3));
+        assert_eq!(magic.sniff(b"\xff\xeb"), None);
+        assert_eq!(magic.sniff(b"%PDF"), None, "Truncated files match nothing");
+        assert_eq!(magic.sniff(b""), None);
+        assert_eq!(magic.name(3), None);
+    }
+
+    #[test]
+    fn test_add_errors() {
+        let mut builder = MagicBuilder::new();
+        assert!(builder.add_hex("empty", "").is_err());
+        assert!(builder.add_hex("odd", "ABC").is_err());
+        assert!(builder.add_escaped("escape", r"\q").is_err());
+        assert!(builder.build().is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{QId, ANFA};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// An epsilon-free non-deterministic finite automaton over the alphabet `S`.
+/// Every transition consumes exactly one symbol. Unlike `ANFA`, a state may
+/// have any number of transitions and any number of states may be final.
+/// Transitions are ordered by the priority of the ANFA path they replace.
+#[derive(Debug, PartialEq, Eq)]
+pub struct NFA<S = char> {
+    pub q0: QId,
+    /// `finals[q]` is true when state `q` is accepting
+    pub finals: Vec<bool>,
+    /// `delta[q]` lists every `(label, q_next)` leaving state `q`
+    pub delta: Vec<Vec<(S, QId)>>,
+}
+
+impl<S: Copy + Eq> NFA<S> {
+    /// Returns true when the NFA accepts exactly `input`
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::star(&mut machine).unwrap();
+    /// let nfa = machine.remove_epsilon().unwrap();
+    /// assert!(nfa.accepts("aaa".chars()));
+    /// assert!(!nfa.accepts("ab".chars()));
+    /// ```
+    pub fn accepts<I: IntoIterator<Item = S>>(&self, input: I) -> bool {
+        let mut current = vec![false; self.delta.len()];
+        let mut next = vec![false; self.delta.len()];
+        current[self.q0] = true;
+        for c in input {
+            for (q, active) in current.iter().enumerate() {
+                if *active {
+                    for (label, q_next) in self.delta[q].iter() {
+                        if *label == c {
+                            next[*q_next] = true;
+                        }
+                    }
+                }
+            }
+            core::mem::swap(&mut current, &mut next);
+            next.iter_mut().for_each(|q| *q = false);
+        }
+        current
+            .iter()
+            .zip(self.finals.iter())
+            .any(|(active, is_final)| *active && *is_final)
+    }
+}
+
+/// Every state reachable from `q` along epsilon transitions, including `q`,
+/// in priority order: the 0 branch of a union before its 1 branch.
+fn epsilon_closure<S>(anfa: &ANFA<S>, q: QId) -> Vec<QId> {
+    let mut closure = Vec::new();
+    let mut seen = vec![false; anfa.delta.len()];
+    let mut stack = vec![q];
+    while let Some(q) = stack.pop() {
+        if seen[q] {
+            continue;
+        }
+        seen[q] = true;
+        closure.push(q);
+        if let (None, q_nexts) = &anfa.delta[q] {
+            // push the right branch first so the left branch is visited first
+            for q_next in q_nexts.iter().rev().flatten() {
+                if !seen[*q_next] {
+                    stack.push(*q_next);
+                }
+            }
+        }
+    }
+    closure
+}
+
+impl<S: Copy + Eq> ANFA<S> {
+    /// Computes the epsilon closure of every state and rewrites `delta` into an
+    /// epsilon-free `NFA`. The ANFA must hold exactly one automaton, i.e. every
+    /// operand has been composed. Only states reachable from q0 are kept.
+    /// Assertions depend on the input around a position, which an NFA cannot
+    /// read, so automata with assertions are rejected.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// // RE (a+b)*b
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::union(&mut machine).unwrap();
+    /// ForwardCompiler::star(&mut machine).unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// let nfa = machine.remove_epsilon().unwrap();
+    /// assert!(nfa.accepts("abab".chars()));
+    /// ```
+    pub fn remove_epsilon(&self) -> Result<NFA<S>, &'static str> {
+        let [machine_q0, machine_f] = match self.automata_refs.as_slice() {
+            [machine] => *machine,
+            _ => {
+                return Err("Epsilon elimination requires exactly one automaton.");
+            }
+        };
+        if !self.assertions.is_empty() {
+            return Err("Epsilon elimination does not support assertions.");
+        }
+        // NFA states are q0 plus every state entered by consuming a symbol
+        let mut nfa_ids: Vec<Option<QId>> = vec![None; self.delta.len()];
+        let mut anfa_ids: Vec<QId> = vec![machine_q0];
+        nfa_ids[machine_q0] = Some(0);
+        let mut nfa = NFA {
+            q0: 0,
+            finals: Vec::new(),
+            delta: Vec::new(),
+        };
+        let mut next_unvisited = 0;
+        while next_unvisited < anfa_ids.len() {
+            let q = anfa_ids[next_unvisited];
+            next_unvisited += 1;
+            let mut transitions: Vec<(S, QId)> = Vec::new();
+            let mut is_final = false;
+            for q_closure in epsilon_closure(self, q) {
+                if q_closure == machine_f {
+                    is_final = true;
+                }
+                if let (Some(c), q_nexts) = &self.delta[q_closure] {
+                    for q_next in q_nexts.iter().flatten() {
+                        let nfa_id = match nfa_ids[*q_next] {
+                            Some(nfa_id) => nfa_id,
+                            None => {
+                                let nfa_id = anfa_ids.len();
+                                nfa_ids[*q_next] = Some(nfa_id);
+                                anfa_ids.push(*q_next);
+                                nfa_id
+                            }
+                        };
+                        if !transitions.contains(&(*c, nfa_id)) {
+                            transitions.push((*c, nfa_id));
+                        }
+                    }
+                }
+            }
+            nfa.finals.push(is_final);
+            nfa.delta.push(transitions);
+        }
+        Ok(nfa)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::ANFA;
+    use alloc::vec;
+
+    /// RE (a+b)*b
+    fn a_or_b_star_b() -> ANFA {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        machine
+    }
+
+    #[test]
+    fn test_remove_epsilon() {
+        let machine = a_or_b_star_b();
+        let nfa = machine.remove_epsilon().unwrap();
+        assert_eq!(
+            nfa.delta.len(),
+            4,
+            "(a+b)*b keeps q0 and the three states entered along 'a', 'b', and 'b'"
+        );
+        assert_eq!(
+            nfa.delta[nfa.q0],
+            vec![('a', 1), ('b', 2), ('b', 3)],
+            "q0 transitions in priority order: star body before the trailing 'b'"
+        );
+        assert!(
+            !nfa.finals[nfa.q0],
+            "(a+b)*b does not accept the empty string"
+        );
+        assert!(nfa.finals[3], "Only the trailing 'b' enters a final state");
+        for input in ["b", "ab", "bb", "aabab"] {
+            assert!(nfa.accepts(input.chars()), "(a+b)*b accepts {}", input);
+        }
+        for input in ["", "a", "ba", "abc"] {
+            assert!(!nfa.accepts(input.chars()), "(a+b)*b rejects {}", input);
+        }
+    }
+
+    #[test]
+    fn test_remove_epsilon_requires_one_automaton() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        assert!(
+            machine.remove_epsilon().is_err(),
+            "Epsilon elimination rejects uncomposed operands"
+        );
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let nfa = machine.remove_epsilon().unwrap();
+        assert!(nfa.accepts("ab".chars()));
+        assert!(!nfa.accepts("a".chars()));
+    }
+
+    #[test]
+    fn test_remove_epsilon_expr_0_and_1() {
+        let nothing: ANFA = ForwardCompiler::from_expr_0().unwrap();
+        let nfa = nothing.remove_epsilon().unwrap();
+        assert!(!nfa.accepts("".chars()), "0 accepts nothing");
+        let epsilon: ANFA = ForwardCompiler::from_expr_1().unwrap();
+        let nfa = epsilon.remove_epsilon().unwrap();
+        assert!(nfa.accepts("".chars()), "1 accepts the empty string");
+        assert!(!nfa.accepts("a".chars()), "1 accepts only the empty string");
+    }
+}
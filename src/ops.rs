@@ -0,0 +1,813 @@
+use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+use crate::{AutomataRef, QId, ANFA};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+impl<S: Copy + Eq> ANFA<S> {
+    /// Copies the automaton of `other` into this ANFA and pushes it onto
+    /// `automata_refs`, like compiling it again on top of the stack. States of
+    /// `other` are re-indexed after the states of this ANFA, keeping their
+    /// creation order, and their ids are recorded, see `provenance`. `other`
+    /// must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// let fragment = ForwardCompiler::from_expr_a('b').unwrap();
+    /// assert_eq!(machine.import(&fragment).unwrap(), [2, 3]);
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// ```
+    pub fn import(&mut self, other: &ANFA<S>) -> Result<AutomataRef, &'static str> {
+        let [other_q0, other_f] = match other.automata_refs.as_slice() {
+            [machine] => *machine,
+            _ => {
+                return Err("Import requires exactly one automaton.");
+            }
+        };
+        let offset = self.delta.len();
+        for (label, q_nexts) in other.delta.iter() {
+            self.push_state((
+                *label,
+                [
+                    q_nexts[0].map(|q| q + offset),
+                    q_nexts[1].map(|q| q + offset),
+                ],
+            ));
+        }
+        for (q, assertion) in other.assertions.iter() {
+            self.assertions.insert(q + offset, *assertion);
+        }
+        self.provenance.push(offset..self.delta.len());
+        self.provenance.extend(
+            other
+                .provenance
+                .iter()
+                .map(|states| states.start + offset..states.end + offset),
+        );
+        let machine = [other_q0 + offset, other_f + offset];
+        self.automata_refs.push(machine);
+        Ok(machine)
+    }
+
+    /// Copies the live automaton `automaton` and pushes the copy onto
+    /// `automata_refs`. Composition rewrites the final state of its operands,
+    /// so an automaton can only be composed once; composing a copy instead lets
+    /// one compiled fragment be used several times, e.g. `a{2,3}` as
+    /// `aa(a+1)`. Only the states reachable from q0, and f, are copied, in
+    /// creation order, and each keeps its assertion.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// // RE aa
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// let machine_a = machine.automata_refs[0];
+    /// assert_eq!(machine.clone_subautomaton(machine_a).unwrap(), [2, 3]);
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// assert_eq!(machine.find("baab").unwrap(), Some((1, 3)));
+    /// ```
+    pub fn clone_subautomaton(
+        &mut self,
+        automaton: AutomataRef,
+    ) -> Result<AutomataRef, &'static str> {
+        let [q0, f] = automaton;
+        let mut reachable = vec![false; self.delta.len()];
+        for q in self.reachable_states(automaton)? {
+            reachable[q] = true;
+        }
+        reachable[f] = true;
+        let mut clone_ids: Vec<Option<QId>> = vec![None; self.delta.len()];
+        let copied = (0..reachable.len()).filter(|q| reachable[*q]);
+        for (clone_q, q) in (self.delta.len()..).zip(copied) {
+            clone_ids[q] = Some(clone_q);
+        }
+        for q in (0..reachable.len()).filter(|q| reachable[*q]) {
+            let (label, q_nexts) = self.delta[q];
+            let clone_q = self.push_state((
+                label,
+                [
+                    q_nexts[0].and_then(|q_next| clone_ids[q_next]),
+                    q_nexts[1].and_then(|q_next| clone_ids[q_next]),
+                ],
+            ));
+            if let Some(assertion) = self.assertions.get(&q).copied() {
+                self.assertions.insert(clone_q, assertion);
+            }
+        }
+        let clone = match (clone_ids[q0], clone_ids[f]) {
+            (Some(clone_q0), Some(clone_f)) => [clone_q0, clone_f],
+            // exhaustive sanity check, q0 and f are always copied
+            _ => return Err("AutomataRef was not copied."),
+        };
+        self.automata_refs.push(clone);
+        Ok(clone)
+    }
+
+    /// Returns a machine accepting the reverse of every word of this finalized
+    /// machine. Every transition is reversed and q0 and f swap; a state that
+    /// ends up with several transitions fans out like `extend_transitions`.
+    /// Assertions keep guarding their states, with `^` and `$` swapped.
+    /// Priority among the reversed transitions is not meaningful.
+    ///
+    /// Running the reverse machine backwards from the end of a match finds
+    /// where the match starts, and matching a reversed haystack finds suffixes.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE a*b
+    /// let machine = Expr::literal('a').star().concat(Expr::literal('b')).build().unwrap();
+    /// let reversed = machine.reverse().unwrap().determinize().unwrap();
+    /// assert!(reversed.accepts("baa".chars()));
+    /// assert!(!reversed.accepts("aab".chars()));
+    /// ```
+    pub fn reverse(&self) -> Result<ANFA<S>, &'static str> {
+        let [q0, f] = match self.automata_refs.as_slice() {
+            [machine] => *machine,
+            _ => {
+                return Err("Reverse requires exactly one automaton.");
+            }
+        };
+        let mut anfa = ANFA::new();
+        for _ in self.delta.iter() {
+            anfa.push_state((None, [None, None]));
+        }
+        for (q, assertion) in self.assertions.iter() {
+            anfa.assertions.insert(*q, assertion.reversed());
+        }
+        anfa.extend_transitions(self.states().flat_map(|(q, (label, q_nexts))| {
+            q_nexts
+                .iter()
+                .flatten()
+                .map(move |q_next| (*q_next, *label, q))
+        }))?;
+        anfa.automata_refs.push([f, q0]);
+        Ok(anfa)
+    }
+
+    /// Removes every state that no live automaton can pass through, i.e. states
+    /// that are not reachable from the q0 of an automaton in `automata_refs` or
+    /// that cannot reach its f, such as the states of operands abandoned by
+    /// `expr_0`. The q0 and f of every live automaton are always kept. Kept
+    /// states are renumbered in creation order, and `automata_refs` and
+    /// `assertions` are updated. Returns the new id of every old state, `None`
+    /// for removed states, so refs held elsewhere can be remapped.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE a0+b, the 'a' branch can never reach f
+    /// let mut machine = Expr::literal('a').concat(Expr::zero()).union(Expr::literal('b')).build().unwrap();
+    /// assert_eq!(machine.delta().len(), 8);
+    /// machine.compact();
+    /// assert_eq!(machine.delta().len(), 4);
+    /// assert_eq!(machine.find("ab").unwrap(), Some((1, 2)));
+    /// ```
+    pub fn compact(&mut self) -> Vec<Option<QId>> {
+        let mut reachable = vec![false; self.delta.len()];
+        let mut stack: Vec<QId> = self.automata_refs.iter().map(|[q0, _f]| *q0).collect();
+        while let Some(q) = stack.pop() {
+            if !reachable[q] {
+                reachable[q] = true;
+                stack.extend(self.delta[q].1.iter().flatten());
+            }
+        }
+        let mut predecessors: Vec<Vec<QId>> = vec![Vec::new(); self.delta.len()];
+        for (q, (_label, q_nexts)) in self.states() {
+            for q_next in q_nexts.iter().flatten() {
+                predecessors[*q_next].push(q);
+            }
+        }
+        let mut reaches_f = vec![false; self.delta.len()];
+        let mut stack: Vec<QId> = self.automata_refs.iter().map(|[_q0, f]| *f).collect();
+        while let Some(q) = stack.pop() {
+            if !reaches_f[q] {
+                reaches_f[q] = true;
+                stack.extend(predecessors[q].iter());
+            }
+        }
+        let mut keep: Vec<bool> = (0..self.delta.len())
+            .map(|q| reachable[q] && reaches_f[q])
+            .collect();
+        for [q0, f] in self.automata_refs.iter() {
+            keep[*q0] = true;
+            keep[*f] = true;
+        }
+        let mut new_ids: Vec<Option<QId>> = vec![None; self.delta.len()];
+        let kept = (0..keep.len()).filter(|q| keep[*q]);
+        for (new_q, q) in kept.enumerate() {
+            new_ids[q] = Some(new_q);
+        }
+        let delta = core::mem::take(&mut self.delta);
+        for (q, (label, q_nexts)) in delta.into_iter().enumerate() {
+            if !keep[q] {
+                continue;
+            }
+            // a removed 0 branch leaves the 1 branch as the only transition
+            let mut remapped = q_nexts
+                .iter()
+                .flatten()
+                .filter_map(|q_next| new_ids[*q_next].filter(|_| keep[*q_next]));
+            self.push_state((label, [remapped.next(), remapped.next()]));
+        }
+        self.assertions = core::mem::take(&mut self.assertions)
+            .into_iter()
+            .filter_map(|(q, assertion)| new_ids[q].map(|new_q| (new_q, assertion)))
+            .collect();
+        for automaton in self.automata_refs.iter_mut() {
+            for q in automaton.iter_mut() {
+                if let Some(new_q) = new_ids[*q] {
+                    *q = new_q;
+                }
+            }
+        }
+        new_ids
+    }
+}
+
+/// Returns a new machine accepting the union of the languages of finalized
+/// `machines`, i.e. machines holding exactly one automaton each. Their states
+/// are copied and re-indexed, and a new start state chooses between them in
+/// order, so earlier machines have priority. An empty slice accepts nothing.
+///
+/// ```rust
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// use regexxx::ops;
+/// let a = ForwardCompiler::from_expr_a('a').unwrap();
+/// let mut b = ForwardCompiler::from_expr_a('b').unwrap();
+/// ForwardCompiler::star(&mut b).unwrap();
+/// let machine = ops::union_machines(&[&a, &b]).unwrap();
+/// assert!(machine.is_match("a").unwrap());
+/// assert!(machine.determinize().unwrap().accepts("bbb".chars()));
+/// ```
+pub fn union_machines<S: Copy + Eq>(machines: &[&ANFA<S>]) -> Result<ANFA<S>, &'static str> {
+    let mut anfa = ANFA::new();
+    if machines.is_empty() {
+        ForwardCompiler::expr_0(&mut anfa)?;
+        return Ok(anfa);
+    }
+    for machine in machines.iter() {
+        anfa.import(machine)?;
+    }
+    // union pops the top two operands, so this nests to the right: a + (b + (c + ...))
+    for _ in 1..machines.len() {
+        ForwardCompiler::union(&mut anfa)?;
+    }
+    Ok(anfa)
+}
+
+/// Returns a new machine accepting the words of finalized machine `a`
+/// followed by the words of finalized machine `b`. Both machines are copied
+/// and re-indexed, so cached fragments can be sequenced without rebuilding
+/// them, and neither operand is modified.
+///
+/// ```rust
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// use regexxx::ops;
+/// let mut a = ForwardCompiler::from_expr_a('a').unwrap();
+/// ForwardCompiler::star(&mut a).unwrap();
+/// let b = ForwardCompiler::from_expr_a('b').unwrap();
+/// let machine = ops::concat_machines(&a, &b).unwrap();
+/// assert_eq!(machine.find("xaab").unwrap(), Some((1, 4)));
+/// ```
+pub fn concat_machines<S: Copy + Eq>(a: &ANFA<S>, b: &ANFA<S>) -> Result<ANFA<S>, &'static str> {
+    let mut anfa = ANFA::new();
+    anfa.import(a)?;
+    anfa.import(b)?;
+    ForwardCompiler::concatenate(&mut anfa)?;
+    Ok(anfa)
+}
+
+/// Returns a new machine accepting any number of words of finalized machine
+/// `a`, including none. `a` is copied, not modified.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::ops;
+/// let ab = Expr::literal('a').concat(Expr::literal('b')).build().unwrap();
+/// let machine = ops::star_machine(&ab).unwrap();
+/// assert!(machine.determinize().unwrap().accepts("abab".chars()));
+/// assert!(machine.determinize().unwrap().accepts("".chars()));
+/// ```
+pub fn star_machine<S: Copy + Eq>(a: &ANFA<S>) -> Result<ANFA<S>, &'static str> {
+    let mut anfa = ANFA::new();
+    anfa.import(a)?;
+    ForwardCompiler::star(&mut anfa)?;
+    Ok(anfa)
+}
+
+/// Returns a new machine accepting one or more words of finalized machine
+/// `a`, built as `a⋅a*` from two copies of `a`.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::ops;
+/// let a = Expr::literal('a').build().unwrap();
+/// let machine = ops::plus_machine(&a).unwrap();
+/// assert_eq!(machine.find("baaa").unwrap(), Some((1, 4)));
+/// assert!(!machine.is_match("b").unwrap());
+/// ```
+pub fn plus_machine<S: Copy + Eq>(a: &ANFA<S>) -> Result<ANFA<S>, &'static str> {
+    let mut anfa = ANFA::new();
+    anfa.import(a)?;
+    anfa.import(a)?;
+    ForwardCompiler::star(&mut anfa)?;
+    ForwardCompiler::concatenate(&mut anfa)?;
+    Ok(anfa)
+}
+
+/// Returns a new machine accepting the words of finalized machine `a` or the
+/// empty word, built as `a+1`, so `a` has priority over skipping it.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::ops;
+/// let a = Expr::literal('a').build().unwrap();
+/// let machine = ops::optional_machine(&a).unwrap();
+/// assert_eq!(machine.find("ab").unwrap(), Some((0, 1)));
+/// assert_eq!(machine.find("b").unwrap(), Some((0, 0)));
+/// ```
+pub fn optional_machine<S: Copy + Eq>(a: &ANFA<S>) -> Result<ANFA<S>, &'static str> {
+    let mut anfa = ANFA::new();
+    anfa.import(a)?;
+    ForwardCompiler::expr_1(&mut anfa)?;
+    ForwardCompiler::union(&mut anfa)?;
+    Ok(anfa)
+}
+
+/// Makes `q` an ε state fanning out to every state of `entries`, along a
+/// chain of unions when there are more than two: `q -> (e0, (e1, (e2, ...)))`
+fn fan_out<S: Copy + Eq>(anfa: &mut ANFA<S>, q: QId, entries: &[QId]) {
+    let mut q_union = q;
+    for (i, entry) in entries.iter().enumerate() {
+        if i + 2 < entries.len() {
+            let q_rest = anfa.push_state((None, [None, None]));
+            anfa.delta[q_union] = (None, [Some(*entry), Some(q_rest)]);
+            q_union = q_rest;
+        } else if i + 2 == entries.len() {
+            anfa.delta[q_union] = (None, [Some(*entry), Some(entries[i + 1])]);
+            break;
+        } else {
+            anfa.delta[q_union] = (None, [Some(*entry), None]);
+        }
+    }
+}
+
+/// Returns a new machine accepting the slices `w[range]` of the words `w`
+/// of finalized machine `a` that are at least `range.end` symbols long,
+/// e.g. the language of one fixed-width column of a record pattern.
+///
+/// The machine is a product of the states of `a` without ε transitions and
+/// a counter of the symbols read in the slice. It starts in the states `a`
+/// reaches after `range.start` symbols and accepts after `range.len()` more
+/// symbols in states from which `a` still accepts some suffix. Assertions
+/// are rejected, see `ANFA::remove_epsilon`.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::ops;
+/// use regexxx::ANFA;
+/// let digit = || Expr::literal('0').union(Expr::literal('1'));
+/// let letter = || Expr::literal('a').union(Expr::literal('b'));
+/// // records of two digits and a letter, or a letter and two digits
+/// let record = digit().concat(digit()).concat(letter()).union(letter().concat(digit()).concat(digit()));
+/// let column = ops::slice_language(&record.build().unwrap(), 0..1).unwrap();
+/// let expected = digit().union(letter()).build().unwrap();
+/// assert!(ANFA::equivalent(&column, &expected).unwrap());
+/// ```
+pub fn slice_language<S: Copy + Eq>(
+    a: &ANFA<S>,
+    range: Range<usize>,
+) -> Result<ANFA<S>, &'static str> {
+    if range.start > range.end {
+        return Err("Slice must not end before it starts.");
+    }
+    let nfa = a.remove_epsilon()?;
+    let n = nfa.delta.len();
+    let width = range.end - range.start;
+
+    // states that reach a final state, found backwards from the final states
+    let mut live = nfa.finals.clone();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (q, transitions) in nfa.delta.iter().enumerate() {
+            if !live[q] && transitions.iter().any(|(_label, q_next)| live[*q_next]) {
+                live[q] = true;
+                changed = true;
+            }
+        }
+    }
+    let step = |reached: &[bool]| {
+        let mut next = vec![false; n];
+        for (q, transitions) in nfa.delta.iter().enumerate() {
+            if reached[q] {
+                for (_label, q_next) in transitions.iter() {
+                    next[*q_next] = live[*q_next];
+                }
+            }
+        }
+        next
+    };
+    // states reached by the prefixes before the slice
+    let mut reached = vec![false; n];
+    reached[nfa.q0] = live[nfa.q0];
+    for _ in 0..range.start {
+        reached = step(&reached);
+    }
+
+    // product state (q, k) is q after k symbols of the slice, its hub is k * n + q
+    let mut anfa = ANFA::new();
+    for _ in 0..(width + 1) * n {
+        anfa.push_state((None, [None, None]));
+    }
+    let q0 = anfa.push_state((None, [None, None]));
+    let f = anfa.push_state((None, [None, None]));
+    let starts: Vec<QId> = (0..n).filter(|q| reached[*q]).collect();
+    fan_out(&mut anfa, q0, &starts);
+    for k in 0..=width {
+        for (q, transitions) in nfa.delta.iter().enumerate() {
+            if !reached[q] {
+                continue;
+            }
+            let entries: Vec<QId> = if k == width {
+                vec![f]
+            } else {
+                transitions
+                    .iter()
+                    .filter(|(_label, q_next)| live[*q_next])
+                    .map(|(label, q_next)| {
+                        anfa.push_state((Some(*label), [Some((k + 1) * n + q_next), None]))
+                    })
+                    .collect()
+            };
+            fan_out(&mut anfa, k * n + q, &entries);
+        }
+        if k < width {
+            reached = step(&reached);
+        }
+    }
+    anfa.automata_refs.push([q0, f]);
+    Ok(anfa)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::expr::Expr;
+    use crate::ops::{
+        concat_machines, optional_machine, plus_machine, slice_language, star_machine,
+        union_machines,
+    };
+    use crate::ANFA;
+    use alloc::string::String;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_import() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        let mut fragment = ForwardCompiler::from_expr_a('b').unwrap();
+        ForwardCompiler::star(&mut fragment).unwrap();
+        let [fragment_q0, fragment_f] = fragment.automata_refs[0];
+        assert_eq!(
+            machine.import(&fragment).unwrap(),
+            [fragment_q0 + 2, fragment_f + 2]
+        );
+        assert_eq!(machine.delta.len(), 2 + fragment.delta.len());
+        assert_eq!(
+            machine.delta[2..]
+                .iter()
+                .map(|(label, _)| *label)
+                .collect::<Vec<_>>(),
+            fragment
+                .delta
+                .iter()
+                .map(|(label, _)| *label)
+                .collect::<Vec<_>>(),
+            "States keep their creation order"
+        );
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let expected = Expr::literal('a')
+            .concat(Expr::literal('b').star())
+            .build()
+            .unwrap();
+        assert!(ANFA::equivalent(&machine, &expected).unwrap());
+
+        ForwardCompiler::expr_a(&mut fragment, 'c').unwrap();
+        assert!(
+            machine.import(&fragment).is_err(),
+            "Only finalized machines are imported"
+        );
+    }
+
+    #[test]
+    fn test_clone_subautomaton() {
+        // RE a(b+c)*
+        let mut machine = ForwardCompiler::from_expr_a('x').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let before = machine.delta.len();
+        let fragment = machine.automata_refs[1];
+        let clone = machine.clone_subautomaton(fragment).unwrap();
+        assert_eq!(
+            machine.delta.len() - before,
+            before - 2,
+            "Every state except the unrelated 'x' automaton is copied"
+        );
+        assert_eq!(machine.automata_refs, vec![[0, 1], fragment, clone]);
+        // RE a(b+c)*a(b+c)*, composing both the fragment and its copy
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let x = machine.automata_refs.remove(0);
+        assert_eq!(x, [0, 1]);
+        let expected = Expr::literal('a')
+            .concat(Expr::literal('b').union(Expr::literal('c')).star())
+            .concat(Expr::literal('a'))
+            .concat(Expr::literal('b').union(Expr::literal('c')).star())
+            .build()
+            .unwrap();
+        assert!(ANFA::equivalent(&machine, &expected).unwrap());
+        assert!(
+            machine.clone_subautomaton(fragment).is_err(),
+            "Consumed refs are stale"
+        );
+    }
+
+    #[test]
+    fn test_clone_subautomaton_assertions_and_0() {
+        let mut machine: ANFA = ForwardCompiler::from_expr_0().unwrap();
+        let clone = machine
+            .clone_subautomaton(machine.automata_refs[0])
+            .unwrap();
+        assert_eq!(clone, [2, 3], "f is copied even when q0 cannot reach it");
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert!(machine.is_empty().unwrap());
+
+        let mut machine: ANFA = ForwardCompiler::from_expr_assert(Assertion::WordBoundary).unwrap();
+        let [q0, f] = machine.automata_refs[0];
+        let [clone_q0, clone_f] = machine.clone_subautomaton([q0, f]).unwrap();
+        for (q, clone_q) in [(q0, clone_q0), (f, clone_f)] {
+            assert_eq!(
+                machine.assertions.get(&q),
+                machine.assertions.get(&clone_q),
+                "Assertions are copied with their states"
+            );
+        }
+    }
+
+    #[test]
+    fn test_compact() {
+        // RE (a0+b)*c, with a second live automaton 0 on the stack
+        let mut machine = Expr::literal('a')
+            .concat(Expr::zero())
+            .union(Expr::literal('b'))
+            .star()
+            .concat(Expr::literal('c'))
+            .build()
+            .unwrap();
+        ForwardCompiler::expr_0(&mut machine).unwrap();
+        let [zero_q0, zero_f] = machine.automata_refs[1];
+        let expected = Expr::literal('b')
+            .star()
+            .concat(Expr::literal('c'))
+            .build()
+            .unwrap();
+        let before = machine.delta.len();
+        let new_ids = machine.compact();
+        assert_eq!(new_ids.len(), before);
+        assert_eq!(
+            machine.delta.len(),
+            before - 4,
+            "'a', its f, and both states of its 0 are removed"
+        );
+        assert_eq!(
+            machine.automata_refs[1],
+            [new_ids[zero_q0].unwrap(), new_ids[zero_f].unwrap()],
+            "Live automata keep their q0 and f, even when f is unreachable"
+        );
+        for (q, (_label, q_nexts)) in machine.states() {
+            assert!(
+                q_nexts[1].is_none() || q_nexts[0].is_some(),
+                "State {} keeps the branch invariant",
+                q
+            );
+        }
+        let zero = machine.automata_refs.pop().unwrap();
+        assert!(ANFA::equivalent(&machine, &expected).unwrap());
+        machine.automata_refs.push(zero);
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert!(ANFA::equivalent(&machine, &expected).unwrap());
+        let compacted = machine.delta.len();
+        machine.compact();
+        assert_eq!(
+            machine.delta.len(),
+            compacted - 2,
+            "Both states of the 0 operand are dropped once it is composed"
+        );
+    }
+
+    #[test]
+    fn test_compact_assertions() {
+        // RE \b(a+b0)
+        let mut machine = Expr::assert(Assertion::WordBoundary)
+            .concat(Expr::literal('a').union(Expr::literal('b').concat(Expr::zero())))
+            .build()
+            .unwrap();
+        machine.compact();
+        assert_eq!(machine.assertions.len(), 1);
+        for q in machine.assertions.keys() {
+            assert!(*q < machine.delta.len(), "Assertions are renumbered");
+        }
+        assert_eq!(machine.find("a").unwrap(), Some((0, 1)));
+        assert_eq!(machine.find("ba").unwrap(), None);
+    }
+
+    #[test]
+    fn test_union_machines() {
+        let a = Expr::literal('a')
+            .concat(Expr::literal('b'))
+            .build()
+            .unwrap();
+        let b = Expr::literal('c').star().build().unwrap();
+        let c = Expr::literal('d').build().unwrap();
+        let machine = union_machines(&[&a, &b, &c]).unwrap();
+        let expected = Expr::literal('a')
+            .concat(Expr::literal('b'))
+            .union(Expr::literal('c').star().union(Expr::literal('d')))
+            .build()
+            .unwrap();
+        assert!(ANFA::equivalent(&machine, &expected).unwrap());
+        assert_eq!(machine.automata_refs.len(), 1);
+        assert_eq!(
+            machine.find("xd").unwrap(),
+            Some((0, 0)),
+            "c* matches first"
+        );
+
+        let single = union_machines(&[&a]).unwrap();
+        assert!(ANFA::equivalent(&single, &a).unwrap());
+        let nothing: ANFA = union_machines(&[]).unwrap();
+        assert!(nothing.is_empty().unwrap());
+    }
+
+    #[test]
+    fn test_concat_machines() {
+        let a = Expr::literal('a')
+            .union(Expr::literal('b'))
+            .star()
+            .build()
+            .unwrap();
+        let b = Expr::literal('b').build().unwrap();
+        let a_delta = a.delta.clone();
+        let machine = concat_machines(&a, &b).unwrap();
+        let expected = Expr::literal('a')
+            .union(Expr::literal('b'))
+            .star()
+            .concat(Expr::literal('b'))
+            .build()
+            .unwrap();
+        assert!(ANFA::equivalent(&machine, &expected).unwrap());
+        assert_eq!(a.delta, a_delta, "Operands are not modified");
+
+        let twice = concat_machines(&machine, &machine).unwrap();
+        assert_eq!(twice.shortest_accepted().unwrap(), Some(vec!['b', 'b']));
+
+        let mut unfinished = Expr::literal('a').build().unwrap();
+        Expr::literal('b')
+            .compile::<ForwardCompiler>(&mut unfinished)
+            .unwrap();
+        assert!(concat_machines(&a, &unfinished).is_err());
+    }
+
+    #[test]
+    fn test_star_plus_optional_machines() {
+        let ab = || Expr::literal('a').concat(Expr::literal('b'));
+        let machine = ab().build().unwrap();
+        let delta = machine.delta.clone();
+        let cases = [
+            (star_machine(&machine).unwrap(), ab().star()),
+            (plus_machine(&machine).unwrap(), ab().concat(ab().star())),
+            (optional_machine(&machine).unwrap(), ab().union(Expr::one())),
+        ];
+        for (machine, expected) in cases.iter() {
+            assert_eq!(machine.automata_refs.len(), 1);
+            assert!(
+                ANFA::equivalent(machine, &expected.build().unwrap()).unwrap(),
+                "{}",
+                expected
+            );
+        }
+        assert_eq!(machine.delta, delta, "Operands are not modified");
+        assert_eq!(
+            cases[1].0.shortest_accepted().unwrap(),
+            Some(vec!['a', 'b']),
+            "Plus requires one word"
+        );
+
+        let nested =
+            star_machine(&plus_machine(&optional_machine(&machine).unwrap()).unwrap()).unwrap();
+        assert!(
+            ANFA::equivalent(&nested, &cases[0].0).unwrap(),
+            "((ab+1)(ab+1)*)* = (ab)*"
+        );
+
+        let mut unfinished = Expr::literal('a').build().unwrap();
+        Expr::literal('b')
+            .compile::<ForwardCompiler>(&mut unfinished)
+            .unwrap();
+        assert!(star_machine(&unfinished).is_err());
+        assert!(plus_machine(&unfinished).is_err());
+        assert!(optional_machine(&unfinished).is_err());
+    }
+
+    #[test]
+    fn test_reverse() {
+        // RE (a+bc)*c
+        let expr = Expr::literal('a')
+            .union(Expr::literal('b').concat(Expr::literal('c')))
+            .star()
+            .concat(Expr::literal('c'));
+        let machine = expr.build().unwrap();
+        let reversed = machine.reverse().unwrap();
+        let expected = Expr::literal('c')
+            .concat(
+                Expr::literal('a')
+                    .union(Expr::literal('c').concat(Expr::literal('b')))
+                    .star(),
+            )
+            .build()
+            .unwrap();
+        assert!(ANFA::equivalent(&reversed, &expected).unwrap());
+        assert!(
+            ANFA::equivalent(&reversed.reverse().unwrap(), &machine).unwrap(),
+            "Reversing twice accepts the same language"
+        );
+
+        // running the reverse machine back from the end of a match finds its start
+        let haystack = "xxabcac";
+        let (start, end) = (2, haystack.len());
+        let backwards: String = haystack[..end].chars().rev().collect();
+        let dense = reversed.to_dense().unwrap();
+        assert_eq!(dense.find(&backwards), Some((0, end - start)));
+
+        let mut unfinished = Expr::literal('a').build().unwrap();
+        ForwardCompiler::expr_a(&mut unfinished, 'b').unwrap();
+        assert!(unfinished.reverse().is_err());
+    }
+
+    #[test]
+    fn test_reverse_assertions() {
+        // RE ^a\b
+        let machine = Expr::assert(Assertion::StartText)
+            .concat(Expr::literal('a'))
+            .concat(Expr::assert(Assertion::WordBoundary))
+            .build()
+            .unwrap();
+        let reversed = machine.reverse().unwrap();
+        assert!(reversed
+            .assertions
+            .values()
+            .any(|a| *a == Assertion::EndText));
+        assert_eq!(reversed.find("ba").unwrap(), None, "a must end the input");
+        assert_eq!(reversed.find(" a").unwrap(), Some((1, 2)));
+        assert_eq!(machine.find("a ").unwrap(), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_slice_language() {
+        // RE abc+de
+        let machine = Expr::word("abc").union(Expr::word("de")).build().unwrap();
+        for (range, expected) in [
+            (0..1, Expr::literal('a').union(Expr::literal('d'))),
+            (1..2, Expr::literal('b').union(Expr::literal('e'))),
+            (1..3, Expr::word("bc")),
+            (2..2, Expr::one()),
+            (3..3, Expr::one()),
+            (4..4, Expr::zero()),
+            (2..4, Expr::zero()),
+        ]
+        .iter()
+        {
+            let slice = slice_language(&machine, range.clone()).unwrap();
+            let expected = expected.build().unwrap();
+            assert!(ANFA::equivalent(&slice, &expected).unwrap(), "{:?}", range);
+        }
+        // RE (ab)*c, slices of a loop
+        let machine = Expr::word("ab")
+            .star()
+            .concat(Expr::literal('c'))
+            .build()
+            .unwrap();
+        let slice = slice_language(&machine, 1..3).unwrap();
+        let expected = Expr::word("ba").union(Expr::word("bc")).build().unwrap();
+        assert!(ANFA::equivalent(&slice, &expected).unwrap());
+        #[allow(clippy::reversed_empty_ranges)]
+        let backwards = slice_language(&machine, 2..1);
+        assert!(backwards.is_err());
+        let with_assertion: ANFA = Expr::assert(Assertion::WordBoundary).build().unwrap();
+        assert!(slice_language(&with_assertion, 0..0).is_err());
+    }
+}
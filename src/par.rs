@@ -0,0 +1,152 @@
+use crate::search::{MatchKind, SearchOptions};
+use crate::ANFA;
+use alloc::vec::Vec;
+use std::thread;
+
+/// Applies `f` to every haystack, splitting the haystacks into one contiguous
+/// chunk per available thread. Results are in the order of `haystacks`.
+fn map_chunks<T, F>(haystacks: &[&str], f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&str) -> T + Sync,
+{
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    if threads == 1 || haystacks.len() < 2 {
+        return haystacks.iter().map(|haystack| f(haystack)).collect();
+    }
+    let chunk_len = haystacks.len().div_ceil(threads);
+    let f = &f;
+    thread::scope(|scope| {
+        let workers: Vec<_> = haystacks
+            .chunks(chunk_len)
+            .map(|chunk| {
+                scope.spawn(move || chunk.iter().map(|haystack| f(haystack)).collect::<Vec<T>>())
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| match worker.join() {
+                Ok(results) => results,
+                Err(panic) => std::panic::resume_unwind(panic),
+            })
+            .collect()
+    })
+}
+
+impl ANFA {
+    /// Returns whether each haystack has a match, see `is_match`. The
+    /// haystacks are searched on as many threads as are available, all
+    /// sharing the automaton, which is only read while searching.
+    /// The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE ab
+    /// let machine = Expr::literal('a').concat(Expr::literal('b')).build().unwrap();
+    /// let haystacks = ["xab", "ba", "", "aab"];
+    /// assert_eq!(machine.is_match_many(&haystacks).unwrap(), vec![true, false, false, true]);
+    /// ```
+    pub fn is_match_many(&self, haystacks: &[&str]) -> Result<Vec<bool>, &'static str> {
+        self.is_match_many_with(haystacks, &SearchOptions::default())
+    }
+
+    /// Like `is_match_many`, with `options` applied during every search
+    pub fn is_match_many_with(
+        &self,
+        haystacks: &[&str],
+        options: &SearchOptions,
+    ) -> Result<Vec<bool>, &'static str> {
+        // any match will do, so stop at the first one seen
+        let earliest = SearchOptions {
+            match_kind: MatchKind::Earliest,
+            ..*options
+        };
+        Ok(self
+            .find_many_with(haystacks, &earliest)?
+            .iter()
+            .map(Option::is_some)
+            .collect())
+    }
+
+    /// Returns the leftmost match of each haystack, see `find` and
+    /// `is_match_many`.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE a*b
+    /// let machine = Expr::literal('a').star().concat(Expr::literal('b')).build().unwrap();
+    /// assert_eq!(machine.find_many(&["xaab", "c"]).unwrap(), vec![Some((1, 4)), None]);
+    /// ```
+    pub fn find_many(
+        &self,
+        haystacks: &[&str],
+    ) -> Result<Vec<Option<(usize, usize)>>, &'static str> {
+        self.find_many_with(haystacks, &SearchOptions::default())
+    }
+
+    /// Like `find_many`, with `options` applied during every search
+    pub fn find_many_with(
+        &self,
+        haystacks: &[&str],
+        options: &SearchOptions,
+    ) -> Result<Vec<Option<(usize, usize)>>, &'static str> {
+        if self.automata_refs.len() != 1 {
+            return Err("Search requires exactly one automaton.");
+        }
+        // the number of automata is checked, so searches can't fail
+        Ok(map_chunks(haystacks, |haystack| {
+            self.find_with(haystack, options).ok().flatten()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::Expr;
+    use crate::search::SearchOptions;
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_find_many() {
+        // RE (a+b)*c
+        let machine = Expr::literal('a')
+            .union(Expr::literal('b'))
+            .star()
+            .concat(Expr::literal('c'))
+            .build()
+            .unwrap();
+        let owned: Vec<String> = (0..100).map(|i| format!("{:b}c", i)).collect();
+        let haystacks: Vec<&str> = owned
+            .iter()
+            .map(String::as_str)
+            .chain(["", "ABC"].iter().copied())
+            .collect();
+        let expected: Vec<_> = haystacks
+            .iter()
+            .map(|haystack| machine.find(haystack).unwrap())
+            .collect();
+        assert_eq!(
+            machine.find_many(&haystacks).unwrap(),
+            expected,
+            "Results are in the order of the haystacks"
+        );
+        let options = SearchOptions {
+            case_insensitive: true,
+            ..SearchOptions::default()
+        };
+        let matches = machine.is_match_many_with(&haystacks, &options).unwrap();
+        assert_eq!(matches.len(), haystacks.len());
+        assert!(matches[..100].iter().all(|matched| *matched));
+        assert_eq!(&matches[100..], &[false, true]);
+        assert_eq!(machine.find_many(&[]).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_find_many_requires_one_automaton() {
+        let mut machine = Expr::literal('a').build().unwrap();
+        machine.automata_refs.clear();
+        assert!(machine.is_match_many(&["a"]).is_err());
+    }
+}
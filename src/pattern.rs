@@ -0,0 +1,465 @@
+use crate::assertion::Assertion;
+use crate::expr::Expr;
+use crate::flavor::Flavor;
+use crate::{QId, ANFA};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+/// `a + b`, dropping `0` and duplicate operands
+fn union<S: Copy + Eq>(a: Expr<S>, b: Expr<S>) -> Expr<S> {
+    match (a, b) {
+        (Expr::Zero, b) => b,
+        (a, Expr::Zero) => a,
+        (a, b) if a == b => a,
+        // 1 + a* = a*
+        (Expr::One, b @ Expr::Star(_)) => b,
+        (a @ Expr::Star(_), Expr::One) => a,
+        (a, b) => a.union(b),
+    }
+}
+
+/// `a b`, dropping `1` and absorbing into `0`
+fn concat<S: Copy + Eq>(a: Expr<S>, b: Expr<S>) -> Expr<S> {
+    match (a, b) {
+        (Expr::Zero, _) | (_, Expr::Zero) => Expr::Zero,
+        (Expr::One, b) => b,
+        (a, Expr::One) => a,
+        (a, b) => a.concat(b),
+    }
+}
+
+/// `a*`, where `0* = 1* = 1` and `a** = a*`
+fn star<S: Copy + Eq>(a: Expr<S>) -> Expr<S> {
+    match a {
+        Expr::Zero | Expr::One => Expr::One,
+        a @ Expr::Star(_) => a,
+        a => a.star(),
+    }
+}
+
+/// Marks the states reachable from `from` along `next`
+fn reachable(from: QId, next: &[Vec<QId>]) -> Vec<bool> {
+    let mut seen = vec![false; next.len()];
+    let mut stack = vec![from];
+    while let Some(q) = stack.pop() {
+        if seen[q] {
+            continue;
+        }
+        seen[q] = true;
+        stack.extend(next[q].iter().copied());
+    }
+    seen
+}
+
+/// Generalized NFA, transitions are labeled by expressions
+#[allow(clippy::upper_case_acronyms)]
+struct GNFA<S> {
+    out: Vec<BTreeMap<QId, Expr<S>>>,
+    into: Vec<BTreeSet<QId>>,
+    /// most nodes of any transition's expression, see `Expr::size`
+    max_size: usize,
+}
+
+impl<S: Copy + Eq> GNFA<S> {
+    /// Adds `expr` to the transition from `p` to `r` as a union
+    fn add(&mut self, p: QId, r: QId, expr: Expr<S>) -> Result<(), &'static str> {
+        let label = match self.out[p].remove(&r) {
+            Some(existing) => union(existing, expr),
+            None => expr,
+        };
+        if label.size() > self.max_size {
+            return Err("Expression exceeds the size limit.");
+        }
+        self.out[p].insert(r, label);
+        self.into[r].insert(p);
+        Ok(())
+    }
+
+    /// Removes `q`, rerouting every path `p → q → r` as `(p,q) (q,q)* (q,r)`
+    fn eliminate(&mut self, q: QId) -> Result<(), &'static str> {
+        let loop_expr = match self.out[q].remove(&q) {
+            Some(expr) => star(expr),
+            None => Expr::One,
+        };
+        self.into[q].remove(&q);
+        let rs: Vec<(QId, Expr<S>)> = core::mem::take(&mut self.out[q]).into_iter().collect();
+        for p in core::mem::take(&mut self.into[q]).into_iter() {
+            let p_q = match self.out[p].remove(&q) {
+                Some(expr) => expr,
+                None => continue,
+            };
+            for (r, q_r) in rs.iter() {
+                let expr = concat(concat(p_q.clone(), loop_expr.clone()), q_r.clone());
+                self.add(p, *r, expr)?;
+            }
+        }
+        for (r, _q_r) in rs.iter() {
+            self.into[*r].remove(&q);
+        }
+        Ok(())
+    }
+}
+
+impl<S: Copy + Eq> ANFA<S> {
+    /// Converts the automaton into an equivalent expression by state elimination.
+    ///
+    /// The automaton is read as a generalized NFA whose transitions are labeled
+    /// by expressions: symbols are literals, ε is `1`, and transitions into
+    /// states guarded by an assertion end with the assertion. States are then removed
+    /// one at a time, cheapest first, rerouting each path `p → q → r` through
+    /// the removed state `q` as `p → r` labeled `(p,q) (q,q)* (q,r)`, until only
+    /// the transition from q0 to f is left. The result is simplified with
+    /// identities like `1a = a` and `0 + a = a`, but it is not minimal.
+    /// The ANFA must hold exactly one automaton.
+    pub fn to_expr(&self) -> Result<Expr<S>, &'static str> {
+        self.to_expr_with_limit(usize::MAX)
+    }
+
+    /// Like `to_expr`, failing once any intermediate expression has more than
+    /// `max_size` nodes, see `Expr::size`. State elimination can grow
+    /// expressions exponentially in the number of states, so the limit bounds
+    /// the memory and time of converting untrusted machines.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE (a+b)*c
+    /// let machine = Expr::literal('a').union(Expr::literal('b')).star().concat(Expr::literal('c')).build().unwrap();
+    /// assert_eq!(machine.to_expr_with_limit(6).unwrap().size(), 6);
+    /// assert!(machine.to_expr_with_limit(5).is_err());
+    /// ```
+    pub fn to_expr_with_limit(&self, max_size: usize) -> Result<Expr<S>, &'static str> {
+        let [machine_q0, machine_f] = match self.automata_refs.as_slice() {
+            [machine] => *machine,
+            _ => {
+                return Err("Conversion to an expression requires exactly one automaton.");
+            }
+        };
+        let state_count = self.delta.len();
+        let mut successors: Vec<Vec<QId>> = vec![Vec::new(); state_count];
+        let mut predecessors: Vec<Vec<QId>> = vec![Vec::new(); state_count];
+        for (q, (_label, q_nexts)) in self.delta.iter().enumerate() {
+            for q_next in q_nexts.iter().flatten() {
+                successors[q].push(*q_next);
+                predecessors[*q_next].push(q);
+            }
+        }
+        let from_q0 = reachable(machine_q0, &successors);
+        let to_f = reachable(machine_f, &predecessors);
+        if !from_q0[machine_f] {
+            return Ok(Expr::Zero);
+        }
+        let useful = |q: QId| from_q0[q] && to_f[q];
+
+        // a new start state without incoming transitions and a new accept state
+        // without outgoing transitions
+        let start = state_count;
+        let accept = state_count + 1;
+        let mut gnfa = GNFA {
+            out: vec![BTreeMap::new(); state_count + 2],
+            into: vec![BTreeSet::new(); state_count + 2],
+            max_size,
+        };
+        // a guarded state may only be entered where its assertion holds,
+        // so every transition into it is followed by the assertion
+        let into_state = |q: QId, expr: Expr<S>| match self.assertions.get(&q) {
+            Some(assertion) => concat(expr, Expr::Assert(*assertion)),
+            None => expr,
+        };
+        gnfa.add(start, machine_q0, into_state(machine_q0, Expr::One))?;
+        gnfa.add(machine_f, accept, Expr::One)?;
+        for (q, (label, q_nexts)) in self.delta.iter().enumerate() {
+            if !useful(q) {
+                continue;
+            }
+            for q_next in q_nexts.iter().flatten().filter(|q_next| useful(**q_next)) {
+                let expr = match label {
+                    Some(c) => Expr::Literal(*c),
+                    None => Expr::One,
+                };
+                gnfa.add(q, *q_next, into_state(*q_next, expr))?;
+            }
+        }
+
+        let mut remaining: Vec<QId> = (0..state_count).filter(|q| useful(*q)).collect();
+        while !remaining.is_empty() {
+            // eliminating the state with the fewest rerouted paths keeps expressions small,
+            // and ties go to the lowest state so union branches tend to keep their order
+            let (i, _cost) = remaining
+                .iter()
+                .enumerate()
+                .map(|(i, q)| (i, gnfa.into[*q].len() * gnfa.out[*q].len()))
+                .min_by_key(|(_i, cost)| *cost)
+                .unwrap_or((0, 0));
+            gnfa.eliminate(remaining.remove(i))?;
+        }
+        Ok(gnfa.out[start].remove(&accept).unwrap_or(Expr::Zero))
+    }
+}
+
+impl<S: Copy + Eq + Display> ANFA<S> {
+    /// Renders the automaton as a pattern string, see `to_expr` and the
+    /// `Display` implementation of `Expr` for the notation.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// // RE (a+b)*
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::union(&mut machine).unwrap();
+    /// ForwardCompiler::star(&mut machine).unwrap();
+    /// assert_eq!(machine.to_pattern().unwrap(), "(a+b)*");
+    /// ```
+    pub fn to_pattern(&self) -> Result<String, &'static str> {
+        Ok(self.to_expr()?.to_string())
+    }
+}
+
+/// An unfinished group while parsing, see `Expr::parse`
+struct Group {
+    /// the union of the finished alternatives
+    union: Option<Expr>,
+    /// the concatenation of the finished operands of the current alternative
+    concat: Option<Expr>,
+    /// the last operand, which a following `*` applies to
+    last: Option<Expr>,
+}
+
+impl Group {
+    fn new() -> Group {
+        Group {
+            union: None,
+            concat: None,
+            last: None,
+        }
+    }
+
+    /// Concatenates the last operand to the current alternative
+    fn flush(&mut self) {
+        if let Some(last) = self.last.take() {
+            self.concat = Some(match self.concat.take() {
+                Some(concat) => concat.concat(last),
+                None => last,
+            });
+        }
+    }
+
+    fn push(&mut self, operand: Expr) {
+        self.flush();
+        self.last = Some(operand);
+    }
+
+    /// Ends the current alternative at a `+`. An empty alternative is `1`
+    /// when `empty_operands` is set, see `Flavor::empty_operands`.
+    fn alternative(&mut self, empty_operands: bool) -> Result<(), &'static str> {
+        self.flush();
+        let alternative = match self.concat.take() {
+            Some(concat) => concat,
+            None if empty_operands => Expr::One,
+            None => return Err("Expected an operand."),
+        };
+        self.union = Some(match self.union.take() {
+            Some(union) => union.union(alternative),
+            None => alternative,
+        });
+        Ok(())
+    }
+
+    fn finish(mut self, empty_operands: bool) -> Result<Expr, &'static str> {
+        self.alternative(empty_operands)?;
+        self.union.ok_or("Expected an operand.")
+    }
+}
+
+impl Expr<char> {
+    /// Parses the notation written by the `Display` implementation of
+    /// `Expr`, e.g. `(a+b)*c`. Concatenations and unions are grouped to the
+    /// left, so parsing a displayed expression returns an equivalent one
+    /// that may be grouped differently. Groups are kept on an explicit stack,
+    /// so deeply nested patterns don't grow the call stack. See `Flavor::parse`
+    /// for the syntax of other engines.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // a or the literals +*, repeated, then a word boundary
+    /// let expr = Expr::parse(r"^(a+\+\*)*\b").unwrap();
+    /// assert_eq!(expr.to_string(), r"^(a+\+\*)*\b");
+    /// assert!(Expr::parse("a+").is_err());
+    /// assert!(Expr::parse("(ab").is_err());
+    /// ```
+    pub fn parse(pattern: &str) -> Result<Expr<char>, &'static str> {
+        parse_with(pattern, &Flavor::default())
+    }
+}
+
+/// Parses `pattern` with the escapes, empty operands and word boundaries of
+/// `flavor`, see `Expr::parse`
+pub(crate) fn parse_with(pattern: &str, flavor: &Flavor) -> Result<Expr<char>, &'static str> {
+    let mut groups = vec![Group::new()];
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        let group = groups.last_mut().ok_or("Unbalanced parentheses.")?;
+        match c {
+            '(' => {
+                let ascii = [
+                    (r"?-u:\b)", Assertion::AsciiWordBoundary),
+                    (r"?-u:\B)", Assertion::AsciiNotWordBoundary),
+                ];
+                match ascii.iter().find(|(text, _)| rest.starts_with(text)) {
+                    Some((text, assertion)) => {
+                        rest = &rest[text.len()..];
+                        group.push(Expr::Assert(*assertion));
+                    }
+                    None => groups.push(Group::new()),
+                }
+            }
+            ')' => {
+                let inner = match groups.pop() {
+                    Some(inner) if !groups.is_empty() => inner.finish(flavor.empty_operands)?,
+                    _ => return Err("Unbalanced parentheses."),
+                };
+                groups
+                    .last_mut()
+                    .ok_or("Unbalanced parentheses.")?
+                    .push(inner);
+            }
+            '+' => group.alternative(flavor.empty_operands)?,
+            '*' => {
+                let operand = group.last.take().ok_or("Expected an operand before '*'.")?;
+                group.last = Some(operand.star());
+            }
+            '0' => group.push(Expr::Zero),
+            '1' => group.push(Expr::One),
+            '^' => group.push(Expr::Assert(Assertion::StartText)),
+            '$' => group.push(Expr::Assert(Assertion::EndText)),
+            '\\' => {
+                let escaped = rest.chars().next().ok_or("Unknown escape.")?;
+                rest = &rest[escaped.len_utf8()..];
+                group.push(match escaped {
+                    'b' => Expr::Assert(Assertion::WordBoundary.with_word_kind(flavor.word_kind)),
+                    'B' => {
+                        Expr::Assert(Assertion::NotWordBoundary.with_word_kind(flavor.word_kind))
+                    }
+                    c => Expr::Literal(flavor.escapes.unescape(c)?),
+                });
+            }
+            c => group.push(Expr::Literal(c)),
+        }
+    }
+    match (groups.pop(), groups.is_empty()) {
+        (Some(group), true) => group.finish(flavor.empty_operands),
+        _ => Err("Unbalanced parentheses."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::compilers::coverage_compiler::CoverageCompiler;
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::expr::Expr;
+    use crate::ANFA;
+    use alloc::string::ToString;
+
+    fn round_trip(expr: Expr) {
+        let machine = expr.build().unwrap();
+        let converted = machine.to_expr().unwrap().build().unwrap();
+        assert!(
+            ANFA::equivalent(&machine, &converted).unwrap(),
+            "{} converts to an equivalent expression",
+            expr
+        );
+    }
+
+    #[test]
+    fn test_to_pattern() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert_eq!(machine.to_pattern().unwrap(), "ab");
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert_eq!(machine.to_pattern().unwrap(), "ab+c");
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert_eq!(machine.to_pattern().unwrap(), "(ab+c)*");
+
+        let nothing: ANFA = ForwardCompiler::from_expr_0().unwrap();
+        assert_eq!(nothing.to_pattern().unwrap(), "0");
+        let empty: ANFA = ForwardCompiler::from_expr_1().unwrap();
+        assert_eq!(empty.to_pattern().unwrap(), "1");
+    }
+
+    #[test]
+    fn test_to_expr_round_trip() {
+        let a = || Expr::literal('a');
+        let b = || Expr::literal('b');
+        round_trip(a().union(b()).star().concat(b()));
+        round_trip(a().star().concat(b()).star().concat(a().star()).concat(b()));
+        round_trip(a().concat(b().union(Expr::one())).star());
+        round_trip(a().union(Expr::zero()).concat(b().star().star()));
+
+        let mut reversed = CoverageCompiler::from_expr_a('a').unwrap();
+        CoverageCompiler::expr_a(&mut reversed, 'b').unwrap();
+        CoverageCompiler::concatenate(&mut reversed).unwrap();
+        CoverageCompiler::star(&mut reversed).unwrap();
+        let converted = reversed.to_expr().unwrap().build().unwrap();
+        assert!(ANFA::equivalent(&reversed, &converted).unwrap());
+    }
+
+    #[test]
+    fn test_to_pattern_assertions() {
+        let mut machine = ForwardCompiler::from_expr_assert(Assertion::StartText).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_assert(&mut machine, Assertion::WordBoundary).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, '$').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert_eq!(machine.to_pattern().unwrap(), "^a\\b\\$");
+    }
+
+    #[test]
+    fn test_parse() {
+        let a = || Expr::literal('a');
+        let b = || Expr::literal('b');
+        let exprs = [
+            a().union(b()).star().concat(b()),
+            a().concat(b().union(Expr::one()))
+                .star()
+                .union(Expr::zero()),
+            Expr::assert(Assertion::StartText)
+                .concat(Expr::literal('0'))
+                .concat(Expr::literal('\\').star())
+                .concat(Expr::assert(Assertion::AsciiWordBoundary))
+                .concat(Expr::assert(Assertion::AsciiNotWordBoundary))
+                .concat(Expr::literal('é'))
+                .concat(Expr::assert(Assertion::EndText)),
+        ];
+        for expr in exprs.iter() {
+            let parsed = Expr::parse(&expr.to_string()).unwrap();
+            assert_eq!(parsed.to_string(), expr.to_string());
+        }
+        // abc is read as (ab)c
+        let right = a().concat(b().concat(a()));
+        let parsed = Expr::parse(&right.to_string()).unwrap();
+        assert_ne!(parsed, right);
+        assert!(ANFA::equivalent(&parsed.build().unwrap(), &right.build().unwrap()).unwrap());
+        assert_eq!(Expr::parse("a**").unwrap(), a().star().star());
+        for invalid in ["", "*", "a+", "+a", "()", "(a", "a)", ")(", "\\", "\\q"].iter() {
+            assert!(Expr::parse(invalid).is_err(), "{}", invalid);
+        }
+        // deep nesting doesn't grow the call stack
+        let deep = "(".repeat(100_000) + "a" + &")".repeat(100_000);
+        assert_eq!(Expr::parse(&deep).unwrap(), a());
+    }
+
+    #[test]
+    fn test_to_expr_requires_one_automaton() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        assert!(machine.to_expr().is_err());
+    }
+}
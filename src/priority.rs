@@ -0,0 +1,293 @@
+use crate::dfa::DFA;
+use crate::{QId, ANFA};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A DFA compiled from an ordered list of rules, whose accepting states
+/// remember which rule they accept. When a word is accepted by several rules
+/// the earliest wins, as in lex and flex, so a keyword listed before an
+/// identifier rule is lexed as the keyword.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::priority::PriorityDFA;
+/// let keyword = Expr::literal('i').concat(Expr::literal('f')).build().unwrap();
+/// let letter = || Expr::literal('i').union(Expr::literal('f')).union(Expr::literal('x'));
+/// let identifier = letter().concat(letter().star()).build().unwrap();
+/// let lexer = PriorityDFA::compile(&[&keyword, &identifier]).unwrap().minimize();
+/// assert_eq!(lexer.rule("if".chars()), Some(0));
+/// assert_eq!(lexer.rule("ifx".chars()), Some(1));
+/// assert_eq!(lexer.longest("fix if".chars()), Some((3, 1)));
+/// ```
+#[derive(Debug, PartialEq, Eq)]
+pub struct PriorityDFA<S = char> {
+    pub q0: QId,
+    /// `rules[q]` is the earliest rule accepted in state `q`, if any
+    pub rules: Vec<Option<usize>>,
+    /// `delta[q]` lists every `(label, q_next)` leaving state `q`, labels are unique
+    pub delta: Vec<Vec<(S, QId)>>,
+}
+
+impl<S: Copy + Eq> PriorityDFA<S> {
+    /// Subset construction over every rule at once. Each rule must hold
+    /// exactly one automaton, and rule indexes follow the order of `rules`.
+    /// A subset accepts the earliest rule whose final state it contains.
+    pub fn compile(rules: &[&ANFA<S>]) -> Result<PriorityDFA<S>, &'static str> {
+        // the rules as one NFA with a start state per rule
+        let mut starts: Vec<QId> = Vec::new();
+        let mut tags: Vec<Option<usize>> = Vec::new();
+        let mut delta: Vec<Vec<(S, QId)>> = Vec::new();
+        for (rule, machine) in rules.iter().enumerate() {
+            let nfa = machine.remove_epsilon()?;
+            let offset = delta.len();
+            starts.push(offset + nfa.q0);
+            tags.extend(
+                nfa.finals
+                    .iter()
+                    .map(|f| if *f { Some(rule) } else { None }),
+            );
+            delta.extend(nfa.delta.into_iter().map(|transitions| {
+                transitions
+                    .into_iter()
+                    .map(|(label, q_next)| (label, offset + q_next))
+                    .collect()
+            }));
+        }
+        starts.sort_unstable();
+
+        let mut dfa = PriorityDFA {
+            q0: 0,
+            rules: Vec::new(),
+            delta: Vec::new(),
+        };
+        let mut subsets: Vec<Vec<QId>> = vec![starts.clone()];
+        let mut subset_ids: BTreeMap<Vec<QId>, QId> = BTreeMap::new();
+        subset_ids.insert(starts, 0);
+        let mut next_unvisited = 0;
+        while next_unvisited < subsets.len() {
+            let subset = subsets[next_unvisited].clone();
+            next_unvisited += 1;
+            // group every transition of the subset by its label, in order of appearance
+            let mut targets: Vec<(S, Vec<QId>)> = Vec::new();
+            for q in subset.iter() {
+                for (label, q_next) in delta[*q].iter() {
+                    match targets.iter_mut().find(|(c, _)| c == label) {
+                        Some((_c, q_nexts)) => {
+                            if !q_nexts.contains(q_next) {
+                                q_nexts.push(*q_next);
+                            }
+                        }
+                        None => targets.push((*label, vec![*q_next])),
+                    }
+                }
+            }
+            let mut transitions = Vec::with_capacity(targets.len());
+            for (label, mut q_nexts) in targets {
+                q_nexts.sort_unstable();
+                let dfa_id = match subset_ids.get(&q_nexts) {
+                    Some(dfa_id) => *dfa_id,
+                    None => {
+                        let dfa_id = subsets.len();
+                        subset_ids.insert(q_nexts.clone(), dfa_id);
+                        subsets.push(q_nexts);
+                        dfa_id
+                    }
+                };
+                transitions.push((label, dfa_id));
+            }
+            dfa.rules.push(subset.iter().filter_map(|q| tags[*q]).min());
+            dfa.delta.push(transitions);
+        }
+        Ok(dfa)
+    }
+
+    /// Returns the minimal DFA accepting every word with the same rule, see
+    /// `DFA::minimize`. The initial partition splits the accepting states by
+    /// rule, so states accepting different rules are never merged.
+    pub fn minimize(&self) -> PriorityDFA<S> {
+        // states that reach an accepting state, found backwards from the accepting states
+        let mut live: Vec<bool> = self.rules.iter().map(Option::is_some).collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (q, transitions) in self.delta.iter().enumerate() {
+                if !live[q] && transitions.iter().any(|(_label, q_next)| live[*q_next]) {
+                    live[q] = true;
+                    changed = true;
+                }
+            }
+        }
+        let mut alphabet: Vec<S> = Vec::new();
+        for transitions in self.delta.iter() {
+            for (label, _q_next) in transitions.iter() {
+                if !alphabet.contains(label) {
+                    alphabet.push(*label);
+                }
+            }
+        }
+        let next_live = |q: QId, c: S| self.next_state(q, c).filter(|q_next| live[*q_next]);
+
+        // block of each state, rejecting states in block 0 and states accepting
+        // rule r in block r + 1, states that are not live share the block usize::MAX
+        let mut blocks: Vec<usize> = (0..self.delta.len())
+            .map(|q| match (live[q], self.rules[q]) {
+                (false, _) => usize::MAX,
+                (true, None) => 0,
+                (true, Some(rule)) => rule + 1,
+            })
+            .collect();
+        let mut block_count = 0;
+        loop {
+            let mut block_ids: BTreeMap<Vec<Option<usize>>, usize> = BTreeMap::new();
+            let mut refined = vec![usize::MAX; self.delta.len()];
+            for q in 0..self.delta.len() {
+                if !live[q] {
+                    continue;
+                }
+                let mut signature = vec![Some(blocks[q])];
+                for c in alphabet.iter() {
+                    signature.push(next_live(q, *c).map(|q_next| blocks[q_next]));
+                }
+                let next_id = block_ids.len();
+                refined[q] = *block_ids.entry(signature).or_insert(next_id);
+            }
+            blocks = refined;
+            if block_ids.len() == block_count {
+                break;
+            }
+            block_count = block_ids.len();
+        }
+
+        if !live[self.q0] {
+            return PriorityDFA {
+                q0: 0,
+                rules: vec![None],
+                delta: vec![Vec::new()],
+            };
+        }
+        let mut dfa = PriorityDFA {
+            q0: blocks[self.q0],
+            rules: vec![None; block_count],
+            delta: vec![Vec::new(); block_count],
+        };
+        let mut built = vec![false; block_count];
+        for q in 0..self.delta.len() {
+            if !live[q] || built[blocks[q]] {
+                continue;
+            }
+            built[blocks[q]] = true;
+            dfa.rules[blocks[q]] = self.rules[q];
+            dfa.delta[blocks[q]] = self.delta[q]
+                .iter()
+                .filter(|(_label, q_next)| live[*q_next])
+                .map(|(label, q_next)| (*label, blocks[*q_next]))
+                .collect();
+        }
+        dfa
+    }
+
+    /// Returns the state entered from `q` along `c`, if any
+    pub fn next_state(&self, q: QId, c: S) -> Option<QId> {
+        self.delta[q]
+            .iter()
+            .find(|(label, _q_next)| *label == c)
+            .map(|(_label, q_next)| *q_next)
+    }
+
+    /// Returns the earliest rule accepting exactly `input`, if any
+    pub fn rule<I: IntoIterator<Item = S>>(&self, input: I) -> Option<usize> {
+        let mut q = self.q0;
+        for c in input {
+            q = self.next_state(q, c)?;
+        }
+        self.rules[q]
+    }
+
+    /// Returns the length of the longest prefix of `input` accepted by some
+    /// rule and the earliest rule accepting it, i.e. the next token under
+    /// lex's longest match rule
+    pub fn longest<I: IntoIterator<Item = S>>(&self, input: I) -> Option<(usize, usize)> {
+        let mut q = self.q0;
+        let mut longest = self.rules[q].map(|rule| (0, rule));
+        for (i, c) in input.into_iter().enumerate() {
+            match self.next_state(q, c) {
+                Some(q_next) => q = q_next,
+                None => break,
+            }
+            if let Some(rule) = self.rules[q] {
+                longest = Some((i + 1, rule));
+            }
+        }
+        longest
+    }
+
+    /// Forgets the rules, accepting every word some rule accepts
+    pub fn to_dfa(&self) -> DFA<S> {
+        DFA {
+            q0: self.q0,
+            finals: self.rules.iter().map(Option::is_some).collect(),
+            delta: self.delta.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::Expr;
+    use crate::priority::PriorityDFA;
+    use crate::ANFA;
+
+    fn identifier() -> ANFA {
+        let letter = || {
+            "efilns"
+                .chars()
+                .map(Expr::literal)
+                .reduce(Expr::union)
+                .unwrap()
+        };
+        letter().concat(letter().star()).build().unwrap()
+    }
+
+    #[test]
+    fn test_earlier_rules_win() {
+        let keyword = Expr::word("if").union(Expr::word("else")).build().unwrap();
+        let identifier = identifier();
+        let lexer = PriorityDFA::compile(&[&keyword, &identifier]).unwrap();
+        let minimal = lexer.minimize();
+        for dfa in [&lexer, &minimal].iter() {
+            assert_eq!(dfa.rule("if".chars()), Some(0));
+            assert_eq!(dfa.rule("else".chars()), Some(0));
+            assert_eq!(dfa.rule("elif".chars()), Some(1));
+            assert_eq!(dfa.rule("i".chars()), Some(1));
+            assert_eq!(dfa.rule("".chars()), None);
+            assert_eq!(dfa.rule("if!".chars()), None);
+            assert_eq!(dfa.longest("else if".chars()), Some((4, 0)));
+            assert_eq!(dfa.longest("elsen".chars()), Some((5, 1)));
+            assert_eq!(dfa.longest("!".chars()), None);
+        }
+        // forgetting the rules merges the keyword states into the identifier's
+        assert!(minimal.delta.len() > minimal.to_dfa().minimize().delta.len());
+        assert_eq!(minimal.to_dfa().minimize().delta.len(), 2);
+
+        // listed after the identifier rule, keywords never win
+        let shadowed = PriorityDFA::compile(&[&identifier, &keyword])
+            .unwrap()
+            .minimize();
+        assert_eq!(shadowed.rule("if".chars()), Some(0));
+        assert_eq!(shadowed.delta.len(), 2);
+    }
+
+    #[test]
+    fn test_no_rules() {
+        let lexer: PriorityDFA = PriorityDFA::compile(&[]).unwrap();
+        assert_eq!(lexer.rule("".chars()), None);
+        assert_eq!(lexer.minimize().delta.len(), 1);
+        let empty = Expr::one().build().unwrap();
+        let lexer = PriorityDFA::compile(&[&empty]).unwrap().minimize();
+        assert_eq!(lexer.longest("abc".chars()), Some((0, 0)));
+        let mut unfinished = Expr::word("a").build().unwrap();
+        unfinished.automata_refs.clear();
+        assert!(PriorityDFA::compile(&[&unfinished]).is_err());
+    }
+}
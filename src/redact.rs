@@ -0,0 +1,349 @@
+use crate::set::Set;
+use crate::ANFA;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// What replaces the text a pattern of a `Redactor` matched
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Policy {
+    /// Every character is replaced by this one, keeping the length in chars
+    Mask(char),
+    /// The text is replaced by 16 hex digits of its SipHash-2-4 under this
+    /// 128-bit key, so equal texts stay linkable without being shown.
+    /// SipHash is a keyed pseudorandom function: without the key, hashes
+    /// reveal neither the texts nor the key, even alongside known texts.
+    /// With the key, short texts are found by hashing every candidate, so
+    /// the key must be random and kept secret.
+    Hash([u8; 16]),
+    /// The text is removed
+    Drop,
+}
+
+impl Policy {
+    fn apply(&self, text: &str, dst: &mut String) {
+        match self {
+            Policy::Mask(mask) => dst.extend(text.chars().map(|_| *mask)),
+            Policy::Hash(key) => dst.push_str(&format!("{:016x}", siphash24(key, text.as_bytes()))),
+            Policy::Drop => {}
+        }
+    }
+}
+
+/// SipHash-2-4 of `bytes` under `key`, as specified by Aumasson and Bernstein
+fn siphash24(key: &[u8; 16], bytes: &[u8]) -> u64 {
+    // the little-endian number of up to 8 bytes
+    let word = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .rev()
+            .fold(0u64, |word, byte| (word << 8) | u64::from(*byte))
+    };
+    let (k0, k1) = (word(&key[..8]), word(&key[8..]));
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+    let sip_round = |v: &mut [u64; 4]| {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    };
+    let compress = |v: &mut [u64; 4], m: u64| {
+        v[3] ^= m;
+        sip_round(v);
+        sip_round(v);
+        v[0] ^= m;
+    };
+    let blocks = bytes.chunks_exact(8);
+    // the last block holds the remaining bytes and the length mod 256
+    let last = ((bytes.len() as u64) << 56) | word(blocks.remainder());
+    for block in blocks {
+        compress(&mut v, word(block));
+    }
+    compress(&mut v, last);
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        sip_round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+/// One rewritten match of a `Redaction`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Edit {
+    /// The index of the pattern that matched, in the order added
+    pub pattern: usize,
+    /// The byte span of the match in the original document
+    pub original: (usize, usize),
+    /// The byte span of its replacement in the redacted text
+    pub redacted: (usize, usize),
+}
+
+/// A redacted document and the edits that produced it, in document order,
+/// for audit logs that must point back into the original
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Redaction {
+    pub text: String,
+    pub edits: Vec<Edit>,
+}
+
+impl Redaction {
+    /// Maps a byte offset of the redacted text back to the original document.
+    /// Offsets inside a replacement map to the start of the text it replaced.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::redact::{Policy, Redactor};
+    /// let mut redactor = Redactor::new();
+    /// redactor.add(&Expr::literal('x').build().unwrap(), Policy::Drop).unwrap();
+    /// let redaction = redactor.redact("axxb");
+    /// assert_eq!(redaction.text, "ab");
+    /// assert_eq!(redaction.original_offset(1), 3);
+    /// ```
+    pub fn original_offset(&self, offset: usize) -> usize {
+        // the last edit starting at or before offset
+        let mut shift = (0, 0);
+        for edit in self.edits.iter() {
+            if edit.redacted.0 > offset {
+                break;
+            }
+            if offset < edit.redacted.1 {
+                return edit.original.0;
+            }
+            shift = (edit.redacted.1, edit.original.1);
+        }
+        offset - shift.0 + shift.1
+    }
+}
+
+/// Patterns with replacement policies, kept in one `Set`, so a document is
+/// rewritten by searching every pattern at once rather than one pass per
+/// pattern.
+///
+/// Matches are those of `Set::find_iter`: leftmost-longest across all
+/// patterns, ties going to the pattern added first, never overlapping, and
+/// never empty.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::redact::{Edit, Policy, Redactor};
+/// let digit = || (b'0'..=b'9').map(|b| Expr::literal(b as char)).reduce(Expr::union).unwrap();
+/// let mut redactor = Redactor::new();
+/// redactor.add(&digit().concat(digit().star()).build().unwrap(), Policy::Mask('#')).unwrap();
+/// let secret = Expr::word("secret");
+/// redactor.add(&secret.build().unwrap(), Policy::Drop).unwrap();
+/// let redaction = redactor.redact("card 4111, secret!");
+/// assert_eq!(redaction.text, "card ####, !");
+/// assert_eq!(
+///     redaction.edits[1],
+///     Edit { pattern: 1, original: (11, 17), redacted: (11, 11) }
+/// );
+/// ```
+#[derive(Debug)]
+pub struct Redactor {
+    set: Set,
+    policies: Vec<Policy>,
+}
+
+impl Default for Redactor {
+    fn default() -> Redactor {
+        Redactor::new()
+    }
+}
+
+impl Redactor {
+    pub fn new() -> Redactor {
+        Redactor {
+            set: Set::default(),
+            policies: Vec::new(),
+        }
+    }
+
+    /// Adds a pattern and returns its index. The machine must hold exactly
+    /// one automaton, and may use assertions, e.g. word boundaries.
+    pub fn add(&mut self, machine: &ANFA, policy: Policy) -> Result<usize, &'static str> {
+        let pattern = self.set.push(machine)?;
+        self.policies.push(policy);
+        Ok(pattern)
+    }
+
+    /// The number of patterns added
+    pub fn len(&self) -> usize {
+        self.policies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// Rewrites every match in `document` by the policy of its pattern, and
+    /// copies the text between matches unchanged
+    pub fn redact(&self, document: &str) -> Redaction {
+        let mut text = String::with_capacity(document.len());
+        let mut edits = Vec::new();
+        let mut copied = 0;
+        for found in self.set.find_iter(document) {
+            let (start, end) = found.span;
+            text.push_str(&document[copied..start]);
+            let redacted_start = text.len();
+            self.policies[found.pattern].apply(&document[start..end], &mut text);
+            edits.push(Edit {
+                pattern: found.pattern,
+                original: (start, end),
+                redacted: (redacted_start, text.len()),
+            });
+            copied = end;
+        }
+        text.push_str(&document[copied..]);
+        Redaction { text, edits }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::expr::Expr;
+    use crate::redact::{siphash24, Edit, Policy, Redactor};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_redact_policies() {
+        let mut redactor = Redactor::new();
+        assert_eq!(
+            redactor
+                .add(&Expr::word("ab").build().unwrap(), Policy::Mask('*'))
+                .unwrap(),
+            0
+        );
+        redactor
+            .add(&Expr::word("abc").build().unwrap(), Policy::Hash([7; 16]))
+            .unwrap();
+        redactor
+            .add(&Expr::word("b").build().unwrap(), Policy::Drop)
+            .unwrap();
+        redactor
+            .add(&Expr::word("é").build().unwrap(), Policy::Mask('_'))
+            .unwrap();
+        assert_eq!(redactor.len(), 4);
+
+        let redaction = redactor.redact("xabcyabébz");
+        let hashed = &redaction.text[1..17];
+        assert!(hashed.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(&redaction.text[17..], "y**_z", "Longest match wins");
+        assert_eq!(
+            redaction.edits,
+            vec![
+                Edit {
+                    pattern: 1,
+                    original: (1, 4),
+                    redacted: (1, 17)
+                },
+                Edit {
+                    pattern: 0,
+                    original: (5, 7),
+                    redacted: (18, 20)
+                },
+                Edit {
+                    pattern: 3,
+                    original: (7, 9),
+                    redacted: (20, 21)
+                },
+                Edit {
+                    pattern: 2,
+                    original: (9, 10),
+                    redacted: (21, 21)
+                },
+            ]
+        );
+        let again = redactor.redact("abc");
+        assert_eq!(again.text, hashed, "Equal texts hash alike");
+        let mut keyed = Redactor::new();
+        keyed
+            .add(&Expr::word("abc").build().unwrap(), Policy::Hash([8; 16]))
+            .unwrap();
+        assert_ne!(keyed.redact("abc").text, hashed);
+    }
+
+    #[test]
+    fn test_redact_ties_and_assertions() {
+        // \bid\b, and id+ which also matches idd
+        let bounded = Expr::assert(Assertion::WordBoundary)
+            .concat(Expr::literal('i'))
+            .concat(Expr::literal('d'))
+            .concat(Expr::assert(Assertion::WordBoundary));
+        let mut redactor = Redactor::new();
+        redactor
+            .add(&bounded.build().unwrap(), Policy::Mask('#'))
+            .unwrap();
+        redactor
+            .add(&Expr::word("id").build().unwrap(), Policy::Drop)
+            .unwrap();
+        let redaction = redactor.redact("id idx");
+        assert_eq!(redaction.text, "## x", "First pattern wins ties");
+        assert_eq!(redaction.edits[1].pattern, 1);
+
+        let mut empty = Redactor::new();
+        assert!(empty.is_empty());
+        assert_eq!(empty.redact("abc").text, "abc");
+        empty
+            .add(
+                &Expr::literal('a').star().build().unwrap(),
+                Policy::Mask('#'),
+            )
+            .unwrap();
+        assert_eq!(
+            empty.redact("baab").text,
+            "b##b",
+            "Empty matches are ignored"
+        );
+        let mut unfinished = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut unfinished, 'b').unwrap();
+        assert!(empty.add(&unfinished, Policy::Drop).is_err());
+    }
+
+    #[test]
+    fn test_siphash24() {
+        // the reference vectors, with key 00 01 .. 0f and message 00 01 ..
+        let mut key = [0; 16];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let message: Vec<u8> = (0..64).collect();
+        assert_eq!(siphash24(&key, &message[..0]), 0x726f_db47_dd0e_0e31);
+        assert_eq!(siphash24(&key, &message[..7]), 0xab02_00f5_8b01_d137);
+        assert_eq!(siphash24(&key, &message[..8]), 0x93f5_f579_9a93_2462);
+        assert_eq!(siphash24(&key, &message[..15]), 0xa129_ca61_49be_45e5);
+        assert_eq!(siphash24(&key, &message[..63]), 0x958a_324c_eb06_4572);
+    }
+
+    #[test]
+    fn test_original_offset() {
+        let mut redactor = Redactor::new();
+        redactor
+            .add(&Expr::word("xy").build().unwrap(), Policy::Mask('-'))
+            .unwrap();
+        redactor
+            .add(&Expr::word("z").build().unwrap(), Policy::Hash([0; 16]))
+            .unwrap();
+        let redaction = redactor.redact("axyzb");
+        // a--<16 hex digits>b
+        assert_eq!(redaction.text.len(), 20);
+        assert_eq!(redaction.original_offset(0), 0);
+        assert_eq!(redaction.original_offset(2), 1);
+        assert_eq!(redaction.original_offset(10), 3);
+        assert_eq!(redaction.original_offset(19), 4);
+        assert_eq!(redaction.original_offset(20), 5);
+    }
+}
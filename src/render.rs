@@ -0,0 +1,339 @@
+use crate::{QId, ANFA};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Write};
+
+/// Writes the label of the transition leaving `q` along `branch`: the symbol
+/// for labeled states, ε for a single ε transition, and the branch for unions
+fn write_label<S: Debug>(out: &mut String, anfa: &ANFA<S>, q: QId, branch: usize) -> fmt::Result {
+    match &anfa.delta[q] {
+        (Some(c), _) => write!(out, "{:?}", c),
+        (None, [Some(_), Some(_)]) => write!(out, "{}", branch),
+        (None, _) => out.write_str("ε"),
+    }
+}
+
+/// Writes `q` as drawn in the ASCII graphs of the compiler docs: `( q )`,
+/// `(( q ))` for f, and the assertion guarding `q` after its id
+fn write_state<S>(out: &mut String, anfa: &ANFA<S>, q: QId, f: QId) -> fmt::Result {
+    let (open, close) = if q == f { ("((", "))") } else { ("(", ")") };
+    write!(out, "{} {}", open, q)?;
+    if let Some(assertion) = anfa.assertions.get(&q) {
+        write!(out, " {}", assertion)?;
+    }
+    write!(out, " {}", close)
+}
+
+fn write_ascii<S: Debug>(anfa: &ANFA<S>, out: &mut String) -> fmt::Result {
+    // the state table, one row per state
+    let mut rows: Vec<[String; 4]> = Vec::with_capacity(anfa.delta.len());
+    for (q, (_label, q_nexts)) in anfa.delta.iter().enumerate() {
+        let mut label = String::new();
+        if q_nexts[0].is_some() {
+            match &anfa.delta[q] {
+                (Some(c), _) => write!(label, "{:?}", c)?,
+                (None, _) => label.push('ε'),
+            }
+        }
+        let mut next = String::new();
+        for (i, q_next) in q_nexts.iter().flatten().enumerate() {
+            if i > 0 {
+                next.push_str(", ");
+            }
+            write!(next, "{}", q_next)?;
+        }
+        let mut notes: Vec<String> = Vec::new();
+        for [q0, f] in anfa.automata_refs.iter() {
+            match (q == *q0, q == *f) {
+                (true, true) => notes.push(String::from("q0 = f")),
+                (true, false) => notes.push(String::from("q0")),
+                (false, true) => notes.push(String::from("f")),
+                (false, false) => {}
+            }
+        }
+        if let Some(assertion) = anfa.assertions.get(&q) {
+            let mut note = String::new();
+            write!(note, "{}", assertion)?;
+            notes.push(note);
+        }
+        let mut note = String::new();
+        if !notes.is_empty() {
+            write!(note, " ({})", notes.join(", "))?;
+        }
+        let mut id = String::new();
+        write!(id, "{}", q)?;
+        rows.push([id, label, next, note]);
+    }
+    let width = |column: usize| {
+        rows.iter()
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(1)
+    };
+    let widths = [width(0), width(1), width(2)];
+    writeln!(out, "State table:")?;
+    writeln!(
+        out,
+        "| {:w0$} | {:w1$} | {:w2$} |",
+        "Q",
+        "T",
+        "Q",
+        w0 = widths[0],
+        w1 = widths[1],
+        w2 = widths[2]
+    )?;
+    for width in widths.iter() {
+        write!(out, "|{}", "-".repeat(width + 2))?;
+    }
+    writeln!(out, "|")?;
+    for [id, label, next, note] in rows.iter() {
+        writeln!(
+            out,
+            "| {:w0$} | {:w1$} | {:w2$} |{}",
+            id,
+            label,
+            next,
+            note,
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2]
+        )?;
+    }
+
+    // one graph per automaton, each line a path that stops at a drawn state
+    for [q0, f] in anfa.automata_refs.iter() {
+        writeln!(out)?;
+        writeln!(out, "Graph:")?;
+        let mut drawn = vec![false; anfa.delta.len()];
+        // (state, branch of the line's first transition), the first line starts at q0
+        let mut lines: Vec<(QId, Option<usize>)> = vec![(*q0, None)];
+        let mut i = 0;
+        while i < lines.len() {
+            let (start, branch) = lines[i];
+            i += 1;
+            let mut q = start;
+            match branch {
+                None => {
+                    out.push_str("--> ");
+                    write_state(out, anfa, q, *f)?;
+                    drawn[q] = true;
+                }
+                Some(branch) => {
+                    write_state(out, anfa, q, *f)?;
+                    out.push_str(" -- ");
+                    write_label(out, anfa, q, branch)?;
+                    out.push_str(" --> ");
+                    q = match anfa.delta[q].1[branch] {
+                        Some(q_next) => q_next,
+                        None => continue,
+                    };
+                    write_state(out, anfa, q, *f)?;
+                    if drawn[q] {
+                        writeln!(out)?;
+                        continue;
+                    }
+                    drawn[q] = true;
+                }
+            }
+            while let Some(q_next) = anfa.delta[q].1[0] {
+                if anfa.delta[q].1[1].is_some() {
+                    lines.push((q, Some(1)));
+                }
+                out.push_str(" -- ");
+                write_label(out, anfa, q, 0)?;
+                out.push_str(" --> ");
+                write_state(out, anfa, q_next, *f)?;
+                if drawn[q_next] {
+                    break;
+                }
+                drawn[q_next] = true;
+                q = q_next;
+            }
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Escapes text inside a quoted Mermaid label
+fn escape_mermaid(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("#quot;"),
+            '#' => out.push_str("#35;"),
+            '<' => out.push_str("#lt;"),
+            '>' => out.push_str("#gt;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn write_mermaid<S: Debug>(anfa: &ANFA<S>, out: &mut String) -> fmt::Result {
+    writeln!(out, "flowchart LR")?;
+    for q in 0..anfa.delta.len() {
+        let is_final = anfa.automata_refs.iter().any(|[_q0, f]| *f == q);
+        let (open, close) = if is_final {
+            ("(((", ")))")
+        } else {
+            ("((", "))")
+        };
+        write!(out, "    q{}{}\"{}", q, open, q)?;
+        if let Some(assertion) = anfa.assertions.get(&q) {
+            let mut text = String::new();
+            write!(text, " {}", assertion)?;
+            escape_mermaid(out, &text);
+        }
+        writeln!(out, "\"{}", close)?;
+    }
+    for (i, [q0, _f]) in anfa.automata_refs.iter().enumerate() {
+        writeln!(out, "    start_{}[start] --> q{}", i, q0)?;
+    }
+    for (q, (_label, q_nexts)) in anfa.delta.iter().enumerate() {
+        for (branch, q_next) in q_nexts.iter().enumerate() {
+            if let Some(q_next) = q_next {
+                let mut label = String::new();
+                write_label(&mut label, anfa, q, branch)?;
+                write!(out, "    q{} -->|\"", q)?;
+                escape_mermaid(out, &label);
+                writeln!(out, "\"| q{}", q_next)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl<S: Copy + Eq + Debug> ANFA<S> {
+    /// Renders the automaton as the ASCII diagrams drawn in the compiler
+    /// docs: a state table of every state, then one graph per automaton on
+    /// `automata_refs`.
+    ///
+    /// The state table lists each state's label, `ε`, or nothing, and its
+    /// next states, marking every q0, f, and assertion. Each line of a graph
+    /// is a path of transitions, labeled by their symbol, `ε`, or the branch
+    /// of a union. A path ends at a state that is already drawn or has no
+    /// transitions, and every second branch of a union starts a new line.
+    /// Symbols are written with their `Debug` representation.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// assert_eq!(
+    ///     machine.to_ascii().unwrap(),
+    ///     "State table:
+    /// | Q | T   | Q |
+    /// |---|-----|---|
+    /// | 0 | 'a' | 1 | (q0)
+    /// | 1 | ε   | 2 |
+    /// | 2 | 'b' | 3 |
+    /// | 3 |     |   | (f)
+    ///
+    /// Graph:
+    /// --> ( 0 ) -- 'a' --> ( 1 ) -- ε --> ( 2 ) -- 'b' --> (( 3 ))
+    /// "
+    /// );
+    /// ```
+    pub fn to_ascii(&self) -> Result<String, &'static str> {
+        for automaton in self.automata_refs.iter() {
+            self.check_ref(*automaton)?;
+        }
+        let mut out = String::new();
+        write_ascii(self, &mut out).map_err(|_| "Failed to format ASCII output.")?;
+        Ok(out)
+    }
+
+    /// Renders the automaton as a Mermaid flowchart. States are circles,
+    /// every f is a double circle, and each q0 is entered from a `start` node.
+    /// Transitions are labeled like `to_ascii`.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(
+    ///     machine.to_mermaid().unwrap(),
+    ///     "flowchart LR
+    ///     q0((\"0\"))
+    ///     q1(((\"1\")))
+    ///     start_0[start] --> q0
+    ///     q0 -->|\"'a'\"| q1
+    /// "
+    /// );
+    /// ```
+    pub fn to_mermaid(&self) -> Result<String, &'static str> {
+        for automaton in self.automata_refs.iter() {
+            self.check_ref(*automaton)?;
+        }
+        let mut out = String::new();
+        write_mermaid(self, &mut out).map_err(|_| "Failed to format Mermaid output.")?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+
+    #[test]
+    fn test_to_ascii_star() {
+        // RE a*, as drawn in the docs of `ForwardCompiler::star`
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert_eq!(
+            machine.to_ascii().unwrap(),
+            "State table:
+| Q | T   | Q    |
+|---|-----|------|
+| 0 | 'a' | 1    |
+| 1 | ε   | 3    |
+| 2 | ε   | 3    | (q0)
+| 3 | ε   | 0, 4 |
+| 4 |     |      | (f)
+
+Graph:
+--> ( 2 ) -- ε --> ( 3 ) -- 0 --> ( 0 ) -- 'a' --> ( 1 ) -- ε --> ( 3 )
+( 3 ) -- 1 --> (( 4 ))
+"
+        );
+    }
+
+    #[test]
+    fn test_to_ascii_operands_and_assertions() {
+        // the operands of a concatenation, before it is applied
+        let mut machine = ForwardCompiler::from_expr_assert(Assertion::StartText).unwrap();
+        ForwardCompiler::expr_a(&mut machine, '"').unwrap();
+        let ascii = machine.to_ascii().unwrap();
+        assert!(ascii.contains("| 0 |     |   | (q0 = f, ^)\n"));
+        assert!(ascii.contains("Graph:\n--> (( 0 ^ ))\n\nGraph:\n--> ( 1 ) -- '\"' --> (( 2 ))\n"));
+
+        let mermaid = machine.to_mermaid().unwrap();
+        assert!(mermaid.contains("    q0(((\"0 ^\")))\n"));
+        assert!(mermaid.contains("    start_1[start] --> q1\n"));
+        assert!(
+            mermaid.contains("    q1 -->|\"'#quot;'\"| q2\n"),
+            "Quotes are escaped"
+        );
+    }
+
+    #[test]
+    fn test_render_union() {
+        // RE a+b
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        let ascii = machine.to_ascii().unwrap();
+        let graph = &ascii[ascii.find("Graph:\n").unwrap()..];
+        assert_eq!(
+            graph,
+            "Graph:
+--> ( 4 ) -- 0 --> ( 0 ) -- 'a' --> ( 1 ) -- ε --> (( 5 ))
+( 4 ) -- 1 --> ( 2 ) -- 'b' --> ( 3 ) -- ε --> (( 5 ))
+"
+        );
+        let mermaid = machine.to_mermaid().unwrap();
+        assert!(mermaid.contains("    q4 -->|\"0\"| q0\n    q4 -->|\"1\"| q2\n"));
+    }
+}
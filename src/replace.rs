@@ -0,0 +1,154 @@
+use crate::ANFA;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Produces the text that replaces a match, see `ANFA::replace_all`
+pub trait Replacer {
+    /// Appends the replacement for the match `haystack[span.0..span.1]` to `dst`
+    fn replace_append(&mut self, haystack: &str, span: (usize, usize), dst: &mut String);
+}
+
+/// A string replaces every match as is
+impl Replacer for &str {
+    fn replace_append(&mut self, _haystack: &str, _span: (usize, usize), dst: &mut String) {
+        dst.push_str(self);
+    }
+}
+
+/// A closure receives the matched text and its byte span and returns the replacement
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// // RE ab*
+/// let machine = Expr::literal('a').concat(Expr::literal('b').star()).build().unwrap();
+/// let replaced = machine.replace_all("xabbya", |text: &str, (start, _end): (usize, usize)| {
+///     format!("<{}@{}>", text.len(), start)
+/// });
+/// assert_eq!(replaced.unwrap(), "x<3@1>y<1@5>");
+/// ```
+impl<F: FnMut(&str, (usize, usize)) -> T, T: AsRef<str>> Replacer for F {
+    fn replace_append(&mut self, haystack: &str, span: (usize, usize), dst: &mut String) {
+        dst.push_str(self(&haystack[span.0..span.1], span).as_ref());
+    }
+}
+
+impl ANFA {
+    /// Replaces the leftmost match in `haystack`, see `replacen`
+    pub fn replace<R: Replacer>(
+        &self,
+        haystack: &str,
+        replacer: R,
+    ) -> Result<String, &'static str> {
+        self.replacen(haystack, 1, replacer)
+    }
+
+    /// Replaces every match in `haystack`, see `replacen`
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// let machine = Expr::literal('a').union(Expr::literal('e')).build().unwrap();
+    /// assert_eq!(machine.replace_all("banana bread", "_").unwrap(), "b_n_n_ br__d");
+    /// ```
+    pub fn replace_all<R: Replacer>(
+        &self,
+        haystack: &str,
+        replacer: R,
+    ) -> Result<String, &'static str> {
+        self.replacen(haystack, 0, replacer)
+    }
+
+    /// Replaces the first `limit` matches in `haystack`, or every match when
+    /// `limit` is 0. Matches are the successive non-overlapping matches of
+    /// `find_iter`, and the text between them is copied unchanged.
+    /// The ANFA must hold exactly one automaton.
+    pub fn replacen<R: Replacer>(
+        &self,
+        haystack: &str,
+        limit: usize,
+        mut replacer: R,
+    ) -> Result<String, &'static str> {
+        let mut replaced = String::with_capacity(haystack.len());
+        let mut copied = 0;
+        for (i, (start, end)) in self.find_iter(haystack)?.enumerate() {
+            if limit > 0 && i == limit {
+                break;
+            }
+            replaced.push_str(&haystack[copied..start]);
+            replacer.replace_append(haystack, (start, end), &mut replaced);
+            copied = end;
+        }
+        replaced.push_str(&haystack[copied..]);
+        Ok(replaced)
+    }
+
+    /// Returns the substrings of `haystack` between successive matches, see
+    /// `find_iter`. A haystack with `n` matches splits into `n + 1` pieces,
+    /// some possibly empty. The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE ,  *
+    /// let machine = Expr::literal(',').concat(Expr::literal(' ').star()).build().unwrap();
+    /// assert_eq!(machine.split("a, b,c,").unwrap(), vec!["a", "b", "c", ""]);
+    /// ```
+    pub fn split<'a>(&self, haystack: &'a str) -> Result<Vec<&'a str>, &'static str> {
+        let mut pieces = Vec::new();
+        let mut copied = 0;
+        for (start, end) in self.find_iter(haystack)? {
+            pieces.push(&haystack[copied..start]);
+            copied = end;
+        }
+        pieces.push(&haystack[copied..]);
+        Ok(pieces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::expr::Expr;
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+
+    #[test]
+    fn test_replace() {
+        // RE 0+1, digits of a binary number
+        let machine = Expr::literal('0')
+            .union(Expr::literal('1'))
+            .build()
+            .unwrap();
+        assert_eq!(machine.replace("a01b", "#").unwrap(), "a#1b");
+        assert_eq!(machine.replacen("a01b1", 2, "#").unwrap(), "a##b1");
+        assert_eq!(machine.replace_all("a01b1", "#").unwrap(), "a##b#");
+        assert_eq!(machine.replace_all("ab", "#").unwrap(), "ab");
+        let mut spans = vec![];
+        let flipped = machine
+            .replace_all("x10", |text: &str, span: (usize, usize)| {
+                spans.push(span);
+                if text == "0" {
+                    "1".to_string()
+                } else {
+                    String::from("0")
+                }
+            })
+            .unwrap();
+        assert_eq!(flipped, "x01");
+        assert_eq!(spans, vec![(1, 2), (2, 3)]);
+
+        // RE a*, empty matches insert between characters
+        let machine = Expr::literal('a').star().build().unwrap();
+        assert_eq!(machine.replace_all("baé", "-").unwrap(), "-b-é-");
+    }
+
+    #[test]
+    fn test_split() {
+        let machine = Expr::literal(';').build().unwrap();
+        assert_eq!(machine.split("a;b;;c").unwrap(), vec!["a", "b", "", "c"]);
+        assert_eq!(machine.split("").unwrap(), vec![""]);
+        assert_eq!(machine.split(";").unwrap(), vec!["", ""]);
+        let mut unfinished = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut unfinished, 'b').unwrap();
+        assert!(unfinished.split("ab").is_err());
+        assert!(unfinished.replace_all("ab", "").is_err());
+    }
+}
@@ -0,0 +1,341 @@
+use crate::assertion::Assertion;
+use crate::expr::Expr;
+use crate::set::Set;
+use crate::ANFA;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Characters allowed in a path segment by RFC 3986: `pchar` without the
+/// hex digits of percent-encodings, which are already alphanumeric
+const PCHARS: &str = "-._~%!$&'()*+,;=:@";
+
+/// A path segment: any `pchar`, see RFC 3986. Other characters must be
+/// percent-encoded in a valid path, so the class is finite.
+fn pchar() -> Expr {
+    ('a'..='z')
+        .chain('A'..='Z')
+        .chain('0'..='9')
+        .chain(PCHARS.chars())
+        .map(Expr::literal)
+        .reduce(Expr::union)
+        .unwrap_or_else(Expr::zero)
+}
+
+/// One `/`-separated part of a route pattern
+#[derive(Clone, Debug)]
+enum Segment {
+    Literal(String),
+    /// `:name`, one segment, optionally constrained by an expression
+    Param(String, Option<Expr>),
+    /// `*` or `*name`, the rest of the path, `/`s included
+    Rest(String),
+}
+
+impl Segment {
+    /// The name of a parameter
+    fn name(&self) -> Option<&str> {
+        match self {
+            Segment::Literal(_) => None,
+            Segment::Param(name, _) | Segment::Rest(name) => Some(name.as_str()),
+        }
+    }
+}
+
+/// A router pattern such as `/users/:id/posts/*`, compiled to an automaton
+/// that only ever matches whole paths.
+///
+/// - a literal segment matches itself
+/// - `:name` matches one non-empty segment of RFC 3986 path characters,
+///   never a `/`, or the expression given to `constrain`
+/// - `*name`, or `*` named `"*"`, matches the rest of the path, possibly
+///   empty and `/`s included, and must be the last segment
+///
+/// Because parameters never match a `/`, the segments of a matching path
+/// line up with those of the pattern, and each parameter captures the path
+/// segment at its position.
+///
+/// ```rust
+/// use regexxx::route::Route;
+/// let route = Route::parse("/users/:id/posts/*").unwrap();
+/// let params = route.captures("/users/42/posts/2024/05").unwrap();
+/// assert_eq!(params, vec![("id", "42"), ("*", "2024/05")]);
+/// assert_eq!(route.captures("/users/4/2/posts/x"), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Route {
+    segments: Vec<Segment>,
+    /// the compiled route, rebuilt by `constrain`
+    machine: ANFA,
+}
+
+impl Route {
+    /// Parses a route pattern, which must start with `/`. Parameter names
+    /// must be unique and non-empty, except for an unnamed `*`.
+    pub fn parse(pattern: &str) -> Result<Route, &'static str> {
+        let rest = match pattern.strip_prefix('/') {
+            Some(rest) => rest,
+            None => return Err("Routes must start with '/'."),
+        };
+        let mut segments = Vec::new();
+        let parts: Vec<&str> = rest.split('/').collect();
+        for (i, part) in parts.iter().enumerate() {
+            let segment = if let Some(name) = part.strip_prefix(':') {
+                if name.is_empty() {
+                    return Err("Route parameters must be named.");
+                }
+                Segment::Param(name.to_string(), None)
+            } else if let Some(name) = part.strip_prefix('*') {
+                if i + 1 != parts.len() {
+                    return Err("Route wildcards must be the last segment.");
+                }
+                Segment::Rest(if name.is_empty() { "*" } else { name }.to_string())
+            } else {
+                Segment::Literal(part.to_string())
+            };
+            segments.push(segment);
+        }
+        let names: Vec<&str> = segments.iter().filter_map(Segment::name).collect();
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].contains(name) {
+                return Err("Route parameter names must be unique.");
+            }
+        }
+        let machine = Route::compile(&segments).build()?;
+        Ok(Route { segments, machine })
+    }
+
+    /// The parameter names, in order
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.segments.iter().filter_map(Segment::name)
+    }
+
+    /// Restricts parameter `name` to the strings `expr` accepts, e.g. digits
+    /// only. `expr` must not read a `/`, so the parameter stays one segment.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::route::Route;
+    /// let digit = || Expr::literal('0').union(Expr::literal('1'));
+    /// let route = Route::parse("/users/:id").unwrap().constrain("id", digit().concat(digit().star())).unwrap();
+    /// assert!(route.captures("/users/101").is_some());
+    /// assert!(route.captures("/users/me").is_none());
+    /// ```
+    pub fn constrain(mut self, name: &str, expr: Expr) -> Result<Route, &'static str> {
+        let machine = expr.build()?;
+        if machine.delta.iter().any(|(label, _)| *label == Some('/')) {
+            return Err("Route constraints must not read '/'.");
+        }
+        match self.segments.iter_mut().find(|segment| match segment {
+            Segment::Param(param, _) => param == name,
+            _ => false,
+        }) {
+            Some(Segment::Param(_, constraint)) => *constraint = Some(expr),
+            _ => return Err("Route has no parameter with this name."),
+        }
+        self.machine = self.build()?;
+        Ok(self)
+    }
+
+    /// The expression of the route, anchored at both ends, for composing
+    /// with other expressions
+    pub fn to_expr(&self) -> Expr {
+        Route::compile(&self.segments)
+    }
+
+    /// The expression of a route made of `segments`, see `to_expr`
+    fn compile(segments: &[Segment]) -> Expr {
+        let mut expr = Expr::assert(Assertion::StartText);
+        for segment in segments.iter() {
+            expr = expr.concat(Expr::literal('/'));
+            expr = match segment {
+                Segment::Literal(literal) => {
+                    literal.chars().map(Expr::literal).fold(expr, Expr::concat)
+                }
+                Segment::Param(_, Some(constraint)) => expr.concat(constraint.clone()),
+                Segment::Param(_, None) => expr.concat(pchar()).concat(pchar().star()),
+                Segment::Rest(_) => expr.concat(pchar().union(Expr::literal('/')).star()),
+            };
+        }
+        expr.concat(Expr::assert(Assertion::EndText))
+    }
+
+    /// Compiles the route anew, see `to_expr`
+    pub fn build(&self) -> Result<ANFA, &'static str> {
+        self.to_expr().build()
+    }
+
+    /// Splits a path the route matches into its parameters, without checking
+    /// the match
+    fn split<'p>(&self, path: &'p str) -> Option<Vec<(&str, &'p str)>> {
+        let mut params = Vec::new();
+        let mut rest = path.strip_prefix('/')?;
+        for segment in self.segments.iter() {
+            let (part, after) = match segment {
+                Segment::Rest(name) => {
+                    params.push((name.as_str(), rest));
+                    return Some(params);
+                }
+                _ => match rest.find('/') {
+                    Some(at) => (&rest[..at], &rest[at + 1..]),
+                    None => (rest, ""),
+                },
+            };
+            if let Segment::Param(name, _) = segment {
+                params.push((name.as_str(), part));
+            }
+            rest = after;
+        }
+        Some(params)
+    }
+
+    /// Returns the parameters of `path` in order when the route matches it.
+    /// `path` must not include a query string or fragment.
+    pub fn captures<'p>(&self, path: &'p str) -> Option<Vec<(&str, &'p str)>> {
+        if !self.machine.is_match(path).ok()? {
+            return None;
+        }
+        self.split(path)
+    }
+}
+
+/// A route that matched, see `Router::at`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RouteMatch<'r, 'p> {
+    /// The index of the route, in the order added
+    pub route: usize,
+    pub params: Vec<(&'r str, &'p str)>,
+}
+
+/// Routes compiled into one `Set`, so a path is matched against every route
+/// in a single pass. Where several routes match, the first one added wins,
+/// so specific routes go before general ones. Each route is copied into the
+/// set once, when it is added.
+///
+/// ```rust
+/// use regexxx::route::Router;
+/// let mut router = Router::new();
+/// router.add("/users/me").unwrap();
+/// router.add("/users/:id").unwrap();
+/// router.add("/static/*path").unwrap();
+/// assert_eq!(router.at("/users/me").unwrap().route, 0);
+/// let found = router.at("/users/42").unwrap();
+/// assert_eq!((found.route, found.params), (1, vec![("id", "42")]));
+/// assert_eq!(router.at("/static/css/a.css").unwrap().params, vec![("path", "css/a.css")]);
+/// assert!(router.at("/users/42/x").is_none());
+/// ```
+#[derive(Debug, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    set: Option<Set>,
+}
+
+impl Router {
+    pub fn new() -> Router {
+        Router::default()
+    }
+
+    /// Parses and adds a route pattern, see `Route`, and returns its index
+    pub fn add(&mut self, pattern: &str) -> Result<usize, &'static str> {
+        self.add_route(Route::parse(pattern)?)
+    }
+
+    /// Adds a route, e.g. one with constraints, and returns its index
+    pub fn add_route(&mut self, route: Route) -> Result<usize, &'static str> {
+        match &mut self.set {
+            Some(set) => {
+                set.push(&route.machine)?;
+            }
+            None => self.set = Some(Set::new(&[&route.machine])?),
+        }
+        self.routes.push(route);
+        Ok(self.routes.len() - 1)
+    }
+
+    /// The route at `index`
+    pub fn route(&self, index: usize) -> Option<&Route> {
+        self.routes.get(index)
+    }
+
+    /// Returns the first route matching the whole of `path`, and its
+    /// parameters. `path` must not include a query string or fragment.
+    pub fn at<'r, 'p>(&'r self, path: &'p str) -> Option<RouteMatch<'r, 'p>> {
+        let matches = self.set.as_ref()?.matches(path).ok()?;
+        let route = matches.iter().next()?;
+        Some(RouteMatch {
+            route,
+            params: self.routes[route].split(path)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::Expr;
+    use crate::route::{Route, Router};
+    use alloc::vec;
+
+    #[test]
+    fn test_captures() {
+        let route = Route::parse("/a/:x/b/:y").unwrap();
+        assert_eq!(
+            route.names().collect::<alloc::vec::Vec<_>>(),
+            vec!["x", "y"]
+        );
+        assert_eq!(
+            route.captures("/a/1/b/%20~"),
+            Some(vec![("x", "1"), ("y", "%20~")])
+        );
+        assert_eq!(route.captures("/a//b/2"), None, "Parameters are non-empty");
+        assert_eq!(route.captures("/a/1/b/2/"), None);
+        assert_eq!(
+            route.captures("/a/é/b/2"),
+            None,
+            "Paths are percent-encoded"
+        );
+
+        let rest = Route::parse("/files/*").unwrap();
+        assert_eq!(rest.captures("/files/"), Some(vec![("*", "")]));
+        assert_eq!(rest.captures("/files/a/b/"), Some(vec![("*", "a/b/")]));
+        assert_eq!(rest.captures("/files"), None);
+
+        let root = Route::parse("/").unwrap();
+        assert_eq!(root.captures("/"), Some(vec![]));
+        assert_eq!(root.captures("/x"), None);
+        assert_eq!(root.captures(""), None);
+    }
+
+    #[test]
+    fn test_route_errors() {
+        assert!(Route::parse("users").is_err());
+        assert!(Route::parse("/users/:").is_err());
+        assert!(Route::parse("/*/users").is_err());
+        assert!(Route::parse("/:id/:id").is_err());
+        let route = Route::parse("/users/:id").unwrap();
+        assert!(route.clone().constrain("name", Expr::literal('a')).is_err());
+        assert!(route.constrain("id", Expr::literal('/')).is_err());
+    }
+
+    #[test]
+    fn test_router_composes_with_expressions() {
+        // RE /v(1+2)/... written as an expression, then routed with the others
+        let version = Expr::literal('1').union(Expr::literal('2'));
+        let versioned = Route::parse("/:version/status")
+            .unwrap()
+            .constrain("version", Expr::literal('v').concat(version))
+            .unwrap();
+        let mut router = Router::new();
+        assert!(
+            router.at("/v1/status").is_none(),
+            "Empty routers match nothing"
+        );
+        router.add_route(versioned).unwrap();
+        router.add("/:anything/status").unwrap();
+        assert_eq!(router.at("/v2/status").unwrap().route, 0);
+        assert_eq!(router.at("/v3/status").unwrap().route, 1);
+        assert_eq!(
+            router.at("/v3/status").unwrap().params,
+            vec![("anything", "v3")]
+        );
+        assert!(router.route(1).is_some());
+    }
+}
@@ -0,0 +1,698 @@
+use crate::dfa::DFA;
+use crate::{QId, ANFA};
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use rand::Rng;
+
+/// Weighting controls for `ANFA::sample_with`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampleOptions {
+    /// Probability of taking the branch of a union that loops back to the
+    /// union, e.g. repeating the body of a star. Lower values terminate loops
+    /// sooner and produce shorter words.
+    pub repeat_probability: f64,
+    /// Probability of taking the 0 branch of a union that does not loop
+    pub left_probability: f64,
+}
+
+impl Default for SampleOptions {
+    fn default() -> SampleOptions {
+        SampleOptions {
+            repeat_probability: 0.5,
+            left_probability: 0.5,
+        }
+    }
+}
+
+/// Minimum number of symbols and of transitions from every state to `f`,
+/// computed by breadth-first searches over the reversed transitions.
+fn distances_to_final<S>(anfa: &ANFA<S>, f: QId) -> (Vec<Option<usize>>, Vec<Option<usize>>) {
+    let mut predecessors: Vec<Vec<QId>> = vec![Vec::new(); anfa.delta.len()];
+    for (q, (_label, q_nexts)) in anfa.delta.iter().enumerate() {
+        for q_next in q_nexts.iter().flatten() {
+            predecessors[*q_next].push(q);
+        }
+    }
+    // 0-1 search: ε transitions cost no symbols
+    let mut symbols: Vec<Option<usize>> = vec![None; anfa.delta.len()];
+    let mut deque = VecDeque::new();
+    symbols[f] = Some(0);
+    deque.push_back(f);
+    while let Some(q) = deque.pop_front() {
+        let d = symbols[q].unwrap_or(0);
+        for q_prev in predecessors[q].iter() {
+            let cost = if anfa.delta[*q_prev].0.is_some() {
+                1
+            } else {
+                0
+            };
+            if symbols[*q_prev].is_none_or(|d_prev| d + cost < d_prev) {
+                symbols[*q_prev] = Some(d + cost);
+                if cost == 0 {
+                    deque.push_front(*q_prev);
+                } else {
+                    deque.push_back(*q_prev);
+                }
+            }
+        }
+    }
+    let mut steps: Vec<Option<usize>> = vec![None; anfa.delta.len()];
+    steps[f] = Some(0);
+    deque.push_back(f);
+    while let Some(q) = deque.pop_front() {
+        let d = steps[q].unwrap_or(0);
+        for q_prev in predecessors[q].iter() {
+            if steps[*q_prev].is_none() {
+                steps[*q_prev] = Some(d + 1);
+                deque.push_back(*q_prev);
+            }
+        }
+    }
+    (symbols, steps)
+}
+
+/// Returns true when `q` can be reached from `from`
+fn reaches<S>(anfa: &ANFA<S>, from: QId, q: QId) -> bool {
+    let mut seen = vec![false; anfa.delta.len()];
+    let mut stack = vec![from];
+    while let Some(q_next) = stack.pop() {
+        if q_next == q {
+            return true;
+        }
+        if seen[q_next] {
+            continue;
+        }
+        seen[q_next] = true;
+        stack.extend(anfa.delta[q_next].1.iter().flatten());
+    }
+    false
+}
+
+/// `counts[r][q]` is the number of words of exactly `r` symbols that lead
+/// from `q` to an accepting state of `dfa`, for every `r <= max_len`
+fn count_words<S: Copy + Eq>(dfa: &DFA<S>, max_len: usize) -> Result<Vec<Vec<u128>>, &'static str> {
+    let mut counts: Vec<Vec<u128>> = vec![dfa
+        .finals
+        .iter()
+        .map(|is_final| *is_final as u128)
+        .collect()];
+    for r in 1..=max_len {
+        let mut row = Vec::with_capacity(dfa.delta.len());
+        for transitions in dfa.delta.iter() {
+            let mut count: u128 = 0;
+            for (_label, q_next) in transitions.iter() {
+                count = count
+                    .checked_add(counts[r - 1][*q_next])
+                    .ok_or("Too many words to count.")?;
+            }
+            row.push(count);
+        }
+        counts.push(row);
+    }
+    Ok(counts)
+}
+
+/// Reads the `index`th word of exactly `len` symbols from q0, in the order of
+/// `dfa`'s transitions. `index` must be less than `counts[len][dfa.q0]`.
+fn nth_word<S: Copy + Eq>(
+    dfa: &DFA<S>,
+    counts: &[Vec<u128>],
+    len: usize,
+    mut index: u128,
+) -> Vec<S> {
+    let mut word = Vec::with_capacity(len);
+    let mut q = dfa.q0;
+    for r in (1..=len).rev() {
+        for (label, q_next) in dfa.delta[q].iter() {
+            if index < counts[r - 1][*q_next] {
+                word.push(*label);
+                q = *q_next;
+                break;
+            }
+            index -= counts[r - 1][*q_next];
+        }
+    }
+    word
+}
+
+impl<S: Copy + Eq> ANFA<S> {
+    /// Walks the automaton from q0 to f choosing union branches at random and
+    /// returns the symbols read along the way, which is always an accepted word
+    /// of at most `max_len` symbols. Returns `None` when no accepted word is
+    /// that short. See `sample_with` to weight the choices.
+    /// The ANFA must hold exactly one automaton.
+    pub fn sample<R: Rng>(
+        &self,
+        rng: &mut R,
+        max_len: usize,
+    ) -> Result<Option<Vec<S>>, &'static str> {
+        self.sample_with(rng, max_len, &SampleOptions::default())
+    }
+
+    /// Like `sample`, weighting union branches by `options`.
+    ///
+    /// Branches that cannot reach f, or that need more symbols than remain of
+    /// `max_len`, are never taken. Walks that run far longer than the automaton
+    /// without finishing take the branch closest to f, so sampling terminates
+    /// even for loops that read nothing, like `1*`.
+    pub fn sample_with<R: Rng>(
+        &self,
+        rng: &mut R,
+        max_len: usize,
+        options: &SampleOptions,
+    ) -> Result<Option<Vec<S>>, &'static str> {
+        // NaN is in no range
+        if !(0.0..=1.0).contains(&options.repeat_probability) {
+            return Err("Sampling repeat probability must be in [0, 1].");
+        }
+        if !(0.0..=1.0).contains(&options.left_probability) {
+            return Err("Sampling left probability must be in [0, 1].");
+        }
+        let [machine_q0, machine_f] = match self.automata_refs.as_slice() {
+            [machine] => *machine,
+            _ => {
+                return Err("Sampling requires exactly one automaton.");
+            }
+        };
+        if !self.assertions.is_empty() {
+            return Err("Sampling does not support assertions.");
+        }
+        let (symbols, steps) = distances_to_final(self, machine_f);
+        if symbols[machine_q0].is_none_or(|d| d > max_len) {
+            return Ok(None);
+        }
+        let patience = self
+            .delta
+            .len()
+            .saturating_mul(max_len + 2)
+            .saturating_mul(4);
+        let mut word = Vec::new();
+        let mut q = machine_q0;
+        let mut walked = 0usize;
+        while q != machine_f {
+            walked += 1;
+            let remaining = max_len - word.len();
+            let (label, q_nexts) = &self.delta[q];
+            let cost = if label.is_some() { 1 } else { 0 };
+            let feasible = |q_next: &QId| symbols[*q_next].is_some_and(|d| d + cost <= remaining);
+            let branches: Vec<QId> = q_nexts.iter().flatten().copied().filter(feasible).collect();
+            let q_next = match branches.as_slice() {
+                [] => return Err("Sampling walked into a state that cannot reach f."),
+                [q_next] => *q_next,
+                [left, right] if walked > patience => {
+                    if steps[*left] <= steps[*right] {
+                        *left
+                    } else {
+                        *right
+                    }
+                }
+                [left, right] => {
+                    let left_loops = reaches(self, *left, q);
+                    let right_loops = reaches(self, *right, q);
+                    let take_left = if left_loops && !right_loops {
+                        rng.gen_bool(options.repeat_probability)
+                    } else if right_loops && !left_loops {
+                        !rng.gen_bool(options.repeat_probability)
+                    } else {
+                        rng.gen_bool(options.left_probability)
+                    };
+                    if take_left {
+                        *left
+                    } else {
+                        *right
+                    }
+                }
+                _ => return Err("A state has at most two transitions."),
+            };
+            if let Some(c) = label {
+                word.push(*c);
+            }
+            q = q_next;
+        }
+        Ok(Some(word))
+    }
+
+    /// Returns an accepted word of exactly `len` symbols, every such word
+    /// being equally likely, or `None` when there is none.
+    ///
+    /// Random walks like `sample` favor words that need few branch choices,
+    /// usually short ones. Here the words of each length leaving every state
+    /// of the minimal DFA are counted first, so each transition is taken in
+    /// proportion to the number of words it leads to. Counting takes
+    /// `O(len × transitions)` time and fails when a state has more than
+    /// `u128::MAX` words of some length. The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE (a+b)*c
+    /// let machine = Expr::literal('a').union(Expr::literal('b')).star().concat(Expr::literal('c')).build().unwrap();
+    /// let mut rng = rand::rngs::mock::StepRng::new(0, 0x9e37_79b9_7f4a_7c15);
+    /// let word = machine.sample_uniform(&mut rng, 4).unwrap().unwrap();
+    /// assert_eq!(word.len(), 4);
+    /// assert_eq!(word[3], 'c');
+    /// ```
+    pub fn sample_uniform<R: Rng>(
+        &self,
+        rng: &mut R,
+        len: usize,
+    ) -> Result<Option<Vec<S>>, &'static str> {
+        let dfa = self.determinize()?.minimize();
+        let counts = count_words(&dfa, len)?;
+        let total = counts[len][dfa.q0];
+        if total == 0 {
+            return Ok(None);
+        }
+        Ok(Some(nth_word(&dfa, &counts, len, rng.gen_range(0..total))))
+    }
+
+    /// Returns an accepted word of at most `max_len` symbols, every such word
+    /// being equally likely, or `None` when there is none. Lengths are weighted
+    /// by their number of words, so long words dominate languages that grow
+    /// with length. See `sample_uniform`.
+    pub fn sample_uniform_up_to<R: Rng>(
+        &self,
+        rng: &mut R,
+        max_len: usize,
+    ) -> Result<Option<Vec<S>>, &'static str> {
+        let dfa = self.determinize()?.minimize();
+        let counts = count_words(&dfa, max_len)?;
+        let mut total: u128 = 0;
+        for row in counts.iter() {
+            total = total
+                .checked_add(row[dfa.q0])
+                .ok_or("Too many words to count.")?;
+        }
+        if total == 0 {
+            return Ok(None);
+        }
+        let mut index = rng.gen_range(0..total);
+        for (len, row) in counts.iter().enumerate() {
+            if index < row[dfa.q0] {
+                return Ok(Some(nth_word(&dfa, &counts, len, index)));
+            }
+            index -= row[dfa.q0];
+        }
+        Err("Sampling index exceeded the word count.")
+    }
+}
+
+/// The choices example words made in one state after reading one symbol
+#[derive(Debug)]
+struct Context<S> {
+    /// The symbol read before entering the state, `None` at the start of a word
+    previous: Option<S>,
+    /// `transitions[i]` counts the words that then took `dfa.delta[q][i]`
+    transitions: Vec<usize>,
+    /// Counts the words that ended here
+    stops: usize,
+}
+
+/// Transition weights learned from example words, see `ANFA::markov`.
+///
+/// Every state of the minimal DFA counts, for each symbol read just before
+/// entering it, how often the examples left it along each of its transitions
+/// and how often they ended in it. Sampling walks the DFA choosing in
+/// proportion to the counts of the current state and previous symbol, like a
+/// first-order Markov chain over symbols that never leaves the language. When
+/// the examples never entered a state after the previous symbol, the counts of
+/// every previous symbol of that state are used instead.
+#[derive(Debug)]
+pub struct Markov<S = char> {
+    dfa: DFA<S>,
+    /// `contexts[q]` lists the previous symbols seen in state `q`
+    contexts: Vec<Vec<Context<S>>>,
+    /// Added to every count, so choices the examples never made stay possible
+    smoothing: f64,
+}
+
+impl<S: Copy + Eq> ANFA<S> {
+    /// Learns transition weights from `examples`, words that must each be
+    /// accepted by the automaton. `smoothing` is added to every count and must
+    /// be finite and not negative; with `0.0` only choices seen in the
+    /// examples are made, unless none of them fits in the remaining length.
+    /// The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE (0+1+2)*, learned from words that never repeat a digit
+    /// let digit = || Expr::literal('0').union(Expr::literal('1')).union(Expr::literal('2'));
+    /// let machine = digit().star().build().unwrap();
+    /// let markov = machine.markov(["01", "12", "2010"].iter().map(|word| word.chars()), 0.0).unwrap();
+    /// let mut rng = rand::rngs::mock::StepRng::new(0, 0x9e37_79b9_7f4a_7c15);
+    /// for _ in 0..10 {
+    ///     let word = markov.sample(&mut rng, 5).unwrap();
+    ///     assert!(word.windows(2).all(|pair| pair[0] != pair[1]));
+    /// }
+    /// ```
+    pub fn markov<I: IntoIterator<Item = W>, W: IntoIterator<Item = S>>(
+        &self,
+        examples: I,
+        smoothing: f64,
+    ) -> Result<Markov<S>, &'static str> {
+        // NaN is in no range
+        if !(0.0..f64::INFINITY).contains(&smoothing) {
+            return Err("Markov smoothing must be finite and not negative.");
+        }
+        let dfa = self.determinize()?.minimize();
+        let mut contexts: Vec<Vec<Context<S>>> = dfa.delta.iter().map(|_| Vec::new()).collect();
+        let context = |contexts: &mut Vec<Vec<Context<S>>>, q: QId, previous: Option<S>| {
+            let position = contexts[q]
+                .iter()
+                .position(|context| context.previous == previous);
+            match position {
+                Some(i) => i,
+                None => {
+                    contexts[q].push(Context {
+                        previous,
+                        transitions: vec![0; dfa.delta[q].len()],
+                        stops: 0,
+                    });
+                    contexts[q].len() - 1
+                }
+            }
+        };
+        for example in examples {
+            let mut q = dfa.q0;
+            let mut previous = None;
+            for c in example {
+                let i = dfa.delta[q]
+                    .iter()
+                    .position(|(label, _q_next)| *label == c)
+                    .ok_or("An example is not accepted by the automaton.")?;
+                let j = context(&mut contexts, q, previous);
+                contexts[q][j].transitions[i] += 1;
+                q = dfa.delta[q][i].1;
+                previous = Some(c);
+            }
+            if !dfa.finals[q] {
+                return Err("An example is not accepted by the automaton.");
+            }
+            let j = context(&mut contexts, q, previous);
+            contexts[q][j].stops += 1;
+        }
+        Ok(Markov {
+            dfa,
+            contexts,
+            smoothing,
+        })
+    }
+}
+
+impl<S: Copy + Eq> Markov<S> {
+    /// The counts of the choices at `q` after `previous`: (stops, transitions)
+    fn counts(&self, q: QId, previous: Option<S>) -> (usize, Vec<usize>) {
+        let contexts = &self.contexts[q];
+        match contexts.iter().find(|context| context.previous == previous) {
+            Some(context) => (context.stops, context.transitions.clone()),
+            None => {
+                let mut transitions = vec![0; self.dfa.delta[q].len()];
+                let mut stops = 0;
+                for context in contexts.iter() {
+                    stops += context.stops;
+                    for (total, count) in transitions.iter_mut().zip(context.transitions.iter()) {
+                        *total += count;
+                    }
+                }
+                (stops, transitions)
+            }
+        }
+    }
+
+    /// Returns an accepted word of at most `max_len` symbols, or `None` when
+    /// no accepted word is that short. Choices that cannot finish within
+    /// `max_len` are never made.
+    pub fn sample<R: Rng>(&self, rng: &mut R, max_len: usize) -> Option<Vec<S>> {
+        // fewest symbols from each state to an accepting state
+        let mut distance: Vec<Option<usize>> = self
+            .dfa
+            .finals
+            .iter()
+            .map(|is_final| if *is_final { Some(0) } else { None })
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (q, transitions) in self.dfa.delta.iter().enumerate() {
+                for (_label, q_next) in transitions.iter() {
+                    if let Some(d) = distance[*q_next] {
+                        if distance[q].is_none_or(|d_q| d + 1 < d_q) {
+                            distance[q] = Some(d + 1);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+        if distance[self.dfa.q0].is_none_or(|d| d > max_len) {
+            return None;
+        }
+        let mut word: Vec<S> = Vec::new();
+        let mut q = self.dfa.q0;
+        loop {
+            let remaining = max_len - word.len();
+            let transitions = &self.dfa.delta[q];
+            let (stops, counts) = self.counts(q, word.last().copied());
+            // (weight, transition index), None stops at q
+            let mut choices: Vec<(f64, Option<usize>)> = Vec::new();
+            if self.dfa.finals[q] {
+                choices.push((stops as f64 + self.smoothing, None));
+            }
+            for (i, (_label, q_next)) in transitions.iter().enumerate() {
+                if distance[*q_next].is_some_and(|d| d < remaining) {
+                    choices.push((counts[i] as f64 + self.smoothing, Some(i)));
+                }
+            }
+            let total: f64 = choices.iter().map(|(weight, _choice)| weight).sum();
+            let choice = if total > 0.0 {
+                let mut x = rng.gen_range(0.0..total);
+                let mut chosen = choices[choices.len() - 1].1;
+                for (weight, choice) in choices.iter() {
+                    if x < *weight {
+                        chosen = *choice;
+                        break;
+                    }
+                    x -= weight;
+                }
+                chosen
+            } else {
+                // the examples made none of the feasible choices
+                choices[rng.gen_range(0..choices.len())].1
+            };
+            match choice {
+                None => return Some(word),
+                Some(i) => {
+                    let (label, q_next) = transitions[i];
+                    word.push(label);
+                    q = q_next;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::Expr;
+    use crate::sample::SampleOptions;
+    use alloc::vec;
+    use rand::{Error, RngCore};
+
+    /// xorshift64, deterministic so tests are reproducible
+    struct TestRng(u64);
+
+    impl RngCore for TestRng {
+        fn next_u32(&mut self) -> u32 {
+            (self.next_u64() >> 32) as u32
+        }
+        fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for chunk in dest.chunks_mut(8) {
+                let bytes = self.next_u64().to_le_bytes();
+                chunk.copy_from_slice(&bytes[..chunk.len()]);
+            }
+        }
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        let machine = Expr::parse("(a+b)*c").unwrap().build().unwrap();
+        let dfa = machine.determinize().unwrap();
+        let mut rng = TestRng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..200 {
+            let word = machine.sample(&mut rng, 6).unwrap().unwrap();
+            assert!(word.len() <= 6, "Samples respect max_len");
+            assert!(dfa.accepts(word.iter().copied()), "Samples are accepted");
+        }
+        assert_eq!(
+            machine.sample(&mut rng, 0).unwrap(),
+            None,
+            "(a+b)*c has no word shorter than one symbol"
+        );
+        let nothing = Expr::parse("0").unwrap().build().unwrap();
+        assert_eq!(nothing.sample(&mut rng, 10).unwrap(), None);
+    }
+
+    #[test]
+    fn test_sample_repeat_probability() {
+        let machine = Expr::parse("(a+b)*c").unwrap().build().unwrap();
+        let mut rng = TestRng(0x9e37_79b9_7f4a_7c15);
+        let never = SampleOptions {
+            repeat_probability: 0.0,
+            ..SampleOptions::default()
+        };
+        for _ in 0..20 {
+            assert_eq!(
+                machine.sample_with(&mut rng, 100, &never).unwrap(),
+                Some(vec!['c']),
+                "Loops are never repeated"
+            );
+        }
+        let always = SampleOptions {
+            repeat_probability: 1.0,
+            ..SampleOptions::default()
+        };
+        for _ in 0..20 {
+            let word = machine.sample_with(&mut rng, 8, &always).unwrap().unwrap();
+            assert_eq!(word.len(), 8, "Loops repeat until only max_len remains");
+        }
+    }
+
+    #[test]
+    fn test_sample_invalid_probabilities() {
+        let machine = Expr::parse("(a+b)*c").unwrap().build().unwrap();
+        let mut rng = TestRng(0x9e37_79b9_7f4a_7c15);
+        for p in [-0.1, 1.5, f64::NAN, f64::INFINITY].iter() {
+            let repeat = SampleOptions {
+                repeat_probability: *p,
+                ..SampleOptions::default()
+            };
+            assert!(machine.sample_with(&mut rng, 10, &repeat).is_err(), "{}", p);
+            let left = SampleOptions {
+                left_probability: *p,
+                ..SampleOptions::default()
+            };
+            assert!(machine.sample_with(&mut rng, 10, &left).is_err(), "{}", p);
+        }
+    }
+
+    #[test]
+    fn test_sample_uniform() {
+        // RE a*b+ccc, a walk reads "ccc" half the time but it is one of 2 words of length 3
+        let machine = Expr::parse("a*b+ccc").unwrap().build().unwrap();
+        let mut rng = TestRng(0x2545_f491_4f6c_dd1d);
+        let mut ccc = 0;
+        for _ in 0..1000 {
+            let word = machine.sample_uniform(&mut rng, 3).unwrap().unwrap();
+            if word == vec!['c', 'c', 'c'] {
+                ccc += 1;
+            } else {
+                assert_eq!(word, vec!['a', 'a', 'b']);
+            }
+        }
+        assert!((400..600).contains(&ccc), "ccc and aab are equally likely");
+        assert_eq!(machine.sample_uniform(&mut rng, 0).unwrap(), None);
+        // 1 + 1 + 2 words of at most 3 symbols: b, ab, aab, ccc
+        let mut b = 0;
+        for _ in 0..1000 {
+            let word = machine.sample_uniform_up_to(&mut rng, 3).unwrap().unwrap();
+            if word == vec!['b'] {
+                b += 1;
+            }
+        }
+        assert!((180..320).contains(&b), "b is one of four words");
+        let nothing = Expr::parse("0").unwrap().build().unwrap();
+        assert_eq!(nothing.sample_uniform_up_to(&mut rng, 5).unwrap(), None);
+    }
+
+    #[test]
+    fn test_sample_uniform_overflow() {
+        // RE (a+b)*, 2^200 words of 200 symbols
+        let machine = Expr::parse("(a+b)*").unwrap().build().unwrap();
+        let mut rng = TestRng(7);
+        assert_eq!(
+            machine
+                .sample_uniform(&mut rng, 100)
+                .unwrap()
+                .unwrap()
+                .len(),
+            100
+        );
+        assert!(machine.sample_uniform(&mut rng, 200).is_err());
+    }
+
+    #[test]
+    fn test_markov() {
+        let machine = Expr::parse("(a+b)*c").unwrap().build().unwrap();
+        let examples = ["abc", "ababc", "abababc"];
+        let markov = machine
+            .markov(examples.iter().map(|word| word.chars()), 0.0)
+            .unwrap();
+        let mut rng = TestRng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..50 {
+            let word = markov.sample(&mut rng, 9).unwrap();
+            assert_eq!(
+                word.len() % 2,
+                1,
+                "Examples only ever read 'b' after 'a' and 'a' after 'b'"
+            );
+            for pair in word[..word.len() - 1].chunks(2) {
+                assert_eq!(pair, ['a', 'b']);
+            }
+        }
+        assert_eq!(
+            markov.sample(&mut rng, 1).unwrap(),
+            vec!['c'],
+            "Unseen choices are made when nothing else fits"
+        );
+        assert_eq!(markov.sample(&mut rng, 0), None);
+        let smoothed = machine
+            .markov(examples.iter().map(|word| word.chars()), 1.0)
+            .unwrap();
+        let mut seen_bb = false;
+        for _ in 0..200 {
+            let word = smoothed.sample(&mut rng, 9).unwrap();
+            seen_bb |= word.windows(2).any(|pair| pair == ['b', 'b']);
+        }
+        assert!(seen_bb, "Smoothing keeps unseen choices possible");
+        assert!(
+            machine
+                .markov(["ab"].iter().map(|word| word.chars()), 0.0)
+                .is_err(),
+            "Examples must be accepted"
+        );
+        for smoothing in [-1.0, f64::INFINITY, f64::NAN].iter() {
+            assert!(
+                machine
+                    .markov(examples.iter().map(|word| word.chars()), *smoothing)
+                    .is_err(),
+                "{}",
+                smoothing
+            );
+        }
+    }
+
+    #[test]
+    fn test_sample_epsilon_loop() {
+        let machine = Expr::parse("1*").unwrap().build().unwrap();
+        let mut rng = TestRng(1);
+        let always = SampleOptions {
+            repeat_probability: 1.0,
+            ..SampleOptions::default()
+        };
+        assert_eq!(
+            machine.sample_with(&mut rng, 3, &always).unwrap(),
+            Some(vec![]),
+            "Loops that read nothing still terminate"
+        );
+    }
+}
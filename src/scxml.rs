@@ -0,0 +1,122 @@
+use crate::dfa::DFA;
+use crate::ANFA;
+use alloc::string::String;
+use core::fmt::{self, Display, Write};
+
+/// Writes `c` as an SCXML event name. Event names are tokens where `.`
+/// separates name segments, so anything but ASCII letters, digits, `_`, and
+/// `-` is written as `_u{hex}_`, e.g. the symbol `.` is the event `_u2e_`.
+fn write_event<S: Display>(out: &mut String, c: &S) -> fmt::Result {
+    let mut symbol = String::new();
+    write!(symbol, "{}", c)?;
+    for c in symbol.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+            out.push(c);
+        } else {
+            write!(out, "_u{:x}_", c as u32)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_scxml<S: Copy + Eq + Display>(dfa: &DFA<S>, out: &mut String) -> fmt::Result {
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        out,
+        "<scxml xmlns=\"http://www.w3.org/2005/07/scxml\" xmlns:regexxx=\"https://github.com/bever1337/regexxx\" version=\"1.0\" initial=\"q{}\">",
+        dfa.q0
+    )?;
+    for (q, transitions) in dfa.delta.iter().enumerate() {
+        let accepting = if dfa.finals[q] {
+            " regexxx:accepting=\"true\""
+        } else {
+            ""
+        };
+        if dfa.finals[q] && transitions.is_empty() {
+            writeln!(out, "  <final id=\"q{}\"{}/>", q, accepting)?;
+            continue;
+        }
+        writeln!(out, "  <state id=\"q{}\"{}>", q, accepting)?;
+        for (label, q_next) in transitions.iter() {
+            write!(out, "    <transition event=\"")?;
+            write_event(out, label)?;
+            writeln!(out, "\" target=\"q{}\"/>", q_next)?;
+        }
+        // transitions are selected in document order, so the wildcard only catches the rest
+        writeln!(out, "    <transition event=\"*\" target=\"reject\"/>")?;
+        writeln!(out, "  </state>")?;
+    }
+    writeln!(out, "  <state id=\"reject\"/>")?;
+    writeln!(out, "</scxml>")
+}
+
+impl<S: Copy + Eq + Display> ANFA<S> {
+    /// Exports the automaton as an SCXML statechart.
+    ///
+    /// Statecharts are deterministic, so the automaton is determinized first.
+    /// Each symbol is an event named by its `Display` output, where anything
+    /// but ASCII letters, digits, `_`, and `-` is escaped as `_u{hex}_`. Accepting states are marked with
+    /// `regexxx:accepting="true"`, and accepting states without outgoing
+    /// transitions are `<final>` states. Any unexpected event moves the chart
+    /// to the `reject` state, which ignores all further events.
+    /// The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// let scxml = machine.to_scxml().unwrap();
+    /// assert!(scxml.contains("<transition event=\"a\" target=\"q1\"/>"));
+    /// ```
+    pub fn to_scxml(&self) -> Result<String, &'static str> {
+        let dfa = self.determinize()?;
+        let mut out = String::new();
+        write_scxml(&dfa, &mut out).map_err(|_| "Failed to format SCXML output.")?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+
+    #[test]
+    fn test_to_scxml() {
+        // RE a*.
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, '.').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let scxml = machine.to_scxml().unwrap();
+        assert!(
+            scxml.contains("version=\"1.0\" initial=\"q0\">"),
+            "The determinized q0 is the initial state"
+        );
+        assert!(
+            scxml.contains(
+                "  <state id=\"q0\">\n    <transition event=\"a\" target=\"q1\"/>\n    <transition event=\"_u2e_\" target=\"q2\"/>\n    <transition event=\"*\" target=\"reject\"/>\n  </state>"
+            ),
+            "Symbols are events and unexpected events reject"
+        );
+        assert!(
+            scxml.contains("<final id=\"q2\" regexxx:accepting=\"true\"/>"),
+            "Accepting states without transitions are final"
+        );
+        assert!(scxml.contains("<state id=\"reject\"/>"));
+    }
+
+    #[test]
+    fn test_to_scxml_accepting_state() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        let scxml = machine.to_scxml().unwrap();
+        assert!(
+            scxml.contains("<state id=\"q0\" regexxx:accepting=\"true\">"),
+            "Accepting states with transitions are marked accepting"
+        );
+        let mut unfinished = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut unfinished, 'b').unwrap();
+        assert!(unfinished.to_scxml().is_err());
+    }
+}
@@ -0,0 +1,821 @@
+use crate::case::case_variants;
+use crate::tiny;
+use crate::{QId, ANFA};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Flags applied by `ANFA::find_with` when searching, independent of how the
+/// automaton was compiled, so one compiled machine serves every combination
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Labels match any of their case variants, like compiling every literal
+    /// with `case::expr_a_ci`, see `case::case_variants`
+    pub case_insensitive: bool,
+    /// `^` and `$` also match after and before a `\n`,
+    /// see `Assertion::holds_multi_line`
+    pub multi_line: bool,
+    /// Matches must start where the search starts
+    pub anchored_start: bool,
+    /// Matches must end at the end of the haystack
+    pub anchored_end: bool,
+    /// Which of the matches is reported, see `MatchKind`
+    pub match_kind: MatchKind,
+}
+
+/// Which match a search reports among the matches of the haystack
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchKind {
+    /// The leftmost match preferred by union branch 0 and greedy stars,
+    /// like backtracking engines
+    #[default]
+    LeftmostFirst,
+    /// The leftmost match, and the longest of those, like POSIX
+    LeftmostLongest,
+    /// The match that ends first, reported as soon as it is seen. Its start
+    /// is that of the preferred thread ending there.
+    Earliest,
+}
+
+/// Threads of the simulation in priority order, each a state, the position
+/// its match started at, and whether it is a match in f rather than a state
+/// waiting to read a symbol
+pub(crate) struct Threads {
+    list: Vec<(QId, usize, bool)>,
+    /// the step each state was last added in
+    seen: Vec<usize>,
+    step: usize,
+}
+
+impl Threads {
+    pub(crate) fn new(state_count: usize) -> Threads {
+        Threads {
+            list: Vec::new(),
+            seen: vec![0; state_count],
+            step: 1,
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.list.clear();
+        self.step += 1;
+    }
+}
+
+/// Adds the threads of the ε-closure of `q` in priority order, i.e. branch 0
+/// of a union before branch 1. Guarded states are only entered when their
+/// assertion holds between `before` and `after`.
+#[allow(clippy::too_many_arguments)]
+fn add_thread(
+    anfa: &ANFA,
+    f: QId,
+    threads: &mut Threads,
+    q: QId,
+    start: usize,
+    before: Option<char>,
+    after: Option<char>,
+    options: &SearchOptions,
+) {
+    // (state, whether the state was expanded and f only needs to be matched)
+    let mut stack = vec![(q, false)];
+    while let Some((q, expanded)) = stack.pop() {
+        if expanded {
+            threads.list.push((q, start, true));
+            continue;
+        }
+        if threads.seen[q] == threads.step {
+            continue;
+        }
+        threads.seen[q] = threads.step;
+        if let Some(assertion) = anfa.assertions.get(&q) {
+            let holds = if options.multi_line {
+                assertion.holds_multi_line(before, after)
+            } else {
+                assertion.holds(before, after)
+            };
+            if !holds {
+                continue;
+            }
+        }
+        if q == f {
+            // transitions leaving f are preferred over matching, like a greedy star
+            stack.push((q, true));
+        }
+        match anfa.delta[q] {
+            (None, [Some(q_left), Some(q_right)]) => {
+                stack.push((q_right, false));
+                stack.push((q_left, false));
+            }
+            (None, [Some(q_next), None]) => stack.push((q_next, false)),
+            (Some(_), _) => threads.list.push((q, start, false)),
+            (None, _) => {}
+        }
+    }
+}
+
+impl ANFA {
+    /// Returns the byte span of the leftmost match in `haystack`, or `None`.
+    ///
+    /// The automaton is simulated on all threads at once, so the search runs
+    /// in time proportional to the length of the haystack times the number of
+    /// states. A new thread starts at every position until a match is found.
+    /// Threads are kept in the order of union branches, so among matches
+    /// starting at the leftmost position the one preferred by branch 0 wins,
+    /// e.g. `a*` prefers longer matches and `(a+ab)` matches `a` in `ab`.
+    /// Assertions are evaluated against the characters around each position.
+    /// The ANFA must hold exactly one automaton.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// // RE bc*
+    /// let mut machine = ForwardCompiler::from_expr_a('b').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+    /// ForwardCompiler::star(&mut machine).unwrap();
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// assert_eq!(machine.find("abccd").unwrap(), Some((1, 4)));
+    /// ```
+    pub fn find(&self, haystack: &str) -> Result<Option<(usize, usize)>, &'static str> {
+        self.find_with(haystack, &SearchOptions::default())
+    }
+
+    /// Like `find`, with `options` applied during the search.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::search::SearchOptions;
+    /// use regexxx::assertion::Assertion;
+    /// // RE ^ab
+    /// let machine = Expr::assert(Assertion::StartText).concat(Expr::literal('a')).concat(Expr::literal('b')).build().unwrap();
+    /// assert_eq!(machine.find("x\nAB").unwrap(), None);
+    /// let options = SearchOptions { case_insensitive: true, multi_line: true, ..SearchOptions::default() };
+    /// assert_eq!(machine.find_with("x\nAB", &options).unwrap(), Some((2, 4)));
+    /// ```
+    ///
+    /// Anchoring both ends accepts only the whole haystack, and the match kind
+    /// picks among overlapping matches.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::search::{MatchKind, SearchOptions};
+    /// // RE a+ab
+    /// let machine = Expr::literal('a').union(Expr::literal('a').concat(Expr::literal('b'))).build().unwrap();
+    /// let full = SearchOptions { anchored_start: true, anchored_end: true, ..SearchOptions::default() };
+    /// assert_eq!(machine.find_with("ab", &full).unwrap(), Some((0, 2)));
+    /// assert_eq!(machine.find_with("abb", &full).unwrap(), None);
+    /// assert_eq!(machine.find("ab").unwrap(), Some((0, 1)));
+    /// let longest = SearchOptions { match_kind: MatchKind::LeftmostLongest, ..SearchOptions::default() };
+    /// assert_eq!(machine.find_with("ab", &longest).unwrap(), Some((0, 2)));
+    /// ```
+    pub fn find_with(
+        &self,
+        haystack: &str,
+        options: &SearchOptions,
+    ) -> Result<Option<(usize, usize)>, &'static str> {
+        self.find_at(haystack, 0, options)
+    }
+
+    /// Like `find_with`, ignoring matches that start before byte offset
+    /// `start`. Assertions still see the character before `start`, so `^`
+    /// does not match there unless `start` is 0. `start` must be a char
+    /// boundary of `haystack`, any other offset is an error rather than a
+    /// search starting inside a UTF-8 sequence.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::search::SearchOptions;
+    /// let machine = Expr::literal('a').build().unwrap();
+    /// assert_eq!(machine.find_at("aba", 1, &SearchOptions::default()).unwrap(), Some((2, 3)));
+    /// ```
+    pub fn find_at(
+        &self,
+        haystack: &str,
+        start: usize,
+        options: &SearchOptions,
+    ) -> Result<Option<(usize, usize)>, &'static str> {
+        let [machine_q0, machine_f] = match self.automata_refs.as_slice() {
+            [machine] => *machine,
+            _ => {
+                return Err("Search requires exactly one automaton.");
+            }
+        };
+        if !haystack.is_char_boundary(start) {
+            return Err("Search must start at a char boundary.");
+        }
+        let labels = self.search_labels(options);
+        let mut current = Threads::new(self.delta.len());
+        let mut next = Threads::new(self.delta.len());
+        Ok(self.search(
+            [machine_q0, machine_f],
+            haystack,
+            start,
+            options,
+            &labels,
+            &mut current,
+            &mut next,
+        ))
+    }
+
+    /// Every symbol each labeled state reads under `options`, its own label
+    /// first
+    pub(crate) fn search_labels(&self, options: &SearchOptions) -> Vec<Vec<char>> {
+        self.delta
+            .iter()
+            .map(|(label, _q_nexts)| match label {
+                Some(c) if options.case_insensitive => case_variants(*c),
+                Some(c) => vec![*c],
+                None => Vec::new(),
+            })
+            .collect()
+    }
+
+    /// The simulation behind `find_at`, on the automaton `machine` with the
+    /// labels of `search_labels` and thread lists kept by the caller, so
+    /// repeated searches don't allocate them again. `start` must be a char
+    /// boundary.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn search(
+        &self,
+        machine: [QId; 2],
+        haystack: &str,
+        start: usize,
+        options: &SearchOptions,
+        labels: &[Vec<char>],
+        current: &mut Threads,
+        next: &mut Threads,
+    ) -> Option<(usize, usize)> {
+        let [machine_q0, machine_f] = machine;
+        // lists may be left over from a search that returned early
+        current.clear();
+        next.clear();
+        let mut matched = None;
+        let mut at = start;
+        let mut before = haystack[..start].chars().next_back();
+        loop {
+            let after = haystack[at..].chars().next();
+            if matched.is_none() && (!options.anchored_start || at == start) {
+                // lowest priority, threads that started earlier are preferred
+                add_thread(
+                    self, machine_f, current, machine_q0, at, before, after, options,
+                );
+            } else if current.list.is_empty() {
+                break;
+            }
+            let after_next = after.and_then(|c| haystack[at + c.len_utf8()..].chars().next());
+            for (q, start, is_match) in current.list.iter() {
+                #[cfg(test)]
+                crate::engine::steps::count();
+                if *is_match {
+                    if options.anchored_end && after.is_some() {
+                        continue;
+                    }
+                    match options.match_kind {
+                        MatchKind::LeftmostFirst => {
+                            // threads after this one have lower priority
+                            matched = Some((*start, at));
+                            break;
+                        }
+                        MatchKind::LeftmostLongest => {
+                            // threads are ordered by start, later matches are longer
+                            if matched.is_none_or(|(matched_start, _)| *start <= matched_start) {
+                                matched = Some((*start, at));
+                            }
+                            continue;
+                        }
+                        MatchKind::Earliest => return Some((*start, at)),
+                    }
+                }
+                if matched.is_some_and(|(matched_start, _)| *start > matched_start) {
+                    // only reachable by leftmost-longest, which can't prefer these
+                    continue;
+                }
+                if let (Some(_c), [Some(q_next), _]) = self.delta[*q] {
+                    if after.is_some_and(|after| labels[*q].contains(&after)) {
+                        add_thread(
+                            self, machine_f, next, q_next, *start, after, after_next, options,
+                        );
+                    }
+                }
+            }
+            core::mem::swap(current, next);
+            next.clear();
+            match after {
+                None => break,
+                Some(c) => {
+                    at += c.len_utf8();
+                    before = Some(c);
+                }
+            }
+        }
+        matched
+    }
+
+    /// Iterates over the successive non-overlapping matches in `haystack`,
+    /// each found as by `find_at` where the previous one ended. After an empty
+    /// match the search resumes one character later, and an empty match
+    /// right where the previous match ended is skipped.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE a*
+    /// let machine = Expr::literal('a').star().build().unwrap();
+    /// let matches: Vec<_> = machine.find_iter("baab").unwrap().collect();
+    /// assert_eq!(matches, vec![(0, 0), (1, 3), (4, 4)]);
+    /// ```
+    pub fn find_iter<'a>(&'a self, haystack: &'a str) -> Result<Matches<'a>, &'static str> {
+        self.find_iter_with(haystack, &SearchOptions::default())
+    }
+
+    /// Like `find_iter`, with `options` applied during the search
+    pub fn find_iter_with<'a>(
+        &'a self,
+        haystack: &'a str,
+        options: &SearchOptions,
+    ) -> Result<Matches<'a>, &'static str> {
+        let machine = match self.automata_refs.as_slice() {
+            [machine] => *machine,
+            _ => {
+                return Err("Search requires exactly one automaton.");
+            }
+        };
+        Ok(Matches {
+            anfa: self,
+            machine,
+            haystack,
+            options: *options,
+            labels: self.search_labels(options),
+            current: Threads::new(self.delta.len()),
+            next: Threads::new(self.delta.len()),
+            at: Some(0),
+            last_end: None,
+        })
+    }
+
+    /// Returns true when some substring of `haystack` is accepted, see `find`.
+    /// Machines of at most `tiny::MAX_STATES` states are matched without
+    /// allocating, see `is_match_tiny`.
+    pub fn is_match(&self, haystack: &str) -> Result<bool, &'static str> {
+        if self.delta.len() <= tiny::MAX_STATES {
+            return self.is_match_tiny(haystack);
+        }
+        Ok(self.find(haystack)?.is_some())
+    }
+
+    /// Returns true when some substring of `haystack` is accepted, see `find_with`.
+    pub fn is_match_with(
+        &self,
+        haystack: &str,
+        options: &SearchOptions,
+    ) -> Result<bool, &'static str> {
+        Ok(self.find_with(haystack, options)?.is_some())
+    }
+}
+
+/// The char boundary one code point after the boundary `at`, or `None` at
+/// the end of `haystack`
+fn next_boundary(haystack: &str, at: usize) -> Option<usize> {
+    haystack[at..].chars().next().map(|c| at + c.len_utf8())
+}
+
+/// The successive non-overlapping matches of an ANFA, see `ANFA::find_iter`
+///
+/// Every search starts strictly after the previous one, so iteration always
+/// terminates, whatever the pattern:
+///
+/// - after a match `(start, end)` with `start < end`, the next search starts at `end`
+/// - after an empty match `(end, end)`, the next search starts one character
+///   after `end`, or iteration ends when `end` is the end of the haystack.
+///   A character is a whole code point, so every search starts, and every
+///   match starts and ends, at a char boundary. A combining mark is its own
+///   code point, so an empty match may fall between it and its base.
+/// - an empty match at the end of the previous match is skipped, so matches
+///   never overlap and never repeat
+///
+/// A haystack of `n` characters thus yields at most `n + 1` matches, with
+/// strictly increasing ends, after at most `n + 1` searches plus one per
+/// skipped empty match. Once `next` returns `None`, it always does.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// // RE a*, empty at every position of bbb
+/// let machine = Expr::literal('a').star().build().unwrap();
+/// let matches: Vec<_> = machine.find_iter("bbb").unwrap().collect();
+/// assert_eq!(matches, vec![(0, 0), (1, 1), (2, 2), (3, 3)]);
+/// ```
+pub struct Matches<'a> {
+    anfa: &'a ANFA,
+    machine: [QId; 2],
+    haystack: &'a str,
+    options: SearchOptions,
+    /// the labels and thread lists of `ANFA::search`, reused by every search
+    labels: Vec<Vec<char>>,
+    current: Threads,
+    next: Threads,
+    /// where the next search starts, `None` once the haystack is exhausted
+    at: Option<usize>,
+    /// the end of the previous match
+    last_end: Option<usize>,
+}
+
+impl Iterator for Matches<'_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        loop {
+            let at = self.at?;
+            // `at` is always a char boundary
+            let (start, end) = match self.anfa.search(
+                self.machine,
+                self.haystack,
+                at,
+                &self.options,
+                &self.labels,
+                &mut self.current,
+                &mut self.next,
+            ) {
+                Some(span) => span,
+                None => {
+                    self.at = None;
+                    return None;
+                }
+            };
+            self.at = if start == end {
+                next_boundary(self.haystack, end)
+            } else {
+                Some(end)
+            };
+            if start == end && self.last_end == Some(end) {
+                continue;
+            }
+            self.last_end = Some(end);
+            return Some((start, end));
+        }
+    }
+}
+
+impl core::iter::FusedIterator for Matches<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::{Assertion, WordKind};
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::expr::Expr;
+    use crate::search::{MatchKind, SearchOptions};
+    use alloc::string::ToString;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_find() {
+        let machine = Expr::word("ab").build().unwrap();
+        assert_eq!(machine.find("xxabab").unwrap(), Some((2, 4)));
+        assert_eq!(machine.find("xxa").unwrap(), None);
+        assert_eq!(
+            Expr::word("é").build().unwrap().find("café").unwrap(),
+            Some((3, 5)),
+            "Spans are in bytes"
+        );
+
+        // RE a+ab, branch 0 is preferred
+        let mut machine = Expr::word("a").build().unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert_eq!(machine.find("ab").unwrap(), Some((0, 1)));
+
+        // RE a*, empty matches are found
+        let mut machine = Expr::word("a").build().unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert_eq!(machine.find("baa").unwrap(), Some((0, 0)));
+        assert_eq!(machine.find("aab").unwrap(), Some((0, 2)));
+    }
+
+    #[test]
+    fn test_find_anchors() {
+        // RE ^ab$
+        let mut machine = ForwardCompiler::from_expr_assert(Assertion::StartText).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_assert(&mut machine, Assertion::EndText).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match("ab").unwrap());
+        assert!(!machine.is_match("xab").unwrap());
+        assert!(!machine.is_match("abx").unwrap());
+        assert!(!machine.is_match("").unwrap());
+    }
+
+    #[test]
+    fn test_find_word_boundary() {
+        // RE \bcat\b
+        let mut machine = ForwardCompiler::from_expr_assert(Assertion::WordBoundary).unwrap();
+        for c in "cat".chars() {
+            ForwardCompiler::expr_a(&mut machine, c).unwrap();
+            ForwardCompiler::concatenate(&mut machine).unwrap();
+        }
+        ForwardCompiler::expr_assert(&mut machine, Assertion::WordBoundary).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert_eq!(machine.find("concat cat.").unwrap(), Some((7, 10)));
+        assert_eq!(machine.find("concatenate").unwrap(), None);
+
+        // RE \Ba
+        let mut machine = ForwardCompiler::from_expr_assert(Assertion::NotWordBoundary).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert_eq!(machine.find("a ba").unwrap(), Some((3, 4)));
+    }
+
+    #[test]
+    fn test_find_word_kind() {
+        // RE \bé, é is a word character only in Unicode
+        let unicode = Expr::assert(Assertion::WordBoundary)
+            .concat(Expr::literal('é'))
+            .build()
+            .unwrap();
+        let mut ascii = Expr::assert(Assertion::AsciiWordBoundary)
+            .concat(Expr::literal('é'))
+            .build()
+            .unwrap();
+        assert_eq!(unicode.find("xé é").unwrap(), Some((4, 6)));
+        assert_eq!(ascii.find("xé é").unwrap(), Some((1, 3)));
+        assert!(!ascii.is_match(" é").unwrap());
+        assert!(ascii.is_match_tiny("xé").unwrap(), "Every engine agrees");
+        assert!(!unicode.is_match_tiny("xé").unwrap());
+
+        // switching the whole pattern
+        ascii.set_word_kind(WordKind::Unicode);
+        assert_eq!(ascii.assertions, unicode.assertions);
+        assert_eq!(ascii.find("xé é").unwrap(), Some((4, 6)));
+        ascii.set_word_kind(WordKind::Ascii);
+        assert_eq!(
+            ascii.assertions.values().next(),
+            Some(&Assertion::AsciiWordBoundary)
+        );
+
+        // RE (?-u:\w)(?-u:\B)
+        let machine = Expr::ascii_word()
+            .concat(Expr::assert(Assertion::AsciiNotWordBoundary))
+            .build()
+            .unwrap();
+        assert_eq!(machine.find("a b_").unwrap(), Some((2, 3)));
+        assert_eq!(machine.find("aé").unwrap(), None);
+        assert_eq!(
+            Expr::<char>::assert(Assertion::AsciiWordBoundary).to_string(),
+            "(?-u:\\b)"
+        );
+    }
+
+    #[test]
+    fn test_find_with_options() {
+        // RE ^ab$
+        let mut machine = ForwardCompiler::from_expr_assert(Assertion::StartText).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_assert(&mut machine, Assertion::EndText).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let haystack = "xy\nAb\nab\nz";
+        let case_insensitive = SearchOptions {
+            case_insensitive: true,
+            ..SearchOptions::default()
+        };
+        let multi_line = SearchOptions {
+            multi_line: true,
+            ..SearchOptions::default()
+        };
+        let both = SearchOptions {
+            case_insensitive: true,
+            multi_line: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(machine.find(haystack).unwrap(), None);
+        assert_eq!(
+            machine.find_with(haystack, &case_insensitive).unwrap(),
+            None
+        );
+        assert_eq!(
+            machine.find_with(haystack, &multi_line).unwrap(),
+            Some((6, 8))
+        );
+        assert_eq!(machine.find_with(haystack, &both).unwrap(), Some((3, 5)));
+        assert!(machine.is_match_with("AB", &case_insensitive).unwrap());
+        assert!(
+            !machine.is_match("AB").unwrap(),
+            "Flags do not change the machine"
+        );
+    }
+
+    #[test]
+    fn test_find_iter() {
+        let machine = Expr::word("ab").build().unwrap();
+        let matches: Vec<_> = machine.find_iter("abxabab").unwrap().collect();
+        assert_eq!(matches, vec![(0, 2), (3, 5), (5, 7)]);
+        assert_eq!(machine.find_iter("").unwrap().count(), 0);
+
+        // RE a*, an empty match right after a match is skipped
+        let mut machine = Expr::word("a").build().unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        let matches: Vec<_> = machine.find_iter("aaébaa").unwrap().collect();
+        assert_eq!(matches, vec![(0, 2), (4, 4), (5, 7)]);
+
+        // RE ^a, ^ only holds at the start of the haystack
+        let mut machine = ForwardCompiler::from_expr_assert(Assertion::StartText).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let matches: Vec<_> = machine.find_iter("aaa").unwrap().collect();
+        assert_eq!(matches, vec![(0, 1)]);
+    }
+
+    #[test]
+    fn test_find_iter_progress() {
+        // patterns whose every match may be empty
+        let star = || Expr::literal('a').star();
+        let patterns = [
+            star(),
+            Expr::one(),
+            Expr::assert(Assertion::WordBoundary),
+            Expr::assert(Assertion::StartText).union(Expr::assert(Assertion::EndText)),
+            star().union(Expr::literal('b').concat(Expr::literal('b'))),
+        ];
+        let haystacks = ["", "bbb", "aba", "a b", "ébé", "bbbb"];
+        let kinds = [
+            MatchKind::LeftmostFirst,
+            MatchKind::LeftmostLongest,
+            MatchKind::Earliest,
+        ];
+        for expr in patterns.iter() {
+            let machine = expr.build().unwrap();
+            for haystack in haystacks.iter() {
+                for match_kind in kinds.iter() {
+                    let options = SearchOptions {
+                        match_kind: *match_kind,
+                        ..SearchOptions::default()
+                    };
+                    let mut matches = machine.find_iter_with(haystack, &options).unwrap();
+                    let mut last_end = None;
+                    for _ in 0..=haystack.chars().count() {
+                        let (start, end) = match matches.next() {
+                            Some(span) => span,
+                            None => break,
+                        };
+                        assert!(start <= end && haystack.is_char_boundary(end));
+                        assert!(
+                            last_end.is_none_or(|last_end| last_end <= start && last_end < end),
+                            "{} on {:?} repeated a match",
+                            expr,
+                            haystack
+                        );
+                        last_end = Some(end);
+                    }
+                    assert_eq!(matches.next(), None, "{} on {:?}", expr, haystack);
+                    assert_eq!(matches.next(), None, "Exhausted iterators stay exhausted");
+                }
+            }
+        }
+        let machine = star().build().unwrap();
+        let matches: Vec<_> = machine.find_iter("bbb").unwrap().collect();
+        assert_eq!(matches, vec![(0, 0), (1, 1), (2, 2), (3, 3)]);
+        assert_eq!(machine.replace_all("bbb", "-").unwrap(), "-b-b-b-");
+        assert_eq!(machine.split("bbb").unwrap(), vec!["", "b", "b", "b", ""]);
+    }
+
+    #[test]
+    fn test_find_iter_multi_byte() {
+        // é is 2 bytes, ह is 3 bytes, 😀 is 4 bytes
+        let haystack = "éह😀a😀";
+        let machine = Expr::literal('a').star().build().unwrap();
+        let matches: Vec<_> = machine.find_iter(haystack).unwrap().collect();
+        assert_eq!(matches, vec![(0, 0), (2, 2), (5, 5), (9, 10), (14, 14)]);
+        assert_eq!(machine.replace_all(haystack, "-").unwrap(), "-é-ह-😀-😀-");
+        assert_eq!(
+            machine.split(haystack).unwrap(),
+            vec!["", "é", "ह", "😀", "😀", ""]
+        );
+
+        // e followed by a combining acute accent, two code points
+        let combining = "e\u{301}";
+        let matches: Vec<_> = machine.find_iter(combining).unwrap().collect();
+        assert_eq!(matches, vec![(0, 0), (1, 1), (3, 3)]);
+
+        // RE 😀, matched by the other engines at the same boundaries
+        let machine = Expr::literal('😀').build().unwrap();
+        let matches: Vec<_> = machine.find_iter(haystack).unwrap().collect();
+        assert_eq!(matches, vec![(5, 9), (10, 14)]);
+        assert_eq!(machine.to_dense().unwrap().find(haystack), Some((5, 9)));
+        let hybrid = crate::hybrid::Regex::new(&machine).unwrap();
+        assert_eq!(hybrid.find(haystack), Some((5, 9)));
+
+        let options = SearchOptions::default();
+        assert_eq!(
+            machine.find_at(haystack, 9, &options).unwrap(),
+            Some((10, 14))
+        );
+        for inside in [1, 3, 4, 6, 7, 8, 11].iter() {
+            assert!(
+                machine.find_at(haystack, *inside, &options).is_err(),
+                "{} is inside a UTF-8 sequence",
+                inside
+            );
+        }
+        assert!(machine
+            .find_at(haystack, haystack.len() + 1, &options)
+            .is_err());
+    }
+
+    #[test]
+    fn test_find_with_match_kind() {
+        // RE (a+b)*b
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let with = |match_kind| SearchOptions {
+            match_kind,
+            ..SearchOptions::default()
+        };
+        let haystack = "xabbab";
+        assert_eq!(machine.find(haystack).unwrap(), Some((1, 6)));
+        assert_eq!(
+            machine
+                .find_with(haystack, &with(MatchKind::LeftmostLongest))
+                .unwrap(),
+            Some((1, 6))
+        );
+        assert_eq!(
+            machine
+                .find_with(haystack, &with(MatchKind::Earliest))
+                .unwrap(),
+            Some((1, 3))
+        );
+
+        // RE a+ab, the longest of the leftmost matches
+        let mut machine = Expr::word("a").build().unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert_eq!(machine.find("zabc").unwrap(), Some((1, 2)));
+        assert_eq!(
+            machine
+                .find_with("zabc", &with(MatchKind::LeftmostLongest))
+                .unwrap(),
+            Some((1, 3))
+        );
+        assert_eq!(
+            machine
+                .find_with("zabc", &with(MatchKind::Earliest))
+                .unwrap(),
+            Some((1, 2))
+        );
+    }
+
+    #[test]
+    fn test_find_with_anchors() {
+        let machine = Expr::word("ab").build().unwrap();
+        let anchored_start = SearchOptions {
+            anchored_start: true,
+            ..SearchOptions::default()
+        };
+        let anchored_end = SearchOptions {
+            anchored_end: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(machine.find_with("xab", &anchored_start).unwrap(), None);
+        assert_eq!(
+            machine.find_with("abab", &anchored_start).unwrap(),
+            Some((0, 2))
+        );
+        assert_eq!(
+            machine.find_at("xab", 1, &anchored_start).unwrap(),
+            Some((1, 3))
+        );
+        assert_eq!(machine.find_with("abx", &anchored_end).unwrap(), None);
+        assert_eq!(
+            machine.find_with("abab", &anchored_end).unwrap(),
+            Some((2, 4))
+        );
+        let matches: Vec<_> = machine
+            .find_iter_with("ababxab", &anchored_start)
+            .unwrap()
+            .collect();
+        assert_eq!(matches, vec![(0, 2), (2, 4)]);
+
+        // RE a*, a greedy star still ends at the end when anchored
+        let mut machine = Expr::word("a").build().unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert_eq!(
+            machine.find_with("baa", &anchored_end).unwrap(),
+            Some((1, 3))
+        );
+    }
+
+    #[test]
+    fn test_find_requires_one_automaton() {
+        let mut machine = Expr::word("a").build().unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        assert!(machine.find("ab").is_err());
+    }
+}
@@ -0,0 +1,559 @@
+use crate::{QId, ANFA};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The patterns of a `Set` that matched, as a bitset
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SetMatches {
+    bits: Vec<u64>,
+    len: usize,
+}
+
+impl SetMatches {
+    fn new(len: usize) -> SetMatches {
+        SetMatches {
+            bits: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    fn insert(&mut self, pattern: usize) {
+        self.bits[pattern / 64] |= 1 << (pattern % 64);
+    }
+
+    /// Returns true when pattern `pattern` matched
+    pub fn matched(&self, pattern: usize) -> bool {
+        pattern < self.len && self.bits[pattern / 64] & (1 << (pattern % 64)) != 0
+    }
+
+    /// Returns true when any pattern matched
+    pub fn matched_any(&self) -> bool {
+        self.bits.iter().any(|bits| *bits != 0)
+    }
+
+    /// Returns true when every pattern matched
+    pub fn matched_all(&self) -> bool {
+        (0..self.len).all(|pattern| self.matched(pattern))
+    }
+
+    /// The number of patterns in the set, matched or not
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over the indexes of the patterns that matched, in order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(move |pattern| self.matched(*pattern))
+    }
+}
+
+/// What `Tagged::close` reached
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Reached {
+    /// A state that reads a symbol
+    Labeled(QId),
+    /// The final state of a pattern
+    Final(usize),
+}
+
+/// The states added in the current step of a simulation, so that every
+/// state is added at most once per step
+#[derive(Clone, Debug)]
+pub(crate) struct Visited {
+    /// the step each state was last added in
+    seen: Vec<usize>,
+    step: usize,
+}
+
+impl Visited {
+    pub(crate) fn new(state_count: usize) -> Visited {
+        Visited {
+            seen: vec![0; state_count],
+            step: 1,
+        }
+    }
+
+    /// Starts a new step, in which every state may be added again
+    pub(crate) fn next_step(&mut self) {
+        self.step += 1;
+    }
+
+    /// Returns true when `q` was not added in this step yet, and marks it
+    fn insert(&mut self, q: QId) -> bool {
+        let added = self.seen[q] != self.step;
+        self.seen[q] = self.step;
+        added
+    }
+}
+
+/// Finalized machines imported into one ANFA, the automaton behind `Set`
+/// and the other multi-pattern matchers. Each pattern keeps its own start
+/// state in `entries`, and its final state is tagged with its index, so a
+/// simulation starts a thread of every pattern at once and learns which
+/// pattern each match belongs to.
+#[derive(Clone, Debug)]
+pub(crate) struct Tagged<S = char> {
+    pub(crate) anfa: ANFA<S>,
+    /// the start state of each pattern
+    pub(crate) entries: Vec<QId>,
+    /// the pattern each final state belongs to
+    tags: Vec<Option<usize>>,
+}
+
+impl<S: Copy + Eq> Tagged<S> {
+    pub(crate) fn new() -> Tagged<S> {
+        Tagged {
+            anfa: ANFA::new(),
+            entries: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// The number of patterns
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Imports a finalized machine as the next pattern and returns its index
+    pub(crate) fn push(&mut self, machine: &ANFA<S>) -> Result<usize, &'static str> {
+        let [q0, f] = self.anfa.import(machine)?;
+        // imported states are no longer an automaton of their own
+        self.anfa.automata_refs.pop();
+        self.tags.resize(self.anfa.delta.len(), None);
+        self.tags[f] = Some(self.entries.len());
+        self.entries.push(q0);
+        Ok(self.entries.len() - 1)
+    }
+
+    /// Like `push`, for patterns searched at every offset of a stream: the
+    /// machine must not have assertions, and must not accept the empty
+    /// string, which would match everywhere
+    pub(crate) fn push_non_empty(&mut self, machine: &ANFA<S>) -> Result<usize, &'static str> {
+        let nfa = machine.remove_epsilon()?;
+        if nfa.finals[nfa.q0] {
+            return Err("Patterns must not accept the empty string.");
+        }
+        self.push(machine)
+    }
+
+    /// The state labeled state `q` enters along `symbol`, if it reads it
+    pub(crate) fn step(&self, q: QId, symbol: S) -> Option<QId> {
+        match self.anfa.delta[q] {
+            (Some(label), [Some(q_next), _]) if label == symbol => Some(q_next),
+            _ => None,
+        }
+    }
+
+    /// Calls `reached` with the ε-closure of `q` between `before` and
+    /// `after`, in priority order, i.e. branch 0 of a union before branch 1.
+    /// A final state is reached after the states its own transitions lead
+    /// to, which are preferred over matching. States already added in this
+    /// step of `visited`, and states whose assertion does not hold, are
+    /// skipped. Patterns without assertions may pass any context.
+    pub(crate) fn close<F: FnMut(Reached)>(
+        &self,
+        q: QId,
+        visited: &mut Visited,
+        before: Option<char>,
+        after: Option<char>,
+        mut reached: F,
+    ) {
+        // (state, whether the state was expanded and only its tag remains)
+        let mut stack = vec![(q, false)];
+        while let Some((q, expanded)) = stack.pop() {
+            if expanded {
+                if let Some(pattern) = self.tags[q] {
+                    reached(Reached::Final(pattern));
+                }
+                continue;
+            }
+            if !visited.insert(q) {
+                continue;
+            }
+            if let Some(assertion) = self.anfa.assertions.get(&q) {
+                if !assertion.holds(before, after) {
+                    continue;
+                }
+            }
+            if self.tags[q].is_some() {
+                stack.push((q, true));
+            }
+            match self.anfa.delta[q] {
+                (Some(_), _) => reached(Reached::Labeled(q)),
+                (None, [Some(q_left), Some(q_right)]) => {
+                    stack.push((q_right, false));
+                    stack.push((q_left, false));
+                }
+                (None, [Some(q_next), None]) => stack.push((q_next, false)),
+                (None, _) => {}
+            }
+        }
+    }
+}
+
+/// Several patterns compiled into one shared automaton, so a single pass
+/// over the input reports every pattern that matches somewhere in it.
+///
+/// Each finalized machine is imported into one ANFA, see `ANFA::import`.
+/// Every search starts a thread at the start state of each pattern, and
+/// their final states stay distinct and are tagged with the index of their
+/// pattern.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::set::Set;
+/// let error = Expr::literal('E').build().unwrap();
+/// let warning = Expr::literal('W').build().unwrap();
+/// let digits = Expr::literal('0').union(Expr::literal('1')).build().unwrap();
+/// let set = Set::new(&[&error, &warning, &digits]).unwrap();
+/// let matches = set.matches("E: code 1").unwrap();
+/// assert_eq!(matches.iter().collect::<Vec<_>>(), vec![0, 2]);
+/// ```
+#[derive(Debug)]
+pub struct Set {
+    tagged: Tagged,
+}
+
+impl Default for Set {
+    /// A set without patterns, see `Set::push`
+    fn default() -> Set {
+        Set {
+            tagged: Tagged::new(),
+        }
+    }
+}
+
+impl Set {
+    /// Builds a set from finalized machines, i.e. machines holding exactly one
+    /// automaton each. Pattern indexes follow the order of `machines`.
+    pub fn new(machines: &[&ANFA]) -> Result<Set, &'static str> {
+        let mut tagged = Tagged::new();
+        for machine in machines.iter() {
+            tagged.push(machine)?;
+        }
+        Ok(Set { tagged })
+    }
+
+    /// Adds a finalized machine as the next pattern and returns its index.
+    /// Only the machine is copied, so building a set one pattern at a time
+    /// takes time linear in the size of all of them.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::set::Set;
+    /// let mut set = Set::new(&[]).unwrap();
+    /// assert_eq!(set.push(&Expr::literal('E').build().unwrap()).unwrap(), 0);
+    /// assert_eq!(set.push(&Expr::literal('W').build().unwrap()).unwrap(), 1);
+    /// assert!(set.matches("W").unwrap().matched(1));
+    /// ```
+    pub fn push(&mut self, machine: &ANFA) -> Result<usize, &'static str> {
+        self.tagged.push(machine)
+    }
+
+    /// The number of patterns in the set
+    pub fn len(&self) -> usize {
+        self.tagged.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns which patterns match somewhere in `haystack`. Every pattern is
+    /// tried at every position in one pass, and the search stops early once
+    /// all patterns have matched.
+    pub fn matches(&self, haystack: &str) -> Result<SetMatches, &'static str> {
+        let tagged = &self.tagged;
+        let mut matches = SetMatches::new(self.len());
+        let mut current: Vec<QId> = Vec::new();
+        let mut next: Vec<QId> = Vec::new();
+        let mut visited = Visited::new(tagged.anfa.delta.len());
+        let mut at = 0;
+        let mut before = None;
+        loop {
+            let after = haystack[at..].chars().next();
+            for entry in tagged.entries.iter() {
+                tagged.close(
+                    *entry,
+                    &mut visited,
+                    before,
+                    after,
+                    |reached| match reached {
+                        Reached::Labeled(q) => current.push(q),
+                        Reached::Final(pattern) => matches.insert(pattern),
+                    },
+                );
+            }
+            if matches.matched_all() {
+                break;
+            }
+            let c = match after {
+                None => break,
+                Some(c) => c,
+            };
+            let after_next = haystack[at + c.len_utf8()..].chars().next();
+            visited.next_step();
+            for q in current.iter() {
+                #[cfg(test)]
+                crate::engine::steps::count();
+                if let Some(q_next) = tagged.step(*q, c) {
+                    tagged.close(
+                        q_next,
+                        &mut visited,
+                        Some(c),
+                        after_next,
+                        |reached| match reached {
+                            Reached::Labeled(q) => next.push(q),
+                            Reached::Final(pattern) => matches.insert(pattern),
+                        },
+                    );
+                }
+            }
+            core::mem::swap(&mut current, &mut next);
+            next.clear();
+            at += c.len_utf8();
+            before = Some(c);
+        }
+        Ok(matches)
+    }
+
+    /// Iterates over the leftmost-longest matches of all patterns in
+    /// `haystack`, in order. Among matches starting at the same position the
+    /// longest wins, and among equally long ones the pattern added first.
+    /// Matches never overlap, and empty matches are skipped.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::set::{Set, SetMatch};
+    /// let ab = Expr::word("ab").build().unwrap();
+    /// let abc = Expr::word("abc").build().unwrap();
+    /// let c = Expr::word("c").build().unwrap();
+    /// let set = Set::new(&[&ab, &abc, &c]).unwrap();
+    /// let matches: Vec<_> = set.find_iter("abc ab c").collect();
+    /// assert_eq!(matches[0], SetMatch { pattern: 1, span: (0, 3) });
+    /// assert_eq!(matches.len(), 3);
+    /// ```
+    pub fn find_iter<'a>(&'a self, haystack: &'a str) -> SetFindIter<'a> {
+        SetFindIter {
+            set: self,
+            haystack,
+            current: Vec::new(),
+            next: Vec::new(),
+            visited: Visited::new(self.tagged.anfa.delta().len()),
+            at: 0,
+        }
+    }
+}
+
+/// A match of one pattern of a `Set`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SetMatch {
+    /// The index of the pattern, in the order added
+    pub pattern: usize,
+    /// The byte span of the match
+    pub span: (usize, usize),
+}
+
+/// The successive leftmost-longest matches of a `Set`, see `Set::find_iter`.
+/// Matches are never empty, so every search starts after the previous
+/// match and iteration always terminates.
+pub struct SetFindIter<'a> {
+    set: &'a Set,
+    haystack: &'a str,
+    /// threads waiting to read the next character, with the start of their
+    /// match, earliest start first
+    current: Vec<(QId, usize)>,
+    next: Vec<(QId, usize)>,
+    visited: Visited,
+    /// where the next search starts
+    at: usize,
+}
+
+impl SetFindIter<'_> {
+    /// The leftmost-longest non-empty match at or after byte offset `from`
+    fn find_at(&mut self, from: usize) -> Option<SetMatch> {
+        let SetFindIter {
+            set,
+            haystack,
+            current,
+            next,
+            visited,
+            ..
+        } = self;
+        let tagged = &set.tagged;
+        current.clear();
+        visited.next_step();
+        let mut best: Option<SetMatch> = None;
+        let mut at = from;
+        let mut before = haystack[..from].chars().next_back();
+        loop {
+            let after = haystack[at..].chars().next();
+            if best.is_none() {
+                // threads that started earlier are already in current, and
+                // patterns matching here would match the empty string
+                for entry in tagged.entries.iter() {
+                    tagged.close(*entry, visited, before, after, |reached| {
+                        if let Reached::Labeled(q) = reached {
+                            current.push((q, at));
+                        }
+                    });
+                }
+            } else if current.is_empty() {
+                break;
+            }
+            let c = match after {
+                None => break,
+                Some(c) => c,
+            };
+            let end = at + c.len_utf8();
+            let after_next = haystack[end..].chars().next();
+            visited.next_step();
+            for (q, start) in current.iter() {
+                #[cfg(test)]
+                crate::engine::steps::count();
+                if best.is_some_and(|best| *start > best.span.0) {
+                    continue;
+                }
+                if let Some(q_next) = tagged.step(*q, c) {
+                    tagged.close(
+                        q_next,
+                        visited,
+                        Some(c),
+                        after_next,
+                        |reached| match reached {
+                            Reached::Labeled(q) => next.push((q, *start)),
+                            Reached::Final(pattern) => {
+                                let found = SetMatch {
+                                    pattern,
+                                    span: (*start, end),
+                                };
+                                if best.is_none_or(|best| leftmost_longest(found, best)) {
+                                    best = Some(found);
+                                }
+                            }
+                        },
+                    );
+                }
+            }
+            core::mem::swap(current, next);
+            next.clear();
+            at = end;
+            before = Some(c);
+        }
+        best
+    }
+}
+
+/// Whether `a` is preferred over `b`: it starts earlier, or ends later, or
+/// belongs to a pattern added earlier
+fn leftmost_longest(a: SetMatch, b: SetMatch) -> bool {
+    let key = |m: SetMatch| (m.span.0, core::cmp::Reverse(m.span.1), m.pattern);
+    key(a) < key(b)
+}
+
+impl Iterator for SetFindIter<'_> {
+    type Item = SetMatch;
+
+    fn next(&mut self) -> Option<SetMatch> {
+        let found = self.find_at(self.at)?;
+        self.at = found.span.1;
+        Some(found)
+    }
+}
+
+impl core::iter::FusedIterator for SetFindIter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::expr::Expr;
+    use crate::set::Set;
+    use crate::ANFA;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_matches() {
+        let error = Expr::word("error").build().unwrap();
+        let warn = Expr::word("warn").build().unwrap();
+        let disk = Expr::word("disk").build().unwrap();
+        let set = Set::new(&[&error, &warn, &disk]).unwrap();
+        assert_eq!(set.len(), 3);
+
+        let matches = set.matches("warn: disk almost full").unwrap();
+        assert_eq!(matches.iter().collect::<Vec<_>>(), vec![1, 2]);
+        assert!(matches.matched_any());
+        assert!(!matches.matched_all());
+        assert!(!matches.matched(0));
+        assert!(!matches.matched(3), "Out of range patterns never match");
+
+        let matches = set.matches("error: warn disk").unwrap();
+        assert!(matches.matched_all());
+        assert!(!set.matches("ok").unwrap().matched_any());
+    }
+
+    #[test]
+    fn test_matches_overlapping_and_assertions() {
+        // patterns that end in the middle of each other, and ^a
+        let ab = Expr::word("ab").build().unwrap();
+        let b = Expr::word("b").build().unwrap();
+        let anchored = Expr::assert(Assertion::StartText)
+            .concat(Expr::literal('a'))
+            .build()
+            .unwrap();
+        let empty = Expr::word("").build().unwrap();
+        let set = Set::new(&[&ab, &b, &anchored, &empty]).unwrap();
+        let matches = set.matches("xab").unwrap();
+        assert_eq!(matches.iter().collect::<Vec<_>>(), vec![0, 1, 3]);
+        let matches = set.matches("ab").unwrap();
+        assert!(matches.matched_all());
+        let spans: Vec<_> = set
+            .find_iter("xab ab b")
+            .map(|found| (found.pattern, found.span))
+            .collect();
+        assert_eq!(spans, vec![(0, (1, 3)), (0, (4, 6)), (1, (7, 8))]);
+        let spans: Vec<_> = set.find_iter("a").map(|found| found.pattern).collect();
+        assert_eq!(spans, vec![2], "Empty matches are skipped");
+    }
+
+    #[test]
+    fn test_many_patterns() {
+        let letters: Vec<ANFA> = ('a'..='z')
+            .chain('A'..='Z')
+            .chain('0'..='9')
+            .chain("+-_".chars())
+            .map(|c| Expr::literal(c).build().unwrap())
+            .collect();
+        let machines: Vec<&ANFA> = letters.iter().collect();
+        let set = Set::new(&machines).unwrap();
+        assert_eq!(set.len(), 65);
+        let matches = set.matches("a_").unwrap();
+        assert_eq!(matches.iter().collect::<Vec<_>>(), vec![0, 64]);
+
+        let empty = Set::new(&[]).unwrap();
+        assert!(empty.is_empty());
+        assert!(!empty.matches("abc").unwrap().matched_any());
+
+        let mut pushed = Set::new(&machines[..1]).unwrap();
+        for machine in machines[1..].iter() {
+            pushed.push(machine).unwrap();
+        }
+        assert_eq!(pushed.len(), 65);
+        for haystack in ["a_", "Z9+", "", "é"].iter() {
+            assert_eq!(
+                pushed.matches(haystack).unwrap(),
+                set.matches(haystack).unwrap(),
+                "{}",
+                haystack
+            );
+        }
+        let mut unfinished = Expr::literal('a').build().unwrap();
+        unfinished.automata_refs.clear();
+        assert!(pushed.push(&unfinished).is_err());
+        assert_eq!(pushed.len(), 65);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::expr::Expr;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A failing test case, an expression and the haystack it diverged on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Case<S = char> {
+    pub expr: Expr<S>,
+    pub haystack: Vec<S>,
+}
+
+/// Returns the sub-expressions of `expr` in pre-order, so larger
+/// sub-expressions are tried before the ones they contain.
+fn nodes<S>(expr: &Expr<S>) -> Vec<&Expr<S>> {
+    let mut nodes = Vec::new();
+    let mut stack = vec![expr];
+    while let Some(node) = stack.pop() {
+        nodes.push(node);
+        match node {
+            Expr::Zero | Expr::One | Expr::Literal(_) | Expr::Assert(_) => {}
+            Expr::Concat(a, b) | Expr::Union(a, b) => {
+                stack.push(b);
+                stack.push(a);
+            }
+            Expr::Star(a) => stack.push(a),
+        }
+    }
+    nodes
+}
+
+/// Simpler expressions that may replace `node`. Every replacement has fewer
+/// nodes, or as many nodes and a simpler leaf (`Literal`, `Assert` > `One` > `Zero`),
+/// so shrinking always terminates.
+fn replacements<S: Copy + Eq>(node: &Expr<S>) -> Vec<Expr<S>> {
+    match node {
+        Expr::Zero => vec![],
+        Expr::One => vec![Expr::Zero],
+        Expr::Literal(_) | Expr::Assert(_) => vec![Expr::Zero, Expr::One],
+        Expr::Concat(a, b) | Expr::Union(a, b) => vec![(**a).clone(), (**b).clone()],
+        Expr::Star(a) => vec![(**a).clone()],
+    }
+}
+
+/// Copies `expr`, replacing the sub-expression at `target` with `with`
+fn replace<S: Copy + Eq>(expr: &Expr<S>, target: *const Expr<S>, with: &Expr<S>) -> Expr<S> {
+    expr.rebuild(|node| {
+        if core::ptr::eq(node, target) {
+            Some(with.clone())
+        } else {
+            None
+        }
+    })
+}
+
+/// Tries each simpler expression and returns the first case that still fails
+fn shrink_expr<S: Copy + Eq, F: FnMut(&Case<S>) -> bool>(
+    case: &Case<S>,
+    fails: &mut F,
+) -> Option<Case<S>> {
+    for node in nodes(&case.expr) {
+        for with in replacements(node) {
+            let candidate = Case {
+                expr: replace(&case.expr, node, &with),
+                haystack: case.haystack.clone(),
+            };
+            if fails(&candidate) {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+/// Tries removing runs of symbols from the haystack, longest runs first,
+/// and returns the first case that still fails
+fn shrink_haystack<S: Copy + Eq, F: FnMut(&Case<S>) -> bool>(
+    case: &Case<S>,
+    fails: &mut F,
+) -> Option<Case<S>> {
+    let mut run = case.haystack.len();
+    while run > 0 {
+        let mut start = 0;
+        while start < case.haystack.len() {
+            let end = (start + run).min(case.haystack.len());
+            let mut haystack = case.haystack[..start].to_vec();
+            haystack.extend_from_slice(&case.haystack[end..]);
+            let candidate = Case {
+                expr: case.expr.clone(),
+                haystack,
+            };
+            if fails(&candidate) {
+                return Some(candidate);
+            }
+            start += run;
+        }
+        run /= 2;
+    }
+    None
+}
+
+/// Minimizes a failing case for a bug report.
+///
+/// `fails` returns true while a case still shows the divergence, e.g. when two
+/// compilers disagree on whether the haystack is accepted. Sub-expressions are
+/// replaced by their operands, `1`, or `0`, and runs of symbols are removed
+/// from the haystack, for as long as `fails` still holds. The result is a case
+/// where no single one of these steps preserves the failure.
+/// A case that does not fail is returned unchanged.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::shrink::{minimize, Case};
+/// let case = Case {
+///     // RE (a+b)*c
+///     expr: Expr::literal('a').union(Expr::literal('b')).star().concat(Expr::literal('c')),
+///     haystack: vec!['a', 'b', 'b', 'c'],
+/// };
+/// // a stand-in for a real divergence: the haystack is accepted and contains 'b'
+/// let minimal = minimize(case, |case| {
+///     let dfa = case.expr.build().unwrap().determinize().unwrap();
+///     dfa.accepts(case.haystack.iter().copied()) && case.haystack.contains(&'b')
+/// });
+/// assert_eq!(minimal.haystack, vec!['b', 'c']);
+/// assert_eq!(minimal.expr, Expr::literal('b').concat(Expr::literal('c')));
+/// ```
+pub fn minimize<S: Copy + Eq, F: FnMut(&Case<S>) -> bool>(case: Case<S>, mut fails: F) -> Case<S> {
+    if !fails(&case) {
+        return case;
+    }
+    let mut case = case;
+    loop {
+        // a shorter haystack exercises less of the expression, so shrink it first
+        if let Some(shorter) = shrink_haystack(&case, &mut fails) {
+            case = shorter;
+        } else if let Some(smaller) = shrink_expr(&case, &mut fails) {
+            case = smaller;
+        } else {
+            return case;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::Expr;
+    use crate::shrink::{minimize, Case};
+    use alloc::vec;
+
+    fn accepts(case: &Case) -> bool {
+        let dfa = case.expr.build().unwrap().determinize().unwrap();
+        dfa.accepts(case.haystack.iter().copied())
+    }
+
+    #[test]
+    fn test_minimize() {
+        // RE a(b+c)*d
+        let case = Case {
+            expr: Expr::literal('a').concat(
+                Expr::literal('b')
+                    .union(Expr::literal('c'))
+                    .star()
+                    .concat(Expr::literal('d')),
+            ),
+            haystack: vec!['a', 'b', 'c', 'c', 'b', 'd'],
+        };
+        let minimal = minimize(case, |case| accepts(case) && case.haystack.len() >= 2);
+        assert!(accepts(&minimal), "The failure is preserved");
+        assert_eq!(minimal.haystack.len(), 2, "The haystack is minimal");
+        assert_eq!(
+            minimal.expr,
+            Expr::literal('a').concat(Expr::literal('d')),
+            "The expression is minimal"
+        );
+    }
+
+    #[test]
+    fn test_minimize_passing_case() {
+        let case = Case {
+            expr: Expr::literal('a').star(),
+            haystack: vec!['b'],
+        };
+        assert_eq!(minimize(case.clone(), accepts), case);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::set::{Reached, Tagged, Visited};
+use crate::{QId, ANFA};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Identifies a registered signature, in registration order
+pub type SignatureId = usize;
+
+/// A signature that matched in the stream
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Alert {
+    pub signature: SignatureId,
+    /// The stream offset just past the last byte of the match
+    pub end: u64,
+}
+
+/// A database of named byte signatures, scanned all at once over a stream of
+/// packets. Signatures are imported into one tagged automaton, like the
+/// patterns of a `Set`, and a `Scanner` keeps the threads
+/// of that automaton between packets, so a match split across packets is
+/// found exactly as if the stream were one buffer.
+///
+/// ```rust
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// use regexxx::signatures::{Alert, Signatures};
+/// use regexxx::ANFA;
+/// fn literal(bytes: &[u8]) -> ANFA<u8> {
+///     let mut machine: ANFA<u8> = ForwardCompiler::from_expr_a(bytes[0]).unwrap();
+///     for byte in bytes[1..].iter() {
+///         ForwardCompiler::expr_a(&mut machine, *byte).unwrap();
+///         ForwardCompiler::concatenate(&mut machine).unwrap();
+///     }
+///     machine
+/// }
+/// let mut signatures = Signatures::new();
+/// let ssh = signatures.register("ssh banner", &literal(b"SSH-2.0")).unwrap();
+/// let mut scanner = signatures.scanner();
+/// assert_eq!(scanner.feed(b"...SSH-"), vec![]);
+/// assert_eq!(scanner.feed(b"2.0-x"), vec![Alert { signature: ssh, end: 10 }]);
+/// assert_eq!(signatures.name(ssh), Some("ssh banner"));
+/// ```
+#[derive(Debug)]
+pub struct Signatures {
+    tagged: Tagged<u8>,
+    names: Vec<String>,
+}
+
+impl Default for Signatures {
+    fn default() -> Signatures {
+        Signatures::new()
+    }
+}
+
+impl Signatures {
+    pub fn new() -> Signatures {
+        Signatures {
+            tagged: Tagged::new(),
+            names: Vec::new(),
+        }
+    }
+
+    /// Adds a signature and returns its id. The machine must hold exactly one
+    /// automaton without assertions, and must not accept the empty string,
+    /// which would alert at every offset.
+    pub fn register(
+        &mut self,
+        name: &str,
+        machine: &ANFA<u8>,
+    ) -> Result<SignatureId, &'static str> {
+        let signature = self.tagged.push_non_empty(machine)?;
+        self.names.push(name.to_string());
+        Ok(signature)
+    }
+
+    /// The number of signatures registered
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The name `signature` was registered with
+    pub fn name(&self, signature: SignatureId) -> Option<&str> {
+        self.names.get(signature).map(String::as_str)
+    }
+
+    /// A scanner at the start of a new stream
+    pub fn scanner(&self) -> Scanner<'_> {
+        Scanner {
+            signatures: self,
+            current: Vec::new(),
+            next: Vec::new(),
+            visited: Visited::new(self.tagged.anfa.delta().len()),
+            alerted: vec![0; self.names.len()],
+            offset: 0,
+        }
+    }
+
+    /// Scans `bytes` as one whole stream, see `Scanner::feed`
+    pub fn scan(&self, bytes: &[u8]) -> Vec<Alert> {
+        self.scanner().feed(bytes)
+    }
+}
+
+/// The position of one stream in a `Signatures` automaton, see
+/// `Signatures::scanner`. Memory is bounded by the number of states, however
+/// long the stream.
+#[derive(Debug)]
+pub struct Scanner<'a> {
+    signatures: &'a Signatures,
+    /// states waiting to read the next byte
+    current: Vec<QId>,
+    next: Vec<QId>,
+    /// `current` was built in the current step of `visited`
+    visited: Visited,
+    /// the end offset each signature last alerted at, 0 before any alert
+    alerted: Vec<u64>,
+    offset: u64,
+}
+
+impl Scanner<'_> {
+    /// The number of bytes fed so far
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Forgets the stream, as if no bytes had been fed
+    pub fn reset(&mut self) {
+        *self = self.signatures.scanner();
+    }
+
+    /// Scans the next packet of the stream and returns the alerts ending in
+    /// it, ordered by end offset, then by signature id. Every signature
+    /// alerts at most once per end offset, and matches may start in earlier
+    /// packets.
+    pub fn feed(&mut self, packet: &[u8]) -> Vec<Alert> {
+        let Scanner {
+            signatures,
+            current,
+            next,
+            visited,
+            alerted,
+            offset,
+        } = self;
+        let tagged = &signatures.tagged;
+        let mut alerts = Vec::new();
+        for byte in packet.iter() {
+            // a match of every signature may start here, and none ends here
+            // as signatures never accept the empty string
+            for entry in tagged.entries.iter() {
+                tagged.close(*entry, visited, None, None, |reached| {
+                    if let Reached::Labeled(q) = reached {
+                        current.push(q);
+                    }
+                });
+            }
+            visited.next_step();
+            *offset += 1;
+            let end = *offset;
+            let first = alerts.len();
+            for q in current.iter() {
+                if let Some(q_next) = tagged.step(*q, *byte) {
+                    tagged.close(q_next, visited, None, None, |reached| match reached {
+                        Reached::Labeled(q) => next.push(q),
+                        Reached::Final(signature) => {
+                            if alerted[signature] != end {
+                                alerted[signature] = end;
+                                alerts.push(Alert { signature, end });
+                            }
+                        }
+                    });
+                }
+            }
+            alerts[first..].sort_unstable_by_key(|alert| alert.signature);
+            core::mem::swap(current, next);
+            next.clear();
+        }
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::signatures::{Alert, Signatures};
+    use crate::ANFA;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn literal(bytes: &[u8]) -> ANFA<u8> {
+        let mut machine: ANFA<u8> = ForwardCompiler::from_expr_a(bytes[0]).unwrap();
+        for byte in bytes[1..].iter() {
+            ForwardCompiler::expr_a(&mut machine, *byte).unwrap();
+            ForwardCompiler::concatenate(&mut machine).unwrap();
+        }
+        machine
+    }
+
+    fn database() -> Signatures {
+        // 0x16 0x03 (0x01+0x03)*, a TLS record header and any version bytes
+        let mut tls = literal(&[0x16, 0x03]);
+        ForwardCompiler::expr_a(&mut tls, 0x01).unwrap();
+        ForwardCompiler::expr_a(&mut tls, 0x03).unwrap();
+        ForwardCompiler::union(&mut tls).unwrap();
+        ForwardCompiler::star(&mut tls).unwrap();
+        ForwardCompiler::concatenate(&mut tls).unwrap();
+        let mut signatures = Signatures::new();
+        signatures.register("http", &literal(b"GET /")).unwrap();
+        signatures.register("tls", &tls).unwrap();
+        signatures.register("overlap", &literal(b"T /")).unwrap();
+        signatures
+    }
+
+    #[test]
+    fn test_scan() {
+        let signatures = database();
+        assert_eq!(signatures.len(), 3);
+        let stream = b"xGET /\x16\x03\x03\x01GET /";
+        let alert = |signature, end| Alert { signature, end };
+        assert_eq!(
+            signatures.scan(stream),
+            vec![
+                alert(0, 6),
+                alert(2, 6),
+                alert(1, 8),
+                alert(1, 9),
+                alert(1, 10),
+                alert(0, 15),
+                alert(2, 15),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_feed_split_anywhere() {
+        let signatures = database();
+        let stream: &[u8] = b"GET /\x16\x03\x01GET\x16\x03 /T /";
+        let whole = signatures.scan(stream);
+        for split in 0..=stream.len() {
+            let mut scanner = signatures.scanner();
+            let mut alerts = scanner.feed(&stream[..split]);
+            alerts.extend(scanner.feed(&stream[split..]));
+            assert_eq!(alerts, whole, "split at {}", split);
+            assert_eq!(scanner.offset(), stream.len() as u64);
+        }
+        let mut scanner = signatures.scanner();
+        let byte_by_byte: Vec<Alert> = stream
+            .iter()
+            .flat_map(|byte| scanner.feed(&[*byte]))
+            .collect();
+        assert_eq!(byte_by_byte, whole);
+        scanner.reset();
+        assert_eq!(scanner.offset(), 0);
+        assert_eq!(scanner.feed(b" /"), vec![], "Reset forgets GET");
+    }
+
+    #[test]
+    fn test_register_errors() {
+        let mut signatures = Signatures::new();
+        let mut empty = literal(b"a");
+        ForwardCompiler::star(&mut empty).unwrap();
+        assert!(signatures.register("empty", &empty).is_err());
+        let mut asserted: ANFA<u8> =
+            ForwardCompiler::from_expr_assert(Assertion::StartText).unwrap();
+        ForwardCompiler::expr_a(&mut asserted, b'a').unwrap();
+        ForwardCompiler::concatenate(&mut asserted).unwrap();
+        assert!(signatures.register("asserted", &asserted).is_err());
+        assert!(signatures.is_empty());
+        assert_eq!(signatures.scan(b"aaa"), vec![]);
+        assert_eq!(signatures.name(0), None);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::ANFA;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// How a monitoring system should treat the value of a `Metric`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MetricKind {
+    /// A value that may go up or down, e.g. the number of states
+    Gauge,
+    /// A value that only goes up, e.g. the number of cache hits
+    Counter,
+}
+
+/// One named value of a compiled pattern, ready for export
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Metric {
+    /// `snake_case` name, without a prefix. Counters end in `_total`.
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: MetricKind,
+    pub value: u64,
+}
+
+/// Anything that reports metrics, e.g. `Stats` or `hybrid::CacheStats`
+pub trait Metrics {
+    fn metrics(&self) -> Vec<Metric>;
+
+    /// The metrics as one flat JSON object of names to values
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::stats::Metrics;
+    /// let stats = Expr::literal('a').build().unwrap().stats();
+    /// assert_eq!(
+    ///     stats.to_json(),
+    ///     r#"{"automata":1,"states":2,"labeled_states":1,"epsilon_states":1,"union_states":0,"assertion_states":0}"#
+    /// );
+    /// ```
+    fn to_json(&self) -> String {
+        let fields: Vec<String> = self
+            .metrics()
+            .iter()
+            .map(|metric| format!("\"{}\":{}", metric.name, metric.value))
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    }
+
+    /// The metrics in the Prometheus text exposition format. Every name is
+    /// prefixed with `regexxx_`, and every sample carries `labels`. Label
+    /// values are escaped, label names are not. A name may only be described
+    /// once per exposition, so export several patterns with `prometheus`
+    /// instead of concatenating.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::stats::Metrics;
+    /// let stats = Expr::literal('a').build().unwrap().stats();
+    /// let text = stats.to_prometheus(&[("pattern", "a")]);
+    /// assert!(text.starts_with("# HELP regexxx_automata Number of automata\n# TYPE regexxx_automata gauge\nregexxx_automata{pattern=\"a\"} 1\n"));
+    /// ```
+    fn to_prometheus(&self, labels: &[(&str, &str)]) -> String {
+        let labels = format_labels(labels);
+        let mut out = String::new();
+        for metric in self.metrics().iter() {
+            write_description(&mut out, metric);
+            write_sample(&mut out, metric.name, &labels, metric.value);
+        }
+        out
+    }
+}
+
+/// Prometheus labels as `(name, value)` pairs
+pub type Labels<'a> = &'a [(&'a str, &'a str)];
+
+/// The metrics of several sources in one Prometheus text exposition, e.g.
+/// one source per pattern, told apart by their labels. Each name is
+/// described once, by `# HELP` and `# TYPE` from the first source reporting
+/// it, followed by the sample of every source reporting it, in the order of
+/// `sources`.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::stats::{self, Metrics};
+/// let a = Expr::literal('a').build().unwrap().stats();
+/// let b = Expr::literal('b').star().build().unwrap().stats();
+/// let text = stats::prometheus(&[(&[("pattern", "a")], &a), (&[("pattern", "b*")], &b)]);
+/// assert_eq!(text.matches("# TYPE regexxx_states gauge").count(), 1);
+/// assert!(text.contains("regexxx_automata{pattern=\"a\"} 1\nregexxx_automata{pattern=\"b*\"} 1\n"));
+/// ```
+pub fn prometheus(sources: &[(Labels<'_>, &dyn Metrics)]) -> String {
+    // every metric name in order of first appearance, with its samples
+    let mut families: Vec<(Metric, Vec<(String, u64)>)> = Vec::new();
+    for (labels, source) in sources.iter() {
+        let labels = format_labels(labels);
+        for metric in source.metrics() {
+            let sample = (labels.clone(), metric.value);
+            match families
+                .iter_mut()
+                .find(|(first, _samples)| first.name == metric.name)
+            {
+                Some((_first, samples)) => samples.push(sample),
+                None => families.push((metric, alloc::vec![sample])),
+            }
+        }
+    }
+    let mut out = String::new();
+    for (metric, samples) in families.iter() {
+        write_description(&mut out, metric);
+        for (labels, value) in samples.iter() {
+            write_sample(&mut out, metric.name, labels, *value);
+        }
+    }
+    out
+}
+
+/// Writes the `# HELP` and `# TYPE` lines of `metric`
+fn write_description(out: &mut String, metric: &Metric) {
+    let kind = match metric.kind {
+        MetricKind::Gauge => "gauge",
+        MetricKind::Counter => "counter",
+    };
+    out.push_str(&format!(
+        "# HELP regexxx_{name} {help}\n# TYPE regexxx_{name} {kind}\n",
+        name = metric.name,
+        help = metric.help,
+        kind = kind,
+    ));
+}
+
+fn write_sample(out: &mut String, name: &str, labels: &str, value: u64) {
+    out.push_str(&format!("regexxx_{}{} {}\n", name, labels, value));
+}
+
+/// Formats labels as `{name="value",...}`, or nothing without labels
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+        .collect();
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels.join(","))
+    }
+}
+
+/// Escapes a Prometheus label value
+fn escape_label(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Inventory of a compiled ANFA, see `ANFA::stats`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Stats {
+    pub automata: usize,
+    pub states: usize,
+    /// States reading a symbol
+    pub labeled_states: usize,
+    /// States moving without reading, including final states
+    pub epsilon_states: usize,
+    /// ε states with two next states
+    pub union_states: usize,
+    /// States guarded by an assertion
+    pub assertion_states: usize,
+}
+
+impl Metrics for Stats {
+    fn metrics(&self) -> Vec<Metric> {
+        let gauge = |name, help, value: usize| Metric {
+            name,
+            help,
+            kind: MetricKind::Gauge,
+            value: value as u64,
+        };
+        alloc::vec![
+            gauge("automata", "Number of automata", self.automata),
+            gauge("states", "Number of states", self.states),
+            gauge(
+                "labeled_states",
+                "Number of states reading a symbol",
+                self.labeled_states
+            ),
+            gauge(
+                "epsilon_states",
+                "Number of states moving without reading",
+                self.epsilon_states
+            ),
+            gauge(
+                "union_states",
+                "Number of states with two next states",
+                self.union_states
+            ),
+            gauge(
+                "assertion_states",
+                "Number of states guarded by an assertion",
+                self.assertion_states
+            ),
+        ]
+    }
+}
+
+impl<S: Copy + Eq> ANFA<S> {
+    /// Counts the automata and states of the ANFA, for inventories of
+    /// compiled patterns. See `Metrics` to export them.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE a+b
+    /// let stats = Expr::literal('a').union(Expr::literal('b')).build().unwrap().stats();
+    /// assert_eq!(stats.labeled_states, 2);
+    /// assert_eq!(stats.union_states, 1);
+    /// ```
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats {
+            automata: self.automata_refs.len(),
+            states: self.delta.len(),
+            assertion_states: self.assertions.len(),
+            ..Stats::default()
+        };
+        for (label, q_nexts) in self.delta.iter() {
+            match (label, q_nexts) {
+                (Some(_), _) => stats.labeled_states += 1,
+                (None, [Some(_), Some(_)]) => {
+                    stats.epsilon_states += 1;
+                    stats.union_states += 1;
+                }
+                (None, _) => stats.epsilon_states += 1,
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::expr::Expr;
+    use crate::stats::{prometheus, Metric, MetricKind, Metrics};
+    use alloc::format;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_stats() {
+        // RE ^(a+b)*
+        let machine = Expr::assert(Assertion::StartText)
+            .concat(Expr::literal('a').union(Expr::literal('b')).star())
+            .build()
+            .unwrap();
+        let stats = machine.stats();
+        assert_eq!(stats.automata, 1);
+        assert_eq!(stats.states, machine.delta.len());
+        assert_eq!(stats.labeled_states + stats.epsilon_states, stats.states);
+        assert_eq!(stats.labeled_states, 2);
+        assert_eq!(stats.assertion_states, 1);
+        assert!(stats.union_states >= 2, "union and star both branch");
+    }
+
+    struct Fixed;
+
+    impl Metrics for Fixed {
+        fn metrics(&self) -> Vec<Metric> {
+            vec![Metric {
+                name: "hits_total",
+                help: "Hits",
+                kind: MetricKind::Counter,
+                value: 3,
+            }]
+        }
+    }
+
+    #[test]
+    fn test_export() {
+        assert_eq!(Fixed.to_json(), r#"{"hits_total":3}"#);
+        assert_eq!(
+            Fixed.to_prometheus(&[]),
+            "# HELP regexxx_hits_total Hits\n# TYPE regexxx_hits_total counter\nregexxx_hits_total 3\n"
+        );
+        assert_eq!(
+            Fixed.to_prometheus(&[("pattern", "\"a\\b\"\n"), ("engine", "hybrid")]),
+            "# HELP regexxx_hits_total Hits\n# TYPE regexxx_hits_total counter\nregexxx_hits_total{pattern=\"\\\"a\\\\b\\\"\\n\",engine=\"hybrid\"} 3\n"
+        );
+    }
+
+    #[test]
+    fn test_export_several() {
+        let a = Expr::literal('a').build().unwrap().stats();
+        let b = Expr::literal('b')
+            .union(Expr::literal('c'))
+            .build()
+            .unwrap()
+            .stats();
+        let text = prometheus(&[
+            (&[("pattern", "a")], &a),
+            (&[("pattern", "b+c")], &b),
+            (&[], &Fixed),
+        ]);
+        for metric in a.metrics().iter().chain(Fixed.metrics().iter()) {
+            let help = format!("# HELP regexxx_{} ", metric.name);
+            let kind = format!("# TYPE regexxx_{} ", metric.name);
+            assert_eq!(text.matches(&help).count(), 1, "{}", metric.name);
+            assert_eq!(text.matches(&kind).count(), 1, "{}", metric.name);
+        }
+        assert!(text.contains(
+            "# TYPE regexxx_union_states gauge\nregexxx_union_states{pattern=\"a\"} 0\nregexxx_union_states{pattern=\"b+c\"} 1\n"
+        ));
+        assert!(text.ends_with("# TYPE regexxx_hits_total counter\nregexxx_hits_total 3\n"));
+        assert_eq!(prometheus(&[]), "");
+        assert_eq!(prometheus(&[(&[], &Fixed)]), Fixed.to_prometheus(&[]));
+    }
+}
@@ -0,0 +1,423 @@
+use crate::{is_well_formed, AutomataRef, QId, Transition, ANFA};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// Storage for the transitions of an ANFA, indexed by `QId`. `DeltaFunction`,
+/// i.e. a `Vec` of transitions, is the storage the compilers build, and
+/// `ANFA::store` and `ANFA::from_storage` copy an ANFA into and out of any
+/// other storage, e.g. a shared-memory table.
+pub trait DeltaStorage<S> {
+    /// The number of states stored
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends a state and returns its id, the number of states before it
+    fn push_state(&mut self, transition: Transition<S>) -> Result<QId, &'static str>;
+
+    /// The transition of `q`, or `None` when `q` is not stored
+    fn transitions(&self, q: QId) -> Option<Transition<S>>;
+
+    /// Replaces the transition of `q`
+    fn set(&mut self, q: QId, transition: Transition<S>) -> Result<(), &'static str>;
+}
+
+impl<S: Copy> DeltaStorage<S> for Vec<Transition<S>> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn push_state(&mut self, transition: Transition<S>) -> Result<QId, &'static str> {
+        self.push(transition);
+        Ok(Vec::len(self) - 1)
+    }
+
+    fn transitions(&self, q: QId) -> Option<Transition<S>> {
+        self.get(q).copied()
+    }
+
+    fn set(&mut self, q: QId, transition: Transition<S>) -> Result<(), &'static str> {
+        match self.get_mut(q) {
+            Some(stored) => {
+                *stored = transition;
+                Ok(())
+            }
+            None => Err("State is not stored."),
+        }
+    }
+}
+
+/// Compressed sparse rows: the next states of every state in one array, and
+/// where each state's run of next states starts in another. States without
+/// transitions take no space for next states, so machines with many final or
+/// dead states are stored more compactly than in a `Vec` of transitions.
+/// `set` moves the runs after the state when its number of next states
+/// changes, so it takes time linear in the number of transitions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Csr<S> {
+    labels: Vec<Option<S>>,
+    /// `next[starts[q]..starts[q + 1]]` are the next states of `q`
+    starts: Vec<usize>,
+    next: Vec<QId>,
+}
+
+impl<S> Csr<S> {
+    pub fn new() -> Csr<S> {
+        Csr {
+            labels: Vec::new(),
+            starts: Vec::new(),
+            next: Vec::new(),
+        }
+    }
+
+    /// The next states of `q` in branch order
+    pub fn next_states(&self, q: QId) -> &[QId] {
+        match self.labels.get(q) {
+            Some(_label) => &self.next[self.starts[q]..self.end(q)],
+            None => &[],
+        }
+    }
+
+    fn end(&self, q: QId) -> usize {
+        self.starts.get(q + 1).copied().unwrap_or(self.next.len())
+    }
+}
+
+impl<S: Copy> DeltaStorage<S> for Csr<S> {
+    fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    fn push_state(&mut self, (label, q_nexts): Transition<S>) -> Result<QId, &'static str> {
+        self.labels.push(label);
+        self.starts.push(self.next.len());
+        self.next.extend(q_nexts.iter().flatten());
+        Ok(self.labels.len() - 1)
+    }
+
+    fn transitions(&self, q: QId) -> Option<Transition<S>> {
+        let label = *self.labels.get(q)?;
+        let next = self.next_states(q);
+        Some((label, [next.first().copied(), next.get(1).copied()]))
+    }
+
+    fn set(&mut self, q: QId, (label, q_nexts): Transition<S>) -> Result<(), &'static str> {
+        if q >= self.labels.len() {
+            return Err("State is not stored.");
+        }
+        self.labels[q] = label;
+        let (start, end) = (self.starts[q], self.end(q));
+        let replacement: Vec<QId> = q_nexts.iter().flatten().copied().collect();
+        let added = replacement.len();
+        self.next.splice(start..end, replacement);
+        // later runs start at or after `end`, so this never underflows
+        for later in self.starts[q + 1..].iter_mut() {
+            *later = *later + added - (end - start);
+        }
+        Ok(())
+    }
+}
+
+/// Bytes of one state in `SliceStorage`
+const RECORD_LEN: usize = 12;
+
+/// Marks a missing label or next state in `SliceStorage`
+const NONE: u32 = u32::MAX;
+
+/// Reads the 4 byte word at `at`
+fn read_word(bytes: &[u8], at: usize) -> u32 {
+    let mut word = [0u8; 4];
+    word.copy_from_slice(&bytes[at..at + 4]);
+    u32::from_le_bytes(word)
+}
+
+/// Reads the record of `q`, see `SliceStorage`
+fn read_record(bytes: &[u8], q: QId) -> Transition<char> {
+    let at = q * RECORD_LEN;
+    let label = match read_word(bytes, at) {
+        NONE => None,
+        // a corrupt label reads as a transition nothing can take
+        label => Some(char::from_u32(label).unwrap_or(char::REPLACEMENT_CHARACTER)),
+    };
+    let q_next = |word: u32| match word {
+        NONE => None,
+        q_next => Some(q_next as QId),
+    };
+    (
+        label,
+        [
+            q_next(read_word(bytes, at + 4)),
+            q_next(read_word(bytes, at + 8)),
+        ],
+    )
+}
+
+/// Transitions of an `ANFA<char>` read from a caller-provided byte buffer,
+/// e.g. a memory-mapped file or a shared-memory segment written with
+/// `SliceStorageMut`, so machines can be read from memory the crate does not
+/// own. Each state is 12 bytes: its label, then its two next states, each a
+/// little-endian `u32` where `u32::MAX` is `None`.
+///
+/// The storage only borrows bytes: mapping the file or segment, and keeping
+/// it mapped and unchanged while the storage is read, is left to the caller.
+/// The storage is read-only, so `push_state` and `set` fail.
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::storage::{DeltaStorage, SliceStorage, SliceStorageMut};
+/// use regexxx::ANFA;
+/// let machine = Expr::literal('a').star().build().unwrap();
+/// let mut buffer = [0u8; 1024];
+/// let mut storage = SliceStorageMut::new(&mut buffer);
+/// machine.store(&mut storage).unwrap();
+/// let len = storage.len();
+/// // later, e.g. in another process mapping the same memory
+/// let storage = SliceStorage::open(&buffer, len).unwrap();
+/// let loaded = ANFA::from_storage(&storage, machine.automata_refs.clone()).unwrap();
+/// assert!(loaded.is_match("baa").unwrap());
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct SliceStorage<'a> {
+    bytes: &'a [u8],
+    len: usize,
+}
+
+impl<'a> SliceStorage<'a> {
+    /// Storage of the first `len` states already written to `bytes`
+    pub fn open(bytes: &'a [u8], len: usize) -> Result<SliceStorage<'a>, &'static str> {
+        if len > bytes.len() / RECORD_LEN {
+            return Err("Buffer is too small for the states.");
+        }
+        Ok(SliceStorage { bytes, len })
+    }
+}
+
+impl DeltaStorage<char> for SliceStorage<'_> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn push_state(&mut self, _transition: Transition<char>) -> Result<QId, &'static str> {
+        Err("Storage is read-only.")
+    }
+
+    fn transitions(&self, q: QId) -> Option<Transition<char>> {
+        if q >= self.len {
+            return None;
+        }
+        Some(read_record(self.bytes, q))
+    }
+
+    fn set(&mut self, _q: QId, _transition: Transition<char>) -> Result<(), &'static str> {
+        Err("Storage is read-only.")
+    }
+}
+
+/// Writes the transitions of an `ANFA<char>` into a caller-provided byte
+/// buffer, in the records `SliceStorage` reads. The buffer holds at most
+/// `bytes.len() / 12` states. As with `SliceStorage`, mapping the memory
+/// and sharing it is left to the caller.
+#[derive(Debug)]
+pub struct SliceStorageMut<'a> {
+    bytes: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceStorageMut<'a> {
+    /// Storage with no states in `bytes`
+    pub fn new(bytes: &'a mut [u8]) -> SliceStorageMut<'a> {
+        SliceStorageMut { bytes, len: 0 }
+    }
+
+    /// The number of states the buffer can hold
+    pub fn capacity(&self) -> usize {
+        self.bytes.len() / RECORD_LEN
+    }
+
+    /// The states written so far, read-only
+    pub fn as_storage(&self) -> SliceStorage<'_> {
+        SliceStorage {
+            bytes: self.bytes,
+            len: self.len,
+        }
+    }
+
+    fn write(&mut self, q: QId, (label, q_nexts): Transition<char>) -> Result<(), &'static str> {
+        let mut words = [label.map_or(NONE, u32::from), NONE, NONE];
+        for (word, q_next) in words[1..].iter_mut().zip(q_nexts.iter()) {
+            if let Some(q_next) = q_next {
+                *word = u32::try_from(*q_next)
+                    .ok()
+                    .filter(|q_next| *q_next != NONE)
+                    .ok_or("State id does not fit the buffer's records.")?;
+            }
+        }
+        let record = &mut self.bytes[q * RECORD_LEN..(q + 1) * RECORD_LEN];
+        for (bytes, word) in record.chunks_mut(4).zip(words.iter()) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        Ok(())
+    }
+}
+
+impl DeltaStorage<char> for SliceStorageMut<'_> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn push_state(&mut self, transition: Transition<char>) -> Result<QId, &'static str> {
+        if self.len == self.capacity() {
+            return Err("Buffer is full.");
+        }
+        self.write(self.len, transition)?;
+        self.len += 1;
+        Ok(self.len - 1)
+    }
+
+    fn transitions(&self, q: QId) -> Option<Transition<char>> {
+        if q >= self.len {
+            return None;
+        }
+        Some(read_record(self.bytes, q))
+    }
+
+    fn set(&mut self, q: QId, transition: Transition<char>) -> Result<(), &'static str> {
+        if q >= self.len {
+            return Err("State is not stored.");
+        }
+        self.write(q, transition)
+    }
+}
+
+impl<S: Copy + Eq> ANFA<S> {
+    /// Appends every state to `storage`, in order, so the ids of states in
+    /// `storage` are their ids in the ANFA when `storage` starts empty.
+    /// Assertions and `automata_refs` are not part of the storage.
+    pub fn store<D: DeltaStorage<S>>(&self, storage: &mut D) -> Result<(), &'static str> {
+        for transition in self.delta.iter() {
+            storage.push_state(*transition)?;
+        }
+        Ok(())
+    }
+
+    /// Copies the states of `storage` into a new ANFA holding
+    /// `automata_refs`, checking that every transition is well formed and
+    /// every state id is in range, see `Transition`.
+    pub fn from_storage<D: DeltaStorage<S>>(
+        storage: &D,
+        automata_refs: Vec<AutomataRef>,
+    ) -> Result<ANFA<S>, &'static str> {
+        let state_count = storage.len();
+        let mut anfa = ANFA::new();
+        anfa.automata_refs = automata_refs;
+        for q in 0..state_count {
+            let transition = storage
+                .transitions(q)
+                .ok_or("Storage is missing a state.")?;
+            if !is_well_formed(&transition, state_count) {
+                return Err("Storage holds a malformed transition.");
+            }
+            anfa.push_state(transition);
+        }
+        for automaton in anfa.automata_refs.iter() {
+            anfa.check_ref(*automaton)?;
+        }
+        Ok(anfa)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::Expr;
+    use crate::storage::{Csr, DeltaStorage, SliceStorage, SliceStorageMut};
+    use crate::ANFA;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    /// RE (a+é)*b
+    fn machine() -> ANFA {
+        Expr::literal('a')
+            .union(Expr::literal('é'))
+            .star()
+            .concat(Expr::literal('b'))
+            .build()
+            .unwrap()
+    }
+
+    fn round_trip<D: DeltaStorage<char>>(mut storage: D) -> D {
+        let machine = machine();
+        machine.store(&mut storage).unwrap();
+        assert_eq!(storage.len(), machine.delta.len());
+        let loaded = ANFA::from_storage(&storage, machine.automata_refs.clone()).unwrap();
+        assert_eq!(loaded.delta, machine.delta);
+        assert_eq!(loaded.find("xaéb").unwrap(), Some((1, 5)));
+        storage
+    }
+
+    #[test]
+    fn test_vec_storage() {
+        let mut storage = round_trip(Vec::new());
+        assert_eq!(storage.transitions(0), Some((Some('a'), [Some(1), None])));
+        storage.set(1, (None, [None, None])).unwrap();
+        assert_eq!(storage.transitions(1), Some((None, [None, None])));
+        assert!(storage.set(100, (None, [None, None])).is_err());
+        assert_eq!(DeltaStorage::transitions(&storage, 100), None);
+    }
+
+    #[test]
+    fn test_csr_storage() {
+        let mut storage = round_trip(Csr::new());
+        let q_union = machine()
+            .delta
+            .iter()
+            .position(|transition| matches!(transition, (None, [Some(_), Some(_)])))
+            .unwrap();
+        assert_eq!(storage.next_states(q_union).len(), 2);
+        let after: Vec<_> = (q_union + 1..storage.len())
+            .map(|q| storage.transitions(q))
+            .collect();
+        storage.set(q_union, (None, [Some(0), None])).unwrap();
+        assert_eq!(storage.transitions(q_union), Some((None, [Some(0), None])));
+        storage.set(q_union, (Some('z'), [None, None])).unwrap();
+        assert_eq!(storage.next_states(q_union), &[] as &[usize]);
+        let unchanged: Vec<_> = (q_union + 1..storage.len())
+            .map(|q| storage.transitions(q))
+            .collect();
+        assert_eq!(unchanged, after, "Later states are moved, not changed");
+    }
+
+    #[test]
+    fn test_slice_storage() {
+        let mut buffer = vec![0u8; 12 * 20];
+        let storage = round_trip(SliceStorageMut::new(&mut buffer));
+        let len = storage.len();
+        assert_eq!(storage.as_storage().transitions(0), storage.transitions(0));
+        let mut storage = SliceStorage::open(&buffer, len).unwrap();
+        let loaded = ANFA::from_storage(&storage, machine().automata_refs).unwrap();
+        assert_eq!(loaded.delta, machine().delta);
+        assert_eq!(storage.transitions(len), None);
+        assert!(storage.push_state((None, [None, None])).is_err());
+        assert!(storage.set(0, (None, [None, None])).is_err());
+        assert!(SliceStorage::open(&buffer, 21).is_err());
+
+        let mut small = [0u8; 12];
+        let mut storage = SliceStorageMut::new(&mut small);
+        assert!(machine().store(&mut storage).is_err(), "Buffer is full");
+    }
+
+    #[test]
+    fn test_from_storage_validates() {
+        let refs = vec![[0, 1]];
+        let out_of_range = vec![(Some('a'), [Some(2), None]), (None, [None, None])];
+        assert!(ANFA::from_storage(&out_of_range, refs.clone()).is_err());
+        let labeled_union = vec![(Some('a'), [Some(1), Some(1)]), (None, [None, None])];
+        assert!(ANFA::from_storage(&labeled_union, refs.clone()).is_err());
+        let labeled_dead_end = vec![(Some('a'), [None, None]), (None, [None, None])];
+        assert!(ANFA::from_storage(&labeled_dead_end, refs.clone()).is_err());
+        let valid = vec![(Some('a'), [Some(1), None]), (None, [None, None])];
+        assert!(ANFA::from_storage(&valid, refs).is_ok());
+        assert!(ANFA::from_storage(&valid, vec![[0, 2]]).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+use crate::{AutomataRef, ANFA};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A machine with named holes, placeholders that are filled in later with
+/// other finalized machines. A template is built like any other machine, by
+/// compiling onto `anfa` with the `ForwardCompiler`, with `Template::hole`
+/// pushing a placeholder operand. Until it is filled, a hole accepts nothing.
+///
+/// ```rust
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// use regexxx::expr::Expr;
+/// use regexxx::template::Template;
+/// // RE k=<VALUE>
+/// let mut template = Template::new();
+/// Expr::literal('k').concat(Expr::literal('=')).compile::<ForwardCompiler>(&mut template.anfa).unwrap();
+/// template.hole("VALUE").unwrap();
+/// ForwardCompiler::concatenate(&mut template.anfa).unwrap();
+/// let digits = Expr::literal('0').union(Expr::literal('1')).star().build().unwrap();
+/// let machine = template.instantiate("VALUE", &digits).unwrap().build().unwrap();
+/// assert_eq!(machine.find("k=101;").unwrap(), Some((0, 5)));
+/// ```
+#[derive(Debug)]
+pub struct Template<S = char> {
+    pub anfa: ANFA<S>,
+    /// every unfilled placeholder, a name may be used more than once
+    holes: Vec<(String, AutomataRef)>,
+}
+
+impl<S: Copy + Eq> Default for Template<S> {
+    fn default() -> Template<S> {
+        Template::new()
+    }
+}
+
+impl<S: Copy + Eq> Template<S> {
+    pub fn new() -> Template<S> {
+        Template {
+            anfa: ANFA::new(),
+            holes: Vec::new(),
+        }
+    }
+
+    /// Pushes a placeholder operand named `name` onto `anfa` and returns it.
+    /// The placeholder is shaped like `expr_0`: compilers only ever rewrite
+    /// the final state of an operand, so its initial state is left free to
+    /// point at the machine that fills it.
+    pub fn hole(&mut self, name: &str) -> Result<AutomataRef, &'static str> {
+        ForwardCompiler::expr_0(&mut self.anfa)?;
+        let hole = match self.anfa.automata_refs.last() {
+            Some(hole) => *hole,
+            None => {
+                // exhaustive sanity check, should be impossible
+                return Err("Hole requires an operand. (Race condition.)");
+            }
+        };
+        self.holes.push((String::from(name), hole));
+        Ok(hole)
+    }
+
+    /// The names of the unfilled holes, in creation order and without repeats
+    pub fn holes(&self) -> impl Iterator<Item = &str> + '_ {
+        self.holes
+            .iter()
+            .enumerate()
+            .filter(move |(i, (name, _))| !self.holes[..*i].iter().any(|(other, _)| other == name))
+            .map(|(_, (name, _))| name.as_str())
+    }
+
+    /// Returns a copy of the template with every hole named `name` filled by
+    /// a copy of finalized `machine`. The template itself is not modified, so
+    /// it may be instantiated again with other machines.
+    pub fn instantiate(&self, name: &str, machine: &ANFA<S>) -> Result<Template<S>, &'static str> {
+        if !self.holes.iter().any(|(hole_name, _)| hole_name == name) {
+            return Err("Template has no hole with this name.");
+        }
+        let mut template = Template {
+            anfa: self.anfa.clone(),
+            holes: Vec::new(),
+        };
+        for (hole_name, hole) in self.holes.iter() {
+            if hole_name != name {
+                template.holes.push((hole_name.clone(), *hole));
+                continue;
+            }
+            let [hole_q0, hole_f] = *hole;
+            let [machine_q0, machine_f] = template.anfa.import(machine)?;
+            template.anfa.automata_refs.pop();
+            template.anfa.delta[hole_q0] = (
+                // point the hole at the machine
+                None,
+                [Some(machine_q0), None],
+            );
+            template.anfa.delta[machine_f] = (
+                // point the machine at whatever follows the hole
+                None,
+                [Some(hole_f), None],
+            );
+        }
+        Ok(template)
+    }
+
+    /// Returns the machine once every hole is filled
+    pub fn build(self) -> Result<ANFA<S>, &'static str> {
+        if !self.holes.is_empty() {
+            return Err("Template has unfilled holes.");
+        }
+        Ok(self.anfa)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::expr::Expr;
+    use crate::template::Template;
+    use crate::ANFA;
+    use alloc::vec::Vec;
+
+    /// RE <KEY>=<VALUE>(,<VALUE>)*
+    fn key_values() -> Template {
+        let mut template = Template::new();
+        template.hole("KEY").unwrap();
+        ForwardCompiler::expr_a(&mut template.anfa, '=').unwrap();
+        ForwardCompiler::concatenate(&mut template.anfa).unwrap();
+        template.hole("VALUE").unwrap();
+        ForwardCompiler::concatenate(&mut template.anfa).unwrap();
+        ForwardCompiler::expr_a(&mut template.anfa, ',').unwrap();
+        template.hole("VALUE").unwrap();
+        ForwardCompiler::concatenate(&mut template.anfa).unwrap();
+        ForwardCompiler::star(&mut template.anfa).unwrap();
+        ForwardCompiler::concatenate(&mut template.anfa).unwrap();
+        template
+    }
+
+    #[test]
+    fn test_instantiate() {
+        let template = key_values();
+        assert_eq!(template.holes().collect::<Vec<_>>(), ["KEY", "VALUE"]);
+        assert!(
+            template.anfa.is_empty().unwrap(),
+            "Unfilled holes accept nothing"
+        );
+
+        let key = Expr::literal('k').build().unwrap();
+        let keyed = template.instantiate("KEY", &key).unwrap();
+        assert_eq!(keyed.holes().collect::<Vec<_>>(), ["VALUE"]);
+        let a = Expr::literal('a').build().unwrap();
+        let b_star = Expr::literal('b').star().build().unwrap();
+        let machine_a = keyed.instantiate("VALUE", &a).unwrap().build().unwrap();
+        let machine_b = keyed
+            .instantiate("VALUE", &b_star)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let value = |v: Expr| {
+            Expr::literal('k')
+                .concat(Expr::literal('='))
+                .concat(v.clone())
+                .concat(Expr::literal(',').concat(v).star())
+                .build()
+                .unwrap()
+        };
+        assert!(ANFA::equivalent(&machine_a, &value(Expr::literal('a'))).unwrap());
+        assert!(
+            ANFA::equivalent(&machine_b, &value(Expr::literal('b').star())).unwrap(),
+            "Every hole with the name is filled, and templates are reusable"
+        );
+        assert_eq!(machine_b.find("k=bb,,b").unwrap(), Some((0, 7)));
+    }
+
+    #[test]
+    fn test_instantiate_errors() {
+        let template = key_values();
+        let a = Expr::literal('a').build().unwrap();
+        assert_eq!(
+            template.instantiate("MISSING", &a).err(),
+            Some("Template has no hole with this name.")
+        );
+        assert_eq!(
+            template.instantiate("KEY", &a).unwrap().build().err(),
+            Some("Template has unfilled holes.")
+        );
+        let mut unfinished = Expr::literal('a').build().unwrap();
+        ForwardCompiler::expr_a(&mut unfinished, 'b').unwrap();
+        assert!(template.instantiate("KEY", &unfinished).is_err());
+    }
+}
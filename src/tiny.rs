@@ -0,0 +1,185 @@
+use crate::assertion::Assertion;
+use crate::{QId, Transition, ANFA};
+
+/// Most states of an ANFA `ANFA::is_match_tiny` accepts, one bit of a `u64`
+/// per state
+pub const MAX_STATES: usize = 64;
+
+/// A machine of at most `MAX_STATES` states, simulated by `is_match`
+pub(crate) trait TinyMachine {
+    fn transition(&self, q: QId) -> Transition;
+    fn assertion(&self, q: QId) -> Option<Assertion>;
+}
+
+impl TinyMachine for ANFA {
+    fn transition(&self, q: QId) -> Transition {
+        self.delta[q]
+    }
+
+    fn assertion(&self, q: QId) -> Option<Assertion> {
+        self.assertions.get(&q).copied()
+    }
+}
+
+/// Adds `q` to `set` when its assertion, if any, holds between `before` and `after`
+fn enter<M: TinyMachine>(
+    machine: &M,
+    set: u64,
+    q: QId,
+    before: Option<char>,
+    after: Option<char>,
+) -> u64 {
+    match machine.assertion(q) {
+        Some(assertion) if !assertion.holds(before, after) => set,
+        _ => set | 1 << q,
+    }
+}
+
+/// Adds the ε-closure of every state in `set`, using the set of states still
+/// to expand as the work list instead of a stack
+fn close<M: TinyMachine>(
+    machine: &M,
+    mut set: u64,
+    before: Option<char>,
+    after: Option<char>,
+) -> u64 {
+    let mut pending = set;
+    while pending != 0 {
+        let q = pending.trailing_zeros() as QId;
+        pending &= pending - 1;
+        if let (None, q_nexts) = machine.transition(q) {
+            for q_next in q_nexts.iter().flatten() {
+                if set & 1 << q_next == 0 {
+                    set = enter(machine, set, *q_next, before, after);
+                    pending |= set & 1 << q_next;
+                }
+            }
+        }
+    }
+    set
+}
+
+/// Returns true when some substring of `haystack` is accepted by the
+/// automaton from `machine_q0` to `machine_f`
+pub(crate) fn is_match<M: TinyMachine>(
+    machine: &M,
+    machine_q0: QId,
+    machine_f: QId,
+    haystack: &str,
+) -> bool {
+    let mut current = 0u64;
+    let mut before = None;
+    let mut chars = haystack.chars().peekable();
+    loop {
+        let after = chars.peek().copied();
+        // a new thread starts at every position
+        let start = enter(machine, 0, machine_q0, before, after);
+        current |= close(machine, start, before, after);
+        if current & 1 << machine_f != 0 {
+            return true;
+        }
+        let c = match chars.next() {
+            Some(c) => c,
+            None => return false,
+        };
+        let after_next = chars.peek().copied();
+        let mut next = 0u64;
+        let mut reading = current;
+        while reading != 0 {
+            let q = reading.trailing_zeros() as QId;
+            #[cfg(test)]
+            crate::engine::steps::count();
+            reading &= reading - 1;
+            if let (Some(label), [Some(q_next), _]) = machine.transition(q) {
+                if label == c {
+                    next = enter(machine, next, q_next, Some(c), after_next);
+                }
+            }
+        }
+        current = close(machine, next, Some(c), after_next);
+        before = Some(c);
+    }
+}
+
+impl ANFA {
+    /// Returns true when some substring of `haystack` is accepted, like
+    /// `is_match`, without allocating. The set of current states is a `u64`
+    /// bitset, so the ANFA must have at most `MAX_STATES` states, and hold
+    /// exactly one automaton. `is_match` uses this matcher automatically
+    /// for machines that small.
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// // RE (a+b)*c
+    /// let machine = Expr::literal('a').union(Expr::literal('b')).star().concat(Expr::literal('c')).build().unwrap();
+    /// assert!(machine.is_match_tiny("xxabc").unwrap());
+    /// assert!(!machine.is_match_tiny("ab").unwrap());
+    /// ```
+    pub fn is_match_tiny(&self, haystack: &str) -> Result<bool, &'static str> {
+        let [machine_q0, machine_f] = match self.automata_refs.as_slice() {
+            [machine] => *machine,
+            _ => return Err("Search requires exactly one automaton."),
+        };
+        if self.delta.len() > MAX_STATES {
+            return Err("Tiny matching requires at most 64 states.");
+        }
+        Ok(is_match(self, machine_q0, machine_f, haystack))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::expr::Expr;
+
+    #[test]
+    fn test_is_match_tiny_agrees_with_find() {
+        let a = || Expr::literal('a');
+        let b = || Expr::literal('b');
+        let machines = [
+            // RE (a+b)*b
+            a().union(b()).star().concat(b()),
+            // RE ^ab*$
+            Expr::assert(Assertion::StartText)
+                .concat(a())
+                .concat(b().star())
+                .concat(Expr::assert(Assertion::EndText)),
+            // RE \ba\B
+            Expr::assert(Assertion::WordBoundary)
+                .concat(a())
+                .concat(Expr::assert(Assertion::NotWordBoundary)),
+            // RE 1
+            Expr::one(),
+            // RE 0
+            Expr::zero(),
+        ];
+        let haystacks = ["", "a", "b", "ab", "abbb", "ba", "a a", "aab", "x ab", "é"];
+        for expr in machines.iter() {
+            let machine = expr.build().unwrap();
+            for haystack in haystacks.iter() {
+                assert_eq!(
+                    machine.is_match_tiny(haystack).unwrap(),
+                    machine.find(haystack).unwrap().is_some(),
+                    "{} on {:?}",
+                    expr,
+                    haystack
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_match_tiny_limits() {
+        let mut expr = Expr::literal('a');
+        for _ in 0..32 {
+            expr = expr.concat(Expr::literal('a'));
+        }
+        let machine = expr.build().unwrap();
+        assert!(machine.delta.len() > super::MAX_STATES);
+        assert!(machine.is_match_tiny("a").is_err());
+        assert!(
+            !machine.is_match("a").unwrap(),
+            "is_match falls back to find"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use crate::ANFA;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A transformed haystack and where each of its pieces came from, so spans
+/// found in the text can be mapped back to the original, see `Transform`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Mapped {
+    text: String,
+    /// where each piece starts in `text`, non-decreasing
+    starts: Vec<usize>,
+    /// the byte span each piece replaced in the original
+    originals: Vec<(usize, usize)>,
+    /// the length of the original
+    original_len: usize,
+}
+
+impl Mapped {
+    pub fn new() -> Mapped {
+        Mapped::default()
+    }
+
+    /// Appends `text` as the replacement of the bytes `original` of the
+    /// original haystack. Pieces must be pushed in order and cover the
+    /// original without gaps; `text` may be empty to drop bytes.
+    pub fn push(&mut self, original: (usize, usize), text: &str) {
+        self.starts.push(self.text.len());
+        self.originals.push(original);
+        self.text.push_str(text);
+        self.original_len = original.1;
+    }
+
+    /// The transformed haystack
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Maps a byte offset of the text that starts a span to the original,
+    /// the start of the piece it falls in
+    fn original_start(&self, at: usize) -> usize {
+        if at >= self.text.len() {
+            return self.original_len;
+        }
+        // the last piece starting at or before `at`, pieces that dropped
+        // bytes right before it are skipped
+        let i = self.starts.partition_point(|start| *start <= at);
+        self.originals[i - 1].0
+    }
+
+    /// Maps a byte offset of the text that ends a span to the original, the
+    /// end of the piece holding the byte before it
+    fn original_end(&self, at: usize) -> usize {
+        let i = self.starts.partition_point(|start| *start < at);
+        match i {
+            0 => self.original_start(at),
+            i => self.originals[i - 1].1,
+        }
+    }
+
+    /// Maps a byte span of the text to the span of the original it was
+    /// transformed from. A span that covers part of a piece covers all of
+    /// the piece's original bytes.
+    ///
+    /// ```rust
+    /// use regexxx::transform::Mapped;
+    /// // "Straße" with ß spelled out as ss
+    /// let mut mapped = Mapped::new();
+    /// mapped.push((0, 5), "Stra");
+    /// mapped.push((5, 7), "ss");
+    /// mapped.push((7, 8), "e");
+    /// assert_eq!(mapped.text(), "Strasse");
+    /// assert_eq!(mapped.original_span((4, 7)), (5, 8));
+    /// assert_eq!(mapped.original_span((5, 5)), (5, 5));
+    /// ```
+    pub fn original_span(&self, span: (usize, usize)) -> (usize, usize) {
+        let start = self.original_start(span.0);
+        if span.0 == span.1 {
+            (start, start)
+        } else {
+            (start, self.original_end(span.1).max(start))
+        }
+    }
+}
+
+/// A stage rewriting the haystack before it is searched, e.g. lowercasing or
+/// Unicode normalization, which records where each piece of its output came
+/// from. Searches through a transform report spans of the original haystack.
+///
+/// Normalizations that combine several chars, like NFC, push each run of
+/// chars they may combine as one piece. With an external normalizer, split
+/// the original before each char that starts such a run, normalize the runs
+/// one at a time, and push each with its span.
+pub trait Transform {
+    fn transform(&self, haystack: &str) -> Mapped;
+}
+
+/// Lowercases every char with `char::to_lowercase`, which may yield several
+/// chars for one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Lowercase;
+
+impl Transform for Lowercase {
+    fn transform(&self, haystack: &str) -> Mapped {
+        PerChar(|c: char, out: &mut String| out.extend(c.to_lowercase())).transform(haystack)
+    }
+}
+
+/// Replaces each char with what the function appends to the buffer, e.g.
+/// nothing to drop it
+///
+/// ```rust
+/// use regexxx::expr::Expr;
+/// use regexxx::transform::PerChar;
+/// // drop soft hyphens before matching
+/// let unhyphenate = PerChar(|c: char, out: &mut String| if c != '\u{ad}' { out.push(c) });
+/// let machine = Expr::literal('a').concat(Expr::literal('b')).build().unwrap();
+/// assert_eq!(machine.find_transformed("xa\u{ad}b", &unhyphenate).unwrap(), Some((1, 5)));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct PerChar<F>(pub F);
+
+impl<F: Fn(char, &mut String)> Transform for PerChar<F> {
+    fn transform(&self, haystack: &str) -> Mapped {
+        let mut mapped = Mapped::new();
+        let mut piece = String::new();
+        for (i, c) in haystack.char_indices() {
+            piece.clear();
+            (self.0)(c, &mut piece);
+            mapped.push((i, i + c.len_utf8()), &piece);
+        }
+        mapped
+    }
+}
+
+impl ANFA {
+    /// Like `find`, searching the haystack as rewritten by `transform` and
+    /// reporting the span of the original haystack, see `Mapped::original_span`
+    ///
+    /// ```rust
+    /// use regexxx::expr::Expr;
+    /// use regexxx::transform::Lowercase;
+    /// // RE straße
+    /// let machine = Expr::word("straße").build().unwrap();
+    /// assert_eq!(machine.find_transformed("Die STRAßE", &Lowercase).unwrap(), Some((4, 11)));
+    /// ```
+    pub fn find_transformed<T: Transform>(
+        &self,
+        haystack: &str,
+        transform: &T,
+    ) -> Result<Option<(usize, usize)>, &'static str> {
+        let mapped = transform.transform(haystack);
+        Ok(self
+            .find(mapped.text())?
+            .map(|span| mapped.original_span(span)))
+    }
+
+    /// Like `find_iter`, searching the haystack as rewritten by `transform`
+    /// and reporting spans of the original haystack
+    pub fn find_iter_transformed<T: Transform>(
+        &self,
+        haystack: &str,
+        transform: &T,
+    ) -> Result<Vec<(usize, usize)>, &'static str> {
+        let mapped = transform.transform(haystack);
+        Ok(self
+            .find_iter(mapped.text())?
+            .map(|span| mapped.original_span(span))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::Expr;
+    use crate::transform::{Lowercase, Mapped, PerChar, Transform};
+    use alloc::string::String;
+    use alloc::vec;
+
+    #[test]
+    fn test_lowercase() {
+        // İ lowercases to i and a combining dot
+        let mapped = Lowercase.transform("AİB");
+        assert_eq!(mapped.text(), "ai\u{307}b");
+        assert_eq!(mapped.original_span((1, 2)), (1, 3));
+        assert_eq!(mapped.original_span((2, 5)), (1, 4));
+        assert_eq!(mapped.original_span((5, 5)), (4, 4));
+        let machine = Expr::literal('i')
+            .concat(Expr::literal('b').star())
+            .build()
+            .unwrap();
+        assert_eq!(
+            machine.find_iter_transformed("İB xi", &Lowercase).unwrap(),
+            vec![(0, 2), (5, 6)]
+        );
+        assert_eq!(machine.find_transformed("", &Lowercase).unwrap(), None);
+    }
+
+    #[test]
+    fn test_dropped_chars() {
+        let drop_dashes = PerChar(|c: char, out: &mut String| {
+            if c != '-' {
+                out.push(c)
+            }
+        });
+        let mapped = drop_dashes.transform("-a--b-");
+        assert_eq!(mapped.text(), "ab");
+        // dropped chars around a span are left out, those inside are kept
+        assert_eq!(mapped.original_span((0, 2)), (1, 5));
+        assert_eq!(mapped.original_span((1, 2)), (4, 5));
+        assert_eq!(mapped.original_span((2, 2)), (6, 6));
+        let machine = Expr::literal('a').star().build().unwrap();
+        assert_eq!(
+            machine
+                .find_iter_transformed("-a--b-", &drop_dashes)
+                .unwrap(),
+            vec![(1, 2), (6, 6)]
+        );
+        assert_eq!(Mapped::new().original_span((0, 0)), (0, 0));
+    }
+
+    #[test]
+    fn test_runs() {
+        // NFC-like, e followed by a combining acute becomes é
+        struct Compose;
+        impl Transform for Compose {
+            fn transform(&self, haystack: &str) -> Mapped {
+                let mut mapped = Mapped::new();
+                let mut chars = haystack.char_indices().peekable();
+                while let Some((i, c)) = chars.next() {
+                    match chars.peek() {
+                        Some((j, '\u{301}')) if c == 'e' => {
+                            mapped.push((i, j + 2), "é");
+                            chars.next();
+                        }
+                        _ => mapped.push((i, i + c.len_utf8()), c.encode_utf8(&mut [0; 4])),
+                    }
+                }
+                mapped
+            }
+        }
+        let machine = Expr::literal('é').build().unwrap();
+        assert_eq!(
+            machine.find_transformed("cafe\u{301}!", &Compose).unwrap(),
+            Some((3, 6))
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{QId, ANFA};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// The transitions leaving `q` as a list of `(label, to)` in priority order
+fn edges<S: Copy>(anfa: &ANFA<S>, q: QId) -> Vec<(Option<S>, QId)> {
+    let (label, q_nexts) = anfa.delta[q];
+    q_nexts
+        .iter()
+        .flatten()
+        .map(|q_next| (label, *q_next))
+        .collect()
+}
+
+impl<S: Copy + Eq> ANFA<S> {
+    /// Adds transitions given as `(from, label, to)`, where a `None` label is ε.
+    ///
+    /// This is for programs that generate machines from rule tables instead
+    /// of composing expressions. Each transition is listed on its own, so
+    /// callers do not depend on how `delta` stores them: a state holds one
+    /// label and at most two successors, so a state that ends up with more
+    /// transitions becomes an ε union over new states, one per labeled
+    /// transition. New transitions have lower priority than the state's
+    /// existing transitions, and keep their order among each other.
+    ///
+    /// States must already exist, see `push_state`. A transition that is
+    /// listed twice, or that its state already has, is rejected. Nothing is
+    /// added when any transition is rejected.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// // RE a, then loop on b
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// let [q0, f] = machine.automata_refs[0];
+    /// machine.extend_transitions(vec![(f, Some('b'), f)]).unwrap();
+    /// assert_eq!(machine.find("abbb").unwrap(), Some((0, 4)));
+    /// assert!(machine.extend_transitions(vec![(q0, Some('a'), f)]).is_err());
+    /// ```
+    pub fn extend_transitions<I: IntoIterator<Item = (QId, Option<S>, QId)>>(
+        &mut self,
+        transitions: I,
+    ) -> Result<(), &'static str> {
+        let state_count = self.delta.len();
+        let mut added: BTreeMap<QId, Vec<(Option<S>, QId)>> = BTreeMap::new();
+        for (from, label, to) in transitions {
+            if from >= state_count || to >= state_count {
+                return Err("Transition refers to a state that does not exist.");
+            }
+            let edges = added.entry(from).or_insert_with(|| edges(self, from));
+            if edges.contains(&(label, to)) {
+                return Err("Transition is a duplicate.");
+            }
+            edges.push((label, to));
+        }
+
+        for (q, edges) in added.into_iter() {
+            match edges.as_slice() {
+                [(label, to)] => self.delta[q] = (*label, [Some(*to), None]),
+                [(None, left), (None, right)] => {
+                    self.delta[q] = (None, [Some(*left), Some(*right)])
+                }
+                _ => {
+                    // fan out along ε unions: q -> (entry_0, (entry_1, (entry_2, ...)))
+                    let entries: Vec<QId> = edges
+                        .iter()
+                        .map(|(label, to)| match label {
+                            None => *to,
+                            Some(c) => self.push_state((Some(*c), [Some(*to), None])),
+                        })
+                        .collect();
+                    let mut q_union = q;
+                    for (i, entry) in entries.iter().enumerate() {
+                        if i + 2 < entries.len() {
+                            let q_rest = self.push_state((None, [None, None]));
+                            self.delta[q_union] = (None, [Some(*entry), Some(q_rest)]);
+                            q_union = q_rest;
+                        } else {
+                            self.delta[q_union] = (None, [Some(*entry), Some(entries[i + 1])]);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::ANFA;
+    use alloc::vec;
+
+    #[test]
+    fn test_extend_transitions() {
+        // a table for (a+b+c)d, starting from two unconnected states
+        let mut machine: ANFA = ForwardCompiler::from_expr_1().unwrap();
+        let q0 = machine.automata_refs[0][0];
+        let q1 = machine.push_state((None, [None, None]));
+        let f = machine.push_state((None, [None, None]));
+        machine.automata_refs[0] = [q0, f];
+        machine
+            .extend_transitions(vec![
+                (q0, Some('a'), q1),
+                (q0, Some('b'), q1),
+                (q0, Some('c'), q1),
+                (q1, Some('d'), f),
+            ])
+            .unwrap();
+        assert_eq!(machine.delta[q1], (Some('d'), [Some(f), None]));
+        assert_eq!(
+            machine.delta.len(),
+            7,
+            "q0 fans out over three labeled states"
+        );
+        for word in ["ad", "bd", "cd"].iter() {
+            assert!(machine.determinize().unwrap().accepts(word.chars()));
+        }
+        assert!(!machine.determinize().unwrap().accepts("abd".chars()));
+
+        machine.extend_transitions(vec![(q1, None, f)]).unwrap();
+        assert_eq!(
+            machine.delta[q1],
+            (None, [Some(7), Some(f)]),
+            "Existing transitions keep priority over new ones"
+        );
+        assert_eq!(machine.delta[7], (Some('d'), [Some(f), None]));
+        assert!(machine.determinize().unwrap().accepts("a".chars()));
+    }
+
+    #[test]
+    fn test_extend_transitions_rejects() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        let [q0, f] = machine.automata_refs[0];
+        let delta = machine.delta.clone();
+        assert_eq!(
+            machine.extend_transitions(vec![(f, None, q0), (q0, Some('a'), f)]),
+            Err("Transition is a duplicate."),
+            "Transitions the state already has are duplicates"
+        );
+        assert_eq!(
+            machine.extend_transitions(vec![(f, None, q0), (f, None, q0)]),
+            Err("Transition is a duplicate.")
+        );
+        assert_eq!(
+            machine.extend_transitions(vec![(f, None, q0), (f, None, 2)]),
+            Err("Transition refers to a state that does not exist.")
+        );
+        assert_eq!(machine.delta, delta, "Nothing is added on error");
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{AutomataRef, QId, ANFA};
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Callbacks for `ANFA::dfs` and `ANFA::bfs`. Both methods do nothing by
+/// default, so a visitor only implements what it needs.
+///
+/// ```rust
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// use regexxx::visit::Visitor;
+/// use regexxx::QId;
+///
+/// /// Collects every symbol of the alphabet
+/// struct Alphabet(Vec<char>);
+///
+/// impl Visitor<char> for Alphabet {
+///     fn visit_transition(&mut self, _q: QId, label: Option<char>, _q_next: QId) {
+///         if let Some(c) = label {
+///             if !self.0.contains(&c) {
+///                 self.0.push(c);
+///             }
+///         }
+///     }
+/// }
+///
+/// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+/// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+/// ForwardCompiler::union(&mut machine).unwrap();
+/// let mut alphabet = Alphabet(Vec::new());
+/// machine.dfs(machine.automata_refs[0], &mut alphabet).unwrap();
+/// assert_eq!(alphabet.0, vec!['a', 'b']);
+/// ```
+pub trait Visitor<S> {
+    /// Called once for every state reachable from q0, when it is first reached
+    fn visit_state(&mut self, _q: QId) {}
+
+    /// Called for every transition leaving a visited state, after `visit_state`
+    /// of its source, whether or not `q_next` was already visited.
+    /// `label` is `None` for epsilon transitions.
+    fn visit_transition(&mut self, _q: QId, _label: Option<S>, _q_next: QId) {}
+}
+
+impl<S: Copy + Eq> ANFA<S> {
+    /// Iterates over the transitions leaving state `q` as `(label, q_next)`,
+    /// in priority order: the 0 branch of a union before its 1 branch.
+    /// `label` is `None` for epsilon transitions.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(machine.transitions_from(0).collect::<Vec<_>>(), vec![(Some('a'), 1)]);
+    /// assert_eq!(machine.transitions_from(1).count(), 0);
+    /// ```
+    pub fn transitions_from(&self, q: QId) -> impl Iterator<Item = (Option<S>, QId)> + '_ {
+        let (label, q_nexts) = &self.delta[q];
+        q_nexts
+            .iter()
+            .flatten()
+            .map(move |q_next| (*label, *q_next))
+    }
+
+    /// Returns every state reachable from the q0 of live `automaton`,
+    /// including q0, in creation order.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// assert_eq!(machine.reachable_states(machine.automata_refs[1]).unwrap(), vec![2, 3]);
+    /// ```
+    pub fn reachable_states(&self, automaton: AutomataRef) -> Result<Vec<QId>, &'static str> {
+        let mut reachable = vec![false; self.delta.len()];
+        self.dfs(automaton, &mut |q| reachable[q] = true)?;
+        Ok((0..reachable.len()).filter(|q| reachable[*q]).collect())
+    }
+
+    /// Visits the states reachable from the q0 of live `automaton` depth-first,
+    /// taking transitions in priority order. The search uses an explicit stack,
+    /// so long chains of states do not grow the call stack.
+    pub fn dfs<V: Visitor<S>>(
+        &self,
+        automaton: AutomataRef,
+        visitor: &mut V,
+    ) -> Result<(), &'static str> {
+        self.check_ref(automaton)?;
+        let mut seen = vec![false; self.delta.len()];
+        let mut stack = vec![automaton[0]];
+        while let Some(q) = stack.pop() {
+            if seen[q] {
+                continue;
+            }
+            seen[q] = true;
+            visitor.visit_state(q);
+            for (label, q_next) in self.transitions_from(q) {
+                visitor.visit_transition(q, label, q_next);
+            }
+            // push the right branch first so the left branch is visited first
+            let (_label, q_nexts) = &self.delta[q];
+            stack.extend(
+                q_nexts
+                    .iter()
+                    .rev()
+                    .flatten()
+                    .filter(|q_next| !seen[**q_next]),
+            );
+        }
+        Ok(())
+    }
+
+    /// Visits the states reachable from the q0 of live `automaton`
+    /// breadth-first, i.e. in order of the fewest transitions from q0.
+    pub fn bfs<V: Visitor<S>>(
+        &self,
+        automaton: AutomataRef,
+        visitor: &mut V,
+    ) -> Result<(), &'static str> {
+        self.check_ref(automaton)?;
+        let mut seen = vec![false; self.delta.len()];
+        let mut queue = VecDeque::new();
+        seen[automaton[0]] = true;
+        queue.push_back(automaton[0]);
+        while let Some(q) = queue.pop_front() {
+            visitor.visit_state(q);
+            for (label, q_next) in self.transitions_from(q) {
+                visitor.visit_transition(q, label, q_next);
+                if !seen[q_next] {
+                    seen[q_next] = true;
+                    queue.push_back(q_next);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A closure visits states
+impl<S, F: FnMut(QId)> Visitor<S> for F {
+    fn visit_state(&mut self, q: QId) {
+        self(q)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::visit::Visitor;
+    use crate::{QId, ANFA};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    /// Records every callback in order
+    #[derive(Default)]
+    struct Trace {
+        states: Vec<QId>,
+        transitions: Vec<(QId, Option<char>, QId)>,
+    }
+
+    impl Visitor<char> for Trace {
+        fn visit_state(&mut self, q: QId) {
+            self.states.push(q);
+        }
+        fn visit_transition(&mut self, q: QId, label: Option<char>, q_next: QId) {
+            self.transitions.push((q, label, q_next));
+        }
+    }
+
+    /// RE (ab+c)*
+    fn ab_or_c_star() -> ANFA {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        machine
+    }
+
+    #[test]
+    fn test_dfs_and_bfs() {
+        let machine = ab_or_c_star();
+        let automaton = machine.automata_refs[0];
+        let mut dfs = Trace::default();
+        machine.dfs(automaton, &mut dfs).unwrap();
+        let mut bfs = Trace::default();
+        machine.bfs(automaton, &mut bfs).unwrap();
+        let reachable = machine.reachable_states(automaton).unwrap();
+        assert_eq!(reachable, (0..machine.delta.len()).collect::<Vec<_>>());
+        for trace in [&dfs, &bfs] {
+            let mut states = trace.states.clone();
+            states.sort_unstable();
+            assert_eq!(states, reachable, "Every reachable state is visited once");
+            let transition_count: usize = reachable
+                .iter()
+                .map(|q| machine.transitions_from(*q).count())
+                .sum();
+            assert_eq!(trace.transitions.len(), transition_count);
+        }
+        assert_eq!(dfs.states[0], automaton[0]);
+        assert_eq!(bfs.states[0], automaton[0]);
+        let a = dfs
+            .transitions
+            .iter()
+            .position(|(_q, label, _q_next)| *label == Some('a'));
+        let c = dfs
+            .transitions
+            .iter()
+            .position(|(_q, label, _q_next)| *label == Some('c'));
+        assert!(a < c, "DFS follows the 0 branch of a union first");
+    }
+
+    #[test]
+    fn test_bfs_order() {
+        // RE abc+d, d is one transition further from q0 than a
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'd').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        let mut bfs = Trace::default();
+        machine.bfs(machine.automata_refs[0], &mut bfs).unwrap();
+        let labels: Vec<char> = bfs
+            .transitions
+            .iter()
+            .filter_map(|(_q, label, _q_next)| *label)
+            .collect();
+        assert_eq!(labels, vec!['a', 'd', 'b', 'c']);
+    }
+
+    #[test]
+    fn test_traversal_rejects_stale_refs() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        let machine_a = machine.automata_refs[0];
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert!(machine.reachable_states(machine_a).is_err());
+        assert!(machine.bfs(machine_a, &mut |_q| {}).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+use crate::{QId, ANFA};
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 1em; }
+#graph { width: 100%; height: 70vh; border: 1px solid #ccc; cursor: grab; }
+.state circle { fill: #fff; stroke: #000; stroke-width: 1.5; }
+.state.active circle { fill: #fd6; }
+.edge { fill: none; stroke: #555; marker-end: url(#arrow); }
+.label { font-size: 12px; text-anchor: middle; }
+.state text { text-anchor: middle; dominant-baseline: central; font-size: 12px; }
+";
+
+const SCRIPT: &str = "
+const svg = document.getElementById('graph');
+const view = svg.viewBox.baseVal;
+svg.addEventListener('wheel', (event) => {
+  event.preventDefault();
+  const zoom = event.deltaY > 0 ? 1.1 : 1 / 1.1;
+  const rect = svg.getBoundingClientRect();
+  const x = view.x + ((event.clientX - rect.left) / rect.width) * view.width;
+  const y = view.y + ((event.clientY - rect.top) / rect.height) * view.height;
+  view.x = x - (x - view.x) * zoom;
+  view.y = y - (y - view.y) * zoom;
+  view.width *= zoom;
+  view.height *= zoom;
+});
+let drag = null;
+svg.addEventListener('mousedown', (event) => { drag = [event.clientX, event.clientY]; });
+window.addEventListener('mouseup', () => { drag = null; });
+window.addEventListener('mousemove', (event) => {
+  if (drag === null) return;
+  const rect = svg.getBoundingClientRect();
+  view.x -= ((event.clientX - drag[0]) / rect.width) * view.width;
+  view.y -= ((event.clientY - drag[1]) / rect.height) * view.height;
+  drag = [event.clientX, event.clientY];
+});
+function closure(states) {
+  const seen = new Set();
+  const stack = [...states];
+  while (stack.length > 0) {
+    const q = stack.pop();
+    if (seen.has(q)) continue;
+    seen.add(q);
+    const [label, nexts] = ANFA.delta[q];
+    if (label === null) nexts.filter((n) => n !== null).forEach((n) => stack.push(n));
+  }
+  return seen;
+}
+function step(states, c) {
+  const nexts = [];
+  for (const q of states) {
+    const [label, qs] = ANFA.delta[q];
+    if (label === c) qs.filter((n) => n !== null).forEach((n) => nexts.push(n));
+  }
+  return closure(nexts);
+}
+let trace = [];
+let position = 0;
+let timer = null;
+function render() {
+  const active = trace[position] || new Set();
+  ANFA.delta.forEach((_, q) => {
+    document.getElementById('q' + q).classList.toggle('active', active.has(q));
+  });
+  const input = [...document.getElementById('input').value];
+  const done = position === trace.length - 1;
+  document.getElementById('status').textContent =
+    'step ' + position + '/' + input.length + ', read \"' + input.slice(0, position).join('') + '\"' +
+    (done ? (active.has(ANFA.f) ? ', accepted' : ', rejected') : '');
+}
+document.getElementById('run').addEventListener('click', () => {
+  const input = [...document.getElementById('input').value];
+  trace = [closure([ANFA.q0])];
+  input.forEach((c) => trace.push(step(trace[trace.length - 1], c)));
+  position = 0;
+  render();
+  clearInterval(timer);
+  timer = setInterval(() => {
+    if (position >= trace.length - 1) {
+      clearInterval(timer);
+      return;
+    }
+    position += 1;
+    render();
+  }, 600);
+});
+";
+
+/// Escapes text for HTML element content and attribute values
+fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Writes `c` as a JSON string that is also safe inside a `<script>` element
+fn write_json_char(out: &mut String, c: char) -> fmt::Result {
+    match c {
+        '"' => out.push_str("\"\\\"\""),
+        '\\' => out.push_str("\"\\\\\""),
+        c if c.is_control() || c == '<' || c == '>' || c == '&' => {
+            write!(out, "\"\\u{:04x}\"", c as u32)?;
+        }
+        c => {
+            out.push('"');
+            out.push(c);
+            out.push('"');
+        }
+    }
+    Ok(())
+}
+
+/// Renders a self-contained HTML page with a pan- and zoomable graph of the
+/// automaton and a text box that animates its simulation step by step.
+/// The page has no external dependencies; the simulation runs on a copy of
+/// `delta` embedded in the page. The ANFA must hold exactly one automaton.
+///
+/// ```rust
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// use regexxx::viz;
+/// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+/// ForwardCompiler::star(&mut machine).unwrap();
+/// let html = viz::to_html(&machine).unwrap();
+/// assert!(html.starts_with("<!DOCTYPE html>"));
+/// ```
+pub fn to_html(anfa: &ANFA) -> Result<String, &'static str> {
+    let [q0, f] = match anfa.automata_refs.as_slice() {
+        [machine] => *machine,
+        _ => {
+            return Err("HTML visualization requires exactly one automaton.");
+        }
+    };
+    if !anfa.assertions.is_empty() {
+        return Err("HTML visualization does not support assertions.");
+    }
+    let mut out = String::new();
+    write_html(anfa, q0, f, &mut out).map_err(|_| "Failed to format HTML output.")?;
+    Ok(out)
+}
+
+fn write_html(anfa: &ANFA, q0: QId, f: QId, out: &mut String) -> fmt::Result {
+    // lay states out in columns by their breadth-first distance from q0
+    let mut depth: Vec<Option<usize>> = vec![None; anfa.delta.len()];
+    let mut queue = VecDeque::new();
+    depth[q0] = Some(0);
+    queue.push_back(q0);
+    while let Some(q) = queue.pop_front() {
+        for q_next in anfa.delta[q].1.iter().flatten() {
+            if depth[*q_next].is_none() {
+                depth[*q_next] = Some(depth[q].unwrap_or(0) + 1);
+                queue.push_back(*q_next);
+            }
+        }
+    }
+    let columns = depth.iter().flatten().max().map_or(0, |d| d + 1);
+    let mut rows = vec![0usize; columns + 1];
+    let mut position = vec![(0usize, 0usize); anfa.delta.len()];
+    for q in 0..anfa.delta.len() {
+        // unreachable states share a trailing column
+        let column = depth[q].unwrap_or(columns);
+        position[q] = (60 + column * 120, 60 + rows[column] * 80);
+        rows[column] += 1;
+    }
+    let width = 120 * (columns + 1);
+    let height = 80 * rows.iter().max().map_or(1, |r| *r + 1);
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>regexxx automaton</title>")?;
+    writeln!(out, "<style>{}</style>\n</head>\n<body>", STYLE)?;
+    writeln!(
+        out,
+        "<p><input id=\"input\" placeholder=\"input\"> <button id=\"run\">Simulate</button> <span id=\"status\"></span></p>"
+    )?;
+    writeln!(
+        out,
+        "<svg id=\"graph\" viewBox=\"0 0 {} {}\" xmlns=\"http://www.w3.org/2000/svg\">",
+        width, height
+    )?;
+    writeln!(
+        out,
+        "<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\"><path d=\"M0,0 L10,5 L0,10 z\"/></marker></defs>"
+    )?;
+    writeln!(
+        out,
+        "<path class=\"edge\" d=\"M{},{} L{},{}\"/>",
+        position[q0].0 - 50,
+        position[q0].1,
+        position[q0].0 - 20,
+        position[q0].1
+    )?;
+    for (q, (label, q_nexts)) in anfa.delta.iter().enumerate() {
+        let branches = q_nexts.iter().flatten().count();
+        for (branch, q_next) in q_nexts.iter().flatten().enumerate() {
+            let (x1, y1) = position[q];
+            let (x2, y2) = position[*q_next];
+            // bend edges that point backwards so they don't overlap forward edges
+            let bend = if x2 <= x1 { 60 } else { 0 };
+            let (cx, cy) = ((x1 + x2) / 2, (y1 + y2) / 2 + bend);
+            writeln!(
+                out,
+                "<path class=\"edge\" d=\"M{},{} Q{},{} {},{}\"/>",
+                x1 + 20,
+                y1,
+                cx,
+                cy,
+                x2 - 20,
+                y2
+            )?;
+            write!(out, "<text class=\"label\" x=\"{}\" y=\"{}\">", cx, cy - 6)?;
+            match label {
+                Some(c) => {
+                    let mut text = String::new();
+                    text.push(*c);
+                    escape_html(out, &text);
+                }
+                None if branches == 2 => write!(out, "{}", branch)?,
+                None => out.push('ε'),
+            }
+            writeln!(out, "</text>")?;
+        }
+    }
+    for (q, (x, y)) in position.iter().enumerate() {
+        write!(out, "<g id=\"q{}\" class=\"state\">", q)?;
+        write!(out, "<circle cx=\"{}\" cy=\"{}\" r=\"20\"/>", x, y)?;
+        if q == f {
+            write!(out, "<circle cx=\"{}\" cy=\"{}\" r=\"16\"/>", x, y)?;
+        }
+        writeln!(out, "<text x=\"{}\" y=\"{}\">{}</text></g>", x, y, q)?;
+    }
+    writeln!(out, "</svg>")?;
+
+    write!(
+        out,
+        "<script>\nconst ANFA = {{\"q0\":{},\"f\":{},\"delta\":[",
+        q0, f
+    )?;
+    for (q, (label, [q_left, q_right])) in anfa.delta.iter().enumerate() {
+        if q > 0 {
+            out.push(',');
+        }
+        out.push('[');
+        match label {
+            Some(c) => write_json_char(out, *c)?,
+            None => out.push_str("null"),
+        }
+        out.push_str(",[");
+        for (i, q_next) in [q_left, q_right].iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            match q_next {
+                Some(q_next) => write!(out, "{}", q_next)?,
+                None => out.push_str("null"),
+            }
+        }
+        out.push_str("]]");
+    }
+    writeln!(out, "]}};{}</script>\n</body>\n</html>", SCRIPT)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::viz::to_html;
+
+    #[test]
+    fn test_to_html() {
+        let mut machine = ForwardCompiler::from_expr_a('<').unwrap();
+        ForwardCompiler::expr_a(&mut machine, '"').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        let html = to_html(&machine).unwrap();
+        assert!(
+            html.contains("const ANFA = {\"q0\":4,\"f\":5,\"delta\":[[\"\\u003c\",[1,null]],[null,[5,null]],[\"\\\"\",[3,null]],[null,[5,null]],[null,[0,2]],[null,[null,null]]]};"),
+            "delta is embedded as JSON for the simulator"
+        );
+        assert!(html.contains(">&lt;</text>"), "Labels are HTML escaped");
+        assert!(html.contains(">&quot;</text>"), "Labels are HTML escaped");
+        assert!(
+            html.contains("<g id=\"q5\" class=\"state\">"),
+            "Every state is drawn"
+        );
+    }
+
+    #[test]
+    fn test_to_html_requires_one_automaton() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        assert!(to_html(&machine).is_err());
+    }
+}
@@ -0,0 +1,27 @@
+//! Runs the behavioral cases in `tests/corpus/*.toml`, see `regexxx::harness`
+
+use regexxx::harness;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_corpus() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+    for path in paths {
+        let toml = fs::read_to_string(&path).unwrap();
+        let cases =
+            harness::parse_cases(&toml).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        let report = harness::run(&cases);
+        assert!(report.is_ok(), "{}: {}", path.display(), report);
+    }
+}
@@ -0,0 +1,36 @@
+//! Compiles and runs the source `regexxx::codegen::emit_matcher` generates,
+//! checked in at `tests/emitted` and kept up to date by the unit tests
+
+use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+use regexxx::ANFA;
+
+include!("emitted/ends_in_newline.rs");
+
+#[test]
+fn test_ends_in_newline() {
+    // RE (0x00+0xff)*0x0a
+    let mut machine: ANFA<u8> = ForwardCompiler::from_expr_a(0x00).unwrap();
+    ForwardCompiler::expr_a(&mut machine, 0xff).unwrap();
+    ForwardCompiler::union(&mut machine).unwrap();
+    ForwardCompiler::star(&mut machine).unwrap();
+    ForwardCompiler::expr_a(&mut machine, 0x0a).unwrap();
+    ForwardCompiler::concatenate(&mut machine).unwrap();
+    let dfa = machine.determinize().unwrap().minimize();
+    let inputs: [&[u8]; 7] = [
+        b"",
+        b"\n",
+        b"\0\xff\n",
+        b"\n\n",
+        b"\x01\n",
+        b"\xff",
+        b"\n\0",
+    ];
+    for input in inputs.iter() {
+        assert_eq!(
+            ends_in_newline(input),
+            dfa.accepts(input.iter().copied()),
+            "{:?}",
+            input
+        );
+    }
+}